@@ -0,0 +1,235 @@
+/*
+	抽象语法树，由语法分析产生，再由中间代码生成阶段翻译为四元式
+*/
+use crate::common::Tag;
+use crate::span::Span;
+
+// 翻译单元，即一个源文件
+#[derive(Clone, PartialEq, Debug)]
+pub struct TranslationUnit {
+    pub items: Vec<Item>,           // 全局声明和函数
+    pub span: Span,
+}
+
+// 全局定义
+#[derive(Clone, PartialEq, Debug)]
+pub enum Item {
+    Decl(Decl),                     // 全局变量声明
+    Function(FunctionDef),          // 函数声明或定义
+}
+
+// 标识符
+#[derive(Clone, PartialEq, Debug)]
+pub struct Ident {
+    pub name: String,               // 名称，语法错误时为空
+    pub span: Span,
+}
+
+// 类型说明符
+#[derive(Clone, PartialEq, Debug)]
+pub struct TypeSpec {
+    pub tag: Tag,                   // KwInt、KwChar、KwShort、KwLong、KwVoid、KwStruct、KwUnion、KwEnum，类型名为ID
+    pub words: Vec<Tag>,            // 整数类型的关键字，按源代码顺序，如unsigned long long
+    pub record: Option<Box<RecordSpec>>,    // 结构体或联合的标签和成员
+    pub enumer: Option<Box<EnumSpec>>,      // 枚举的标签和常量
+    pub alias: Option<Ident>,       // typedef定义的类型名
+    pub span: Span,
+}
+
+// 结构体或联合: struct name { <localdef> ... } | struct { ... } | struct name
+#[derive(Clone, PartialEq, Debug)]
+pub struct RecordSpec {
+    pub name: Option<Ident>,        // 标签名，匿名时为None
+    pub members: Option<Vec<Decl>>, // 成员声明，只引用标签时为None
+    pub span: Span,
+}
+
+// 枚举: enum name { A, B = 2 } | enum { ... } | enum name
+#[derive(Clone, PartialEq, Debug)]
+pub struct EnumSpec {
+    pub name: Option<Ident>,        // 标签名，匿名时为None
+    pub items: Option<Vec<Enumerator>>, // 枚举常量，只引用标签时为None
+    pub span: Span,
+}
+
+// 枚举常量: name | name = expr
+#[derive(Clone, PartialEq, Debug)]
+pub struct Enumerator {
+    pub name: Ident,
+    pub value: Option<Expr>,        // 常量表达式，省略时为前一个加1
+    pub span: Span,
+}
+
+// 变量声明语句: [extern|typedef] <type> <defdata> {, <defdata>} ;
+#[derive(Clone, PartialEq, Debug)]
+pub struct Decl {
+    pub ext: bool,                  // 是否extern声明
+    pub typedef: bool,              // 是否typedef定义类型名
+    pub ty: TypeSpec,
+    pub vars: Vec<VarDecl>,         // 每个声明的变量
+    pub span: Span,
+}
+
+// 单个变量: **name = init | *name[len][len] = init | (*name)[len] | name[] = init
+#[derive(Clone, PartialEq, Debug)]
+pub struct VarDecl {
+    pub name: Ident,
+    pub decl: Declarator,           // 声明符，描述变量的类型如何由类型说明符构造
+    pub init: Option<Initializer>,  // 初始值
+    pub span: Span,
+}
+
+// 声明符，按C的写法嵌套，如int *a[4]为Pointer(Array(Name, 4))，int (*p)[4]为Array(Pointer(Name), 4)
+#[derive(Clone, PartialEq, Debug)]
+pub struct Declarator {
+    pub kind: DeclaratorKind,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum DeclaratorKind {
+    Name,                                                   // 声明的名字
+    Pointer(Box<Declarator>),                               // *d
+    Array { inner: Box<Declarator>, len: Option<Expr> },    // d[len]，长度可以省略
+    Function { inner: Box<Declarator>, params: Vec<Param> },    // d(params)
+}
+
+// 初始值: expr | {init, init, ...}
+#[derive(Clone, PartialEq, Debug)]
+pub enum Initializer {
+    Expr(Expr),
+    List { items: Vec<Initializer>, span: Span },   // 初始化列表，可以嵌套
+}
+
+// 函数声明或定义
+#[derive(Clone, PartialEq, Debug)]
+pub struct FunctionDef {
+    pub ext: bool,                  // 是否extern
+    pub ret: TypeSpec,              // 返回类型
+    pub name: Ident,
+    pub params: Vec<Param>,         // 参数列表
+    pub body: Option<Block>,        // 函数体，声明时为None
+    pub span: Span,
+}
+
+// 函数参数: <type> **name | <type> name | <type> name[][len] | <type> (*name)[len]
+#[derive(Clone, PartialEq, Debug)]
+pub struct Param {
+    pub ty: TypeSpec,
+    pub name: Ident,
+    pub decl: Declarator,           // 声明符，数组参数的第一维可以省略
+    pub span: Span,
+}
+
+// 语句块
+#[derive(Clone, PartialEq, Debug)]
+pub struct Block {
+    pub items: Vec<BlockItem>,      // 局部声明和语句，按出现顺序
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum BlockItem {
+    Decl(Decl),                     // 局部变量声明
+    Stmt(Stmt),                     // 语句
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum StmtKind {
+    Expr(Option<Expr>),                                     // 表达式语句，空语句为None
+    Block(Block),                                           // 循环体和分支中的语句块
+    While { cond: Option<Expr>, body: Box<Stmt> },
+    DoWhile { body: Box<Stmt>, cond: Option<Expr> },
+    For { init: Box<ForInit>, cond: Option<Expr>, step: Option<Expr>, body: Box<Stmt> },
+    If { cond: Expr, then: Box<Stmt>, els: Option<Box<Stmt>> },
+    Switch { cond: Expr, cases: Vec<Case> },
+    Break,
+    Continue,
+    Return(Option<Expr>),
+}
+
+// for循环的初始化部分
+#[derive(Clone, PartialEq, Debug)]
+pub enum ForInit {
+    Decl(Decl),                     // 局部变量声明
+    Expr(Option<Expr>),             // 表达式，可以为空
+}
+
+// case或default分支
+#[derive(Clone, PartialEq, Debug)]
+pub struct Case {
+    pub label: Option<Expr>,        // case常量，default为None
+    pub items: Vec<BlockItem>,      // 分支内的声明和语句
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ExprKind {
+    Literal(Literal),                                       // 常量
+    Ident(String),                                          // 变量
+    Index { base: Box<Expr>, index: Box<Expr> },            // 数组索引 a[i]
+    Member { base: Box<Expr>, arrow: bool, member: Ident }, // 成员访问 s.a p->a
+    Call { callee: Ident, args: Vec<Expr> },                // 函数调用 f(a, b)
+    Unary { op: Tag, expr: Box<Expr> },                     // 左单目运算 ! - & * ++ --
+    Postfix { op: Tag, expr: Box<Expr> },                   // 右单目运算 ++ --
+    Binary { op: Tag, lhs: Box<Expr>, rhs: Box<Expr> },     // 双目运算
+    Assign { op: Tag, lhs: Box<Expr>, rhs: Box<Expr> },     // 赋值，op为=或复合赋值+= -=等
+    Paren(Box<Expr>),                                       // 括号
+    Error,                                                  // 缺失的表达式，已报告语法错误
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Literal {
+    Num(isize),                     // 整数
+    Char(char),                     // 字符
+    Str(String),                    // 字符串
+    Real(f64),                      // 浮点数
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Expr {
+            kind,
+            span,
+        }
+    }
+}
+
+impl Declarator {
+    pub fn new(kind: DeclaratorKind, span: Span) -> Self {
+        Declarator {
+            kind,
+            span,
+        }
+    }
+
+    // 紧挨着名字的数组维的长度，如a[][4]的第一维，名字不直接是数组时为None
+    pub fn first_dim(&self) -> Option<&Option<Expr>> {
+        match &self.kind {
+            DeclaratorKind::Name => None,
+            DeclaratorKind::Array { inner, len } if inner.kind == DeclaratorKind::Name => Some(len),
+            DeclaratorKind::Pointer(inner) | DeclaratorKind::Array { inner, .. } | DeclaratorKind::Function { inner, .. } => inner.first_dim(),
+        }
+    }
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: Span) -> Self {
+        Stmt {
+            kind,
+            span,
+        }
+    }
+}
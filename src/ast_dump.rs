@@ -0,0 +1,521 @@
+/*
+	语法树输出，缩进文本或JSON格式，用于调试语法分析和编写对照测试
+*/
+use std::collections::HashMap;
+use std::io::{self, Write};
+use crate::ast::{Block, BlockItem, Case, Decl, Declarator, DeclaratorKind, Expr, ExprKind, ForInit, FunctionDef, Initializer, Item, Literal, Param, Stmt, StmtKind, TranslationUnit, TypeSpec, VarDecl};
+use crate::catalog::Catalog;
+use crate::common::Tag::{self, ARROW, ASSIGN, DOT};
+use crate::json::{quote, span_json};
+use crate::preprocess::SourceMap;
+use crate::span::Span;
+
+// 节点属性值
+enum Value {
+    Str(String),
+    Num(isize),
+    Real(f64),
+    Bool(bool),
+}
+
+// 与输出格式无关的节点
+struct Node {
+    kind: &'static str,                     // 节点种类
+    role: Option<&'static str>,             // 在父节点中的角色，如cond、body
+    span: Span,
+    ty: Option<String>,                     // 语义分析推导出的类型
+    attrs: Vec<(&'static str, Value)>,      // 名称、运算符、常量值等
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn new(kind: &'static str, span: Span) -> Self {
+        Node {
+            kind,
+            role: None,
+            span,
+            ty: None,
+            attrs: vec![],
+            children: vec![],
+        }
+    }
+
+    fn attr(mut self, name: &'static str, value: Value) -> Self {
+        self.attrs.push((name, value));
+        self
+    }
+
+    fn child(mut self, role: &'static str, mut node: Node) -> Self {
+        node.role = Some(role);
+        self.children.push(node);
+        self
+    }
+
+    // 区间映射回预处理之前的原文件
+    fn relocate(&mut self, map: &SourceMap) {
+        self.span = map.map_span(self.span).1;
+        for child in self.children.iter_mut() {
+            child.relocate(map);
+        }
+    }
+
+    // 缩进文本，每个节点一行
+    fn write_text(&self, out: &mut dyn Write, depth: usize) -> io::Result<()> {
+        write!(out, "{}", "  ".repeat(depth))?;
+        if let Some(role) = self.role {
+            write!(out, "{}: ", role)?;
+        }
+        write!(out, "{}", self.kind)?;
+        for (name, value) in self.attrs.iter() {
+            match value {
+                Value::Str(s) => write!(out, " {}={:?}", name, s)?,
+                Value::Num(n) => write!(out, " {}={}", name, n)?,
+                Value::Real(r) => write!(out, " {}={:?}", name, r)?,
+                Value::Bool(b) => write!(out, " {}={}", name, b)?,
+            }
+        }
+        write!(out, " <{}:{}-{}:{}>", self.span.line(), self.span.col(), self.span.end_line(), self.span.end_col())?;
+        if let Some(ty) = &self.ty {
+            write!(out, " : {}", ty)?;
+        }
+        writeln!(out)?;
+
+        for child in self.children.iter() {
+            child.write_text(out, depth + 1)?;
+        }
+
+        Ok(())
+    }
+
+    // 单行JSON对象
+    fn to_json(&self) -> String {
+        let mut fields = vec![format!("\"kind\":{}", quote(self.kind))];
+        if let Some(role) = self.role {
+            fields.push(format!("\"role\":{}", quote(role)));
+        }
+        fields.push(format!("\"span\":{}", span_json(self.span)));
+        if let Some(ty) = &self.ty {
+            fields.push(format!("\"type\":{}", quote(ty)));
+        }
+        for (name, value) in self.attrs.iter() {
+            let value = match value {
+                Value::Str(s) => quote(s),
+                Value::Num(n) => n.to_string(),
+                Value::Real(r) if r.is_finite() => format!("{:?}", r),
+                Value::Real(r) => quote(&r.to_string()),     // JSON没有inf和NaN
+                Value::Bool(b) => b.to_string(),
+            };
+            fields.push(format!("{}:{}", quote(name), value));
+        }
+        let children: Vec<String> = self.children.iter().map(|c| c.to_json()).collect();
+        fields.push(format!("\"children\":[{}]", children.join(",")));
+
+        format!("{{{}}}", fields.join(","))
+    }
+}
+
+pub(crate) struct AstDump<'a> {
+    types: &'a HashMap<Span, String>,       // 节点区间到类型，由中间代码生成阶段记录
+    map: Option<&'a SourceMap>,             // 预处理的位置映射
+}
+
+impl<'a> AstDump<'a> {
+    pub(crate) fn new(types: &'a HashMap<Span, String>) -> Self {
+        AstDump {
+            types,
+            map: None,
+        }
+    }
+
+    // 输出原文件中的位置
+    pub(crate) fn with_source_map(mut self, map: &'a SourceMap) -> Self {
+        self.map = Some(map);
+        self
+    }
+
+    // 输出缩进文本
+    pub(crate) fn write_text(&self, unit: &TranslationUnit, out: &mut dyn Write) -> io::Result<()> {
+        self.root(unit).write_text(out, 0)
+    }
+
+    // 输出JSON
+    pub(crate) fn write_json(&self, unit: &TranslationUnit, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "{}", self.root(unit).to_json())
+    }
+
+    fn root(&self, unit: &TranslationUnit) -> Node {
+        let mut node = self.unit(unit);
+        if let Some(map) = self.map {
+            node.relocate(map);
+        }
+        node
+    }
+
+    // 带类型的节点
+    fn typed(&self, kind: &'static str, span: Span) -> Node {
+        let mut node = Node::new(kind, span);
+        node.ty = self.types.get(&span).cloned();
+        node
+    }
+
+    fn unit(&self, unit: &TranslationUnit) -> Node {
+        let mut node = Node::new("TranslationUnit", unit.span);
+        for item in unit.items.iter() {
+            node.children.push(match item {
+                Item::Decl(decl) => self.decl(decl),
+                Item::Function(fun) => self.function(fun),
+            });
+        }
+        node
+    }
+
+    fn decl(&self, decl: &Decl) -> Node {
+        let mut node = Node::new("Decl", decl.span)
+            .attr("extern", Value::Bool(decl.ext))
+            .attr("spec", Value::Str(spec_name(&decl.ty)));
+        if decl.typedef {
+            node = node.attr("typedef", Value::Bool(true));
+        }
+        if let Some(record) = self.record(&decl.ty) {
+            node = node.child("record", record);
+        }
+        if let Some(enumer) = self.enumer(&decl.ty) {
+            node = node.child("enum", enumer);
+        }
+        for var in decl.vars.iter() {
+            node.children.push(self.var_decl(var));
+        }
+        node
+    }
+
+    // 结构体或联合的定义，只引用标签时没有节点
+    fn record(&self, ty: &TypeSpec) -> Option<Node> {
+        let record = ty.record.as_ref()?;
+        let members = record.members.as_ref()?;
+        let mut node = Node::new("Record", record.span).attr("spec", Value::Str(spec_name(ty)));
+        for decl in members.iter() {
+            node = node.child("member", self.decl(decl));
+        }
+        Some(node)
+    }
+
+    // 枚举的定义，只引用标签时没有节点
+    fn enumer(&self, ty: &TypeSpec) -> Option<Node> {
+        let enumer = ty.enumer.as_ref()?;
+        let items = enumer.items.as_ref()?;
+        let mut node = Node::new("Enum", enumer.span).attr("spec", Value::Str(spec_name(ty)));
+        for item in items.iter() {
+            let mut e = Node::new("Enumerator", item.span).attr("name", Value::Str(item.name.name.clone()));
+            if let Some(value) = &item.value {
+                e = e.child("value", self.expr(value));
+            }
+            node = node.child("item", e);
+        }
+        Some(node)
+    }
+
+    // 声明符由外到内嵌套，只有名字时没有节点，省略的数组长度没有len子节点
+    fn declarator(&self, decl: &Declarator) -> Option<Node> {
+        let (mut node, inner) = match &decl.kind {
+            DeclaratorKind::Name => return None,
+            DeclaratorKind::Pointer(inner) => (Node::new("PointerDecl", decl.span), inner),
+            DeclaratorKind::Array { inner, len } => {
+                let mut node = Node::new("ArrayDecl", decl.span);
+                if let Some(len) = len {
+                    node = node.child("len", self.expr(len));
+                }
+                (node, inner)
+            },
+            DeclaratorKind::Function { inner, params } => {
+                let mut node = Node::new("FunctionDecl", decl.span);
+                for para in params.iter() {
+                    node = node.child("param", self.param(para));
+                }
+                (node, inner)
+            },
+        };
+        if let Some(inner) = self.declarator(inner) {
+            node = node.child("inner", inner);
+        }
+        Some(node)
+    }
+
+    fn var_decl(&self, var: &VarDecl) -> Node {
+        let mut node = self.typed("VarDecl", var.span)
+            .attr("name", Value::Str(var.name.name.clone()));
+        if let Some(decl) = self.declarator(&var.decl) {
+            node = node.child("declarator", decl);
+        }
+        if let Some(init) = &var.init {
+            node = node.child("init", self.initializer(init));
+        }
+        node
+    }
+
+    fn initializer(&self, init: &Initializer) -> Node {
+        match init {
+            Initializer::Expr(e) => self.expr(e),
+            Initializer::List { items, span } => {
+                let mut node = Node::new("InitList", *span);
+                for item in items.iter() {
+                    node = node.child("item", self.initializer(item));
+                }
+                node
+            },
+        }
+    }
+
+    fn function(&self, fun: &FunctionDef) -> Node {
+        let mut node = Node::new("Function", fun.span)
+            .attr("extern", Value::Bool(fun.ext))
+            .attr("ret", Value::Str(spec_name(&fun.ret)))
+            .attr("name", Value::Str(fun.name.name.clone()));
+        for para in fun.params.iter() {
+            node = node.child("param", self.param(para));
+        }
+        if let Some(body) = &fun.body {
+            node = node.child("body", self.block(body));
+        }
+        node
+    }
+
+    fn param(&self, para: &Param) -> Node {
+        let mut node = self.typed("Param", para.span)
+            .attr("spec", Value::Str(spec_name(&para.ty)))
+            .attr("name", Value::Str(para.name.name.clone()));
+        if let Some(decl) = self.declarator(&para.decl) {
+            node = node.child("declarator", decl);
+        }
+        node
+    }
+
+    fn block(&self, block: &Block) -> Node {
+        let mut node = Node::new("Block", block.span);
+        node.children = self.block_items(&block.items);
+        node
+    }
+
+    fn block_items(&self, items: &[BlockItem]) -> Vec<Node> {
+        items.iter().map(|item| match item {
+            BlockItem::Decl(decl) => self.decl(decl),
+            BlockItem::Stmt(stmt) => self.statement(stmt),
+        }).collect()
+    }
+}
+
+// 语句
+impl<'a> AstDump<'a> {
+    fn statement(&self, stmt: &Stmt) -> Node {
+        let span = stmt.span;
+        match &stmt.kind {
+            StmtKind::Expr(e) => self.opt_child(Node::new("ExprStmt", span), "expr", e),
+            StmtKind::Block(block) => self.block(block),
+            StmtKind::While { cond, body } => {
+                self.opt_child(Node::new("While", span), "cond", cond)
+                    .child("body", self.statement(body))
+            },
+            StmtKind::DoWhile { body, cond } => {
+                let node = Node::new("DoWhile", span).child("body", self.statement(body));
+                self.opt_child(node, "cond", cond)
+            },
+            StmtKind::For { init, cond, step, body } => {
+                let mut node = Node::new("For", span);
+                node = match init.as_ref() {
+                    ForInit::Decl(decl) => node.child("init", self.decl(decl)),
+                    ForInit::Expr(e) => self.opt_child(node, "init", e),
+                };
+                node = self.opt_child(node, "cond", cond);
+                node = self.opt_child(node, "step", step);
+                node.child("body", self.statement(body))
+            },
+            StmtKind::If { cond, then, els } => {
+                let mut node = Node::new("If", span)
+                    .child("cond", self.expr(cond))
+                    .child("then", self.statement(then));
+                if let Some(els) = els {
+                    node = node.child("else", self.statement(els));
+                }
+                node
+            },
+            StmtKind::Switch { cond, cases } => {
+                let mut node = Node::new("Switch", span).child("cond", self.expr(cond));
+                for case in cases.iter() {
+                    node.children.push(self.case(case));
+                }
+                node
+            },
+            StmtKind::Break => Node::new("Break", span),
+            StmtKind::Continue => Node::new("Continue", span),
+            StmtKind::Return(e) => self.opt_child(Node::new("Return", span), "value", e),
+        }
+    }
+
+    fn case(&self, case: &Case) -> Node {
+        let mut node = match &case.label {
+            Some(label) => Node::new("Case", case.span).child("label", self.expr(label)),
+            None => Node::new("Default", case.span),
+        };
+        node.children.extend(self.block_items(&case.items));
+        node
+    }
+
+    // 可以省略的表达式
+    fn opt_child(&self, node: Node, role: &'static str, e: &Option<Expr>) -> Node {
+        match e {
+            Some(e) => node.child(role, self.expr(e)),
+            None => node,
+        }
+    }
+}
+
+// 表达式
+impl<'a> AstDump<'a> {
+    fn expr(&self, e: &Expr) -> Node {
+        let span = e.span;
+        match &e.kind {
+            ExprKind::Literal(lit) => {
+                let value = match lit {
+                    Literal::Num(n) => Value::Num(*n),
+                    Literal::Char(c) => Value::Str(c.to_string()),
+                    Literal::Str(s) => Value::Str(s.clone()),
+                    Literal::Real(r) => Value::Real(*r),
+                };
+                self.typed("Literal", span).attr("value", value)
+            },
+            ExprKind::Ident(name) => self.typed("Ident", span).attr("name", Value::Str(name.clone())),
+            ExprKind::Index { base, index } => {
+                self.typed("Index", span)
+                    .child("base", self.expr(base))
+                    .child("index", self.expr(index))
+            },
+            ExprKind::Member { base, arrow, member } => {
+                self.typed("Member", span)
+                    .attr("op", Value::Str(tag_name(if *arrow { ARROW } else { DOT })))
+                    .attr("member", Value::Str(member.name.clone()))
+                    .child("base", self.expr(base))
+            },
+            ExprKind::Call { callee, args } => {
+                let mut node = self.typed("Call", span).attr("callee", Value::Str(callee.name.clone()));
+                for arg in args.iter() {
+                    node = node.child("arg", self.expr(arg));
+                }
+                node
+            },
+            ExprKind::Unary { op, expr } => {
+                self.typed("Unary", span)
+                    .attr("op", Value::Str(tag_name(*op)))
+                    .child("expr", self.expr(expr))
+            },
+            ExprKind::Postfix { op, expr } => {
+                self.typed("Postfix", span)
+                    .attr("op", Value::Str(tag_name(*op)))
+                    .child("expr", self.expr(expr))
+            },
+            ExprKind::Binary { op, lhs, rhs } => {
+                self.typed("Binary", span)
+                    .attr("op", Value::Str(tag_name(*op)))
+                    .child("lhs", self.expr(lhs))
+                    .child("rhs", self.expr(rhs))
+            },
+            ExprKind::Assign { op, lhs, rhs } => {
+                let mut node = self.typed("Assign", span);
+                if *op != ASSIGN {      // 普通赋值不输出运算符
+                    node = node.attr("op", Value::Str(tag_name(*op)));
+                }
+                node.child("lhs", self.expr(lhs))
+                    .child("rhs", self.expr(rhs))
+            },
+            ExprKind::Paren(inner) => self.typed("Paren", span).child("expr", self.expr(inner)),
+            ExprKind::Error => Node::new("Error", span),
+        }
+    }
+}
+
+// 运算符和类型关键字的源代码形式
+fn tag_name(tag: Tag) -> String {
+    Catalog::current().token_name(tag).to_string()
+}
+
+// 类型说明符的源代码形式，结构体、联合和枚举带有标签名，如struct node，类型名为其名称
+fn spec_name(ty: &TypeSpec) -> String {
+    if let Some(alias) = &ty.alias {
+        return alias.name.clone();
+    }
+    if !ty.words.is_empty() {
+        return ty.words.iter().map(|t| tag_name(*t)).collect::<Vec<_>>().join(" ");
+    }
+    let tag = ty.record.as_ref().and_then(|r| r.name.as_ref()).or(ty.enumer.as_ref().and_then(|e| e.name.as_ref()));
+    match tag {
+        Some(name) => format!("{} {}", tag_name(ty.tag), name.name),
+        None => tag_name(ty.tag),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast_dump::AstDump;
+    use crate::diagnostic::DiagnosticSink;
+    use crate::lower::compile;
+
+    fn dump(source: &str, json: bool) -> String {
+        let diag = DiagnosticSink::new();
+        let compiled = compile(source, &diag);
+        assert!(diag.diagnostics().is_empty());
+
+        let mut out = vec![];
+        let dumper = AstDump::new(&compiled.types);
+        if json {
+            dumper.write_json(&compiled.unit, &mut out).unwrap();
+        } else {
+            dumper.write_text(&compiled.unit, &mut out).unwrap();
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_dump_text() {
+        let text = dump("int g[4];\nint main() {\n    return g[1] + 2;\n}\n", false);
+        assert_eq!(text, "\
+TranslationUnit <1:1-4:1>
+  Decl extern=false spec=\"int\" <1:1-1:9>
+    VarDecl name=\"g\" <1:5-1:8> : int[4]
+      declarator: ArrayDecl <1:5-1:8>
+        len: Literal value=4 <1:7-1:7>
+  Function extern=false ret=\"int\" name=\"main\" <2:1-4:1>
+    body: Block <2:12-4:1>
+      Return <3:5-3:20>
+        value: Binary op=\"+\" <3:12-3:19> : int
+          lhs: Index <3:12-3:15> : int
+            base: Ident name=\"g\" <3:12-3:12> : int[4]
+            index: Literal value=1 <3:14-3:14> : int
+          rhs: Literal value=2 <3:19-3:19> : int
+");
+    }
+
+    #[test]
+    fn test_dump_json() {
+        let json = dump("void f(char *s) { while (*s) s++; }\n", true);
+        assert!(json.starts_with("{\"kind\":\"TranslationUnit\","));
+        assert!(json.contains("{\"kind\":\"Param\",\"role\":\"param\",\"span\":{\"start\":7,\"end\":14,\"line\":1,\"column\":8,\"end_line\":1,\"end_column\":14},\"type\":\"char*\",\"spec\":\"char\""));
+        assert!(json.contains("{\"kind\":\"Unary\",\"role\":\"cond\""));
+        assert!(json.contains("\"kind\":\"Postfix\",\"role\":\"expr\""));
+    }
+
+    #[test]
+    fn test_dump_str_type() {
+        // 字符串常量的类型包括结尾的0，字符数组由字符串推断长度时同样加1
+        let text = dump("char s[] = \"abc\";\nchar *p = \"x\";\n", false);
+        assert!(text.contains("VarDecl name=\"s\" <1:6-1:16> : char[4]\n"));
+        assert!(text.contains("init: Literal value=\"x\" <2:11-2:13> : char[2]\n"));
+    }
+
+    #[test]
+    fn test_dump_declarator() {
+        // 声明符由外到内嵌套，括号改变指针和数组的结合顺序
+        let text = dump("int (*p)[4];
+int *a[4];
+", false);
+        assert!(text.contains("VarDecl name=\"p\" <1:5-1:11> : int(*)[4]\n      declarator: ArrayDecl <1:5-1:11>\n        len: Literal value=4 <1:10-1:10>\n        inner: PointerDecl <1:5-1:8>\n"));
+        assert!(text.contains("VarDecl name=\"a\" <2:5-2:9> : int*[4]\n      declarator: PointerDecl <2:5-2:9>\n        inner: ArrayDecl <2:6-2:9>\n"));
+    }
+}
@@ -0,0 +1,7 @@
+use std::env;
+use std::process;
+use compiler::format::run;
+
+fn main() {
+    process::exit(run(env::args().skip(1)));
+}
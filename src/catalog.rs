@@ -0,0 +1,413 @@
+/*
+	消息目录，提供各语言的错误信息和记号名称
+*/
+use std::env;
+use std::sync::Mutex;
+use lazy_static::lazy_static;
+use crate::common::{LexError, PreError, SemError, SynError, Tag, Warning};
+use crate::diagnostic::{ErrorCode, Severity};
+
+lazy_static! {
+    static ref CURRENT: Mutex<Option<Lang>> = Mutex::new(None);
+}
+
+// 消息语言
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Lang {
+    En,                         // 英文
+    Zh,                         // 中文
+}
+
+impl Lang {
+    // 解析语言名称，支持en、zh以及zh_CN.UTF-8这样的locale写法
+    pub fn parse(name: &str) -> Option<Lang> {
+        let name = name.trim().to_ascii_lowercase();
+        if name.starts_with("zh") {
+            Some(Lang::Zh)
+        } else if name.starts_with("en") || name == "c" || name == "posix" {
+            Some(Lang::En)
+        } else {
+            None
+        }
+    }
+
+    // 按LC_ALL、LC_MESSAGES、LANG的顺序读取环境变量，默认为英文
+    pub fn from_env() -> Lang {
+        Lang::first(["LC_ALL", "LC_MESSAGES", "LANG"].iter().filter_map(|key| env::var(key).ok()))
+    }
+
+    // 取第一个能解析的语言名称，空值和无法解析的值(如C.UTF-8)跳过
+    fn first<I: IntoIterator<Item = String>>(values: I) -> Lang {
+        values.into_iter()
+            .find_map(|value| Lang::parse(&value))
+            .unwrap_or(Lang::En)
+    }
+}
+
+// 设置全局消息语言
+pub fn set_lang(lang: Lang) {
+    *CURRENT.lock().unwrap() = Some(lang);
+}
+
+// 获取全局消息语言，未设置时读取环境变量
+pub fn lang() -> Lang {
+    let mut current = CURRENT.lock().unwrap();
+    *current.get_or_insert_with(Lang::from_env)
+}
+
+// 错误信息串
+const LEX_ERROR_ZH: [&str; 10] = ["字符串丢失右引号",
+                                 "二进制数没有实体数据",
+                                 "十六进制数没有实体数据",
+                                 "字符丢失右单引号",
+                                 "不支持空字符",
+                                 "错误的或运算符",
+                                 "多行注释没有正常结束",
+                                 "词法记号不存在",
+                                 "浮点数的指数没有数字",
+                                 "整数常量超出范围"];
+
+const LEX_ERROR_EN: [&str; 10] = ["missing closing quote in string literal",
+                                 "binary literal has no digits",
+                                 "hexadecimal literal has no digits",
+                                 "missing closing quote in character literal",
+                                 "empty character literal",
+                                 "invalid or operator",
+                                 "unterminated block comment",
+                                 "unknown token",
+                                 "exponent has no digits",
+                                 "integer literal is too large"];
+
+const SYN_ERROR_ZH: [&str; 15] = ["类型", "标识符", "数组长度",
+                                  "常量", "逗号", "分号", "=",
+                                  "冒号", "while", "(", ")",
+                                  "[", "]", "{", "}"];
+
+const SYN_ERROR_EN: [&str; 15] = ["type", "identifier", "array length",
+                                  "literal", "comma", "semicolon", "=",
+                                  "colon", "while", "(", ")",
+                                  "[", "]", "{", "}"];
+
+const SEM_ERROR_ZH: [&str; 34] = ["变量重定义",										//附加名称信息
+                                  "函数重定义",
+                                  "变量未声明",
+                                  "函数未声明",
+                                  "函数声明与定义不匹配",
+                                  "函数行参实参不匹配",
+                                  "变量声明时不允许初始化",
+                                  "函数定义不能声明extern",
+                                  "数组长度应该是正整数",
+                                  "变量初始化类型错误",
+                                  "全局变量初始化值不是常量",
+                                  "变量不能声明为void类型",					//没有名称信息
+                                  "无效的左值表达式",
+                                  "赋值表达式类型不兼容",
+                                  "表达式运算对象不能是基本类型",
+                                  "表达式运算对象不是基本类型",
+                                  "数组索引运算类型错误",
+                                  "void的函数返回值不能参与表达式运算",
+                                  "break语句不能出现在循环或switch语句之外",
+                                  "continue不能出现在循环之外",
+                                  "return语句和函数返回值类型不匹配",
+                                  "结构体或联合重定义",
+                                  "结构体或联合类型不完整",
+                                  "标签与之前声明的种类不匹配",
+                                  "成员重复声明",
+                                  "没有该成员",
+                                  "成员访问的运算对象类型错误",
+                                  "结构体或联合不能参与运算",
+                                  "初始值过多",
+                                  "不是整数常量表达式",
+                                  "类型说明符组合无效",
+                                  "运算对象不能是浮点类型",
+                                  "整数常量超出int的范围",
+                                  "变量不能是函数类型"];
+
+const SEM_ERROR_EN: [&str; 34] = ["redefinition of variable",
+                                  "redefinition of function",
+                                  "use of undeclared variable",
+                                  "call to undeclared function",
+                                  "function declaration does not match its definition",
+                                  "arguments do not match the function parameters",
+                                  "declaration cannot have an initializer",
+                                  "function definition cannot be extern",
+                                  "array length must be a positive integer",
+                                  "variable initializer has the wrong type",
+                                  "global variable initializer is not a constant",
+                                  "variable cannot have type void",
+                                  "expression is not assignable",
+                                  "incompatible types in assignment",
+                                  "operand must be a pointer or array",
+                                  "operand must be a scalar value",
+                                  "invalid types in array subscript",
+                                  "void function result cannot be used in an expression",
+                                  "break statement not within a loop or switch",
+                                  "continue statement not within a loop",
+                                  "return value does not match the function return type",
+                                  "redefinition of struct or union",
+                                  "incomplete struct or union type",
+                                  "tag does not match the previous declaration",
+                                  "duplicate member",
+                                  "no such member",
+                                  "member reference base is not a struct or union",
+                                  "struct or union value cannot be used in this expression",
+                                  "excess elements in initializer",
+                                  "expression is not an integer constant",
+                                  "invalid combination of type specifiers",
+                                  "operand cannot have floating-point type",
+                                  "integer constant does not fit in int",
+                                  "variable declared as function"];
+
+const PRE_ERROR_ZH: [&str; 14] = ["无效的预处理指令",
+                                  "#include 需要 \"文件名\" 或 <文件名>",
+                                  "找不到包含的文件",
+                                  "#include 嵌套层数过多",
+                                  "缺少宏名称",
+                                  "宏参数列表错误",
+                                  "宏实参个数不匹配",
+                                  "宏调用缺少右括号",
+                                  "#if 条件表达式无效",
+                                  "条件指令没有对应的 #if",
+                                  "条件指令出现在 #else 之后",
+                                  "条件指令缺少 #endif",
+                                  "#line 指令无效",
+                                  "#error"];
+
+const PRE_ERROR_EN: [&str; 14] = ["invalid preprocessing directive",
+                                  "#include expects \"FILENAME\" or <FILENAME>",
+                                  "file not found",
+                                  "#include nested too deeply",
+                                  "macro name missing",
+                                  "invalid macro parameter list",
+                                  "wrong number of arguments for macro",
+                                  "unterminated argument list invoking macro",
+                                  "invalid #if expression",
+                                  "conditional directive without #if",
+                                  "conditional directive after #else",
+                                  "unterminated conditional directive",
+                                  "invalid #line directive",
+                                  "#error"];
+
+const WARNING_ZH: [&str; 1] = ["定义后没有使用"];
+
+const WARNING_EN: [&str; 1] = ["unused variable"];
+
+// 记号名称，标点和关键字两种语言相同
+const TOKEN_NAME: [&str; 76] = [
+    "error",                                      // 错误，异常，结束标记等
+    "",                                           // 文件结束
+    "",                                           // 标识符
+    "int", "char", "void",                        // 数据类型
+    "extern",                                     // extern
+    "", "", "",                                   // 字面量
+    "!", "&",                                    // 单目运算 ! - & *
+    "+", "-", "*", "/", "%",                     // 算术运算符
+    "++","--",
+    ">", ">=", "<", "<=", "==", "!=",            // 比较运算符
+    "&&", "||",                                  // 逻辑运算
+    "(", ")",                                    // ()
+    "[", "]",                                    // []
+    "{", "}",                                    // {}
+    ",", ":", ";",                               // 逗号,冒号,分号
+    "=",                                         // 赋值
+    "if", "else",                                // if-else
+    "switch", "case", "default",                 // swicth-case-deault
+    "while", "do", "for",                        // 循环
+    "break", "continue", "return",               // break, continue, return
+    "struct", "union",                           // 结构体和联合
+    ".", "->",                                   // 成员访问
+    "enum", "typedef",                           // 枚举和类型名
+    "short", "long", "signed", "unsigned",       // 整数类型
+    "float", "double",                           // 浮点类型
+    "",                                          // 浮点数字面量
+    "|", "^", "~", "<<", ">>",                   // 位运算符
+    "+=", "-=", "*=", "/=", "%=",                // 复合赋值
+    "&=", "|=", "^=", "<<=", ">>=",
+];
+
+// 附加说明
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Note {
+    PreviousDefinition,         // 之前的定义
+    PreviousDeclaration,        // 之前的声明
+    FunctionDeclared,           // 函数声明位置
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Catalog {
+    lang: Lang,
+}
+
+impl Catalog {
+    pub fn new(lang: Lang) -> Self {
+        Catalog {
+            lang,
+        }
+    }
+
+    // 当前全局语言的消息目录
+    pub fn current() -> Self {
+        Catalog::new(lang())
+    }
+
+    pub fn lang(&self) -> Lang {
+        self.lang
+    }
+
+    // 词法错误信息
+    pub fn lex_error(&self, code: LexError) -> String {
+        match self.lang {
+            Lang::En => LEX_ERROR_EN[code as usize].to_string(),
+            Lang::Zh => LEX_ERROR_ZH[code as usize].to_string(),
+        }
+    }
+
+    // 语法错误信息，偶数错误码表示丢失，奇数表示没有正确匹配
+    pub fn syn_error(&self, code: SynError, token: &str) -> String {
+        let index = code as usize;
+        let lost = index.is_multiple_of(2);
+        match self.lang {
+            Lang::En if lost => format!("missing {} before {}", SYN_ERROR_EN[index / 2], token),
+            Lang::En => format!("expected {} before {}", SYN_ERROR_EN[index / 2], token),
+            Lang::Zh if lost => format!("在 {} 之前丢失 {}", token, SYN_ERROR_ZH[index / 2]),
+            Lang::Zh => format!("在 {} 之前没有正确匹配 {}", token, SYN_ERROR_ZH[index / 2]),
+        }
+    }
+
+    // 语义错误信息，name为相关的变量或函数名称
+    pub fn sem_error(&self, code: SemError, name: &str) -> String {
+        match self.lang {
+            Lang::En if name.is_empty() => SEM_ERROR_EN[code as usize].to_string(),
+            Lang::En => format!("{} '{}'", SEM_ERROR_EN[code as usize], name),
+            Lang::Zh if name.is_empty() => SEM_ERROR_ZH[code as usize].to_string(),
+            Lang::Zh => format!("{} {}", name, SEM_ERROR_ZH[code as usize]),
+        }
+    }
+
+    // 警告信息，name为相关的变量名称
+    pub fn warning(&self, warning: Warning, name: &str) -> String {
+        match self.lang {
+            Lang::En => format!("{} '{}'", WARNING_EN[warning as usize], name),
+            Lang::Zh => format!("{} {}", name, WARNING_ZH[warning as usize]),
+        }
+    }
+
+    // 预处理错误信息，detail为相关的指令、宏或文件名称，#error指令为其后的文本
+    pub fn pre_error(&self, code: PreError, detail: &str) -> String {
+        let message = match self.lang {
+            Lang::En => PRE_ERROR_EN[code as usize],
+            Lang::Zh => PRE_ERROR_ZH[code as usize],
+        };
+        match self.lang {
+            _ if detail.is_empty() => message.to_string(),
+            _ if code == PreError::ErrorDirective => format!("{} {}", message, detail),
+            Lang::En => format!("{} '{}'", message, detail),
+            Lang::Zh => format!("{} {}", detail, message),
+        }
+    }
+
+    // 记号名称
+    pub fn token_name(&self, tag: Tag) -> &'static str {
+        match (self.lang, tag) {
+            (Lang::En, Tag::END) => "end of file",
+            (Lang::En, Tag::ID) => "identifier",
+            (Lang::En, Tag::NUM) => "number",
+            (Lang::En, Tag::REAL) => "floating number",
+            (Lang::En, Tag::CH) => "character",
+            (Lang::En, Tag::STR) => "string",
+            (Lang::Zh, Tag::END) => "文件结尾",
+            (Lang::Zh, Tag::ID) => "标识符",
+            (Lang::Zh, Tag::NUM) => "数字",
+            (Lang::Zh, Tag::REAL) => "浮点数",
+            (Lang::Zh, Tag::CH) => "字符",
+            (Lang::Zh, Tag::STR) => "字符串",
+            _ => TOKEN_NAME[tag as usize],
+        }
+    }
+
+    // 附加说明
+    pub fn note(&self, note: Note) -> &'static str {
+        match (self.lang, note) {
+            (Lang::En, Note::PreviousDefinition) => "previously defined here",
+            (Lang::En, Note::PreviousDeclaration) => "previously declared here",
+            (Lang::En, Note::FunctionDeclared) => "function declared here",
+            (Lang::Zh, Note::PreviousDefinition) => "此处已有定义",
+            (Lang::Zh, Note::PreviousDeclaration) => "此处已有声明",
+            (Lang::Zh, Note::FunctionDeclared) => "函数在此处声明",
+        }
+    }
+
+    // 错误类别名称
+    pub fn kind(&self, code: ErrorCode) -> &'static str {
+        match (self.lang, code) {
+            (Lang::En, ErrorCode::Lex(_)) => "lexical error",
+            (Lang::En, ErrorCode::Syn(_)) => "syntax error",
+            (Lang::En, ErrorCode::Sem(_)) => "semantic error",
+            (Lang::En, ErrorCode::Pre(_)) => "preprocessing error",
+            (Lang::Zh, ErrorCode::Lex(_)) => "词法错误",
+            (Lang::Zh, ErrorCode::Syn(_)) => "语法错误",
+            (Lang::Zh, ErrorCode::Sem(_)) => "语义错误",
+            (Lang::Zh, ErrorCode::Pre(_)) => "预处理错误",
+        }
+    }
+
+    // 严重程度名称
+    pub fn severity(&self, severity: Severity) -> &'static str {
+        match (self.lang, severity) {
+            (Lang::En, _) => severity.name(),
+            (Lang::Zh, Severity::Error) => "错误",
+            (Lang::Zh, Severity::Warning) => "警告",
+            (Lang::Zh, Severity::Note) => "提示",
+        }
+    }
+
+    // 位置描述
+    pub fn location(&self, file_name: &str, line: usize, col: usize) -> String {
+        match self.lang {
+            Lang::En => format!("{}:{}:{}", file_name, line, col),
+            Lang::Zh => format!("{}<{}行,{}列>", file_name, line, col),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::catalog::{Catalog, Lang};
+    use crate::common::LexError::OrNoPair;
+    use crate::common::SemError::VarReDef;
+    use crate::common::SynError::{SemiconLost, SemiconWrong};
+    use crate::common::Tag::{ID, SEMICON};
+    use crate::common::Warning::UnusedVariable;
+
+    #[test]
+    fn test_catalog_messages() {
+        let en = Catalog::new(Lang::En);
+        let zh = Catalog::new(Lang::Zh);
+
+        assert_eq!(en.lex_error(OrNoPair), "invalid or operator");
+        assert_eq!(zh.lex_error(OrNoPair), "错误的或运算符");
+        assert_eq!(en.syn_error(SemiconLost, "}"), "missing semicolon before }");
+        assert_eq!(zh.syn_error(SemiconWrong, "}"), "在 } 之前没有正确匹配 分号");
+        assert_eq!(en.sem_error(VarReDef, "x"), "redefinition of variable 'x'");
+        assert_eq!(zh.sem_error(VarReDef, "x"), "x 变量重定义");
+        assert_eq!(en.warning(UnusedVariable, "x"), "unused variable 'x'");
+        assert_eq!(zh.warning(UnusedVariable, "x"), "x 定义后没有使用");
+        assert_eq!(en.token_name(ID), "identifier");
+        assert_eq!(zh.token_name(SEMICON), ";");
+    }
+
+    #[test]
+    fn test_parse_lang() {
+        assert_eq!(Lang::parse("zh_CN.UTF-8"), Some(Lang::Zh));
+        assert_eq!(Lang::parse("en_US"), Some(Lang::En));
+        assert_eq!(Lang::parse("C"), Some(Lang::En));
+        assert_eq!(Lang::parse("fr_FR"), None);
+    }
+
+    #[test]
+    fn test_first_lang() {
+        let values = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(Lang::first(values(&["", "C.UTF-8", "zh_CN.UTF-8"])), Lang::Zh);
+        assert_eq!(Lang::first(values(&["fr_FR"])), Lang::En);
+    }
+}
@@ -31,21 +31,35 @@ pub enum Tag {
     KwFor,				 // 循环
     KwBreak,
     KwContinue,
-    KwReturn         // break, continue, return
+    KwReturn,        // break, continue, return
+    KwStruct,
+    KwUnion,                             // 结构体和联合
+    DOT, ARROW,                          // 成员访问. ->
+    KwEnum, KwTypedef,                   // 枚举和类型名
+    KwShort, KwLong, KwSigned, KwUnsigned,   // 整数类型
+    KwFloat, KwDouble,                   // 浮点类型
+    REAL,                                // 浮点数字面量
+    BOR, XOR, BNOT, SHL, SHR,            // 位运算符| ^ ~ << >>，按位与沿用LEA
+    ADDAS, SUBAS, MULAS, DIVAS, MODAS,   // 复合赋值+= -= *= /= %=
+    BANDAS, BORAS, XORAS, SHLAS, SHRAS,  // &= |= ^= <<= >>=
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum LexError {
     StrNoRQution,		    //字符串没有右引号
     NumBinType,				//2进制数没有实体数据
     NumHexType,				//16进制数没有实体数据
     CharNoRQution,		    //字符没有右引号
     CharNoData,				//字符没有数据
-    OrNoPair,					//||只有一个|
+    OrNoPair,					//||只有一个|，已支持按位或，不再报告
     CommentNoEnd,			    //多行注释没有正常结束
-    TokenNoExist                //不存在的词法记号
+    TokenNoExist,               //不存在的词法记号
+    NumExpType,                 //浮点数的指数没有数字
+    NumOverflow,                //整数超出unsigned long long的范围
 }
 
 // 语法错误码
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum SynError {
     TypeLost,					//类型
     TypeWrong,
@@ -80,6 +94,7 @@ pub enum SynError {
 }
 
 // 语义错误码
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum SemError {
     VarReDef,					//变量重定义
     FunReDef,					//函数重定义
@@ -101,11 +116,60 @@ pub enum SemError {
     ExprIsVoid,				//表达式不能是VOID类型
     BreakErr,					//break不在循环或switch-case中
     ContinueErr,				//continue不在循环中
-    ReturnErr                    //return语句和函数返回值类型不匹配
+    ReturnErr,                   //return语句和函数返回值类型不匹配
+    RecordReDef,                 //结构体或联合重定义
+    RecordUnDef,                 //结构体或联合类型不完整
+    RecordTagErr,                //标签与之前声明的种类不匹配
+    MemberReDef,                 //成员重复声明
+    MemberUnDec,                 //没有该成员
+    MemberOpErr,                 //成员访问的运算对象类型错误
+    ExprIsRecord,                //结构体或联合不能参与运算
+    InitTooMany,                 //初始值过多
+    NotConstExpr,                //不是整数常量表达式
+    TypeSpecErr,                 //类型说明符组合无效
+    ExprIsFloat,                 //运算对象不能是浮点类型
+    ConstOverflow,               //整数常量超出int的范围
+    VarIsFun,                    //变量不能是函数类型
+}
+
+// 预处理错误码
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PreError {
+    DirectiveUnknown,           //无效的预处理指令
+    IncludeInvalid,             //#include缺少文件名
+    IncludeNotFound,            //找不到包含的文件
+    IncludeNested,              //#include嵌套过深
+    MacroNameLost,              //缺少宏名称
+    MacroParamInvalid,          //宏参数列表错误
+    MacroArgsCount,             //宏实参个数不匹配
+    MacroArgsNoEnd,             //宏调用没有右括号
+    CondInvalid,                //#if条件表达式无效
+    CondNoIf,                   //#elif、#else、#endif没有对应的#if
+    CondAfterElse,              //#elif、#else出现在#else之后
+    CondNoEnd,                  //条件指令没有#endif
+    LineInvalid,                //#line指令无效
+    ErrorDirective              //#error指令
+}
+
+// 警告类别，名称用于-W选项
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Warning {
+    UnusedVariable,             //局部变量定义后没有使用
+}
+
+impl Warning {
+    pub const ALL: [Warning; 1] = [Warning::UnusedVariable];
+
+    // -W选项中的名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            Warning::UnusedVariable => "unused-variable",
+        }
+    }
 }
 
 // 中间代码操作符枚举类型
-#[derive(Clone)]
+#[derive(Clone, Copy, PartialEq)]
 pub enum Operator {
     // 占位指令,默认值
     OpNop,
@@ -127,4 +191,6 @@ pub enum Operator {
     OpJmp, OpJt, OpJf, OpJne,
     // 函数调用
     OpArg, OpProc, OpCall, OpRet, OpRetv,
+    // 位运算
+    OpBitAnd, OpBitOr, OpXor, OpBitNot, OpShl, OpShr,
 }
@@ -0,0 +1,374 @@
+/*
+	诊断信息，统一描述词法、语法、语义错误
+*/
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+use crate::catalog::Catalog;
+use crate::common::{LexError, PreError, SemError, SynError};
+use crate::json::{quote, quote_opt, span_json};
+use crate::span::Span;
+
+// 严重程度
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    Error,                      // 错误
+    Warning,                    // 警告
+    Note,                       // 提示
+}
+
+impl Severity {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+}
+
+// 错误码，直接复用已有的错误枚举
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ErrorCode {
+    Lex(LexError),
+    Syn(SynError),
+    Sem(SemError),
+    Pre(PreError),
+}
+
+impl ErrorCode {
+    // 错误码字符串：词法E1xxx，语法E2xxx，语义E3xxx，预处理E4xxx，编号为枚举中的序号
+    pub fn code(&self) -> String {
+        match self {
+            ErrorCode::Lex(e) => format!("E1{:03}", *e as usize),
+            ErrorCode::Syn(e) => format!("E2{:03}", *e as usize),
+            ErrorCode::Sem(e) => format!("E3{:03}", *e as usize),
+            ErrorCode::Pre(e) => format!("E4{:03}", *e as usize),
+        }
+    }
+}
+
+// 次要标注，指向与错误相关的其他源代码位置
+#[derive(Clone, PartialEq, Debug)]
+pub struct Label {
+    span: Span,
+    message: String,
+}
+
+impl Label {
+    pub fn new(span: Span, message: String) -> Self {
+        Label {
+            span,
+            message,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Diagnostic {
+    severity: Severity,             // 严重程度
+    code: Option<ErrorCode>,        // 错误码
+    flag: Option<String>,           // 警告名称，用于-W选项控制
+    message: String,                // 错误信息
+    file_name: String,              // 所在文件
+    span: Span,                     // 主要位置
+    labels: Vec<Label>,             // 次要标注
+    notes: Vec<String>,             // 附加说明
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: Option<ErrorCode>, message: String, span: Span) -> Self {
+        Diagnostic {
+            severity,
+            code,
+            flag: None,
+            message,
+            file_name: String::new(),
+            span,
+            labels: vec![],
+            notes: vec![],
+        }
+    }
+
+    // 错误
+    pub fn error(code: ErrorCode, message: String, span: Span) -> Self {
+        Diagnostic::new(Severity::Error, Some(code), message, span)
+    }
+
+    // 警告
+    pub fn warning(message: String, span: Span) -> Self {
+        Diagnostic::new(Severity::Warning, None, message, span)
+    }
+
+    // 添加次要标注
+    pub fn with_label(mut self, span: Span, message: String) -> Self {
+        self.labels.push(Label::new(span, message));
+        self
+    }
+
+    // 添加附加说明
+    pub fn with_note(mut self, note: String) -> Self {
+        self.notes.push(note);
+        self
+    }
+
+    // 设置警告名称
+    pub fn with_flag(mut self, flag: &str) -> Self {
+        self.flag = Some(flag.to_string());
+        self
+    }
+
+    // 修改严重程度，-Werror时将警告提升为错误
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    pub fn with_file_name(mut self, file_name: String) -> Self {
+        self.file_name = file_name;
+        self
+    }
+
+    // 改写位置，locate把区间映射为所在文件和文件中的区间，不在同一文件的标注改为附加说明
+    pub fn relocate<F: Fn(Span) -> (String, Span)>(mut self, locate: F) -> Self {
+        if self.span.is_dummy() {
+            return self;
+        }
+
+        let (file_name, span) = locate(self.span);
+        self.file_name = file_name;
+        self.span = span;
+        for label in std::mem::take(&mut self.labels) {
+            if label.span.is_dummy() {
+                self.labels.push(label);
+                continue;
+            }
+            let (file_name, span) = locate(label.span);
+            if file_name == self.file_name {
+                self.labels.push(Label::new(span, label.message));
+            } else {
+                let location = Catalog::current().location(&file_name, span.line(), span.col());
+                self.notes.push(format!("{}: {}", location, label.message));
+            }
+        }
+
+        self
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn code(&self) -> Option<ErrorCode> {
+        self.code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn flag(&self) -> Option<&str> {
+        self.flag.as_deref()
+    }
+
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn labels(&self) -> &[Label] {
+        &self.labels
+    }
+
+    pub fn notes(&self) -> &[String] {
+        &self.notes
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    // 输出为单行JSON对象，次要标注和附加说明都放在related中，说明没有位置时span为null
+    pub fn to_json(&self) -> String {
+        let mut related: Vec<String> = self.labels.iter()
+            .map(|l| format!("{{\"message\":{},\"span\":{}}}", quote(l.message()), span_json(l.span())))
+            .collect();
+        related.extend(self.notes.iter().map(|n| format!("{{\"message\":{},\"span\":null}}", quote(n))));
+
+        format!("{{\"code\":{},\"severity\":{},\"message\":{},\"file\":{},\"span\":{},\"related\":[{}]}}",
+                quote_opt(self.code.map(|c| c.code()).as_deref()),
+                quote(self.severity.name()),
+                quote(&self.message),
+                quote(&self.file_name),
+                span_json(self.span),
+                related.join(","))
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let catalog = Catalog::current();
+        let kind = match (self.severity, self.code) {
+            (Severity::Error, Some(code)) => catalog.kind(code),
+            (severity, _) => catalog.severity(severity),
+        };
+        let code = self.code.map(|c| format!("[{}]", c.code())).unwrap_or_default();
+
+        write!(f, "{} {}{} : {}.", catalog.location(&self.file_name, self.span.line(), self.span.col()), kind, code, self.message)?;
+        for label in self.labels.iter() {
+            write!(f, "\n    {} {}", catalog.location(&self.file_name, label.span().line(), label.span().col()), label.message())?;
+        }
+        for note in self.notes.iter() {
+            write!(f, "\n    = {}", note)?;
+        }
+
+        Ok(())
+    }
+}
+
+struct SinkInner {
+    file_name: String,                  // 当前编译的文件名，填充到没有文件名的诊断中
+    diagnostics: Vec<Diagnostic>,       // 收集到的诊断
+    echo: bool,                         // 收到诊断时是否立即打印
+}
+
+/*
+	诊断信息收集器，克隆得到的句柄共享同一份记录
+*/
+#[derive(Clone)]
+pub struct DiagnosticSink {
+    inner: Rc<RefCell<SinkInner>>,
+}
+
+impl Default for DiagnosticSink {
+    fn default() -> Self {
+        DiagnosticSink::new()
+    }
+}
+
+impl DiagnosticSink {
+    pub fn new() -> Self {
+        DiagnosticSink {
+            inner: Rc::new(RefCell::new(SinkInner {
+                file_name: String::new(),
+                diagnostics: vec![],
+                echo: false,
+            })),
+        }
+    }
+
+    pub fn set_file_name(&self, file_name: String) {
+        self.inner.borrow_mut().file_name = file_name;
+    }
+
+    // 设置为收到诊断时立即打印
+    pub fn set_echo(&self, echo: bool) {
+        self.inner.borrow_mut().echo = echo;
+    }
+
+    // 提交一条诊断
+    pub fn emit(&self, mut diag: Diagnostic) {
+        let mut inner = self.inner.borrow_mut();
+        if diag.file_name.is_empty() {
+            diag.file_name = inner.file_name.clone();
+        }
+        if inner.echo {
+            println!("{}", diag);
+        }
+        inner.diagnostics.push(diag);
+    }
+
+    // 获取所有诊断
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.inner.borrow().diagnostics.clone()
+    }
+
+    // 取出所有诊断并清空
+    pub fn take(&self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.inner.borrow_mut().diagnostics)
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.borrow().diagnostics.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.count(Severity::Error)
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.count(Severity::Warning)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.error_count() > 0
+    }
+
+    fn count(&self, severity: Severity) -> usize {
+        self.inner.borrow().diagnostics.iter().filter(|d| d.severity() == severity).count()
+    }
+
+    // 打印所有诊断
+    pub fn print(&self) {
+        for diag in self.inner.borrow().diagnostics.iter() {
+            println!("{}", diag);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::SemError::VarReDef;
+    use crate::diagnostic::{Diagnostic, DiagnosticSink, ErrorCode};
+    use crate::span::Span;
+
+    #[test]
+    fn test_sink_collect() {
+        let sink = DiagnosticSink::new();
+        sink.set_file_name("a.c".to_string());
+
+        let shared = sink.clone();
+        shared.emit(Diagnostic::error(ErrorCode::Sem(VarReDef), "x 变量重定义".to_string(), Span::new(4, 5, 2, 5, 2, 5))
+            .with_label(Span::new(0, 1, 1, 1, 1, 1), "此处已有定义".to_string()));
+        shared.emit(Diagnostic::warning("未使用的变量".to_string(), Span::dummy()));
+
+        assert_eq!(sink.len(), 2);
+        assert_eq!(sink.error_count(), 1);
+        assert_eq!(sink.warning_count(), 1);
+
+        let diags = sink.take();
+        assert_eq!(diags[0].code().unwrap().code(), "E3000");
+        assert_eq!(diags[0].file_name(), "a.c");
+        assert_eq!(diags[0].labels().len(), 1);
+        assert!(sink.is_empty());
+    }
+
+    #[test]
+    fn test_to_json() {
+        let diag = Diagnostic::error(ErrorCode::Sem(VarReDef), "redefinition of \"x\"".to_string(), Span::new(4, 5, 2, 5, 2, 5))
+            .with_label(Span::new(0, 1, 1, 1, 1, 1), "previously defined here".to_string())
+            .with_note("remove one".to_string())
+            .with_file_name("a.c".to_string());
+
+        assert_eq!(diag.to_json(), "{\"code\":\"E3000\",\"severity\":\"error\",\"message\":\"redefinition of \\\"x\\\"\",\"file\":\"a.c\",\
+                                    \"span\":{\"start\":4,\"end\":5,\"line\":2,\"column\":5,\"end_line\":2,\"end_column\":5},\
+                                    \"related\":[{\"message\":\"previously defined here\",\
+                                    \"span\":{\"start\":0,\"end\":1,\"line\":1,\"column\":1,\"end_line\":1,\"end_column\":1}},\
+                                    {\"message\":\"remove one\",\"span\":null}]}");
+    }
+}
@@ -0,0 +1,377 @@
+/*
+	命令行驱动，解析参数并串联 扫描->词法->语法/语义->代码生成 的流程
+*/
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use crate::ast_dump::AstDump;
+use crate::catalog::{set_lang, Lang};
+use crate::common::Tag::END;
+use crate::common::Warning;
+use crate::diagnostic::{Diagnostic, DiagnosticSink, Severity};
+use crate::lexer::Lexer;
+use crate::lower::compile;
+use crate::preprocess::{Preprocessor, SourceMap};
+use crate::render::{ErrorFormat, Renderer};
+use crate::scanner::Scanner;
+
+pub const EXIT_SUCCESS: i32 = 0;        // 编译成功
+pub const EXIT_FAILURE: i32 = 1;        // 源代码有错误
+pub const EXIT_USAGE: i32 = 2;          // 参数错误或文件读写失败
+
+const USAGE: &str = "usage: compiler [options] <file>...
+
+options:
+  -o <file>                   write output to <file> ('-' for stdout)
+  -S                          emit assembly (default)
+  -E                          preprocess only, print the result
+  -I <dir>                    add <dir> to the include search path
+  --emit=tokens|ast|ast-json|ir|asm
+                              choose what to emit
+  --error-format=human|json   diagnostic output format
+  --lang=en|zh                diagnostic language (default: from LANG)
+  -w                          suppress all warnings
+  -W<name>, -Wno-<name>       enable or disable warning <name> (unused-variable)
+  -Werror[=<name>]            treat all warnings, or warning <name>, as errors
+  -h, --help                  print this help
+
+An input file named '-' is read from stdin.";
+
+// 输出内容
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Emit {
+    Preprocessed,               // 预处理结果
+    Tokens,                     // 词法记号
+    Ast,                        // 语法树，缩进文本
+    AstJson,                    // 语法树，JSON格式
+    Ir,                         // 中间代码
+    Asm,                        // 汇编代码
+}
+
+impl Emit {
+    pub fn parse(name: &str) -> Option<Emit> {
+        match name {
+            "tokens" => Some(Emit::Tokens),
+            "ast" => Some(Emit::Ast),
+            "ast-json" => Some(Emit::AstJson),
+            "ir" => Some(Emit::Ir),
+            "asm" => Some(Emit::Asm),
+            _ => None,
+        }
+    }
+
+    // 调试用的输出，源代码有错误时也照常输出
+    fn is_debug(self) -> bool {
+        matches!(self, Emit::Preprocessed | Emit::Tokens | Emit::Ast | Emit::AstJson)
+    }
+}
+
+// 警告控制选项
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct WarningOptions {
+    ignore_all: bool,               // -w
+    all_errors: bool,               // -Werror
+    disabled: HashSet<String>,      // -Wno-<name>
+    errors: HashSet<String>,        // -Werror=<name>
+}
+
+impl WarningOptions {
+    // 解析-W之后的部分，未知的警告名称返回错误
+    fn parse(&mut self, arg: &str) -> Result<(), String> {
+        let known_warning = |name: &str| Warning::ALL.iter()
+            .find(|w| w.name() == name)
+            .map(|w| w.name().to_string())
+            .ok_or_else(|| format!("unknown warning option '-W{}'", arg));
+        if arg == "error" {
+            self.all_errors = true;
+        } else if arg == "no-error" {
+            self.all_errors = false;
+        } else if arg == "all" || arg == "extra" {
+            self.disabled.clear();
+        } else if let Some(name) = arg.strip_prefix("error=") {
+            self.errors.insert(known_warning(name)?);
+        } else if let Some(name) = arg.strip_prefix("no-error=") {
+            self.errors.remove(&known_warning(name)?);
+        } else if let Some(name) = arg.strip_prefix("no-") {
+            self.disabled.insert(known_warning(name)?);
+        } else {
+            self.disabled.remove(&known_warning(arg)?);
+        }
+        Ok(())
+    }
+
+    // 按选项处理一条诊断，被关闭的警告返回None
+    pub fn apply(&self, diag: Diagnostic) -> Option<Diagnostic> {
+        if diag.severity() != Severity::Warning {
+            return Some(diag);
+        }
+
+        let flag = diag.flag().unwrap_or_default();
+        if self.ignore_all || self.disabled.contains(flag) {
+            None
+        } else if self.all_errors || self.errors.contains(flag) {
+            Some(diag.with_severity(Severity::Error))
+        } else {
+            Some(diag)
+        }
+    }
+}
+
+// 命令行选项
+#[derive(Clone, PartialEq, Debug)]
+pub struct Options {
+    inputs: Vec<String>,            // 输入文件，"-"表示标准输入
+    output: Option<String>,         // 输出文件，"-"表示标准输出
+    include_paths: Vec<String>,     // 包含目录
+    emit: Emit,                     // 输出内容
+    error_format: ErrorFormat,      // 诊断输出格式
+    lang: Option<Lang>,             // 诊断语言
+    warnings: WarningOptions,       // 警告控制
+    help: bool,                     // 打印帮助
+}
+
+impl Options {
+    // 解析命令行参数(不包含程序名)
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Options, String> {
+        let mut options = Options {
+            inputs: vec![],
+            output: None,
+            include_paths: vec![],
+            emit: Emit::Asm,
+            error_format: ErrorFormat::Human,
+            lang: None,
+            warnings: WarningOptions::default(),
+            help: false,
+        };
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "-o" {
+                match args.next() {
+                    Some(output) => options.output = Some(output),
+                    None => return Err("missing file name after '-o'".to_string()),
+                }
+            } else if arg == "-S" {
+                options.emit = Emit::Asm;
+            } else if arg == "-E" {
+                options.emit = Emit::Preprocessed;
+            } else if arg == "-I" {
+                match args.next() {
+                    Some(dir) => options.include_paths.push(dir),
+                    None => return Err("missing directory after '-I'".to_string()),
+                }
+            } else if let Some(dir) = arg.strip_prefix("-I") {
+                options.include_paths.push(dir.to_string());
+            } else if arg == "-w" {
+                options.warnings.ignore_all = true;
+            } else if arg == "-h" || arg == "--help" {
+                options.help = true;
+            } else if let Some(name) = arg.strip_prefix("--emit=") {
+                options.emit = match Emit::parse(name) {
+                    Some(emit) => emit,
+                    None => return Err(format!("unknown emit kind '{}'", name)),
+                };
+            } else if let Some(name) = arg.strip_prefix("--error-format=") {
+                options.error_format = ErrorFormat::parse(name)
+                    .ok_or_else(|| format!("unknown error format '{}'", name))?;
+            } else if let Some(name) = arg.strip_prefix("--lang=") {
+                options.lang = Some(Lang::parse(name).ok_or_else(|| format!("unknown language '{}'", name))?);
+            } else if let Some(output) = arg.strip_prefix("-o") {
+                options.output = Some(output.to_string());
+            } else if let Some(warning) = arg.strip_prefix("-W") {
+                options.warnings.parse(warning)?;
+            } else if arg.starts_with('-') && arg != "-" {
+                return Err(format!("unknown option '{}'", arg));
+            } else {
+                options.inputs.push(arg);
+            }
+        }
+
+        if options.help {
+            return Ok(options);
+        }
+        if options.inputs.is_empty() {
+            return Err("no input files".to_string());
+        }
+        if options.inputs.len() > 1 && options.output.as_deref().is_some_and(|o| o != "-") {
+            return Err("cannot specify '-o' with multiple input files".to_string());
+        }
+
+        Ok(options)
+    }
+
+    // 输入文件对应的输出位置，None表示标准输出
+    fn output_path(&self, input: &str) -> Option<String> {
+        match self.output.as_deref() {
+            Some("-") => None,
+            Some(output) => Some(output.to_string()),
+            None if self.emit == Emit::Asm && input != "-" => {
+                let stem = Path::new(input).file_stem().map_or("a".into(), |s| s.to_string_lossy());
+                Some(format!("{}.s", stem))
+            },
+            None => None,
+        }
+    }
+}
+
+// 命令行入口，返回进程退出码
+pub fn run<I: IntoIterator<Item = String>>(args: I) -> i32 {
+    let options = match Options::parse(args) {
+        Ok(options) => options,
+        Err(msg) => {
+            eprintln!("error: {}\n\n{}", msg, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return EXIT_SUCCESS;
+    }
+    if let Some(lang) = options.lang {
+        set_lang(lang);
+    }
+
+    let mut status = EXIT_SUCCESS;
+    for input in options.inputs.iter() {
+        match compile_file(&options, input) {
+            Ok(true) => {},
+            Ok(false) => status = status.max(EXIT_FAILURE),
+            Err(e) => {
+                eprintln!("error: {}: {}", input, e);
+                status = EXIT_USAGE;
+            }
+        }
+    }
+
+    status
+}
+
+// 编译一个文件，没有错误时返回true
+fn compile_file(options: &Options, input: &str) -> Result<bool, failure::Error> {
+    let scanner = if input == "-" {
+        Scanner::from_stdin()?
+    } else {
+        Scanner::new(input.to_string())?
+    };
+    let diag = DiagnosticSink::new();
+    diag.set_file_name(scanner.file_name());
+
+    // 预处理，错误信息已经是原文件中的位置
+    let mut pre = Preprocessor::new(diag.clone());
+    for dir in options.include_paths.iter() {
+        pre.add_include_path(dir);
+    }
+    let pp = pre.run(&scanner.file_name(), scanner.source());
+    let mut diags = diag.take();
+    let mut scanner = Scanner::with_name(scanner.file_name(), pp.text);
+
+    let mut out: Vec<u8> = vec![];
+    match options.emit {
+        Emit::Preprocessed => out.extend_from_slice(scanner.source().as_bytes()),
+        Emit::Tokens => emit_tokens(&mut scanner, &diag, &pp.map, &mut out)?,
+        emit => {
+            let mut compiled = compile(scanner.source(), &diag);
+            let dumper = AstDump::new(&compiled.types).with_source_map(&pp.map);
+            match emit {
+                Emit::Ast => dumper.write_text(&compiled.unit, &mut out)?,
+                Emit::AstJson => dumper.write_json(&compiled.unit, &mut out)?,
+                Emit::Ir => compiled.ir.get_sym_tab().print_ir(&mut out)?,
+                _ => compiled.ir.get_sym_tab().gen_asm(&mut out)?,
+            }
+        },
+    }
+
+    // 输出诊断信息，编译阶段的位置映射回原文件
+    diags.extend(diag.take().into_iter().map(|d| pp.map.map_diagnostic(d)));
+    let mut success = true;
+    for d in diags.into_iter().filter_map(|d| options.warnings.apply(d)) {
+        success &= !d.is_error();
+        let renderer = Renderer::new(pp.map.source(d.file_name()));
+        eprintln!("{}", renderer.render_as(&d, options.error_format));
+    }
+
+    // 有错误时不生成输出文件，预处理结果、词法记号和语法树总是输出以便排查
+    if success || options.emit.is_debug() {
+        match options.output_path(input) {
+            Some(path) => fs::write(path, &out)?,
+            None => io::stdout().write_all(&out)?,
+        }
+    }
+
+    Ok(success)
+}
+
+// 输出词法记号，每行一个，位置是原文件中的行列
+fn emit_tokens(scanner: &mut Scanner, diag: &DiagnosticSink, map: &SourceMap, out: &mut dyn Write) -> io::Result<()> {
+    let mut lexer = Lexer::new(scanner, diag.clone());
+    loop {
+        let token = lexer.tokenize();
+        let (_, span) = map.map_span(token.get_span());
+        writeln!(out, "{}:{}\t{}", span.line(), span.col(), token.to_string())?;
+        if token.get_tag() == END {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use crate::diagnostic::{Diagnostic, Severity};
+    use crate::driver::{Emit, Options, EXIT_FAILURE, EXIT_USAGE, run};
+    use crate::render::ErrorFormat;
+    use crate::span::Span;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = parse(&["--emit=ir", "--error-format=json", "-o", "-", "a.c", "-Werror", "-Wno-unused-variable"]).unwrap();
+        assert_eq!(options.inputs, vec!["a.c".to_string()]);
+        assert_eq!(options.emit, Emit::Ir);
+        assert_eq!(options.error_format, ErrorFormat::Json);
+        assert_eq!(options.output_path("a.c"), None);
+        assert_eq!(parse(&["-S", "dir/a.c"]).unwrap().output_path("dir/a.c"), Some("a.s".to_string()));
+
+        let pre = parse(&["-E", "-I", "inc", "-Isys", "a.c"]).unwrap();
+        assert_eq!(pre.emit, Emit::Preprocessed);
+        assert_eq!(pre.include_paths, vec!["inc".to_string(), "sys".to_string()]);
+        assert_eq!(pre.output_path("a.c"), None);
+
+        assert!(parse(&[]).is_err());
+        assert!(parse(&["a.c", "-I"]).is_err());
+        assert!(parse(&["--emit=obj", "a.c"]).is_err());
+        assert!(parse(&["-oout.s", "a.c", "b.c"]).is_err());
+
+        let warning = Diagnostic::warning("unused".to_string(), Span::dummy()).with_flag("unused-variable");
+        assert_eq!(options.warnings.apply(warning.clone()), None);
+        let options = parse(&["-Werror", "a.c"]).unwrap();
+        assert_eq!(options.warnings.apply(warning.clone()).unwrap().severity(), Severity::Error);
+        let options = parse(&["-Werror=unused-variable", "-Wall", "a.c"]).unwrap();
+        assert_eq!(options.warnings.apply(warning).unwrap().severity(), Severity::Error);
+
+        // 未知的警告名称是参数错误
+        assert_eq!(parse(&["-Wfoo", "a.c"]), Err("unknown warning option '-Wfoo'".to_string()));
+        assert!(parse(&["-Wno-foo", "a.c"]).is_err());
+        assert!(parse(&["-Werror=foo", "a.c"]).is_err());
+        assert_eq!(run(vec!["-Wfoo".to_string(), "a.c".to_string()]), EXIT_USAGE);
+    }
+
+    #[test]
+    fn test_missing_operand_exit() {
+        // 缺少运算对象是语法错误，不生成输出，退出码为1
+        for (i, source) in ["int main() { int a; a = 1 + ; return a; }", "int main() { int a; a = ; return a; }"].iter().enumerate() {
+            let input = env::temp_dir().join(format!("compiler_missing_operand_{}_{}.c", std::process::id(), i));
+            fs::write(&input, source).unwrap();
+            let status = run(vec![input.to_string_lossy().to_string(), "-o".to_string(), "-".to_string()]);
+            fs::remove_file(&input).unwrap();
+            assert_eq!(status, EXIT_FAILURE);
+        }
+    }
+}
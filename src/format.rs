@@ -0,0 +1,802 @@
+/*
+	源代码格式化：按统一的缩进、大括号位置和运算符空格重新输出语法树，注释原样保留
+*/
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use crate::ast::{Block, BlockItem, Case, Decl, Declarator, DeclaratorKind, EnumSpec, Expr, ExprKind, ForInit, FunctionDef, Initializer, Item, Param, RecordSpec, Stmt, StmtKind, TranslationUnit, TypeSpec, VarDecl};
+use crate::catalog::Catalog;
+use crate::common::Tag;
+use crate::diagnostic::{Diagnostic, DiagnosticSink};
+use crate::driver::{EXIT_FAILURE, EXIT_SUCCESS, EXIT_USAGE};
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::render::Renderer;
+use crate::scanner::Scanner;
+use crate::token::Comment;
+
+const INDENT: &str = "    ";
+
+const USAGE: &str = "usage: cfmt [options] <file>...
+
+options:
+  --check                     do not write, exit with 1 if any file would change
+  -i                          rewrite files in place instead of printing to stdout
+  -h, --help                  print this help
+
+An input file named '-' is read from stdin.";
+
+// 格式化一段源代码，有词法或语法错误时返回诊断信息
+pub fn format_source(source: &str) -> Result<String, Vec<Diagnostic>> {
+    let mut scanner = Scanner::from_source(source);
+    format_scanner(&mut scanner, DiagnosticSink::new())
+}
+
+fn format_scanner(scanner: &mut Scanner, diag: DiagnosticSink) -> Result<String, Vec<Diagnostic>> {
+    let mut lexer = Lexer::new(scanner, diag.clone());
+    let mut parser = Parser::new(&mut lexer);
+    let unit = parser.analyze();
+    let comments = parser.take_comments();
+    if !diag.diagnostics().is_empty() {
+        return Err(diag.take());
+    }
+
+    let mut fmt = Formatter::new(scanner.source(), comments);
+    fmt.unit(&unit);
+    Ok(fmt.out)
+}
+
+// cfmt命令行入口，返回进程退出码
+pub fn run<I: IntoIterator<Item = String>>(args: I) -> i32 {
+    let mut check = false;
+    let mut in_place = false;
+    let mut inputs = vec![];
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            "-i" => in_place = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return EXIT_SUCCESS;
+            },
+            "-" => inputs.push(arg),
+            _ if arg.starts_with('-') => {
+                eprintln!("error: unknown option '{}'\n\n{}", arg, USAGE);
+                return EXIT_USAGE;
+            },
+            _ => inputs.push(arg),
+        }
+    }
+    if inputs.is_empty() {
+        eprintln!("error: no input files\n\n{}", USAGE);
+        return EXIT_USAGE;
+    }
+
+    let mut status = EXIT_SUCCESS;
+    for input in inputs.iter() {
+        match format_file(input, check, in_place) {
+            Ok(true) => {},
+            Ok(false) => status = status.max(EXIT_FAILURE),
+            Err(e) => {
+                eprintln!("error: {}: {}", input, e);
+                status = EXIT_USAGE;
+            }
+        }
+    }
+
+    status
+}
+
+// 格式化一个文件，源代码有错误或检查模式下需要修改时返回false
+fn format_file(input: &str, check: bool, in_place: bool) -> Result<bool, failure::Error> {
+    let mut scanner = if input == "-" {
+        Scanner::from_stdin()?
+    } else {
+        Scanner::new(input.to_string())?
+    };
+    let diag = DiagnosticSink::new();
+    diag.set_file_name(scanner.file_name());
+
+    let formatted = match format_scanner(&mut scanner, diag) {
+        Ok(formatted) => formatted,
+        Err(diags) => {
+            let renderer = Renderer::new(scanner.source());
+            for d in diags.iter() {
+                eprintln!("{}", renderer.render(d));
+            }
+            return Ok(false);
+        }
+    };
+
+    if check {
+        if formatted != scanner.source() {
+            println!("{}", scanner.file_name());
+            return Ok(false);
+        }
+    } else if in_place && input != "-" {
+        if formatted != scanner.source() {
+            fs::write(input, formatted)?;
+        }
+    } else {
+        io::stdout().write_all(formatted.as_bytes())?;
+    }
+
+    Ok(true)
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    comments: Vec<Comment>,     // 源代码中的注释，按位置排序
+    next: usize,                // 下一个未输出的注释
+    inline: RefCell<HashSet<usize>>,    // 已经在表达式中输出的注释
+    out: String,
+    depth: usize,               // 当前缩进层次
+    last: usize,                // 最后输出内容在源代码中的结束位置，用于保留空行
+    fresh: bool,                // 刚进入语句块，还没有输出内容
+}
+
+impl<'a> Formatter<'a> {
+    fn new(source: &'a str, comments: Vec<Comment>) -> Self {
+        Formatter {
+            source,
+            comments,
+            next: 0,
+            inline: RefCell::new(HashSet::new()),
+            out: String::new(),
+            depth: 0,
+            last: 0,
+            fresh: true,
+        }
+    }
+
+    // 输出一行
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.depth {
+            self.out.push_str(INDENT);
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+        self.fresh = false;
+    }
+
+    // 源代码中start之前有空行时保留一个空行，语句块开头的空行去掉
+    fn blank(&mut self, start: usize) {
+        if self.fresh || start < self.last {
+            return;
+        }
+        let gap = &self.source[self.last..start];
+        let blank = gap.split('\n').skip(1).collect::<Vec<_>>();
+        if blank.len() > 1 && blank[..blank.len() - 1].iter().any(|l| l.trim().is_empty()) {
+            self.out.push('\n');
+        }
+    }
+
+    // 输出start之前的注释，每个注释单独一行
+    fn comments_before(&mut self, start: usize) {
+        while self.next < self.comments.len() && self.comments[self.next].get_span().start() < start {
+            let comment = self.comments[self.next].clone();
+            self.next += 1;
+            if self.inline.borrow().contains(&(self.next - 1)) {
+                continue;
+            }
+            self.blank(comment.get_span().start());
+            if comment.get_text().starts_with('#') {    // 预处理指令顶格输出
+                self.out.push_str(comment.get_text());
+                self.out.push('\n');
+                self.fresh = false;
+            } else {
+                self.line(comment.get_text());
+            }
+            self.last = comment.get_span().end();
+        }
+    }
+
+    // 与刚输出的内容位于同一行的注释，追加到行尾，下一个记号之后的注释不属于该节点
+    fn trailing(&mut self, pos: usize, line: usize) {
+        let limit = self.next_token(pos);
+        self.trailing_before(pos, line, limit);
+    }
+
+    // pos之后第一个记号的位置，跳过空白和注释
+    fn next_token(&self, pos: usize) -> usize {
+        let mut pos = pos;
+        loop {
+            let rest = &self.source[pos..];
+            pos += rest.len() - rest.trim_start().len();
+            match self.comments.iter().find(|c| c.get_span().start() == pos) {
+                Some(comment) => pos = comment.get_span().end(),
+                None => return pos,
+            }
+        }
+    }
+
+    // 只追加limit之前的注释，之后的注释属于同一行的下一个节点
+    fn trailing_before(&mut self, pos: usize, line: usize, limit: usize) {
+        while self.next < self.comments.len() {
+            if self.inline.borrow().contains(&self.next) {
+                self.next += 1;
+                continue;
+            }
+            let span = self.comments[self.next].get_span();
+            let directive = self.comments[self.next].get_text().starts_with('#');
+            if directive || span.start() < pos || span.start() >= limit || span.line() != line || span.end_line() != line {
+                break;
+            }
+            self.out.pop();
+            self.out.push(' ');
+            self.out.push_str(self.comments[self.next].get_text());
+            self.out.push('\n');
+            self.last = span.end();
+            self.next += 1;
+        }
+    }
+
+    // 输出一个节点之前的注释和空行
+    fn before(&mut self, start: usize) {
+        self.comments_before(start);
+        self.blank(start);
+    }
+
+    fn unit(&mut self, unit: &TranslationUnit) {
+        let mut prev_fun = false;
+        for item in unit.items.iter() {
+            let fun = match item {
+                Item::Decl(_) => false,
+                Item::Function(fun) => fun.body.is_some(),
+            };
+            // 函数定义前后总是空一行，函数前的注释跟在空行后面
+            if (fun || prev_fun) && !self.out.is_empty() {
+                if !self.out.ends_with("\n\n") {
+                    self.out.push('\n');
+                }
+                self.fresh = true;
+            }
+            match item {
+                Item::Decl(decl) => self.decl(decl),
+                Item::Function(fun) => self.function(fun),
+            }
+            prev_fun = fun;
+        }
+
+        self.comments_before(usize::MAX);
+    }
+
+    fn block_items(&mut self, items: &[BlockItem]) {
+        for item in items.iter() {
+            match item {
+                BlockItem::Decl(decl) => self.decl(decl),
+                BlockItem::Stmt(stmt) => self.stmt(stmt),
+            }
+        }
+    }
+
+    // 大括号之间的内容，不包括大括号所在行
+    fn block_inner(&mut self, block: &Block) {
+        let first = block.items.first().map_or(block.span.end(), |item| match item {
+            BlockItem::Decl(decl) => decl.span.start(),
+            BlockItem::Stmt(stmt) => stmt.span.start(),
+        });
+        self.trailing_before(block.span.start(), block.span.line(), first);
+        self.depth += 1;
+        self.fresh = true;
+        self.last = block.span.start() + 1;
+        self.block_items(&block.items);
+        self.comments_before(block.span.end() - 1);
+        self.depth -= 1;
+    }
+
+    // 语句块结束的大括号
+    fn close(&mut self, text: &str, span_end: usize, line: usize) {
+        self.line(text);
+        self.last = span_end;
+        self.trailing(span_end, line);
+    }
+}
+
+// 声明与定义
+impl<'a> Formatter<'a> {
+    // 每个变量单独声明一行
+    fn decl(&mut self, decl: &Decl) {
+        self.before(decl.span.start());
+        let prefix = self.decl_prefix(decl);
+        if let Some(record) = decl.ty.record.as_ref().filter(|r| r.members.is_some()) {
+            self.record(decl, record);
+        } else if let Some(enumer) = decl.ty.enumer.as_ref().filter(|e| e.items.is_some()) {
+            self.enumer(decl, enumer);
+        } else if decl.vars.is_empty() {
+            self.line(&format!("{};", prefix.trim_end()));     // struct S;
+        } else {
+            for var in decl.vars.iter() {
+                self.line(&format!("{}{};", prefix, self.var_decl(var)));
+            }
+        }
+        self.last = decl.span.end();
+        self.trailing(decl.span.end(), decl.span.end_line());
+    }
+
+    // 结构体或联合的定义，每个成员一行，声明的变量跟在右大括号后面
+    fn record(&mut self, decl: &Decl, record: &RecordSpec) {
+        let members = record.members.as_deref().unwrap_or_default();
+        let text = &self.source[record.span.start()..record.span.end()];
+        let open = record.span.start() + text.find('{').unwrap_or(0);
+        let first = members.first().map_or(record.span.end(), |m| m.span.start());
+
+        self.line(&format!("{}{{", self.decl_prefix(decl)));
+        self.trailing_before(open, record.span.line(), first);
+        self.depth += 1;
+        self.fresh = true;
+        self.last = open + 1;
+        for member in members.iter() {
+            self.decl(member);
+        }
+        self.comments_before(record.span.end() - 1);
+        self.depth -= 1;
+
+        self.close_decl(decl);
+    }
+
+    // 枚举的定义，每个常量一行
+    fn enumer(&mut self, decl: &Decl, enumer: &EnumSpec) {
+        let items = enumer.items.as_deref().unwrap_or_default();
+        let text = &self.source[enumer.span.start()..enumer.span.end()];
+        let open = enumer.span.start() + text.find('{').unwrap_or(0);
+        let first = items.first().map_or(enumer.span.end(), |e| e.span.start());
+
+        self.line(&format!("{}{{", self.decl_prefix(decl)));
+        self.trailing_before(open, enumer.span.line(), first);
+        self.depth += 1;
+        self.fresh = true;
+        self.last = open + 1;
+        for (i, item) in items.iter().enumerate() {
+            self.before(item.span.start());
+            let mut text = item.name.name.clone();
+            if let Some(value) = &item.value {
+                text.push_str(&format!(" = {}", self.expr(value)));
+            }
+            if i + 1 < items.len() {
+                text.push(',');
+            }
+            self.line(&text);
+            // 行尾注释可以写在逗号后面
+            let comma = self.next_token(item.span.end());
+            let end = if self.source[comma..].starts_with(',') { comma + 1 } else { item.span.end() };
+            self.last = end;
+            self.trailing(end, item.span.end_line());
+        }
+        self.comments_before(enumer.span.end() - 1);
+        self.depth -= 1;
+
+        self.close_decl(decl);
+    }
+
+    // 定义类型的右大括号，声明的变量跟在后面
+    fn close_decl(&mut self, decl: &Decl) {
+        let vars: Vec<String> = decl.vars.iter().map(|v| self.var_decl(v)).collect();
+        if vars.is_empty() {
+            self.line("};");
+        } else {
+            self.line(&format!("}} {};", vars.join(", ")));
+        }
+    }
+
+    fn function(&mut self, fun: &FunctionDef) {
+        self.before(fun.span.start());
+        let params: Vec<String> = fun.params.iter().map(|p| self.param(p)).collect();
+        let head = format!("{}{} {}({})", if fun.ext { "extern " } else { "" }, spec_name(&fun.ret), fun.name.name, params.join(", "));
+
+        match &fun.body {
+            None => {
+                self.line(&format!("{};", head));
+                self.last = fun.span.end();
+                self.trailing(fun.span.end(), fun.span.end_line());
+            },
+            Some(body) => {
+                self.line(&format!("{} {{", head));
+                self.block_inner(body);
+                self.close("}", body.span.end(), body.span.end_line());
+            },
+        }
+    }
+}
+
+// 语句
+impl<'a> Formatter<'a> {
+    fn stmt(&mut self, stmt: &Stmt) {
+        self.before(stmt.span.start());
+        match &stmt.kind {
+            StmtKind::Expr(e) => self.simple(stmt, format!("{};", self.opt_expr(e))),
+            StmtKind::Block(block) => {
+                self.line("{");
+                self.block_inner(block);
+                self.close("}", block.span.end(), block.span.end_line());
+            },
+            StmtKind::While { cond, body } => {
+                if self.body(&format!("while ({})", self.opt_expr(cond)), body) {
+                    self.close_body(body, "}");
+                }
+            },
+            StmtKind::DoWhile { body, cond } => {
+                if self.body("do", body) {
+                    self.close_body(body, &format!("}} while ({});", self.opt_expr(cond)));
+                } else {
+                    self.simple(stmt, format!("while ({});", self.opt_expr(cond)));
+                }
+            },
+            StmtKind::For { init, cond, step, body } => {
+                let init = match init.as_ref() {
+                    ForInit::Decl(decl) => {
+                        let vars: Vec<String> = decl.vars.iter().map(|v| self.var_decl(v)).collect();
+                        format!("{}{}", self.decl_prefix(decl), vars.join(", "))
+                    },
+                    ForInit::Expr(e) => self.opt_expr(e),
+                };
+                let mut head = format!("for ({};", init);
+                if let Some(cond) = cond {
+                    head.push(' ');
+                    head.push_str(&self.expr(cond));
+                }
+                head.push(';');
+                if let Some(step) = step {
+                    head.push(' ');
+                    head.push_str(&self.expr(step));
+                }
+                head.push(')');
+                if self.body(&head, body) {
+                    self.close_body(body, "}");
+                }
+            },
+            StmtKind::If { .. } => self.if_stmt("", stmt),
+            StmtKind::Switch { cond, cases } => {
+                self.line(&format!("switch ({}) {{", self.expr(cond)));
+                self.depth += 1;
+                self.fresh = true;
+                for case in cases.iter() {
+                    self.case(case);
+                }
+                self.comments_before(stmt.span.end() - 1);
+                self.depth -= 1;
+                self.close("}", stmt.span.end(), stmt.span.end_line());
+            },
+            StmtKind::Break => self.simple(stmt, "break;".to_string()),
+            StmtKind::Continue => self.simple(stmt, "continue;".to_string()),
+            StmtKind::Return(e) => {
+                let text = match e {
+                    Some(e) => format!("return {};", self.expr(e)),
+                    None => "return;".to_string(),
+                };
+                self.simple(stmt, text);
+            },
+        }
+    }
+
+    // 单行语句
+    fn simple(&mut self, stmt: &Stmt, text: String) {
+        self.line(&text);
+        self.last = stmt.span.end();
+        self.trailing(stmt.span.end(), stmt.span.end_line());
+    }
+
+    // 循环体或分支，语句块时大括号跟在head后面，返回是否需要输出右大括号
+    fn body(&mut self, head: &str, body: &Stmt) -> bool {
+        match &body.kind {
+            StmtKind::Block(block) => {
+                self.line(&format!("{} {{", head));
+                self.block_inner(block);
+                true
+            },
+            _ => {
+                self.line(head);
+                self.depth += 1;
+                self.fresh = true;
+                self.stmt(body);
+                self.depth -= 1;
+                false
+            },
+        }
+    }
+
+    fn close_body(&mut self, body: &Stmt, text: &str) {
+        self.close(text, body.span.end(), body.span.end_line());
+    }
+
+    // prefix为"} else "或"else "，用于输出else if
+    fn if_stmt(&mut self, prefix: &str, stmt: &Stmt) {
+        if let StmtKind::If { cond, then, els } = &stmt.kind {
+            let braced = self.body(&format!("{}if ({})", prefix, self.expr(cond)), then);
+            match els {
+                None => if braced {
+                    self.close_body(then, "}");
+                },
+                Some(els) => {
+                    // 右大括号和else在同一行
+                    let prefix = if braced { "} else " } else { "else " };
+                    self.last = then.span.end();
+                    if let StmtKind::If { .. } = &els.kind {
+                        self.if_stmt(prefix, els);
+                    } else if self.body(prefix.trim_end(), els) {
+                        self.close_body(els, "}");
+                    }
+                },
+            }
+        }
+    }
+
+    fn case(&mut self, case: &Case) {
+        self.before(case.span.start());
+        match &case.label {
+            Some(label) => self.line(&format!("case {}:", self.expr(label))),
+            None => self.line("default:"),
+        }
+        self.depth += 1;
+        self.fresh = true;
+        self.block_items(&case.items);
+        self.depth -= 1;
+    }
+}
+
+// 表达式和声明的文本
+impl<'a> Formatter<'a> {
+    // 声明的前缀，如"extern int "、"typedef char "
+    fn decl_prefix(&self, decl: &Decl) -> String {
+        let storage = if decl.ext { "extern " } else if decl.typedef { "typedef " } else { "" };
+        format!("{}{} ", storage, spec_name(&decl.ty))
+    }
+
+    fn var_decl(&self, var: &VarDecl) -> String {
+        let mut text = self.declarator(&var.decl, &var.name.name);
+        if let Some(init) = &var.init {
+            text.push_str(" = ");
+            text.push_str(&self.initializer(init));
+        }
+        text
+    }
+
+    // 初始化列表写在一行，如{{1, 2}, {3}}
+    fn initializer(&self, init: &Initializer) -> String {
+        match init {
+            Initializer::Expr(e) => self.expr(e),
+            Initializer::List { items, .. } => {
+                let items: Vec<String> = items.iter().map(|item| self.initializer(item)).collect();
+                format!("{{{}}}", items.join(", "))
+            },
+        }
+    }
+
+    fn param(&self, para: &Param) -> String {
+        format!("{} {}", spec_name(&para.ty), self.declarator(&para.decl, &para.name.name))
+    }
+
+    // 声明符，如*a[0x10]、(*p)[N + 1]，省略的长度为[]，指针外面有数组或函数时加括号
+    fn declarator(&self, decl: &Declarator, name: &str) -> String {
+        let inner = |d: &Declarator| match d.kind {
+            DeclaratorKind::Pointer(_) => format!("({})", self.declarator(d, name)),
+            _ => self.declarator(d, name),
+        };
+        match &decl.kind {
+            DeclaratorKind::Name => name.to_string(),
+            DeclaratorKind::Pointer(d) => format!("*{}", self.declarator(d, name)),
+            DeclaratorKind::Array { inner: d, len } => format!("{}[{}]", inner(d), self.opt_expr(len)),
+            DeclaratorKind::Function { inner: d, params } => {
+                let params: Vec<String> = params.iter().map(|p| self.param(p)).collect();
+                format!("{}({})", inner(d), params.join(", "))
+            },
+        }
+    }
+
+    fn opt_expr(&self, e: &Option<Expr>) -> String {
+        e.as_ref().map(|e| self.expr(e)).unwrap_or_default()
+    }
+
+    fn expr(&self, e: &Expr) -> String {
+        match &e.kind {
+            ExprKind::Literal(_) => self.literal(e),
+            ExprKind::Ident(name) => name.clone(),
+            ExprKind::Index { base, index } => format!("{}[{}]", self.expr(base), self.expr(index)),
+            ExprKind::Member { base, arrow, member } => format!("{}{}{}", self.expr(base), if *arrow { "->" } else { "." }, member.name),
+            ExprKind::Call { callee, args } => {
+                let args: Vec<String> = args.iter().map(|e| self.expr(e)).collect();
+                format!("{}({})", callee.name, args.join(", "))
+            },
+            ExprKind::Unary { op, expr: operand } => {
+                let op = tag_name(*op);
+                let operand = self.expr(operand);
+                // 避免-和-、&和&连在一起被识别为--或&&
+                if operand.starts_with(op.chars().last().unwrap_or(' ')) && (op.ends_with('-') || op.ends_with('&')) {
+                    format!("{} {}", op, operand)
+                } else {
+                    format!("{}{}", op, operand)
+                }
+            },
+            ExprKind::Postfix { op, expr: operand } => format!("{}{}", self.expr(operand), tag_name(*op)),
+            ExprKind::Binary { op, lhs, rhs } | ExprKind::Assign { op, lhs, rhs } => self.binary(*op, lhs, rhs),
+            ExprKind::Paren(inner) => format!("({})", self.expr(inner)),
+            ExprKind::Error => String::new(),
+        }
+    }
+
+    // 双目运算和赋值，运算对象之间的块注释保留在运算符的同一侧
+    fn binary(&self, op: Tag, lhs: &Expr, rhs: &Expr) -> String {
+        let pos = self.next_token(lhs.span.end());
+        let before: String = self.inline_comments(lhs.span.end(), pos).iter().map(|c| format!(" {}", c)).collect();
+        let after: String = self.inline_comments(pos, rhs.span.start()).iter().map(|c| format!("{} ", c)).collect();
+        format!("{}{} {} {}{}", self.expr(lhs), before, tag_name(op), after, self.expr(rhs))
+    }
+
+    // from和to之间的块注释，在表达式中原样输出，行注释仍然单独一行
+    fn inline_comments(&self, from: usize, to: usize) -> Vec<&str> {
+        let mut texts = vec![];
+        for (i, comment) in self.comments.iter().enumerate() {
+            let span = comment.get_span();
+            if span.start() >= from && span.end() <= to && comment.get_text().starts_with("/*") {
+                self.inline.borrow_mut().insert(i);
+                texts.push(comment.get_text());
+            }
+        }
+        texts
+    }
+
+    // 常量保留源代码中的写法，如十六进制数和转义字符
+    fn literal(&self, e: &Expr) -> String {
+        self.source[e.span.start()..e.span.end()].to_string()
+    }
+}
+
+fn tag_name(tag: Tag) -> &'static str {
+    Catalog::current().token_name(tag)
+}
+
+// 类型说明符，结构体和联合带有标签名
+fn spec_name(ty: &TypeSpec) -> String {
+    if let Some(alias) = &ty.alias {
+        return alias.name.clone();
+    }
+    if !ty.words.is_empty() {
+        return ty.words.iter().map(|t| tag_name(*t)).collect::<Vec<_>>().join(" ");
+    }
+    let tag = ty.record.as_ref().and_then(|r| r.name.as_ref()).or(ty.enumer.as_ref().and_then(|e| e.name.as_ref()));
+    match tag {
+        Some(name) => format!("{} {}", tag_name(ty.tag), name.name),
+        None => tag_name(ty.tag).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::format_source;
+
+    #[test]
+    fn test_format() {
+        let source = "\
+// 计数
+int g=0x10,*p;
+int main(){int i; // 循环变量
+for(i=0;i<g;i++){if(i%2)continue;else g=g- -i;}
+  /* 结束 */
+  return g;}
+";
+        let expect = "\
+// 计数
+int g = 0x10;
+int *p;
+
+int main() {
+    int i; // 循环变量
+    for (i = 0; i < g; i++) {
+        if (i % 2)
+            continue;
+        else
+            g = g - -i;
+    }
+    /* 结束 */
+    return g;
+}
+";
+        let formatted = format_source(source).unwrap();
+        assert_eq!(formatted, expect);
+        assert_eq!(format_source(&formatted).unwrap(), expect);
+    }
+
+    #[test]
+    fn test_format_record() {
+        let source = "struct node{int v; // 值\nstruct node*next;}*head,n;\nunion u;\nint main(){return head->next->v+n.v;}\n";
+        let expect = "\
+struct node {
+    int v; // 值
+    struct node *next;
+} *head, n;
+union u;
+
+int main() {
+    return head->next->v + n.v;
+}
+";
+        let formatted = format_source(source).unwrap();
+        assert_eq!(formatted, expect);
+        assert_eq!(format_source(&formatted).unwrap(), expect);
+    }
+
+    #[test]
+    fn test_format_declarator() {
+        let source = "char **argv,*names[ 0x2 ][3]={{\"a\"},{0,}};int g[]={1,2};int ( *p )[4],*(a[4]),(*h)(int n,char*s);\n\
+                      int sum(int m[][ 8 ],char**p){return m[1][2]+**p;}\n";
+        let expect = "\
+char **argv;
+char *names[0x2][3] = {{\"a\"}, {0}};
+int g[] = {1, 2};
+int (*p)[4];
+int *a[4];
+int (*h)(int n, char *s);
+
+int sum(int m[][8], char **p) {
+    return m[1][2] + **p;
+}
+";
+        let formatted = format_source(source).unwrap();
+        assert_eq!(formatted, expect);
+        assert_eq!(format_source(&formatted).unwrap(), expect);
+    }
+
+    #[test]
+    fn test_format_enum_typedef() {
+        let source = "enum color{RED,// 红\nGREEN=RED+2,BLUE,};\ntypedef struct{int x;}pt,*ptp;\ntypedef int len;\n\
+                      int f(len n){enum{A=1}e;pt p;switch(n){case GREEN:return A;}return p.x;}\n";
+        let expect = "\
+enum color {
+    RED, // 红
+    GREEN = RED + 2,
+    BLUE
+};
+typedef struct {
+    int x;
+} pt, *ptp;
+typedef int len;
+
+int f(len n) {
+    enum {
+        A = 1
+    } e;
+    pt p;
+    switch (n) {
+        case GREEN:
+            return A;
+    }
+    return p.x;
+}
+";
+        let formatted = format_source(source).unwrap();
+        assert_eq!(formatted, expect);
+        assert_eq!(format_source(&formatted).unwrap(), expect);
+    }
+
+    #[test]
+    fn test_format_syntax_error() {
+        assert!(format_source("int main() { return 0 }").is_err());
+    }
+
+    #[test]
+    fn test_format_directive() {
+        let source = "#include \"h.h\"\n#define N 4\nint a[N];\nint main(){\n#ifdef DEBUG\nreturn 1;\n#endif\nreturn 0;}\n";
+        let expect = "#include \"h.h\"\n#define N 4\nint a[N];\n\nint main() {\n#ifdef DEBUG\n    return 1;\n#endif\n    return 0;\n}\n";
+        assert_eq!(format_source(source).unwrap(), expect);
+        assert_eq!(format_source(expect).unwrap(), expect);
+    }
+
+    #[test]
+    fn test_format_inline_comment() {
+        // 表达式中的块注释留在原处，行尾注释留在行尾
+        let source = "int x = a /* inline */ + 1; // trailing\nint main(){x=/* c */x*2;\nreturn x+ /* one */1; /* end */\n}\n";
+        let expect = "int x = a /* inline */ + 1; // trailing\n\nint main() {\n    x = /* c */ x * 2;\n    return x + /* one */ 1; /* end */\n}\n";
+        let formatted = format_source(source).unwrap();
+        assert_eq!(formatted, expect);
+        assert_eq!(format_source(&formatted).unwrap(), expect);
+    }
+
+    #[test]
+    fn test_format_trailing() {
+        // 行尾注释属于它前面最近的语句，不会移到另一个分支
+        let source = "int main(){int x,y;if(x>1){y=2;}else y=3; /* after */\nreturn y;}\n";
+        let expect = "int main() {\n    int x;\n    int y;\n    if (x > 1) {\n        y = 2;\n    } else\n        y = 3; /* after */\n    return y;\n}\n";
+        assert_eq!(format_source(source).unwrap(), expect);
+    }
+}
@@ -1,12 +1,15 @@
 use std::sync::Mutex;
 use lazy_static::lazy_static;
-use crate::common::Operator::{OpAdd, OpAnd, OpArg, OpAs, OpCall, OpDiv, OpEntry, OpEqu, OpExit, OpGe, OpGet, OpGt, OpJf, OpJmp, OpJne, OpJt, OpLe, OpLea, OpLt, OpMod, OpMul, OpNe, OpNeg, OpNot, OpOr, OpProc, OpRet, OpRetv, OpSet, OpSub};
-use crate::common::SemError::{ArrTypeErr, AssignTypeErr, BreakErr, ContinueErr, ExprIsBase, ExprIsVoid, ExprNotBase, ExprNotLeftVal, ReturnErr};
+use crate::common::Operator::{OpAdd, OpAnd, OpBitAnd, OpBitNot, OpBitOr, OpShl, OpShr, OpXor, OpArg, OpAs, OpCall, OpDec, OpDiv, OpEntry, OpEqu, OpExit, OpGe, OpGet, OpGt, OpJf, OpJmp, OpJne, OpJt, OpLe, OpLea, OpLt, OpMod, OpMul, OpNe, OpNeg, OpNot, OpOr, OpProc, OpRet, OpRetv, OpSet, OpSub};
+use crate::common::SemError::{ArrTypeErr, AssignTypeErr, BreakErr, ContinueErr, DecInitDeny, ExprIsBase, ExprIsFloat, ExprIsRecord, ExprIsVoid, ExprNotBase, ExprNotLeftVal, GlbInitErr, MemberOpErr, MemberUnDec, RecordUnDef, ReturnErr, VarInitErr};
 use crate::common::Tag;
-use crate::common::Tag::{OR, AND, EQU, NEQU, ADD, SUB, GT, GE, LT, LE, MUL, DIV, MOD, LEA, INC, DEC, NOT};
-use crate::common::Tag::{ASSIGN, KwInt, KwVoid};
+use crate::common::Tag::{OR, AND, EQU, NEQU, ADD, SUB, GT, GE, LT, LE, MUL, DIV, MOD, LEA, INC, DEC, NOT, BOR, XOR, BNOT, SHL, SHR};
+use crate::common::Tag::ASSIGN;
 use crate::intercode::InterInst;
-use crate::symbol::{Fun, sem_error, Var};
+use crate::lower;
+use crate::span::Span;
+use crate::symbol::{Fun, InitItem, sem_error, Var};
+use crate::types::{self, TypeId};
 use crate::symtab::SymTab;
 
 lazy_static! {
@@ -17,9 +20,8 @@ lazy_static! {
 	中间代码生成器
 */
 
-#[derive(Clone)]
 pub(crate) struct GenIR {
-    sym_tab: Box<SymTab>,   // 符号表
+    sym_tab: SymTab,        // 符号表
 
     // break continue辅助标签列表
     heads: Vec<Option<Box<InterInst>>>,
@@ -28,13 +30,18 @@ pub(crate) struct GenIR {
 }
 
 impl GenIR {
-    pub(crate) fn new(sym_tab: Box<SymTab>) -> Self {
+    pub(crate) fn new(sym_tab: SymTab) -> Self {
         GenIR {
             sym_tab,
             heads: vec![],
             tails: vec![],
         }
     }
+
+    // 获取符号表
+    pub(crate) fn get_sym_tab(&mut self) -> &mut SymTab {
+        &mut self.sym_tab
+    }
 }
 
 impl GenIR {
@@ -44,7 +51,7 @@ impl GenIR {
     pub(crate) fn gen_lb() -> String {
         let mut data = COUNTER.lock().unwrap();
         *data += 1;
-        let s = format!("{}.L", *data);
+        let s = format!(".L{}", *data);
         s
     }
 
@@ -56,13 +63,25 @@ impl GenIR {
         let lval = lval.unwrap();
         let rval = rval.unwrap();
 
-        if lval.is_base() && rval.is_base() {
-            return true;
-        } else if !lval.is_base() && !rval.is_base() && lval.get_type() == rval.get_type() {
-            return true;
+        lval.can_assign(&rval)
+    }
+
+    // 结构体或联合对象不能参与运算
+    pub(crate) fn not_record(&self, val: &Var) -> bool {
+        if val.is_record() {
+            sem_error(&self.sym_tab.get_sink(), ExprIsRecord, "", val.get_span());
+            return false;
         }
+        true
+    }
 
-        false
+    // 取模、指针运算和switch的操作数只能是整数
+    pub(crate) fn not_float(&self, val: &Var) -> bool {
+        if val.get_type().is_float() {
+            sem_error(&self.sym_tab.get_sink(), ExprIsFloat, "", val.get_span());
+            return false;
+        }
+        true
     }
 }
 
@@ -82,15 +101,126 @@ impl GenIR {
     }
 }
 
+impl GenIR {
+    // 变量声明，检查并处理初始值，函数内的变量产生声明语句
+    pub(crate) fn gen_var_init(&mut self, var: Box<Var>) -> Box<Var> {
+        let mut var = var;
+        let init = var.get_init_data();
+        let list = var.get_init_list();
+        let local = self.sym_tab.get_cur_fun_mut().is_some();
+
+        if let Some(init) = init.as_ref() {
+            if var.get_extern() {
+                sem_error(&self.sym_tab.get_sink(), DecInitDeny, &var.get_name(), var.get_span());        // 声明不允许初始化
+            } else if !self.type_check(Some(var.clone()), Some(init.clone())) {
+                sem_error(&self.sym_tab.get_sink(), VarInitErr, &var.get_name(), var.get_span());         // 类型不匹配
+            } else if init.get_literal() {
+                var.set_init_val(init);         // 常量初值
+            } else if !local {
+                sem_error(&self.sym_tab.get_sink(), GlbInitErr, &var.get_name(), init.get_span());        // 全局变量只能用常量初始化
+            }
+        }
+        if !list.is_empty() {
+            if var.get_extern() {
+                sem_error(&self.sym_tab.get_sink(), DecInitDeny, &var.get_name(), var.get_span());
+                var.set_init_list(vec![]);
+            } else {
+                var.set_init_list(self.check_init_list(&var, list, local));
+            }
+        }
+
+        let var = self.sym_tab.add_var(var);
+        if local && !var.get_extern() {
+            let inst = Box::new(InterInst::new_param(OpDec, var.clone()));
+            self.sym_tab.add_inst(inst);
+
+            // 局部变量用表达式初始化
+            if let Some(init) = init {
+                if !init.get_literal() && !var.get_extern() && self.type_check(Some(var.clone()), Some(init.clone())) {
+                    self.gen_assign_stmt(var.clone(), init);
+                }
+            }
+            if !var.get_init_list().is_empty() {
+                self.gen_init_list(var.clone());
+            }
+        }
+
+        var
+    }
+
+    // 检查初始化列表中每一项的类型，全局变量只能用常量初始化，去掉出错的项
+    fn check_init_list(&mut self, var: &Var, list: Vec<InitItem>, local: bool) -> Vec<InitItem> {
+        let sink = self.sym_tab.get_sink();
+        list.into_iter().filter(|(_, t, val)| {
+            let slot = Var::new_temp(self.sym_tab.get_scope_path(), *t);
+            if !slot.can_assign(val) {
+                sem_error(&sink, VarInitErr, &var.get_name(), val.get_span());
+                false
+            } else if !local && !val.get_literal() {
+                sem_error(&sink, GlbInitErr, &var.get_name(), val.get_span());
+                false
+            } else {
+                true
+            }
+        }).collect()
+    }
+
+    // 局部数组和结构体按标量逐个赋值，没有给出初值的标量赋0
+    fn gen_init_list(&mut self, var: Box<Var>) {
+        let list = var.get_init_list();
+        let mut base = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), TypeId::CHAR.pointer()));
+        base.set_span(var.get_span());
+        let base = self.sym_tab.add_var(base);
+        let inst = Box::new(InterInst::new_common(OpLea, base.clone(), Some(var.clone()), None));
+        self.sym_tab.add_inst(inst);
+
+        for (offset, t) in var.get_type().scalars(0) {
+            let val = match list.iter().find(|(off, ..)| *off == offset) {
+                Some((_, _, val)) => val.clone(),
+                None => Box::new(Var::new_int(0)),
+            };
+
+            // 标量的地址，指针的类型决定存储的宽度
+            let mut ptr = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), t.pointer()));
+            ptr.set_span(var.get_span());
+            let ptr = self.sym_tab.add_var(ptr);
+            let inst = Box::new(InterInst::new_common(OpAdd, ptr.clone(), Some(base.clone()), Some(Box::new(Var::new_int(offset)))));
+            self.sym_tab.add_inst(inst);
+
+            let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), t));
+            tmp.set_left(true);
+            tmp.set_pointer(ptr);
+            tmp.set_span(var.get_span());
+            let tmp = self.sym_tab.add_var(tmp);
+            self.gen_assign_stmt(tmp, val);
+        }
+    }
+}
+
 impl GenIR {
 
     // 函数调用
-    pub(crate) fn gen_para(&mut self, arg: Box<Var>) {  // 参数传递语句
+    pub(crate) fn gen_para(&mut self, arg: Box<Var>, t: TypeId) {  // 参数传递语句，t为形参类型
+        if !self.not_record(&arg) {
+            return;
+        }
         let mut arg = arg.clone();
         if arg.is_ref() {
             arg = self.gen_assign(arg);
         }
 
+        // long long参数占两个单元，浮点数的存储形式不同，与形参不一致时先转换为形参类型
+        let a = arg.get_type();
+        let convert = if a.is_float() || t.is_float() { a != t } else { a.is_wide() != t.is_wide() };
+        if arg.is_base() && convert {
+            let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), t));
+            tmp.set_span(arg.get_span());
+            let tmp = self.sym_tab.add_var(tmp);
+            let inst = Box::new(InterInst::new_common(OpAs, tmp.clone(), Some(arg), None));
+            self.sym_tab.add_inst(inst);
+            arg = tmp;
+        }
+
         let inst = Box::new(InterInst::new_param(OpArg, arg));
         self.sym_tab.add_inst(inst);
     }
@@ -100,30 +230,43 @@ impl GenIR {
     // 产生特殊语句
 
     // 产生函数入口语句
-    pub(crate) fn gen_fun_head(&mut self, mut function: Box<Fun>) {
+    pub(crate) fn gen_fun_head(&mut self) {
+        let function = match self.sym_tab.get_cur_fun_mut() {
+            Some(function) => function,
+            None => return,
+        };
         function.enter_scope();      // 进入函数作用域
+        function.set_return_point(Some(Box::new(InterInst::new_label())));    // 创建函数返回点
         let inst = Box::new(InterInst::new_call(OpEntry, function.clone(), None));
         self.sym_tab.add_inst(inst);                    // 添加函数入口指令
-        let inst = Box::new(InterInst::new_label());
-        function.set_return_point(Some(inst));          // 创建函数返回点
     }
 
     // 产生函数出口语句
-    pub(crate) fn gen_fun_tail(&mut self, mut function: Box<Fun>) {
-        let inst = Box::new(InterInst::new_call(OpExit, function.clone(), None));
-        self.sym_tab.add_inst(inst);        // 添加函数返回点，return的目的标号
-        let inst = Box::new(InterInst::new_call(OpExit, function.clone(), None));
+    pub(crate) fn gen_fun_tail(&mut self) {
+        let function = match self.sym_tab.get_cur_fun() {
+            Some(function) => function,
+            None => return,
+        };
+        if let Some(point) = function.get_return_point() {
+            self.sym_tab.add_inst(point);   // 添加函数返回点，return的目的标号
+        }
+        let inst = Box::new(InterInst::new_call(OpExit, function, None));
         self.sym_tab.add_inst(inst);        // 添加函数出口指令
-        function.leave_scope();             // 退出函数作用域
+        if let Some(function) = self.sym_tab.get_cur_fun_mut() {
+            function.leave_scope();         // 退出函数作用域
+        }
     }
 
     // 产生return语句
-    pub(crate) fn gen_return(&mut self, ret: Option<Box<Var>>) {
+    pub(crate) fn gen_return(&mut self, ret: Option<Box<Var>>, span: Span) {
         if let Some(ret) = ret {
             let fun = self.sym_tab.get_cur_fun();
             if let Some(fun) = fun {
-                if  (ret.is_base() && fun.get_type() == KwVoid) || (ret.is_void() && fun.get_type() != KwVoid) {
-                    sem_error(ReturnErr as usize, "");
+                if  (ret.is_base() && fun.get_type() == TypeId::VOID) || (ret.is_void() && fun.get_type() != TypeId::VOID) {
+                    sem_error(&self.sym_tab.get_sink(), ReturnErr, "", span);
+                    return;
+                }
+                if !self.not_record(&ret) {
                     return;
                 }
 
@@ -132,12 +275,13 @@ impl GenIR {
                     let inst = Box::new(InterInst::new_jump(OpRet, return_point, None, None));
                     self.sym_tab.add_inst(inst);
                 } else {
+                    let mut r = ret.clone();
                     if ret.is_ref() {
                         // 处理ret是*p的情况
-                        let r = self.gen_assign(ret.clone());
-                        let inst = Box::new(InterInst::new_jump(OpRetv, return_point, Some(r), None));
-                        self.sym_tab.add_inst(inst);
+                        r = self.gen_assign(ret.clone());
                     }
+                    let inst = Box::new(InterInst::new_jump(OpRetv, return_point, Some(r), None));
+                    self.sym_tab.add_inst(inst);
                 }
             }
         }
@@ -145,52 +289,48 @@ impl GenIR {
 
     // 函数调用语句
     pub(crate) fn gen_call(&mut self, fun: Option<Box<Fun>>, args: Vec<Box<Var>>) -> Option<Box<Var>> {
-        if fun.is_none() {
-            return None;
-        }
+        let fun = fun?;
+        let paras = fun.get_para_var();
 
         for i in (0..args.len()).rev() {
             // 逆向传递实际参数
-            self.gen_para(args[i].clone());
+            self.gen_para(args[i].clone(), paras[i].get_type());
         }
 
-        let fun = fun.unwrap();
-        if fun.get_type() == KwVoid {
+        if fun.get_type() == TypeId::VOID {
             // 中间代码fun()
             let inst = Box::new(InterInst::new_call(OpProc, fun.clone(), None));
             self.sym_tab.add_inst(inst);
 
             Var::get_void()
         } else {
-            let ret = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), fun.get_type(), false));
+            let ret = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), fun.get_type()));
+            let ret = self.sym_tab.add_var(ret);
             let inst = Box::new(InterInst::new_call(OpCall, fun.clone(), Some(ret.clone())));
             self.sym_tab.add_inst(inst);
-            self.sym_tab.add_var(ret.clone());
             Some(ret)
         }
     }
 
     // 产生break语句
-    pub(crate) fn gen_break(&mut self) {
-        let tail = self.tails.last().unwrap();   // 取出跳出标签
+    pub(crate) fn gen_break(&mut self, span: Span) {
+        let tail = self.tails.last().cloned().flatten();   // 取出跳出标签
         if tail.is_some() {
-            let tail = tail.clone();
             let inst = Box::new(InterInst::new_jump(OpJmp, tail, None, None));
             self.sym_tab.add_inst(inst);
         } else {
-            sem_error(BreakErr as usize, "");
+            sem_error(&self.sym_tab.get_sink(), BreakErr, "", span);
         }
     }
 
     // 产生continue语句
-    pub(crate) fn gen_continue(&mut self) {
-        let head = self.heads.last().unwrap();
+    pub(crate) fn gen_continue(&mut self, span: Span) {
+        let head = self.heads.iter().rev().find_map(|h| h.clone());    // switch没有continue标签，继续向外查找循环
         if head.is_some() {
-            let head = head.clone().unwrap();
-            let inst = Box::new(InterInst::new_jump(OpJmp, Some(head), None, None));
+            let inst = Box::new(InterInst::new_jump(OpJmp, head, None, None));
             self.sym_tab.add_inst(inst);
         } else {
-            sem_error(ContinueErr as usize, "");
+            sem_error(&self.sym_tab.get_sink(), ContinueErr, "", span);
         }
     }
 
@@ -208,20 +348,19 @@ impl GenIR {
         let mut rval = rval.unwrap();
 
         if lval.is_void() || rval.is_void() {
-            sem_error(ExprIsVoid as usize, "");     // void函数返回值不能出现在表达式中
+            sem_error(&self.sym_tab.get_sink(), ExprIsVoid, "", lval.get_span().to(rval.get_span()));     // void函数返回值不能出现在表达式中
             return None;
         }
 
-        if !lval.is_base() || !rval.is_base() {
-            sem_error(ExprNotBase as usize, "");    // 不是基本类型
-            return Some(lval);
-        }
-
         // 赋值单独处理
         if opt == ASSIGN {
             return Some(self.gen_assign_stmt(lval.clone(), rval.clone()));
         }
 
+        if !self.not_record(&lval) || !self.not_record(&rval) {
+            return None;
+        }
+
         // 先处理(*p)变量
         if lval.is_ref() {
             lval = self.gen_assign(lval.clone());
@@ -247,7 +386,7 @@ impl GenIR {
             // 大于
             GT => self.gen_gt(lval.clone(), rval.clone()),
             // 大于等于
-            GE => self.gen_gt(lval.clone(), rval.clone()),
+            GE => self.gen_ge(lval.clone(), rval.clone()),
             // 小于
             LT => self.gen_lt(lval.clone(), rval.clone()),
             // 小于等于
@@ -258,6 +397,16 @@ impl GenIR {
             DIV => self.gen_div(lval.clone(), rval.clone()),
             // 取模
             MOD => self.gen_mod(lval.clone(), rval.clone()),
+            // 按位与，与取址共用记号
+            LEA => self.gen_bit(LEA, lval.clone(), rval.clone()),
+            // 按位或
+            BOR => self.gen_bit(BOR, lval.clone(), rval.clone()),
+            // 按位异或
+            XOR => self.gen_bit(XOR, lval.clone(), rval.clone()),
+            // 左移
+            SHL => self.gen_bit(SHL, lval.clone(), rval.clone()),
+            // 右移
+            SHR => self.gen_bit(SHR, lval.clone(), rval.clone()),
             // 默认返回左值
             _ => lval,
         };
@@ -265,6 +414,23 @@ impl GenIR {
         Some(ret)
     }
 
+    // 复合赋值语句，左值只求值一次，(*p)形式的引用读写都使用已经算好的地址
+    pub(crate) fn gen_compound(&mut self, lval: Option<Box<Var>>, opt: Tag, rval: Option<Box<Var>>) -> Option<Box<Var>> {
+        if lval.is_none() || rval.is_none() {
+            return None;
+        }
+        let lval = lval.unwrap();
+        let rval = rval.unwrap();
+
+        if !lval.get_left() {
+            sem_error(&self.sym_tab.get_sink(), ExprNotLeftVal, "", lval.get_span().to(rval.get_span()));
+            return Some(rval);
+        }
+
+        let val = self.gen_two_op(Some(lval.clone()), opt, Some(rval))?;
+        Some(self.gen_assign_stmt(lval, val))
+    }
+
     // 左单目运算语句
     pub(crate) fn gen_one_op_left(&mut self, opt: Tag, val: Option<Box<Var>>) -> Option<Box<Var>> {
         if val.is_none() {
@@ -273,21 +439,33 @@ impl GenIR {
 
         let val = val.clone().unwrap();
         if val.is_void() {
-            sem_error(ExprIsVoid as usize, "");
+            sem_error(&self.sym_tab.get_sink(), ExprIsVoid, "", val.get_span());
             return None;
         }
 
+        // 取址、解引用和自增自减需要保留(*p)的引用形式
+        if opt == LEA {
+            return self.gen_lea(val.clone());
+        }
+        if !self.not_record(&val) {
+            return None;
+        }
+        match opt {
+            MUL => return Some(self.gen_ptr(val.clone())),
+            INC => return self.gen_incl(val.clone()),
+            DEC => return self.gen_decl(val.clone()),
+            _ => {},
+        }
+
+        let mut val = val.clone();
         if val.is_ref() {
-            return Some(self.gen_assign(val.clone()));
+            val = self.gen_assign(val.clone());
         }
 
         let ret = match opt {
-            LEA => self.gen_lea(val.clone()),
-            MUL => Some(self.gen_ptr(val.clone())),
-            INC => self.gen_incl(val.clone()),
-            DEC => self.gen_decl(val.clone()),
             NOT => self.gen_not(val.clone()),
             SUB => self.gen_minus(val.clone()),
+            BNOT => self.gen_bit_not(val.clone()),
             _ => Some(val.clone()),
         };
 
@@ -303,12 +481,16 @@ impl GenIR {
         let val = val.unwrap();
 
         if val.is_void() {
-            sem_error(ExprIsVoid as usize, "");
+            sem_error(&self.sym_tab.get_sink(), ExprIsVoid, "", val.get_span());
+            return None;
+        }
+
+        if !self.not_record(&val) {
             return None;
         }
 
         if !val.get_left() {
-            sem_error(ExprNotLeftVal as usize, "");
+            sem_error(&self.sym_tab.get_sink(), ExprNotLeftVal, "", val.get_span());
             return Some(val.clone());
         }
 
@@ -329,11 +511,23 @@ impl GenIR {
             return None;
         }
 
-        let array = array.unwrap();
-        let index = index.unwrap();
+        let mut array = array.unwrap();
+        let mut index = index.unwrap();
+
+        if !self.not_record(&index) {
+            return None;
+        }
+
+        // 先处理(*p)变量
+        if array.is_ref() {
+            array = self.gen_assign(array);
+        }
+        if index.is_ref() {
+            index = self.gen_assign(index);
+        }
 
-        if array.is_base() || !index.is_base() {
-            sem_error(ArrTypeErr as usize, "");
+        if array.is_base() || !index.is_base() || index.get_type().is_float() {
+            sem_error(&self.sym_tab.get_sink(), ArrTypeErr, "", array.get_span().to(index.get_span()));
             return Some(index);
         }
 
@@ -342,6 +536,69 @@ impl GenIR {
 
         Some(ret)
     }
+
+    // 成员访问语句，arrow为->，计算成员地址后返回(*p)形式的引用，数组成员直接返回其首元素地址
+    pub(crate) fn gen_member(&mut self, base: Option<Box<Var>>, arrow: bool, name: &str, span: Span) -> Option<Box<Var>> {
+        let base = base?;
+        let sink = self.sym_tab.get_sink();
+
+        // 取得对象的地址和类型
+        let (addr, record_type) = if arrow {
+            let t = base.get_type().decay().pointee().filter(|t| t.is_record());
+            let t = match t {
+                Some(t) if !base.is_void() => t,
+                _ => {
+                    sem_error(&sink, MemberOpErr, name, base.get_span());
+                    return None;
+                },
+            };
+            (if base.is_ref() { self.gen_assign(base.clone()) } else { base.clone() }, t)
+        } else {
+            if !base.is_record() {
+                sem_error(&sink, MemberOpErr, name, base.get_span());
+                return None;
+            }
+            let addr = match base.get_pointer() {
+                Some(ptr) => ptr,
+                None => self.gen_lea(base.clone())?,
+            };
+            (addr, base.get_type())
+        };
+
+        let (defined, type_name, member) = types::with_record(record_type.record_id()?,
+                                                              |r| (r.is_defined(), r.type_name(), r.get_member(name)));
+        if !defined {
+            sem_error(&sink, RecordUnDef, &type_name, base.get_span());
+            return None;
+        }
+        let member = match member {
+            Some(member) => member,
+            None => {
+                sem_error(&sink, MemberUnDec, name, span);
+                return None;
+            },
+        };
+
+        // 成员地址，数组成员退化为指向元素的指针
+        let t = member.get_type();
+        let ptr_type = if t.is_array() { t.decay() } else { t.pointer() };
+        let mut ptr = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), ptr_type));
+        ptr.set_span(span);
+        let ptr = self.sym_tab.add_var(ptr);
+        let inst = Box::new(InterInst::new_common(OpAdd, ptr.clone(), Some(addr), Some(Box::new(Var::new_int(member.get_offset())))));
+        self.sym_tab.add_inst(inst);
+
+        if t.is_array() {
+            return Some(ptr);
+        }
+
+        let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), t));
+        tmp.set_left(true);
+        tmp.set_pointer(ptr);
+        tmp.set_span(span);
+
+        Some(self.sym_tab.add_var(tmp))
+    }
 }
 
 impl GenIR {
@@ -350,6 +607,7 @@ impl GenIR {
     // 拷贝赋值语句，处理*p的情况
     pub(crate) fn gen_assign(&mut self, val: Box<Var>) -> Box<Var> {
         let tmp = Box::new(Var::new_copy_temp(self.sym_tab.get_scope_path(), val.clone()));   // 拷贝变量信息
+        let tmp = self.sym_tab.add_var(tmp);
 
         let t = tmp.clone();
         let inst;
@@ -369,12 +627,12 @@ impl GenIR {
     pub(crate) fn gen_assign_stmt(&mut self, lval: Box<Var>, rval: Box<Var>) -> Box<Var> {
         // 被赋值对象必须是左值
         if !lval.get_left() {
-            sem_error(ExprNotLeftVal as usize, "");
+            sem_error(&self.sym_tab.get_sink(), ExprNotLeftVal, "", lval.get_span().to(rval.get_span()));
             return rval;
         }
 
         if !self.type_check(Some(lval.clone()), Some(rval.clone())) {
-            sem_error(AssignTypeErr as usize, "");
+            sem_error(&self.sym_tab.get_sink(), AssignTypeErr, "", lval.get_span().to(rval.get_span()));
             return rval;
         }
 
@@ -408,9 +666,10 @@ impl GenIR {
 
     // 或运算语句
     pub(crate) fn gen_or(&mut self, lval: Box<Var>, rval: Box<Var>) -> Box<Var> {
-        let tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), KwInt, false));
+        let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), TypeId::INT));
+        tmp.set_span(lval.get_span().to(rval.get_span()));
 
-        self.sym_tab.add_var(tmp.clone());
+        let tmp = self.sym_tab.add_var(tmp);
         let inst = Box::new(InterInst::new_common(OpOr, tmp.clone(), Some(lval.clone()), Some(rval.clone())));
         self.sym_tab.add_inst(inst);
 
@@ -419,9 +678,10 @@ impl GenIR {
 
     // 与运算符
     pub(crate) fn gen_and(&mut self, lval: Box<Var>, rval: Box<Var>) -> Box<Var> {
-        let tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), KwInt, false));
+        let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), TypeId::INT));
+        tmp.set_span(lval.get_span().to(rval.get_span()));
 
-        self.sym_tab.add_var(tmp.clone());
+        let tmp = self.sym_tab.add_var(tmp);
         let inst = Box::new(InterInst::new_common(OpAnd, tmp.clone(), Some(lval.clone()), Some(rval.clone())));
         self.sym_tab.add_inst(inst);
 
@@ -430,9 +690,10 @@ impl GenIR {
 
     // 大于运算符
     pub(crate) fn gen_gt(&mut self, lval: Box<Var>, rval: Box<Var>) -> Box<Var> {
-        let tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), KwInt, false));
+        let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), TypeId::INT));
+        tmp.set_span(lval.get_span().to(rval.get_span()));
 
-        self.sym_tab.add_var(tmp.clone());
+        let tmp = self.sym_tab.add_var(tmp);
         let inst = Box::new(InterInst::new_common(OpGt, tmp.clone(), Some(lval.clone()), Some(rval.clone())));
         self.sym_tab.add_inst(inst);
 
@@ -441,9 +702,10 @@ impl GenIR {
 
     // 大于等于运算符
     pub(crate) fn gen_ge(&mut self, lval: Box<Var>, rval: Box<Var>) -> Box<Var> {
-        let tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), KwInt, false));
+        let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), TypeId::INT));
+        tmp.set_span(lval.get_span().to(rval.get_span()));
 
-        self.sym_tab.add_var(tmp.clone());
+        let tmp = self.sym_tab.add_var(tmp);
         let inst = Box::new(InterInst::new_common(OpGe, tmp.clone(), Some(lval.clone()), Some(rval.clone())));
         self.sym_tab.add_inst(inst);
 
@@ -452,9 +714,10 @@ impl GenIR {
 
     // 小于运算符
     pub(crate) fn gen_lt(&mut self, lval: Box<Var>, rval: Box<Var>) -> Box<Var> {
-        let tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), KwInt, false));
+        let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), TypeId::INT));
+        tmp.set_span(lval.get_span().to(rval.get_span()));
 
-        self.sym_tab.add_var(tmp.clone());
+        let tmp = self.sym_tab.add_var(tmp);
         let inst = Box::new(InterInst::new_common(OpLt, tmp.clone(), Some(lval.clone()), Some(rval.clone())));
         self.sym_tab.add_inst(inst);
 
@@ -463,9 +726,10 @@ impl GenIR {
 
     // 小于等于运算符
     pub(crate) fn gen_le(&mut self, lval: Box<Var>, rval: Box<Var>) -> Box<Var> {
-        let tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), KwInt, false));
+        let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), TypeId::INT));
+        tmp.set_span(lval.get_span().to(rval.get_span()));
 
-        self.sym_tab.add_var(tmp.clone());
+        let tmp = self.sym_tab.add_var(tmp);
         let inst = Box::new(InterInst::new_common(OpLe, tmp.clone(), Some(lval.clone()), Some(rval.clone())));
         self.sym_tab.add_inst(inst);
 
@@ -474,9 +738,10 @@ impl GenIR {
 
     // 等于运算符
     pub(crate) fn gen_equ(&mut self, lval: Box<Var>, rval: Box<Var>) -> Box<Var> {
-        let tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), KwInt, false));
+        let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), TypeId::INT));
+        tmp.set_span(lval.get_span().to(rval.get_span()));
 
-        self.sym_tab.add_var(tmp.clone());
+        let tmp = self.sym_tab.add_var(tmp);
         let inst = Box::new(InterInst::new_common(OpEqu, tmp.clone(), Some(lval.clone()), Some(rval.clone())));
         self.sym_tab.add_inst(inst);
 
@@ -485,9 +750,10 @@ impl GenIR {
 
     // 不等于运算符
     pub(crate) fn gen_nequ(&mut self, lval: Box<Var>, rval: Box<Var>) -> Box<Var> {
-        let tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), KwInt, false));
+        let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), TypeId::INT));
+        tmp.set_span(lval.get_span().to(rval.get_span()));
 
-        self.sym_tab.add_var(tmp.clone());
+        let tmp = self.sym_tab.add_var(tmp);
         let inst = Box::new(InterInst::new_common(OpNe, tmp.clone(), Some(lval.clone()), Some(rval.clone())));
         self.sym_tab.add_inst(inst);
 
@@ -496,32 +762,39 @@ impl GenIR {
 
     // 加法运算符
     pub(crate) fn gen_add(&mut self, lval: Box<Var>, rval: Box<Var>) -> Box<Var> {
-        let tmp;
+        let mut tmp;
         let mut lval = lval.clone();
         let mut rval = rval.clone();
         // 指针和数组只能和基本类型相加
         if (lval.get_array() || lval.get_ptr()) && rval.is_base() {
+            if !self.not_float(&rval) {
+                return lval;
+            }
             tmp = Box::new(Var::new_copy_temp(self.sym_tab.get_scope_path(), lval.clone()));
             let step = Var::get_step(lval.clone());
             if let Some(s) = step {
                 rval = self.gen_mul(rval, s);
             }
-        } else if rval.is_base() && (rval.get_array() || rval.get_ptr()) {
+        } else if lval.is_base() && !rval.is_base() {
+            if !self.not_float(&lval) {
+                return lval;
+            }
             tmp = Box::new(Var::new_copy_temp(self.sym_tab.get_scope_path(), rval.clone()));
             let stop = Var::get_step(rval.clone());
             if let Some(s) = stop {
                 lval = self.gen_mul(lval, s);
             }
         } else if lval.is_base() && rval.is_base() {
-            // 基本类型
-            tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), KwInt, false));
+            // 基本类型，按常用算术转换确定结果类型
+            tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), types::common(lval.get_type(), rval.get_type())));
         } else {
-            sem_error(ExprNotBase as usize, "");
-            tmp = lval.clone();
+            sem_error(&self.sym_tab.get_sink(), ExprNotBase, "", lval.get_span().to(rval.get_span()));
+            return lval;
         }
 
         // 加法命令
-        self.sym_tab.add_var(tmp.clone());
+        tmp.set_span(lval.get_span().to(rval.get_span()));
+        let tmp = self.sym_tab.add_var(tmp);
         let inst = Box::new(InterInst::new_common(OpAdd, tmp.clone(), Some(lval.clone()), Some(rval.clone())));
         self.sym_tab.add_inst(inst);
 
@@ -530,15 +803,18 @@ impl GenIR {
 
     // 减法运算符
     pub(crate) fn gen_sub(&mut self, lval: Box<Var>, rval: Box<Var>) -> Box<Var> {
-        let tmp;
+        let mut tmp;
         let lval = lval.clone();
         let mut rval = rval.clone();
 
         if !rval.is_base() {
-            sem_error(ExprNotBase as usize, "");
-            tmp = lval.clone();
+            sem_error(&self.sym_tab.get_sink(), ExprNotBase, "", lval.get_span().to(rval.get_span()));
+            return lval;
         } else if lval.get_array() || lval.get_ptr() {
             // 指针和数组
+            if !self.not_float(&rval) {
+                return lval;
+            }
             tmp = Box::new(Var::new_copy_temp(self.sym_tab.get_scope_path(), lval.clone()));
             let step = Var::get_step(lval.clone());
             if let Some(s) = step {
@@ -546,11 +822,12 @@ impl GenIR {
             }
         } else {
             // 基本类型
-            tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), KwInt, false));
+            tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), types::common(lval.get_type(), rval.get_type())));
         }
 
         // 减法命令
-        self.sym_tab.add_var(tmp.clone());
+        tmp.set_span(lval.get_span().to(rval.get_span()));
+        let tmp = self.sym_tab.add_var(tmp);
         let inst = Box::new(InterInst::new_common(OpSub, tmp.clone(), Some(lval.clone()), Some(rval.clone())));
         self.sym_tab.add_inst(inst);
 
@@ -559,9 +836,10 @@ impl GenIR {
 
     // 乘法运算符
     pub(crate) fn gen_mul(&mut self, lval: Box<Var>, rval: Box<Var>) -> Box<Var> {
-        let tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), lval.get_type(), false));
+        let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), types::common(lval.get_type(), rval.get_type())));
+        tmp.set_span(lval.get_span().to(rval.get_span()));
 
-        self.sym_tab.add_var(tmp.clone());
+        let tmp = self.sym_tab.add_var(tmp);
         let inst = Box::new(InterInst::new_common(OpMul, tmp.clone(), Some(lval.clone()), Some(rval.clone())));
         self.sym_tab.add_inst(inst);
 
@@ -570,9 +848,10 @@ impl GenIR {
 
     // 除法运算符
     pub(crate) fn gen_div(&mut self, lval: Box<Var>, rval: Box<Var>) -> Box<Var> {
-        let tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), lval.get_type(), false));
+        let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), types::common(lval.get_type(), rval.get_type())));
+        tmp.set_span(lval.get_span().to(rval.get_span()));
 
-        self.sym_tab.add_var(tmp.clone());
+        let tmp = self.sym_tab.add_var(tmp);
         let inst = Box::new(InterInst::new_common(OpDiv, tmp.clone(), Some(lval.clone()), Some(rval.clone())));
         self.sym_tab.add_inst(inst);
 
@@ -581,14 +860,54 @@ impl GenIR {
 
     // 模运算
     pub(crate) fn gen_mod(&mut self, lval: Box<Var>, rval: Box<Var>) -> Box<Var> {
-        let tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), lval.get_type(), false));
+        if !self.not_float(&lval) || !self.not_float(&rval) {
+            return lval;
+        }
+        let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), types::common(lval.get_type(), rval.get_type())));
+        tmp.set_span(lval.get_span().to(rval.get_span()));
 
-        self.sym_tab.add_var(tmp.clone());
+        let tmp = self.sym_tab.add_var(tmp);
         let inst = Box::new(InterInst::new_common(OpMod, tmp.clone(), Some(lval.clone()), Some(rval.clone())));
         self.sym_tab.add_inst(inst);
 
         tmp
     }
+
+    // 位运算和移位，操作数只能是整数，移位结果的类型是左操作数提升后的类型
+    pub(crate) fn gen_bit(&mut self, opt: Tag, lval: Box<Var>, rval: Box<Var>) -> Box<Var> {
+        if !lval.is_base() || !rval.is_base() {
+            sem_error(&self.sym_tab.get_sink(), ExprNotBase, "", lval.get_span().to(rval.get_span()));
+            return lval;
+        }
+        if !self.not_float(&lval) || !self.not_float(&rval) {
+            return lval;
+        }
+        let t = if opt == SHL || opt == SHR { lval.get_type().promote() } else { types::common(lval.get_type(), rval.get_type()) };
+        if lval.get_literal() && rval.get_literal() {     // 常量直接计算，移位数越界时仍生成指令
+            if let Some(val) = lower::fold(opt, t, lval.get_val(), rval.get_val()) {
+                let mut tmp = Box::new(Var::new_int(val));
+                tmp.set_type(t);
+                tmp.set_span(lval.get_span().to(rval.get_span()));
+                return tmp;
+            }
+        }
+
+        let op = match opt {
+            LEA => OpBitAnd,
+            BOR => OpBitOr,
+            XOR => OpXor,
+            SHL => OpShl,
+            _ => OpShr,
+        };
+        let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), t));
+        tmp.set_span(lval.get_span().to(rval.get_span()));
+
+        let tmp = self.sym_tab.add_var(tmp);
+        let inst = Box::new(InterInst::new_common(op, tmp.clone(), Some(lval.clone()), Some(rval.clone())));
+        self.sym_tab.add_inst(inst);
+
+        tmp
+    }
 }
 
 impl GenIR {
@@ -597,32 +916,40 @@ impl GenIR {
     // 指针取值语句
     pub(crate) fn gen_ptr(&mut self, val: Box<Var>) -> Box<Var> {
         return if val.is_base() {
-            sem_error(ExprIsBase as usize, "");
+            sem_error(&self.sym_tab.get_sink(), ExprIsBase, "", val.get_span());
             val
         } else {
-            let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), val.get_type(), false));
+            let val = if val.is_ref() { self.gen_assign(val) } else { val };    // 先处理(*p)变量
+            let t = val.get_type().pointee().unwrap_or(TypeId::INT);
+            if t.is_array() {   // 指向数组时不取值，地址即为行首，退化为指向元素的指针
+                let mut val = if val.get_left() || val.get_array() { self.gen_assign(val) } else { val };
+                val.set_type(t.decay());
+                return val;
+            }
+
+            let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), t));
 
             tmp.set_left(true);
             tmp.set_pointer(val.clone());
+            tmp.set_span(val.get_span());
 
-            self.sym_tab.add_var(tmp.clone());
-
-            tmp
+            self.sym_tab.add_var(tmp)
         }
     }
 
     // 取址语句
     pub(crate) fn gen_lea(&mut self, val: Box<Var>) -> Option<Box<Var>> {
         return if !val.get_left() {
-            sem_error(ExprNotLeftVal as usize, ""); // 不能取地址
+            sem_error(&self.sym_tab.get_sink(), ExprNotLeftVal, "", val.get_span()); // 不能取地址
             Some(val)
         } else {
             return if val.is_ref() {
                 val.get_pointer()
             } else {
-                let tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), val.get_type(), true));
+                let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), val.get_type().pointer()));
+                tmp.set_span(val.get_span());
 
-                self.sym_tab.add_var(tmp.clone());
+                let tmp = self.sym_tab.add_var(tmp);
 
                 let inst = Box::new(InterInst::new_common(OpLea, tmp.clone(), Some(val.clone()), None));
                 self.sym_tab.add_inst(inst);
@@ -635,9 +962,10 @@ impl GenIR {
     // 取反
     pub(crate) fn gen_not(&mut self, val: Box<Var>) -> Option<Box<Var>> {
         // 生成整数
-        let tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), KwInt, false));
+        let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), TypeId::INT));
+        tmp.set_span(val.get_span());
 
-        self.sym_tab.add_var(tmp.clone());
+        let tmp = self.sym_tab.add_var(tmp);
         let inst = Box::new(InterInst::new_common(OpNot, tmp.clone(), Some(val.clone()), None));
         self.sym_tab.add_inst(inst);
 
@@ -647,23 +975,60 @@ impl GenIR {
     // 取负
     pub(crate) fn gen_minus(&mut self, val: Box<Var>) -> Option<Box<Var>> {
         if !val.is_base() {
-            sem_error(ExprNotBase as usize, "");
+            sem_error(&self.sym_tab.get_sink(), ExprNotBase, "", val.get_span());
             return Some(val.clone());
         }
 
-        let tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), KwInt, false));
+        let t = val.get_type().promote();
+        if val.get_literal() {      // 常量直接取负
+            let mut tmp = Box::new(if t.is_float() { Var::new_float(-val.get_float_val()) } else { Var::new_int(t.cast(-val.get_val())) });
+            tmp.set_type(t);
+            tmp.set_span(val.get_span());
+            return Some(tmp);
+        }
+
+        let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), t));
+        tmp.set_span(val.get_span());
 
-        self.sym_tab.add_var(tmp.clone());
+        let tmp = self.sym_tab.add_var(tmp);
         let inst = Box::new(InterInst::new_common(OpNeg, tmp.clone(), Some(val.clone()), None));
         self.sym_tab.add_inst(inst);
 
         Some(tmp)
     }
 
+    // 按位取反
+    pub(crate) fn gen_bit_not(&mut self, val: Box<Var>) -> Option<Box<Var>> {
+        if !val.is_base() {
+            sem_error(&self.sym_tab.get_sink(), ExprNotBase, "", val.get_span());
+            return Some(val.clone());
+        }
+        if !self.not_float(&val) {
+            return Some(val.clone());
+        }
+
+        let t = val.get_type().promote();
+        if val.get_literal() {      // 常量直接取反
+            let mut tmp = Box::new(Var::new_int(t.cast(!val.get_val())));
+            tmp.set_type(t);
+            tmp.set_span(val.get_span());
+            return Some(tmp);
+        }
+
+        let mut tmp = Box::new(Var::new_temp(self.sym_tab.get_scope_path(), t));
+        tmp.set_span(val.get_span());
+
+        let tmp = self.sym_tab.add_var(tmp);
+        let inst = Box::new(InterInst::new_common(OpBitNot, tmp.clone(), Some(val.clone()), None));
+        self.sym_tab.add_inst(inst);
+
+        Some(tmp)
+    }
+
     // 左自加
     pub(crate) fn gen_incl(&mut self, val: Box<Var>) -> Option<Box<Var>> {
         if !val.get_left() {
-            sem_error(ExprNotLeftVal as usize, "");
+            sem_error(&self.sym_tab.get_sink(), ExprNotLeftVal, "", val.get_span());
             return Some(val.clone());
         }
 
@@ -685,7 +1050,7 @@ impl GenIR {
     // 左自减
     pub(crate) fn gen_decl(&mut self, val: Box<Var>) -> Option<Box<Var>> {
         if !val.get_left() {
-            sem_error(ExprNotLeftVal as usize, "");
+            sem_error(&self.sym_tab.get_sink(), ExprNotLeftVal, "", val.get_span());
             return Some(val.clone());
         }
 
@@ -693,11 +1058,14 @@ impl GenIR {
             let t1 = self.gen_assign(val.clone());                      // t1 = *p
             let step = Var::get_step(val.clone());
             if let Some(s) = step {
-                let t2 = self.gen_sub(t1, s);                           // t2 = t1 + 1
+                let t2 = self.gen_sub(t1, s);                           // t2 = t1 - 1
                 return Some(self.gen_assign_stmt(val.clone(), t2.clone()));        // *p = t2
             }
         }
 
+        let inst = Box::new(InterInst::new_common(OpSub, val.clone(), Some(val.clone()), Var::get_step(val.clone())));
+        self.sym_tab.add_inst(inst);
+
         Some(val.clone())
     }
 
@@ -705,6 +1073,14 @@ impl GenIR {
     pub(crate) fn gen_incr(&mut self, val: Box<Var>) -> Option<Box<Var>> {
         let tmp = self.gen_assign(val.clone());
 
+        if val.is_ref() {           // (*p)++
+            if let Some(s) = Var::get_step(val.clone()) {
+                let t = self.gen_add(tmp.clone(), s);           // t = tmp + 1
+                self.gen_assign_stmt(val.clone(), t);           // *p = t
+            }
+            return Some(tmp);
+        }
+
         let inst = Box::new(InterInst::new_common(OpAdd, val.clone(), Some(val.clone()), Var::get_step(val.clone())));  // 中间代码val++
         self.sym_tab.add_inst(inst);
 
@@ -715,6 +1091,14 @@ impl GenIR {
     pub(crate) fn gen_decr(&mut self, val: Box<Var>) -> Option<Box<Var>> {
         let tmp = self.gen_assign(val.clone());
 
+        if val.is_ref() {           // (*p)--
+            if let Some(s) = Var::get_step(val.clone()) {
+                let t = self.gen_sub(tmp.clone(), s);           // t = tmp - 1
+                self.gen_assign_stmt(val.clone(), t);           // *p = t
+            }
+            return Some(tmp);
+        }
+
         let inst = Box::new(InterInst::new_common(OpSub, val.clone(), Some(val.clone()), Var::get_step(val.clone())));  // 中间代码val--
         self.sym_tab.add_inst(inst);
 
@@ -729,7 +1113,7 @@ impl GenIR {
     // 产生if头部
     pub(crate) fn gen_if_head(&mut self, cond: Option<Box<Var>>) -> Box<InterInst> {
         let _else = Box::new(InterInst::new_label());
-        if let Some(cond) = cond {
+        if let Some(cond) = cond.filter(|c| self.not_record(c)) {
             let mut cond = cond.clone();
             if cond.is_ref() {
                 cond = self.gen_assign(cond);
@@ -806,6 +1190,7 @@ impl GenIR {
 
     // 产生while条件
     pub(crate) fn gen_while_cond(&mut self, cond: Option<Box<Var>>, _exit: Option<Box<InterInst>>) {
+        let cond = cond.filter(|c| self.not_record(c));
         if cond.is_some() {
             let cond = cond.unwrap();
             let c;
@@ -833,12 +1218,14 @@ impl GenIR {
         let _exit = Box::new(InterInst::new_label());
 
         self.sym_tab.add_inst(_do.clone());
+        self.push(Some(_do.clone()), Some(_exit.clone()));
 
         (_do, _exit)
     }
 
     // 产生do-while循环尾部
     pub(crate) fn gen_do_while_tail(&mut self, cond: Option<Box<Var>>, _do: Box<InterInst>, _exit: Box<InterInst>) {
+        let cond = cond.filter(|c| self.not_record(c));
         if cond.is_some() {
             let cond = cond.unwrap();
             let c;
@@ -870,6 +1257,7 @@ impl GenIR {
         let _block = Box::new(InterInst::new_label());
         let _step = Box::new(InterInst::new_label());
 
+        let cond = cond.filter(|c| self.not_record(c));
         if cond.is_some() {
             let cond = cond.unwrap();
             let c;
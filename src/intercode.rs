@@ -2,9 +2,12 @@
 /*
 	四元式类，定义了中间代码的指令的形式
 */
+use std::io::{self, Write};
 use crate::common::Operator;
 use crate::gen_ir::GenIR;
+use crate::span::Span;
 use crate::symbol::{Fun, Var};
+use crate::types::{self, TypeId};
 
 #[derive(Clone)]
 pub(crate) struct InterInst {
@@ -15,6 +18,7 @@ pub(crate) struct InterInst {
     arg2: Option<Box<Var>>,           // 参数2
     fun: Option<Box<Fun>>,            // 函数
     target: Option<Box<InterInst>>,   // 跳转标号
+    span: Span,                       // 产生该指令的源代码位置
 }
 
 impl InterInst {
@@ -28,6 +32,7 @@ impl InterInst {
             arg2: None,
             fun: None,
             target: None,
+            span: Span::dummy(),
         }
     }
 
@@ -36,6 +41,7 @@ impl InterInst {
         let mut inst = InterInst::init();
 
         inst.set_op(op);
+        inst.set_span(rs.get_span());
         inst.set_result(Some(rs));
         inst.set_arg1(arg1);
         inst.set_arg2(arg2);
@@ -48,6 +54,7 @@ impl InterInst {
         let mut inst = InterInst::init();
 
         inst.set_op(op);
+        inst.set_span(rs.as_ref().map_or(fun.get_span(), |rs| rs.get_span()));
         inst.set_fun(fun);
         inst.set_result(rs);
         inst.set_arg2(None);
@@ -60,6 +67,7 @@ impl InterInst {
         let mut inst = InterInst::init();
 
         inst.set_op(op);
+        inst.set_span(arg1.get_span());
         inst.set_arg1(Some(arg1));
         inst.set_arg2(None);
         inst.set_result(None);
@@ -80,6 +88,7 @@ impl InterInst {
     pub(crate) fn new_jump(op: Operator, tar: Option<Box<InterInst>>, arg1: Option<Box<Var>>, arg2: Option<Box<Var>>) -> Self {
         let mut inst = InterInst::init();
         inst.set_op(op);
+        inst.set_span(arg1.as_ref().map_or(Span::dummy(), |arg| arg.get_span()));
         inst.set_target(tar);
         inst.set_arg1(arg1);
         inst.set_arg2(arg2);
@@ -112,10 +121,6 @@ impl InterInst {
         self.fun = Some(fun.clone());
     }
 
-    pub(crate) fn get_fun(&self) -> Option<Box<Fun>> {
-        self.fun.clone()
-    }
-
     pub(crate) fn set_target(&mut self, target: Option<Box<InterInst>>) {
         self.target = target;
     }
@@ -123,307 +128,852 @@ impl InterInst {
     pub(crate) fn get_label(&self) -> String {
         self.label.clone()
     }
+
+    pub(crate) fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
 }
 
 impl InterInst {
-    pub(crate) fn load_var(&self, reg32: String, reg8: String, var: Option<Box<Var>>) {
-        if var.is_none() {
-            return;
+    // 中间代码的文本形式
+    pub(crate) fn to_ir(&self) -> String {
+        let arg = |v: &Option<Box<Var>>| v.as_ref().map_or_else(String::new, |v| v.to_ir());
+        let (rs, arg1, arg2) = (arg(&self.result), arg(&self.arg1), arg(&self.arg2));
+        let target = self.target.as_ref().map_or_else(String::new, |t| t.get_label());
+        let fun = self.fun.as_ref().map_or_else(String::new, |f| f.get_name());
+
+        let inst = match self.op {
+            Operator::OpNop => "nop".to_string(),
+            Operator::OpDec => format!("dec {}", arg1),
+            Operator::OpEntry => "entry".to_string(),
+            Operator::OpExit => "exit".to_string(),
+            Operator::OpAs => format!("{} = {}", rs, arg1),
+            Operator::OpAdd => format!("{} = {} + {}", rs, arg1, arg2),
+            Operator::OpSub => format!("{} = {} - {}", rs, arg1, arg2),
+            Operator::OpMul => format!("{} = {} * {}", rs, arg1, arg2),
+            Operator::OpDiv => format!("{} = {} / {}", rs, arg1, arg2),
+            Operator::OpMod => format!("{} = {} % {}", rs, arg1, arg2),
+            Operator::OpNeg => format!("{} = -{}", rs, arg1),
+            Operator::OpGt => format!("{} = {} > {}", rs, arg1, arg2),
+            Operator::OpGe => format!("{} = {} >= {}", rs, arg1, arg2),
+            Operator::OpLt => format!("{} = {} < {}", rs, arg1, arg2),
+            Operator::OpLe => format!("{} = {} <= {}", rs, arg1, arg2),
+            Operator::OpEqu => format!("{} = {} == {}", rs, arg1, arg2),
+            Operator::OpNe => format!("{} = {} != {}", rs, arg1, arg2),
+            Operator::OpAnd => format!("{} = {} && {}", rs, arg1, arg2),
+            Operator::OpOr => format!("{} = {} || {}", rs, arg1, arg2),
+            Operator::OpNot => format!("{} = !{}", rs, arg1),
+            Operator::OpLea => format!("{} = &{}", rs, arg1),
+            Operator::OpSet => format!("*{} = {}", arg1, rs),
+            Operator::OpGet => format!("{} = *{}", rs, arg1),
+            Operator::OpJmp => format!("goto {}", target),
+            Operator::OpJt => format!("if {} goto {}", arg1, target),
+            Operator::OpJf => format!("if !{} goto {}", arg1, target),
+            Operator::OpJne => format!("if {} != {} goto {}", arg1, arg2, target),
+            Operator::OpArg => format!("arg {}", arg1),
+            Operator::OpProc => format!("call {}", fun),
+            Operator::OpCall => format!("{} = call {}", rs, fun),
+            Operator::OpRet => format!("return goto {}", target),
+            Operator::OpRetv => format!("return {} goto {}", arg1, target),
+            Operator::OpBitAnd => format!("{} = {} & {}", rs, arg1, arg2),
+            Operator::OpBitOr => format!("{} = {} | {}", rs, arg1, arg2),
+            Operator::OpXor => format!("{} = {} ^ {}", rs, arg1, arg2),
+            Operator::OpBitNot => format!("{} = ~{}", rs, arg1),
+            Operator::OpShl => format!("{} = {} << {}", rs, arg1, arg2),
+            Operator::OpShr => format!("{} = {} >> {}", rs, arg1, arg2),
+        };
+
+        if self.label.is_empty() {
+            format!("    {}", inst)
+        } else {
+            format!("{}:", self.label)
         }
+    }
+}
+
+// 汇编中的符号名，加$前缀，避免与寄存器名和NASM关键字冲突，如变量eax、byte
+pub(crate) fn symbol(name: &str) -> String {
+    format!("${}", name)
+}
+
+// 指针的常量初值，字符串标签同样加$前缀
+pub(crate) fn ptr_data(var: &Var) -> String {
+    let val = var.get_ptr_val();
+    if val.parse::<isize>().is_ok() { val } else { symbol(&val) }
+}
+
+// 变量的内存地址，全局变量使用名称，局部变量和参数使用ebp偏移
+fn address(var: &Var) -> String {
+    let offset = var.get_offset();
+    if offset == 0 {
+        symbol(&var.get_name())
+    } else {
+        format!("ebp{:+}", offset)
+    }
+}
+
+// 64位值高32位的地址
+fn high(addr: &str) -> String {
+    match addr.strip_prefix("ebp").and_then(|off| off.parse::<isize>().ok()) {
+        Some(off) => format!("ebp{:+}", off + 4),
+        None => format!("{}+4", addr),
+    }
+}
+
+// 32位寄存器的低8位和低16位，如eax的al和ax
+fn reg8(reg32: &str) -> String {
+    format!("{}l", &reg32[1..2])
+}
+
+fn reg16(reg32: &str) -> &str {
+    &reg32[1..]
+}
+
+// 结构体或联合对象的大小，其他变量返回None
+fn record_size(var: &Option<Box<Var>>) -> Option<isize> {
+    var.as_ref().filter(|v| v.is_record()).map(|v| v.get_size())
+}
+
+// 是否是long long变量，需要用两个寄存器运算
+fn wide(var: &Option<Box<Var>>) -> bool {
+    var.as_ref().is_some_and(|v| v.get_type().is_wide())
+}
+
+// 是否是浮点变量，需要用x87运算
+fn float(var: &Option<Box<Var>>) -> bool {
+    var.as_ref().is_some_and(|v| v.get_type().is_float())
+}
+
+// 浮点类型的内存操作数大小
+fn float_width(t: TypeId) -> &'static str {
+    if t == TypeId::FLOAT { "dword" } else { "qword" }
+}
+
+// 指针指向的类型，决定间接访问的宽度
+fn pointee(ptr: &Option<Box<Var>>) -> TypeId {
+    ptr.as_ref().and_then(|p| p.get_type().pointee()).unwrap_or(TypeId::INT)
+}
+
+// 按类型从内存加载到寄存器，不足4字节的按符号扩展，64位值只取低32位
+fn load_mem(out: &mut dyn Write, reg32: &str, addr: &str, t: TypeId) -> io::Result<()> {
+    let ext = if t.is_unsigned() { "movzx" } else { "movsx" };
+    match t.size() {
+        1 => writeln!(out, "{} {}, byte [{}]", ext, reg32, addr),
+        2 => writeln!(out, "{} {}, word [{}]", ext, reg32, addr),
+        _ => writeln!(out, "mov {}, [{}]", reg32, addr),
+    }
+}
+
+// 按类型从内存加载64位值到lo和hi，不足64位的扩展到hi
+fn load_mem_wide(out: &mut dyn Write, lo: &str, hi: &str, addr: &str, t: TypeId) -> io::Result<()> {
+    if t.is_wide() {
+        writeln!(out, "mov {}, [{}]", hi, high(addr))?;    // 先取高位，lo可能就是地址寄存器
+        return writeln!(out, "mov {}, [{}]", lo, addr);
+    }
+    load_mem(out, lo, addr, t)?;
+    extend(out, lo, hi, t)
+}
+
+// 把lo中类型为t的值扩展到hi，无符号数补0，有符号数补符号位
+fn extend(out: &mut dyn Write, lo: &str, hi: &str, t: TypeId) -> io::Result<()> {
+    if t.is_unsigned() {
+        writeln!(out, "mov {}, 0", hi)
+    } else {
+        writeln!(out, "mov {}, {}", hi, lo)?;
+        writeln!(out, "sar {}, 31", hi)
+    }
+}
+
+// 把edx:eax中类型为t的整数转换为浮点数压入x87栈，unsigned long long的最高位为1时补上2^64
+fn int_to_float(out: &mut dyn Write, t: TypeId) -> io::Result<()> {
+    writeln!(out, "push edx")?;
+    writeln!(out, "push eax")?;
+    writeln!(out, "fild qword [esp]")?;
+    writeln!(out, "add esp, 8")?;
+    if t == TypeId::ULLONG {
+        let label = GenIR::gen_lb();
+        writeln!(out, "test edx, edx")?;
+        writeln!(out, "jns {}", label)?;
+        writeln!(out, "push dword 0x5f800000")?;   // 2^64的单精度表示
+        writeln!(out, "fadd dword [esp]")?;
+        writeln!(out, "add esp, 4")?;
+        writeln!(out, "{}:", label)?;
+    }
+    Ok(())
+}
+
+// 把x87栈顶的值截断为64位整数保存到edx:eax并出栈
+// 临时把控制字的舍入方式设为向0截断，转换后恢复，[esp+8]保存原控制字，[esp+10]为修改后的控制字
+fn float_to_int(out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "sub esp, 12")?;
+    writeln!(out, "fnstcw [esp+8]")?;
+    writeln!(out, "mov ax, [esp+8]")?;
+    writeln!(out, "or ax, 0x0c00")?;
+    writeln!(out, "mov [esp+10], ax")?;
+    writeln!(out, "fldcw [esp+10]")?;
+    writeln!(out, "fistp qword [esp]")?;
+    writeln!(out, "fldcw [esp+8]")?;
+    writeln!(out, "pop eax")?;
+    writeln!(out, "pop edx")?;
+    writeln!(out, "add esp, 4")
+}
+
+// 按类型从内存加载数值到x87栈顶，整数先扩展到64位再转换
+fn load_mem_float(out: &mut dyn Write, addr: &str, t: TypeId) -> io::Result<()> {
+    if t.is_float() {
+        return writeln!(out, "fld {} [{}]", float_width(t), addr);
+    }
+    load_mem_wide(out, "eax", "edx", addr, t)?;
+    int_to_float(out, t)
+}
+
+// 比较的条件码后缀，无符号数使用above和below
+fn condition(cond: &str, unsigned: bool) -> &str {
+    match (cond, unsigned) {
+        ("g", true) => "a",
+        ("ge", true) => "ae",
+        ("l", true) => "b",
+        ("le", true) => "be",
+        _ => cond,
+    }
+}
+
+impl InterInst {
+    // 加载变量的值到寄存器，数组和字符串加载其地址
+    pub(crate) fn load_var(&self, out: &mut dyn Write, reg32: &str, var: &Option<Box<Var>>) -> io::Result<()> {
+        let var = match var {
+            Some(var) => var,
+            None => return Ok(()),
+        };
 
-        let var = var.unwrap();
-        let reg = if var.is_char() {
-            reg8.clone()
+        if var.get_literal() {          // 常量
+            if var.is_base() {
+                writeln!(out, "mov {}, {}", reg32, var.get_val() as i32)?;
+            } else {
+                writeln!(out, "mov {}, {}", reg32, symbol(&var.get_name()))?;
+            }
+        } else if var.get_array() {     // 数组地址
+            self.lea_var(out, reg32, &Some(var.clone()))?;
         } else {
-            reg32.clone()
+            load_mem(out, reg32, &address(var), var.get_type())?;
+        }
+
+        Ok(())
+    }
+
+    // 加载变量的64位值到lo和hi，不足64位的按其类型扩展
+    fn load_wide(&self, out: &mut dyn Write, lo: &str, hi: &str, var: &Option<Box<Var>>) -> io::Result<()> {
+        let v = match var {
+            Some(v) => v,
+            None => return Ok(()),
         };
 
-        if var.is_char() {
-            println!("mov {}, 0", reg32.clone());
+        if v.get_literal() && v.is_base() {
+            writeln!(out, "mov {}, {}", lo, v.get_val() as i32)?;
+            writeln!(out, "mov {}, {}", hi, (v.get_val() >> 32) as i32)?;
+        } else if v.get_literal() || v.get_array() {
+            self.load_var(out, lo, var)?;
+            writeln!(out, "mov {}, 0", hi)?;
+        } else {
+            load_mem_wide(out, lo, hi, &address(v), v.get_type())?;
         }
 
-        let name = var.get_name();
-        if var.not_const() {
-            let offset = var.get_offset();
+        Ok(())
+    }
 
-            if offset == 0 {
-                if !var.get_array() {
-                    println!("mov {}, [{}]", reg, name);
-                } else {
-                    println!("mov {}, {}", reg, name);
-                }
-            } else {
-                if !var.get_array() {
-                    println!("mov {}, [ebp + {}]", reg, offset);
-                } else {
-                    println!("mov {}, [ebp + {}]", reg, offset);
+    // 加载变量作为条件，64位值的两半合并后判断是否为0
+    fn load_test(&self, out: &mut dyn Write, reg32: &str, hi: &str, var: &Option<Box<Var>>) -> io::Result<()> {
+        if float(var) {     // 与0比较，结果为0或1
+            self.load_float(out, var)?;
+            writeln!(out, "fldz")?;
+            writeln!(out, "fcomip st0, st1")?;
+            writeln!(out, "fstp st0")?;
+            writeln!(out, "mov {}, 0", reg32)?;
+            return writeln!(out, "setne {}", reg8(reg32));
+        }
+        if wide(var) {
+            self.load_wide(out, reg32, hi, var)?;
+            writeln!(out, "or {}, {}", reg32, hi)
+        } else {
+            self.load_var(out, reg32, var)
+        }
+    }
+
+    // 加载变量的值到x87栈顶，整数转换为浮点数，浮点常量的二进制位经栈加载
+    fn load_float(&self, out: &mut dyn Write, var: &Option<Box<Var>>) -> io::Result<()> {
+        let v = match var {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        if v.get_literal() && v.get_type().is_float() {
+            let bits = v.get_float_val().to_bits();
+            writeln!(out, "push dword 0x{:08x}", bits >> 32)?;
+            writeln!(out, "push dword 0x{:08x}", bits as u32)?;
+            writeln!(out, "fld qword [esp]")?;
+            writeln!(out, "add esp, 8")
+        } else if v.get_literal() {
+            self.load_wide(out, "eax", "edx", var)?;
+            int_to_float(out, v.get_type())
+        } else {
+            load_mem_float(out, &address(v), v.get_type())
+        }
+    }
+
+    // 把x87栈顶的值保存到变量并出栈，整数变量截断保存
+    fn store_float(&self, out: &mut dyn Write, var: &Option<Box<Var>>) -> io::Result<()> {
+        let v = match var {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        let t = v.get_type();
+        if t.is_float() {
+            return writeln!(out, "fstp {} [{}]", float_width(t), address(v));
+        }
+        float_to_int(out)?;
+        if t.is_wide() {
+            self.store_wide(out, "eax", "edx", var)
+        } else {
+            self.store_var(out, "eax", var)
+        }
+    }
+
+    // 加载变量的地址到寄存器
+    pub(crate) fn lea_var(&self, out: &mut dyn Write, reg32: &str, var: &Option<Box<Var>>) -> io::Result<()> {
+        let var = match var {
+            Some(var) => var,
+            None => return Ok(()),
+        };
+
+        if var.get_offset() == 0 {
+            writeln!(out, "mov {}, {}", reg32, symbol(&var.get_name()))?;
+        } else {
+            writeln!(out, "lea {}, [{}]", reg32, address(var))?;
+        }
+
+        Ok(())
+    }
+
+    // 将寄存器的值按变量的宽度保存到变量
+    pub(crate) fn store_var(&self, out: &mut dyn Write, reg32: &str, var: &Option<Box<Var>>) -> io::Result<()> {
+        let var = match var {
+            Some(var) => var,
+            None => return Ok(()),
+        };
+
+        match var.get_type().size() {
+            1 => writeln!(out, "mov byte [{}], {}", address(var), reg8(reg32))?,
+            2 => writeln!(out, "mov word [{}], {}", address(var), reg16(reg32))?,
+            _ => writeln!(out, "mov [{}], {}", address(var), reg32)?,
+        }
+
+        Ok(())
+    }
+
+    // 将lo和hi中的64位值保存到变量
+    fn store_wide(&self, out: &mut dyn Write, lo: &str, hi: &str, var: &Option<Box<Var>>) -> io::Result<()> {
+        let var = match var {
+            Some(var) => var,
+            None => return Ok(()),
+        };
+
+        let addr = address(var);
+        writeln!(out, "mov [{}], {}", addr, lo)?;
+        writeln!(out, "mov [{}], {}", high(&addr), hi)
+    }
+
+    // 运算结果在eax中，long long结果的高32位在edx中
+    fn store_result(&self, out: &mut dyn Write) -> io::Result<()> {
+        if wide(&self.result) {
+            self.store_wide(out, "eax", "edx", &self.result)
+        } else {
+            self.store_var(out, "eax", &self.result)
+        }
+    }
+
+    // 局部变量的常量初始化
+    pub(crate) fn init_var(&self, out: &mut dyn Write, var: &Option<Box<Var>>) -> io::Result<()> {
+        let v = match var {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        if !v.is_un_init() {
+            let t = v.get_type();
+            if t.is_float() {       // 直接写入浮点数的二进制位
+                let bits = t.float_bits(v.get_float_val());
+                let addr = address(v);
+                writeln!(out, "mov dword [{}], 0x{:08x}", addr, bits as u32)?;
+                if t.size() == 8 {
+                    writeln!(out, "mov dword [{}], 0x{:08x}", high(&addr), bits >> 32)?;
                 }
+                return Ok(());
             }
-        } else {        // 常量
-            if var.is_base() {
-                println!("mov {}, {}", reg, var.get_val())
+            if t.is_wide() {
+                writeln!(out, "mov eax, {}", v.get_val() as i32)?;
+                writeln!(out, "mov edx, {}", (v.get_val() >> 32) as i32)?;
+                return self.store_wide(out, "eax", "edx", var);
+            }
+            if v.is_base() {
+                writeln!(out, "mov eax, {}", v.get_val())?;
             } else {
-                println!("mov {}, {}", reg, name);
+                writeln!(out, "mov eax, {}", ptr_data(v))?;
             }
+            self.store_var(out, "eax", var)?;
         }
+
+        Ok(())
     }
 
-    pub(crate) fn lea_var(&self, reg: String, var: Option<Box<Var>>) {
-        if var.is_none() {
-            return;
+    // 双目运算，操作数分别加载到eax和ebx，long long运算的高32位分别在edx和ecx中
+    fn load_args(&self, out: &mut dyn Write) -> io::Result<()> {
+        if wide(&self.result) {
+            return self.load_wide_args(out);
         }
+        self.load_var(out, "eax", &self.arg1)?;
+        self.load_var(out, "ebx", &self.arg2)
+    }
 
-        let var = var.unwrap();
-        let name = var.get_name();
-        let offset = var.get_offset();
+    fn load_wide_args(&self, out: &mut dyn Write) -> io::Result<()> {
+        self.load_wide(out, "eax", "edx", &self.arg1)?;
+        self.load_wide(out, "ebx", "ecx", &self.arg2)
+    }
 
-        if offset == 0 {
-            println!("mov {}, {}", reg, name);
+    // 比较运算的操作数类型，按常用算术转换确定，指针按无符号数比较
+    fn compare_type(&self) -> TypeId {
+        let t = |v: &Option<Box<Var>>| v.as_ref().map_or(TypeId::INT, |v| v.get_type());
+        let (a, b) = (t(&self.arg1), t(&self.arg2));
+        if a.is_arith() && b.is_arith() { types::common(a, b) } else { TypeId::UINT }
+    }
+
+    // 比较运算，结果保存到ecx，long long按减法的标志位比较，大于和小于等于交换操作数
+    fn compare(&self, out: &mut dyn Write, cond: &str) -> io::Result<()> {
+        let t = self.compare_type();
+        let mut cond = cond;
+        if t.is_float() {
+            // fcomip按无符号数的方式设置标志位
+            self.load_float(out, &self.arg2)?;
+            self.load_float(out, &self.arg1)?;
+            writeln!(out, "fcomip st0, st1")?;
+            writeln!(out, "fstp st0")?;
+            writeln!(out, "mov ecx, 0")?;
+        } else if t.is_wide() {
+            self.load_wide_args(out)?;
+            match cond {
+                "g" | "le" => {
+                    writeln!(out, "cmp ebx, eax")?;
+                    writeln!(out, "sbb ecx, edx")?;
+                    cond = if cond == "g" { "l" } else { "ge" };
+                },
+                "l" | "ge" => {
+                    writeln!(out, "cmp eax, ebx")?;
+                    writeln!(out, "sbb edx, ecx")?;
+                },
+                _ => {
+                    writeln!(out, "xor eax, ebx")?;
+                    writeln!(out, "xor edx, ecx")?;
+                    writeln!(out, "or eax, edx")?;
+                },
+            }
+            writeln!(out, "mov ecx, 0")?;       // mov不影响标志位
         } else {
-            println!("lea {}, [ebp + {}]", reg, offset);
+            self.load_var(out, "eax", &self.arg1)?;
+            self.load_var(out, "ebx", &self.arg2)?;
+            writeln!(out, "mov ecx, 0")?;
+            writeln!(out, "cmp eax, ebx")?;
+        }
+        writeln!(out, "set{} cl", condition(cond, t.is_unsigned() || t.is_float()))?;
+        self.store_var(out, "ecx", &self.result)
+    }
+
+    // long long除法和取模调用的运行库函数
+    pub(crate) fn helper(&self) -> Option<&'static str> {
+        if !wide(&self.result) {
+            return None;
+        }
+        let unsigned = self.result.as_ref().is_some_and(|v| v.get_type().is_unsigned());
+        match (self.op, unsigned) {
+            (Operator::OpDiv, false) => Some("__divdi3"),
+            (Operator::OpDiv, true) => Some("__udivdi3"),
+            (Operator::OpMod, false) => Some("__moddi3"),
+            (Operator::OpMod, true) => Some("__umoddi3"),
+            _ => None,
         }
     }
 
-    pub(crate) fn store_var(&self, reg32: String, reg8: String, var: Option<Box<Var>>) {
-        if var.is_none() {
-            return;
+    // 除法和取模，结果分别在eax和edx中，long long调用运行库函数，结果在edx:eax中
+    fn divide(&self, out: &mut dyn Write) -> io::Result<()> {
+        self.load_args(out)?;
+        if let Some(helper) = self.helper() {
+            for reg in ["ecx", "ebx", "edx", "eax"] {
+                writeln!(out, "push {}", reg)?;
+            }
+            writeln!(out, "call {}", helper)?;
+            writeln!(out, "add esp, 16")?;
+            return self.store_result(out);
         }
 
-        let var = var.unwrap();
-        let reg = if var.is_char() {
-            reg8
+        if self.result.as_ref().is_some_and(|v| v.get_type().is_unsigned()) {
+            writeln!(out, "mov edx, 0")?;
+            writeln!(out, "div ebx")?;
         } else {
-            reg32
-        };
-        let name = var.get_name();
-        let offset = var.get_offset();
-
-        if offset == 0 {
-            println!("mov [{}], {}", name, reg);
+            writeln!(out, "cdq")?;
+            writeln!(out, "idiv ebx")?;
+        }
+        if self.op == Operator::OpMod {
+            self.store_var(out, "edx", &self.result)
         } else {
-            println!("mov [ebp + %{}], {}", offset, name);
+            self.store_var(out, "eax", &self.result)
         }
     }
 
-    pub(crate) fn init_var(&self, var: Option<Box<Var>>) {
-        if var.is_none() {
-            return;
+    // 移位，移位数在cl中，右移按结果类型区分算术和逻辑移位，long long移位数不小于32时再移动一个双字
+    fn shift(&self, out: &mut dyn Write) -> io::Result<()> {
+        let unsigned = self.result.as_ref().is_some_and(|v| v.get_type().is_unsigned());
+        let sr = if unsigned { "shr" } else { "sar" };
+        if !wide(&self.result) {
+            self.load_var(out, "eax", &self.arg1)?;
+            self.load_var(out, "ecx", &self.arg2)?;
+            writeln!(out, "{} eax, cl", if self.op == Operator::OpShl { "shl" } else { sr })?;
+            return self.store_result(out);
         }
 
-        let var = var.unwrap();
-        if !var.is_un_init() {
-            println!("mov eax, {}", var.get_val());
+        let label = GenIR::gen_lb();
+        self.load_wide(out, "eax", "edx", &self.arg1)?;
+        self.load_var(out, "ecx", &self.arg2)?;
+        if self.op == Operator::OpShl {
+            writeln!(out, "shld edx, eax, cl")?;
+            writeln!(out, "shl eax, cl")?;
+            writeln!(out, "test cl, 32")?;
+            writeln!(out, "je {}", label)?;
+            writeln!(out, "mov edx, eax")?;
+            writeln!(out, "mov eax, 0")?;
         } else {
-            println!("mov eax, {}", var.get_ptr_val());
+            writeln!(out, "shrd eax, edx, cl")?;
+            writeln!(out, "{} edx, cl", sr)?;
+            writeln!(out, "test cl, 32")?;
+            writeln!(out, "je {}", label)?;
+            writeln!(out, "mov eax, edx")?;
+            if unsigned {
+                writeln!(out, "mov edx, 0")?;
+            } else {
+                writeln!(out, "sar edx, 31")?;
+            }
+        }
+        writeln!(out, "{}:", label)?;
+        self.store_result(out)
+    }
+
+    // 浮点运算，两个操作数依次压入x87栈，运算并出栈后保存结果
+    fn float_arith(&self, out: &mut dyn Write, inst: &str) -> io::Result<()> {
+        self.load_float(out, &self.arg1)?;
+        self.load_float(out, &self.arg2)?;
+        writeln!(out, "{} st1, st0", inst)?;
+        self.store_float(out, &self.result)
+    }
+
+    // 拷贝结构体或联合，eax为源地址，ebx为目的地址，先按4字节拷贝，剩余部分按字节拷贝
+    fn copy_record(&self, out: &mut dyn Write, size: isize) -> io::Result<()> {
+        let mut off = 0;
+        while off + 4 <= size {
+            writeln!(out, "mov ecx, [eax+{}]", off)?;
+            writeln!(out, "mov [ebx+{}], ecx", off)?;
+            off += 4;
         }
+        while off < size {
+            writeln!(out, "mov cl, [eax+{}]", off)?;
+            writeln!(out, "mov [ebx+{}], cl", off)?;
+            off += 1;
+        }
+
+        Ok(())
+    }
+
+    // 跳转目标标签
+    fn target_label(&self) -> String {
+        self.target.as_ref().map_or_else(String::new, |t| t.get_label())
     }
 
-    pub(crate) fn to_x86(&self) {
-        if self.label != "" {
-            println!("{}:", self.label.as_str());
+    // 翻译为x86汇编，fun为指令所属的函数
+    pub(crate) fn to_x86(&self, out: &mut dyn Write, fun: &Fun) -> io::Result<()> {
+        if !self.label.is_empty() {
+            writeln!(out, "{}:", self.label)?;
+            return Ok(());
         }
 
         match self.op {
             Operator::OpNop => {
-                println!("nop");
+                writeln!(out, "nop")?;
             },
             Operator::OpDec => {
-                self.init_var(self.arg1.clone());
+                self.init_var(out, &self.arg1)?;
             },
             Operator::OpEntry => {
-                println!("push ebp");
-                println!("mov ebp, esp");
-                let fun = self.get_fun().unwrap();
-                println!("sub eps, {}", fun.get_max_depth());
+                writeln!(out, "push ebp")?;
+                writeln!(out, "mov ebp, esp")?;
+                writeln!(out, "sub esp, {}", fun.get_max_depth())?;
+                writeln!(out, "mov [ebp-4], ebx")?;
             },
             Operator::OpExit => {
-                println!("mov esp, ebp");
-                println!("pop ebp");
-                println!("ret");
+                writeln!(out, "mov ebx, [ebp-4]")?;
+                writeln!(out, "mov esp, ebp")?;
+                writeln!(out, "pop ebp")?;
+                writeln!(out, "ret")?;
             },
             Operator::OpAs => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                self.store_var("eax".to_string(), "al".to_string(), self.result.clone());
+                if let Some(size) = record_size(&self.result) {
+                    self.lea_var(out, "eax", &self.arg1)?;
+                    self.lea_var(out, "ebx", &self.result)?;
+                    return self.copy_record(out, size);
+                }
+                if float(&self.result) || float(&self.arg1) {     // 经x87转换
+                    self.load_float(out, &self.arg1)?;
+                    return self.store_float(out, &self.result);
+                }
+                if wide(&self.result) {
+                    self.load_wide(out, "eax", "edx", &self.arg1)?;
+                } else {
+                    self.load_var(out, "eax", &self.arg1)?;
+                }
+                self.store_result(out)?;
             },
+            Operator::OpAdd if float(&self.result) => self.float_arith(out, "faddp")?,
+            Operator::OpSub if float(&self.result) => self.float_arith(out, "fsubp")?,
+            Operator::OpMul if float(&self.result) => self.float_arith(out, "fmulp")?,
+            Operator::OpDiv if float(&self.result) => self.float_arith(out, "fdivp")?,
             Operator::OpAdd => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                self.load_var("ebx".to_string(), "bl".to_string(), self.arg2.clone());
-                println!("add eax, ebx");
-                self.store_var("eax".to_string(), "al".to_string(), self.result.clone());
+                self.load_args(out)?;
+                writeln!(out, "add eax, ebx")?;
+                if wide(&self.result) {
+                    writeln!(out, "adc edx, ecx")?;
+                }
+                self.store_result(out)?;
             },
             Operator::OpSub => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                self.load_var("ebx".to_string(), "bl".to_string(), self.arg2.clone());
-                println!("sub eax, ebx");
-                self.store_var("eax".to_string(), "al".to_string(), self.result.clone());
+                self.load_args(out)?;
+                writeln!(out, "sub eax, ebx")?;
+                if wide(&self.result) {
+                    writeln!(out, "sbb edx, ecx")?;
+                }
+                self.store_result(out)?;
             },
             Operator::OpMul => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                self.load_var("ebx".to_string(), "bl".to_string(), self.arg2.clone());
-                println!("imul ebx");
-                self.store_var("eax".to_string(), "al".to_string(), self.result.clone());
-            },
-            Operator::OpDiv => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                self.load_var("ebx".to_string(), "bl".to_string(), self.arg2.clone());
-                println!("idiv ebx");
-                self.store_var("eax".to_string(), "al".to_string(), self.result.clone());
-            },
-            Operator::OpMod => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                self.load_var("ebx".to_string(), "bl".to_string(), self.arg2.clone());
-                println!("idiv ebx");
-                self.store_var("eax".to_string(), "dl".to_string(), self.result.clone());
+                self.load_args(out)?;
+                if wide(&self.result) {     // 低位相乘，再加上两个交叉项的低32位
+                    writeln!(out, "imul edx, ebx")?;
+                    writeln!(out, "imul ecx, eax")?;
+                    writeln!(out, "add ecx, edx")?;
+                    writeln!(out, "mul ebx")?;
+                    writeln!(out, "add edx, ecx")?;
+                } else {
+                    writeln!(out, "imul ebx")?;
+                }
+                self.store_result(out)?;
             },
+            Operator::OpDiv | Operator::OpMod => self.divide(out)?,
             Operator::OpNeg => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                println!("neg eax");
-                self.store_var("eax".to_string(), "al".to_string(), self.result.clone());
-            },
-            Operator::OpGt => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                self.load_var("ebx".to_string(), "bl".to_string(), self.arg2.clone());
-                println!("mov ecx, 0");
-                println!("cmp eax, ebx");
-                println!("setg cl");
-                self.store_var("ecx".to_string(), "cl".to_string(), self.result.clone());
-            },
-            Operator::OpGe => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                self.load_var("ebx".to_string(), "bl".to_string(), self.arg2.clone());
-                println!("mov ecx, 0");
-                println!("cmp eax, ebx");
-                println!("setge cl");
-                self.store_var("ecx".to_string(), "cl".to_string(), self.result.clone());
-            },
-            Operator::OpLt => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                self.load_var("ebx".to_string(), "bl".to_string(), self.arg2.clone());
-                println!("mov ecx, 0");
-                println!("cmp eax, ebx");
-                println!("setl cl");
-                self.store_var("ecx".to_string(), "cl".to_string(), self.result.clone());
-            },
-            Operator::OpLe => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                self.load_var("ebx".to_string(), "bl".to_string(), self.arg2.clone());
-                println!("mov ecx, 0");
-                println!("cmp eax, ebx");
-                println!("setle cl");
-                self.store_var("ecx".to_string(), "cl".to_string(), self.result.clone());
+                if float(&self.result) {
+                    self.load_float(out, &self.arg1)?;
+                    writeln!(out, "fchs")?;
+                    return self.store_float(out, &self.result);
+                }
+                if wide(&self.result) {
+                    self.load_wide(out, "eax", "edx", &self.arg1)?;
+                    writeln!(out, "neg eax")?;
+                    writeln!(out, "adc edx, 0")?;
+                    writeln!(out, "neg edx")?;
+                } else {
+                    self.load_var(out, "eax", &self.arg1)?;
+                    writeln!(out, "neg eax")?;
+                }
+                self.store_result(out)?;
             },
-            Operator::OpEqu => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                self.load_var("ebx".to_string(), "bl".to_string(), self.arg2.clone());
-                println!("mov ecx, 0");
-                println!("cmp eax, ebx");
-                println!("sete cl");
-                self.store_var("ecx".to_string(), "cl".to_string(), self.result.clone());
+            Operator::OpBitAnd | Operator::OpBitOr | Operator::OpXor => {
+                let inst = match self.op {
+                    Operator::OpBitAnd => "and",
+                    Operator::OpBitOr => "or",
+                    _ => "xor",
+                };
+                self.load_args(out)?;
+                writeln!(out, "{} eax, ebx", inst)?;
+                if wide(&self.result) {
+                    writeln!(out, "{} edx, ecx", inst)?;
+                }
+                self.store_result(out)?;
             },
-            Operator::OpNe => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                self.load_var("ebx".to_string(), "bl".to_string(), self.arg2.clone());
-                println!("mov ecx, 0");
-                println!("cmp eax, ebx");
-                println!("setne cl");
-                self.store_var("ecx".to_string(), "cl".to_string(), self.result.clone());
+            Operator::OpBitNot => {
+                if wide(&self.result) {
+                    self.load_wide(out, "eax", "edx", &self.arg1)?;
+                    writeln!(out, "not edx")?;
+                } else {
+                    self.load_var(out, "eax", &self.arg1)?;
+                }
+                writeln!(out, "not eax")?;
+                self.store_result(out)?;
             },
+            Operator::OpShl | Operator::OpShr => self.shift(out)?,
+            Operator::OpGt => self.compare(out, "g")?,
+            Operator::OpGe => self.compare(out, "ge")?,
+            Operator::OpLt => self.compare(out, "l")?,
+            Operator::OpLe => self.compare(out, "le")?,
+            Operator::OpEqu => self.compare(out, "e")?,
+            Operator::OpNe => self.compare(out, "ne")?,
             Operator::OpAnd => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                println!("cmp eax, 0");
-                println!("setne cl");
-                self.load_var("ebx".to_string(), "bl".to_string(), self.arg2.clone());
-                println!("cmp ebx, 0");
-                println!("setne bl");
-                println!("add eax, ebx");
-                self.store_var("eax".to_string(), "al".to_string(), self.result.clone());
+                self.load_test(out, "eax", "edx", &self.arg1)?;
+                writeln!(out, "cmp eax, 0")?;
+                writeln!(out, "setne cl")?;
+                self.load_test(out, "ebx", "edx", &self.arg2)?;
+                writeln!(out, "cmp ebx, 0")?;
+                writeln!(out, "setne bl")?;
+                writeln!(out, "and cl, bl")?;
+                writeln!(out, "movzx ecx, cl")?;
+                self.store_var(out, "ecx", &self.result)?;
             },
             Operator::OpOr => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                println!("cmp eax, 0");
-                println!("setne al");
-                self.load_var("ebx".to_string(), "bl".to_string(), self.arg2.clone());
-                println!("cmp ebx, 0");
-                println!("setne bl");
-                println!("or eax, ebx");
-                self.store_var("eax".to_string(), "al".to_string(), self.result.clone());
+                self.load_test(out, "eax", "edx", &self.arg1)?;
+                writeln!(out, "cmp eax, 0")?;
+                writeln!(out, "setne cl")?;
+                self.load_test(out, "ebx", "edx", &self.arg2)?;
+                writeln!(out, "cmp ebx, 0")?;
+                writeln!(out, "setne bl")?;
+                writeln!(out, "or cl, bl")?;
+                writeln!(out, "movzx ecx, cl")?;
+                self.store_var(out, "ecx", &self.result)?;
             },
             Operator::OpNot => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                println!("mov ebx, 0");
-                println!("cmp eax, 0");
-                println!("sete bl");
-                self.store_var("ebx".to_string(), "bl".to_string(), self.result.clone());
+                self.load_test(out, "eax", "edx", &self.arg1)?;
+                writeln!(out, "mov ebx, 0")?;
+                writeln!(out, "cmp eax, 0")?;
+                writeln!(out, "sete bl")?;
+                self.store_var(out, "ebx", &self.result)?;
             },
             Operator::OpLea => {
-                self.lea_var("eax".to_string(), self.arg1.clone());
-                self.store_var("eax".to_string(), "al".to_string(), self.result.clone());
+                self.lea_var(out, "eax", &self.arg1)?;
+                self.store_var(out, "eax", &self.result)?;
             },
-            Operator::OpSet => {
-                self.load_var("eax".to_string(), "al".to_string(), self.result.clone());
-                self.load_var("ebx".to_string(), "bl".to_string(), self.result.clone());
-                println!("mov [ebx], eax");
+            Operator::OpSet => {    // *arg1 = result，按指针指向的类型决定宽度
+                if let Some(size) = record_size(&self.result) {
+                    self.lea_var(out, "eax", &self.result)?;
+                    self.load_var(out, "ebx", &self.arg1)?;
+                    return self.copy_record(out, size);
+                }
+                let t = pointee(&self.arg1);
+                if t.is_float() {
+                    self.load_float(out, &self.result)?;
+                    self.load_var(out, "ebx", &self.arg1)?;
+                    return writeln!(out, "fstp {} [ebx]", float_width(t));
+                }
+                if float(&self.result) {
+                    self.load_float(out, &self.result)?;
+                    float_to_int(out)?;
+                } else if t.is_wide() {
+                    self.load_wide(out, "eax", "edx", &self.result)?;
+                } else {
+                    self.load_var(out, "eax", &self.result)?;
+                }
+                self.load_var(out, "ebx", &self.arg1)?;
+                match t.size() {
+                    1 => writeln!(out, "mov [ebx], al")?,
+                    2 => writeln!(out, "mov [ebx], ax")?,
+                    8 => {
+                        writeln!(out, "mov [ebx], eax")?;
+                        writeln!(out, "mov [ebx+4], edx")?;
+                    },
+                    _ => writeln!(out, "mov [ebx], eax")?,
+                }
             },
-            Operator::OpGet => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                println!("mov eax, [eax]");
-                self.store_var("eax".to_string(), "al".to_string(), self.result.clone());
+            Operator::OpGet => {    // result = *arg1
+                if let Some(size) = record_size(&self.result) {
+                    self.load_var(out, "eax", &self.arg1)?;
+                    self.lea_var(out, "ebx", &self.result)?;
+                    return self.copy_record(out, size);
+                }
+                self.load_var(out, "eax", &self.arg1)?;
+                if float(&self.result) || pointee(&self.arg1).is_float() {
+                    load_mem_float(out, "eax", pointee(&self.arg1))?;
+                    return self.store_float(out, &self.result);
+                }
+                if wide(&self.result) {
+                    load_mem_wide(out, "eax", "edx", "eax", pointee(&self.arg1))?;
+                } else {
+                    load_mem(out, "eax", "eax", pointee(&self.arg1))?;
+                }
+                self.store_result(out)?;
             },
             Operator::OpJmp => {
-                let target = self.target.clone().unwrap();
-                let label = target.get_label();
-                println!("jmp {}", label);
+                writeln!(out, "jmp {}", self.target_label())?;
             },
             Operator::OpJt => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                println!("cmp eax, 0");
-                let target = self.target.clone().unwrap();
-                let label = target.get_label();
-                println!("jne {}", label);
+                self.load_test(out, "eax", "edx", &self.arg1)?;
+                writeln!(out, "cmp eax, 0")?;
+                writeln!(out, "jne {}", self.target_label())?;
             },
             Operator::OpJf => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                println!("cmp eax, 0");
-                let target = self.target.clone().unwrap();
-                let label = target.get_label();
-                println!("je {}", label);
+                self.load_test(out, "eax", "edx", &self.arg1)?;
+                writeln!(out, "cmp eax, 0")?;
+                writeln!(out, "je {}", self.target_label())?;
             },
             Operator::OpJne => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                self.load_var("ebx".to_string(), "bl".to_string(), self.arg2.clone());
-                println!("cmp eax, ebx");
-                let target = self.target.clone().unwrap();
-                let label = target.get_label();
-                println!("jne {}", label);
+                if self.compare_type().is_wide() {
+                    self.load_wide_args(out)?;
+                    writeln!(out, "xor eax, ebx")?;
+                    writeln!(out, "xor edx, ecx")?;
+                    writeln!(out, "or eax, edx")?;
+                } else {
+                    self.load_var(out, "eax", &self.arg1)?;
+                    self.load_var(out, "ebx", &self.arg2)?;
+                    writeln!(out, "cmp eax, ebx")?;
+                }
+                writeln!(out, "jne {}", self.target_label())?;
             },
             Operator::OpArg => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                println!("push eax");
-            },
-            Operator::OpProc => {
-                let fun = self.fun.clone().unwrap();
-                println!("call {}", fun.get_name());
-                println!("add esp, {}", fun.get_para_var().len() * 4);
+                if let Some(v) = self.arg1.as_ref().filter(|v| v.get_type().is_float()) {
+                    let t = v.get_type();
+                    self.load_float(out, &self.arg1)?;
+                    writeln!(out, "sub esp, {}", t.size())?;
+                    return writeln!(out, "fstp {} [esp]", float_width(t));
+                }
+                if wide(&self.arg1) {
+                    self.load_wide(out, "eax", "edx", &self.arg1)?;
+                    writeln!(out, "push edx")?;
+                } else {
+                    self.load_var(out, "eax", &self.arg1)?;
+                }
+                writeln!(out, "push eax")?;
             },
-            Operator::OpCall => {
-                let fun = self.fun.clone().unwrap();
-                println!("call {}", fun.get_name());
-                println!("add esp, {}", fun.get_para_var().len() * 4);
-                self.load_var("eax".to_string(), "al".to_string(), self.result.clone());
+            Operator::OpProc | Operator::OpCall => {
+                let callee = self.fun.as_ref().unwrap();
+                writeln!(out, "call {}", symbol(&callee.get_name()))?;
+                let size = callee.get_para_size();
+                if size > 0 {
+                    writeln!(out, "add esp, {}", size)?;
+                }
+                if float(&self.result) {   // 浮点返回值在x87栈顶
+                    self.store_float(out, &self.result)?;
+                } else {
+                    self.store_result(out)?;
+                }
             },
             Operator::OpRet => {
-                let target = self.target.clone().unwrap();
-                let label = target.get_label();
-                println!("jmp {}", label);
+                writeln!(out, "jmp {}", self.target_label())?;
             },
             Operator::OpRetv => {
-                self.load_var("eax".to_string(), "al".to_string(), self.arg1.clone());
-                let target = self.target.clone().unwrap();
-                let label = target.get_label();
-                println!("jmp {}", label);
+                if fun.get_type().is_float() {
+                    self.load_float(out, &self.arg1)?;
+                } else if float(&self.arg1) {
+                    self.load_float(out, &self.arg1)?;
+                    float_to_int(out)?;
+                } else if fun.get_type().is_wide() {
+                    self.load_wide(out, "eax", "edx", &self.arg1)?;
+                } else {
+                    self.load_var(out, "eax", &self.arg1)?;
+                }
+                writeln!(out, "jmp {}", self.target_label())?;
             },
         }
 
+        Ok(())
     }
 }
 
@@ -444,10 +994,217 @@ impl InterCode {
         self.code.push(inst);
     }
 
-    pub(crate) fn print_code(&self) {
+    // 输出中间代码
+    pub(crate) fn print_ir(&self, out: &mut dyn Write) -> io::Result<()> {
+        for inst in self.code.iter() {
+            writeln!(out, "{}", inst.to_ir())?;
+        }
+
+        Ok(())
+    }
+
+    // 用到的运行库函数
+    pub(crate) fn helpers(&self) -> Vec<&'static str> {
+        self.code.iter().filter_map(|inst| inst.helper()).collect()
+    }
+
+    // 输出汇编代码
+    pub(crate) fn print_code(&self, out: &mut dyn Write, fun: &Fun) -> io::Result<()> {
         for inst in self.code.iter() {
-            inst.to_x86();
+            inst.to_x86(out, fun)?;
         }
+
+        Ok(())
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::DiagnosticSink;
+    use crate::lower::compile;
+
+    // 编译源代码，返回函数name的汇编代码，到下一个函数为止
+    fn fun_asm(source: &str, name: &str) -> Vec<String> {
+        let diag = DiagnosticSink::new();
+        let mut compiled = compile(source, &diag);
+        assert!(diag.diagnostics().is_empty());
+
+        let mut out = vec![];
+        compiled.ir.get_sym_tab().gen_asm(&mut out).unwrap();
+        let asm = String::from_utf8(out).unwrap();
+        let label = format!("${}:", name);
+        asm.lines()
+            .skip_while(|l| *l != label)
+            .skip(1)
+            .take_while(|l| !(l.starts_with('$') && l.ends_with(':')))
+            .map(|l| l.to_string())
+            .collect()
+    }
+
+    // 汇编代码中有连续的几行
+    fn assert_seq(lines: &[String], seq: &[&str]) {
+        assert!(lines.windows(seq.len()).any(|w| w.iter().zip(seq).all(|(a, b)| a == b)),
+                "{:?} not found in\n{}", seq, lines.join("\n"));
+    }
+
+    #[test]
+    fn test_x86_symbol() {
+        let source = "int eax;\nchar byte[2];\nextern int cs;\nint word() { eax = cs; return byte[1]; }\n\
+                      int main() { return word(); }\n";
+
+        // 变量名与寄存器名或NASM关键字相同时不能混淆
+        let word = fun_asm(source, "word");
+        assert_seq(&word, &["mov eax, [$cs]", "mov [$eax], eax"]);
+        assert_seq(&word, &["mov eax, $byte", "mov ebx, [ebp-16]", "add eax, ebx"]);
+        assert_seq(&fun_asm(source, "main"), &["call $word", "mov [ebp-16], eax"]);
+    }
+
+    #[test]
+    fn test_x86_record() {
+        let main = fun_asm("struct pair { char a; int b; char name[5]; } g;\nint main() { struct pair x; x = g; return x.b; }\n", "main");
+
+        // 按4字节拷贝整个结构体，共16字节
+        assert_seq(&main, &["mov eax, $g", "lea ebx, [ebp-28]", "mov ecx, [eax+0]", "mov [ebx+0], ecx"]);
+        assert_seq(&main, &["mov ecx, [eax+12]", "mov [ebx+12], ecx", "lea eax, [ebp-28]"]);
+
+        // 成员b的偏移为4，按int读取
+        assert_seq(&main, &["mov ebx, 4", "add eax, ebx", "mov [ebp-36], eax", "mov eax, [ebp-36]", "mov eax, [eax]"]);
+    }
+
+    #[test]
+    fn test_x86_multi_dim() {
+        let source = "int grid[3][4];\nint len(char **argv) { return argv[1][2]; }\n\
+                      int main() { char *names[2]; grid[2][1] = 5; return len(names) + *grid[1]; }\n";
+
+        // argv[1]按4字节读取指针，argv[1][2]按字节读取
+        let len = fun_asm(source, "len");
+        assert_seq(&len, &["mov eax, 1", "mov ebx, 4", "imul ebx"]);
+        assert_seq(&len, &["mov eax, [ebp-20]", "mov eax, [eax]", "mov [ebp-28], eax"]);
+        assert_seq(&len, &["mov eax, [ebp-36]", "movsx eax, byte [eax]"]);
+
+        // 第一维按行的大小16计算偏移，数组实参退化为地址
+        let main = fun_asm(source, "main");
+        assert_seq(&main, &["mov eax, 2", "mov ebx, 16", "imul ebx"]);
+        assert_seq(&main, &["mov eax, 5", "mov ebx, [ebp-36]", "mov [ebx], eax"]);
+        assert_seq(&main, &["lea eax, [ebp-20]", "push eax", "call $len", "add esp, 4"]);
+    }
+
+    #[test]
+    fn test_x86_init() {
+        let main = fun_asm("int main() { char t[3] = {'a'}; return t[0]; }\n", "main");
+
+        // 局部数组逐个元素按字节赋值，其余补0
+        assert_seq(&main, &["mov ebx, 0", "add eax, ebx", "mov [ebp-24], eax",
+                            "mov eax, 97", "mov ebx, [ebp-24]", "mov [ebx], al"]);
+        assert_seq(&main, &["mov ebx, 1", "add eax, ebx", "mov [ebp-32], eax",
+                            "mov eax, 0", "mov ebx, [ebp-32]", "mov [ebx], al"]);
+        assert_seq(&main, &["mov ebx, 2", "add eax, ebx", "mov [ebp-40], eax",
+                            "mov eax, 0", "mov ebx, [ebp-40]", "mov [ebx], al"]);
+    }
+
+    #[test]
+    fn test_x86_enum() {
+        let main = fun_asm("enum color { RED, GREEN = 4, BLUE };\ntypedef char name[BLUE];\nname g;\n\
+                            int main() { switch (g[0]) { case GREEN + 1: return RED; } return BLUE; }\n", "main");
+
+        // case标签按折叠后的常量比较，枚举常量按值返回
+        // 标号由全局计数器生成，不比较标号名
+        assert_seq(&main, &["movsx eax, byte [ebp-28]", "mov ebx, 5", "cmp eax, ebx"]);
+        assert!(main.windows(3).any(|w| w[0].starts_with("jne ") && w[1] == "mov eax, 0" && w[2].starts_with("jmp ")));
+        assert!(main.windows(2).any(|w| w[0] == "mov eax, 5" && w[1].starts_with("jmp ")));
+    }
+
+    #[test]
+    fn test_x86_integer_types() {
+        let source = "short s = -2;\nunsigned short us;\nlong long big = 5000000000;\n\
+                      long long mul(long long a, int b) { return a * b; }\n\
+                      int main() { unsigned u = 7; long long x = big / 3; if (u > s) x = mul(x, 2); return us + u / 3; }\n";
+
+        // 64位乘法按32位分段计算，int参数先符号扩展
+        let mul = fun_asm(source, "mul");
+        assert_seq(&mul, &["mov ecx, ebx", "sar ecx, 31", "imul edx, ebx", "imul ecx, eax",
+                           "add ecx, edx", "mul ebx", "add edx, ecx"]);
+
+        // 64位除法调用库函数，long long参数占8字节
+        let main = fun_asm(source, "main");
+        assert_seq(&main, &["mov ebx, 3", "mov ecx, 0", "push ecx", "push ebx", "push edx", "push eax",
+                            "call __divdi3", "add esp, 16"]);
+        assert_seq(&main, &["push edx", "push eax", "call $mul", "add esp, 12"]);
+        // short符号扩展后按无符号比较，unsigned short零扩展
+        assert_seq(&main, &["movsx ebx, word [$s]", "mov ecx, 0", "cmp eax, ebx", "seta cl"]);
+        assert_seq(&main, &["mov ebx, 3", "mov edx, 0", "div ebx"]);
+        assert_seq(&main, &["movzx eax, word [$us]"]);
+
+        // 有符号和无符号的比较、除法，2字节的存储
+        let f = fun_asm("short s;\nunsigned short us;\n\
+                         int f(int a, unsigned b) { s = a; us = b; if (a < 3) a = a / 3; if (b < 3) b = b / 3; return a + b; }\n", "f");
+        assert_seq(&f, &["mov eax, [ebp+8]", "mov word [$s], ax", "mov eax, [ebp+12]", "mov word [$us], ax"]);
+        assert_seq(&f, &["mov eax, [ebp+8]", "mov ebx, 3", "mov ecx, 0", "cmp eax, ebx", "setl cl"]);
+        assert_seq(&f, &["mov eax, [ebp+8]", "mov ebx, 3", "cdq", "idiv ebx"]);
+        assert_seq(&f, &["mov eax, [ebp+12]", "mov ebx, 3", "mov ecx, 0", "cmp eax, ebx", "setb cl"]);
+        assert_seq(&f, &["mov eax, [ebp+12]", "mov ebx, 3", "mov edx, 0", "div ebx"]);
+    }
+
+    #[test]
+    fn test_x86_float() {
+        let source = "double g = 1.5;\nfloat h = -2;\nint n = 3.9;\n\
+                      float half(float f) { return f / 2; }\n\
+                      int main() { double d = 0.25; d = d * n; if (d > h) d = half(d); return d; }\n";
+
+        // 整数经栈转为浮点数参与运算
+        let half = fun_asm(source, "half");
+        assert_seq(&half, &["fld dword [ebp+8]", "mov eax, 2", "mov edx, 0", "push edx", "push eax",
+                            "fild qword [esp]", "add esp, 8", "fdivp st1, st0"]);
+
+        let main = fun_asm(source, "main");
+        assert_seq(&main, &["mov dword [ebp-20], 0x00000000", "mov dword [ebp-16], 0x3fd00000"]);
+        assert_seq(&main, &["fild qword [esp]", "add esp, 8", "fmulp st1, st0", "fstp qword [ebp-28]"]);
+        // 浮点比较按无符号数设置标志位
+        assert_seq(&main, &["fld dword [$h]", "fld qword [ebp-20]", "fcomip st0, st1", "fstp st0",
+                            "mov ecx, 0", "seta cl"]);
+        // float参数占4字节
+        assert_seq(&main, &["sub esp, 4", "fstp dword [esp]", "call $half", "add esp, 4"]);
+        // 返回int时临时切换为截断舍入
+        assert_seq(&main, &["sub esp, 12", "fnstcw [esp+8]", "mov ax, [esp+8]", "or ax, 0x0c00",
+                            "mov [esp+10], ax", "fldcw [esp+10]", "fistp qword [esp]", "fldcw [esp+8]",
+                            "pop eax", "pop edx", "add esp, 4"]);
+    }
+
+    #[test]
+    fn test_x86_bit() {
+        let source = "unsigned mask = ~0;\nlong long big;\n\
+                      int main() { int x = 6; unsigned u = mask >> x; big = big << x | 1; return (x & 3) ^ ~u; }\n\
+                      int sh(int a, int b) { return a >> b; }\n";
+
+        // 无符号数逻辑右移，有符号数算术右移
+        let main = fun_asm(source, "main");
+        assert_seq(&main, &["mov eax, [$mask]", "mov ecx, [ebp-16]", "shr eax, cl"]);
+        assert_seq(&fun_asm(source, "sh"), &["mov eax, [ebp+8]", "mov ecx, [ebp+12]", "sar eax, cl"]);
+        // 64位移位跨越两个寄存器，移位数不小于32时高位取低位
+        assert_seq(&main, &["shld edx, eax, cl", "shl eax, cl", "test cl, 32"]);
+        assert_seq(&main, &["mov edx, eax", "mov eax, 0"]);
+        assert_seq(&main, &["mov ebx, 1", "mov ecx, 0", "or eax, ebx", "or edx, ecx"]);
+        assert_seq(&main, &["mov ebx, 3", "and eax, ebx"]);
+        assert_seq(&main, &["mov eax, [ebp-24]", "not eax"]);
+        assert_seq(&main, &["mov ebx, [ebp-48]", "xor eax, ebx"]);
+    }
+
+    #[test]
+    fn test_x86_compound() {
+        let main = fun_asm("int a[4];\nint main() { int i = 0; char *p = \"ab\"; a[i++] += 5; *p++ -= i; i <<= 2; return i; }\n",
+                           "main");
+
+        // 左值只求值一次，读写使用同一个地址
+        assert_eq!(main.iter().filter(|l| *l == "mov ebx, 4").count(), 1);
+        assert_eq!(main.iter().filter(|l| *l == "mov ebx, 1").count(), 2);     // i++和p++
+        assert_seq(&main, &["mov eax, [ebp-32]", "mov eax, [eax]", "mov [ebp-40], eax",
+                            "mov eax, [ebp-40]", "mov ebx, 5", "add eax, ebx", "mov [ebp-44], eax",
+                            "mov eax, [ebp-44]", "mov ebx, [ebp-32]", "mov [ebx], eax"]);
+        assert_seq(&main, &["mov eax, [ebp-48]", "movsx eax, byte [eax]"]);
+        assert_seq(&main, &["mov ebx, [ebp-16]", "sub eax, ebx", "mov [ebp-60], eax",
+                            "mov eax, [ebp-60]", "mov ebx, [ebp-48]", "mov [ebx], al"]);
+        assert_seq(&main, &["mov eax, [ebp-16]", "mov ecx, 2", "shl eax, cl", "mov [ebp-64], eax",
+                            "mov eax, [ebp-64]", "mov [ebp-16], eax"]);
+    }
+}
@@ -0,0 +1,47 @@
+/*
+	JSON输出辅助函数
+*/
+use crate::span::Span;
+
+// 转义并加上引号
+pub(crate) fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// 可选字符串，None输出null
+pub(crate) fn quote_opt(s: Option<&str>) -> String {
+    s.map_or_else(|| "null".to_string(), quote)
+}
+
+// 区间的JSON表示，行列从1开始，end_column包含在区间内；没有位置时为null
+pub(crate) fn span_json(span: Span) -> String {
+    if span.is_dummy() {
+        return "null".to_string();
+    }
+    format!("{{\"start\":{},\"end\":{},\"line\":{},\"column\":{},\"end_line\":{},\"end_column\":{}}}",
+            span.start(), span.end(), span.line(), span.col(), span.end_line(), span.end_col())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json::quote;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("a\"b\\c\n\u{1}中"), "\"a\\\"b\\\\c\\n\\u0001中\"");
+    }
+}
@@ -25,6 +25,16 @@ impl Keywords {
         keywords.insert("break".to_string(), KwBreak);
         keywords.insert("continue".to_string(), KwContinue);
         keywords.insert("return".to_string(), KwReturn);
+        keywords.insert("struct".to_string(), KwStruct);
+        keywords.insert("union".to_string(), KwUnion);
+        keywords.insert("enum".to_string(), KwEnum);
+        keywords.insert("typedef".to_string(), KwTypedef);
+        keywords.insert("short".to_string(), KwShort);
+        keywords.insert("long".to_string(), KwLong);
+        keywords.insert("signed".to_string(), KwSigned);
+        keywords.insert("unsigned".to_string(), KwUnsigned);
+        keywords.insert("float".to_string(), KwFloat);
+        keywords.insert("double".to_string(), KwDouble);
 
         Keywords {
             keywords
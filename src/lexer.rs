@@ -1,57 +1,186 @@
-use crate::common::LexError::{CharNoData, CharNoRQution, CommentNoEnd, NumBinType, NumHexType, OrNoPair, StrNoRQution, TokenNoExist};
-use crate::common::Tag::{self, ADD, ASSIGN, DEC, END, ERR, GE, GT, ID, INC, LE, LT, MOD, MUL, SUB, EQU, LEA, AND, NEQU, NOT, COMMA, COLON, SEMICON, LPAREN, RPAREN, LBRACK, RBRACK, LBRACE, RBRACE, DIV, OR};
+use std::collections::VecDeque;
+use std::ops::{Deref, DerefMut};
+use crate::catalog::Catalog;
+use crate::common::LexError::{self, CharNoData, CharNoRQution, CommentNoEnd, NumBinType, NumExpType, NumHexType, NumOverflow, StrNoRQution, TokenNoExist};
+use crate::common::Tag::{self, ADD, ASSIGN, DEC, END, ERR, GE, GT, ID, INC, LE, LT, MOD, MUL, SUB, EQU, LEA, AND, NEQU, NOT, COMMA, COLON, SEMICON, LPAREN, RPAREN, LBRACK, RBRACK, LBRACE, RBRACE, DIV, OR, DOT, ARROW, BOR, XOR, BNOT, SHL, SHR, ADDAS, SUBAS, MULAS, DIVAS, MODAS, BANDAS, BORAS, XORAS, SHLAS, SHRAS};
+use crate::diagnostic::{Diagnostic, DiagnosticSink, ErrorCode};
 use crate::scanner::Scanner;
 use crate::keywords::Keywords;
-use crate::token::{Char, Id, Num, Str, Token, TokenType};
-
-fn lex_error(scanner: &mut Scanner, code: usize) {
-    // 打印词法错误
-    const LEX_ERROR_TABLE: [&str; 8] = ["字符串丢失右引号",
-                                        "二进制数没有实体数据",
-                                        "十六进制数没有实体数据",
-                                        "字符丢失右单引号",
-                                        "不支持空字符",
-                                        "错误的或运算符",
-                                        "多行注释没有正常结束",
-                                        "词法记号不存在"];
-
-    println!("{}<{}行,{}列> 词法错误 : {}.", scanner.file_name(), scanner.line_num(), scanner.col_num(), LEX_ERROR_TABLE[code]);
+use crate::span::Span;
+use crate::token::{Char, Comment, Id, Num, Real, SpannedToken, Str, Token, TokenType};
+
+// 按进制累加一位数字，超出unsigned long long时为None
+fn push_digit(val: Option<u64>, radix: u32, ch: char) -> Option<u64> {
+    val?.checked_mul(radix as u64)?.checked_add(ch.to_digit(radix)? as u64)
+}
+
+fn lex_error(diag: &DiagnosticSink, code: LexError, span: Span) {
+    // 报告词法错误
+    diag.emit(LexDiagnostic { code, span }.into());
+}
+
+// 词法错误，由公开的词法分析器返回
+#[derive(Clone, PartialEq, Debug)]
+pub struct LexDiagnostic {
+    pub code: LexError,
+    pub span: Span,
+}
+
+impl LexDiagnostic {
+    pub fn message(&self) -> String {
+        Catalog::current().lex_error(self.code)
+    }
+}
+
+impl From<LexDiagnostic> for Diagnostic {
+    fn from(d: LexDiagnostic) -> Self {
+        Diagnostic::error(ErrorCode::Lex(d.code), d.message(), d.span)
+    }
+}
+
+// 扫描器，借用外部的或者由词法分析器持有
+enum Input<'a> {
+    Borrowed(&'a mut Scanner),
+    Owned(Scanner),
+}
+
+impl<'a> Deref for Input<'a> {
+    type Target = Scanner;
+
+    fn deref(&self) -> &Scanner {
+        match self {
+            Input::Borrowed(scanner) => scanner,
+            Input::Owned(scanner) => scanner,
+        }
+    }
+}
+
+impl<'a> DerefMut for Input<'a> {
+    fn deref_mut(&mut self) -> &mut Scanner {
+        match self {
+            Input::Borrowed(scanner) => scanner,
+            Input::Owned(scanner) => scanner,
+        }
+    }
 }
 
 pub struct Lexer<'a> {
-    scanner: &'a mut Scanner,
+    scanner: Input<'a>,
     ch: Option<char>,
     token: Option<TokenType>,
     keywords: Keywords,
+    diag: DiagnosticSink,               // 诊断信息收集器
+    comments: Vec<Comment>,             // 跳过的注释
+    pending: VecDeque<Result<SpannedToken, LexDiagnostic>>,    // 迭代时尚未返回的记号和错误
+    errors: Vec<LexDiagnostic>,         // 本词法分析器报告的错误，迭代时取出，不影响收集器中其他阶段的诊断
+
+    // 位置记录
+    start: (usize, usize, usize),       // 当前记号起始位置(字节偏移,行,列)
+    last_end: (usize, usize, usize),    // 最后读入字符的结束位置(字节偏移,行,列)
 }
 
 impl<'a> Lexer<'a> {
-    pub(crate) fn new(scanner: &'a mut Scanner) -> Self {
+    pub(crate) fn new(scanner: &'a mut Scanner, diag: DiagnosticSink) -> Self {
+        Lexer::with_input(Input::Borrowed(scanner), diag)
+    }
+
+    // 分析一段源代码，按迭代器逐个取出记号
+    pub fn from_source<S: Into<String>>(source: S) -> Lexer<'static> {
+        Lexer::with_input(Input::Owned(Scanner::from_source(source)), DiagnosticSink::new())
+    }
+
+    fn with_input(scanner: Input<'a>, diag: DiagnosticSink) -> Self {
         Lexer {
             scanner,
             ch: None,
             token: None,
             keywords: Keywords::new(),
+            diag,
+            comments: vec![],
+            pending: VecDeque::new(),
+            errors: vec![],
+            start: (0, 1, 0),
+            last_end: (0, 1, 0),
         }
     }
 
-    pub(crate) fn get_scanner(&mut self) -> &mut Scanner {
-        self.scanner
+    pub(crate) fn get_sink(&self) -> DiagnosticSink {
+        self.diag.clone()
+    }
+
+    // 取出已经跳过的注释
+    pub(crate) fn take_comments(&mut self) -> Vec<Comment> {
+        std::mem::take(&mut self.comments)
+    }
+
+    // 记录刚刚跳过的注释
+    fn add_comment(&mut self) {
+        let span = self.cur_span();
+        let text = self.scanner.source()[span.start()..span.end()].to_string();
+        self.comments.push(Comment::new(text, span));
     }
 
     fn scan(&mut self, need: Option<char>) -> bool {
-        self.ch = self.scanner.scan();
+        self.read();
         if need != None {
             if self.ch != need {
                 return false;
             }
 
-            self.ch = self.scanner.scan();
+            self.read();
         }
 
         true
     }
 
+    // 读入一个字符，并记录上一个字符的结束位置
+    fn read(&mut self) {
+        self.last_end = (self.scanner.pos(), self.scanner.line_num(), self.scanner.col_num());
+        self.ch = self.scanner.scan();
+    }
+
+    // 当前记号的区间
+    fn cur_span(&self) -> Span {
+        Span::new(self.start.0, self.last_end.0, self.start.1, self.start.2, self.last_end.1, self.last_end.2)
+    }
+
+    fn lex_error(&mut self, code: LexError) {
+        let span = self.cur_span();
+        lex_error(&self.diag, code, span);
+        self.errors.push(LexDiagnostic { code, span });
+    }
+
+    // 浮点数的小数和指数部分，整数部分已经读入，数值由记号的原文转换
+    fn real(&mut self) -> TokenType {
+        if self.ch == Some('.') {
+            self.scan(None);
+        }
+        self.digits();
+        if matches!(self.ch, Some('e') | Some('E')) {
+            self.scan(None);
+            if matches!(self.ch, Some('+') | Some('-')) {
+                self.scan(None);
+            }
+            if !self.digits() {
+                self.lex_error(NumExpType);
+                return TokenType::Token(Token::new(ERR));
+            }
+        }
+
+        let text = &self.scanner.source()[self.start.0..self.last_end.0];
+        TokenType::Real(Real::new(text.parse().unwrap_or(0.0)))
+    }
+
+    // 跳过连续的十进制数字，返回是否有数字
+    fn digits(&mut self) -> bool {
+        let mut any = false;
+        while self.ch.is_some_and(|c| c.is_ascii_digit()) {
+            self.scan(None);
+            any = true;
+        }
+        any
+    }
+
     pub(crate) fn tokenize(&mut self) -> TokenType {
         let mut token: Option<TokenType>;
 
@@ -80,11 +209,14 @@ impl<'a> Lexer<'a> {
                 break;
             }
 
+            // 记录记号起始位置
+            self.start = (self.scanner.pos() - ch.len_utf8(), self.scanner.line_num(), self.scanner.col_num());
+
             // 标识符，关键字
             if (ch >= 'a' && ch <= 'z') || (ch >= 'A' && ch <= 'Z') || ch == '_' {
                 let mut name = String::from("");
 
-                while (ch >= 'a' && ch <= 'z') || (ch >= 'A' && ch <= 'Z') || ch == '_' {
+                while (ch >= 'a' && ch <= 'z') || (ch >= 'A' && ch <= 'Z') || (ch >= '0' && ch <= '9') || ch == '_' {
                     name.push(ch);              // 记录字符
                     self.scan(None);
                     if let None = self.ch {
@@ -123,20 +255,20 @@ impl<'a> Lexer<'a> {
                                         };
                                     }
                                     None => {
-                                        lex_error(self.scanner, StrNoRQution as usize);
+                                        self.lex_error(StrNoRQution);
                                         token = Some(TokenType::Token(Token::new(ERR)));
                                     }
                                 }
                             } else if c == '\r'  {              // 换行
                                 self.scan(Some('\n'));          // 吃掉\n
-                                lex_error(self.scanner, StrNoRQution as usize);
+                                self.lex_error(StrNoRQution);
                                 token = Some(TokenType::Token(Token::new(ERR)));
                             } else {
                                 str.push(c);
                             }
                         },
                         None => {
-                            lex_error(self.scanner, StrNoRQution as usize);
+                            self.lex_error(StrNoRQution);
                             token = Some(TokenType::Token(Token::new(ERR)));
                             break;
                         }
@@ -148,11 +280,12 @@ impl<'a> Lexer<'a> {
                     token = Some(TokenType::Str(Str::new(str)));
                 }
             } else if ch >= '0' && ch <= '9' {  // 数字
-                let mut val: isize = 0;
+                let mut val: Option<u64> = Some(0);
+                let mut radix = 10;
 
                 if ch != '0' {  // 10进制
-                    while ch > '0' && ch < '9' {
-                        val = val * 10 + ch as isize - '0' as isize;
+                    while ch >= '0' && ch <= '9' {
+                        val = push_digit(val, 10, ch);
                         self.scan(None);
                         if let None = self.ch {
                             break;
@@ -164,20 +297,14 @@ impl<'a> Lexer<'a> {
                     if let Some(c) = self.ch {
                         ch = c;
                         if ch == 'x' {      // 16进制
+                            radix = 16;
                             self.scan(None);
                             match self.ch {
                                 Some(c) => {
                                     ch = c;
                                     if (ch >= '0' && ch <= '9') || (ch >= 'A' && ch <= 'F') || (ch >= 'a' && ch <= 'f') {
                                         while (ch >= '0' && ch <= '9') || (ch >= 'A' && ch <= 'F') || (ch >= 'a' && ch <= 'f') {
-                                            val = val * 16 + ch as isize;
-                                            if ch >= '0' && ch <= '9' {
-                                                val = val - '0' as isize;
-                                            } else if ch >= 'A' && ch <= 'F' {
-                                                val = val + 10 - 'A' as isize;
-                                            } else if ch >= 'a' && ch <= 'f' {
-                                                val = val + 10 - 'a' as isize;
-                                            }
+                                            val = push_digit(val, 16, ch);
                                             self.scan(None);
                                             if let None = self.ch {
                                                 break;
@@ -185,23 +312,24 @@ impl<'a> Lexer<'a> {
                                             ch = self.ch.unwrap();
                                         }
                                     } else {
-                                        lex_error(self.scanner, NumHexType as usize);
+                                        self.lex_error(NumHexType);
                                         token = Some(TokenType::Token(Token::new(ERR)));
                                     }
                                 },
                                 None => {
-                                    lex_error(self.scanner, NumHexType as usize);
+                                    self.lex_error(NumHexType);
                                     token = Some(TokenType::Token(Token::new(ERR)));
                                 }
                             }
                         } else if ch == 'b' {       // 二进制
+                            radix = 2;
                             self.scan(None);
                             match self.ch {
                                 Some(c) => {
                                     ch = c;
                                     if ch >= '0' && ch <= '1' {
                                         while ch >= '0' && ch <= '1' {
-                                            val = val * 2 + ch as isize - '0' as isize;
+                                            val = push_digit(val, 2, ch);
                                             self.scan(None);
                                             if let None = self.ch {
                                                 break;
@@ -209,18 +337,18 @@ impl<'a> Lexer<'a> {
                                             ch = self.ch.unwrap();
                                         }
                                     } else {
-                                        lex_error(self.scanner, NumBinType as usize);
+                                        self.lex_error(NumBinType);
                                         token = Some(TokenType::Token(Token::new(ERR)));
                                     }
                                 },
                                 None => {
-                                    lex_error(self.scanner, NumBinType as usize);
+                                    self.lex_error(NumBinType);
                                     token = Some(TokenType::Token(Token::new(ERR)));
                                 }
                             }
                         } else if ch >= '0' && ch <= '7' {
                             while ch >= '0' && ch <= '7' {
-                                val = val * 8 + ch as isize - '0' as isize;
+                                val = push_digit(val, 8, ch);
                                 self.scan(None);
                                 if let None = self.ch {
                                     break;
@@ -231,9 +359,20 @@ impl<'a> Lexer<'a> {
                     }
                 }
 
-                // 最终数字
+                // 十进制和八进制数后面是小数点或指数时为浮点数
+                if token.is_none() && radix == 10 && matches!(self.ch, Some('.') | Some('e') | Some('E')) {
+                    token = Some(self.real());
+                }
+
+                // 最终数字，超过long long的值按unsigned long long的位模式保存
                 if let None = token {
-                    token = Some(TokenType::Num(Num::new(val)));
+                    match val {
+                        Some(v) => token = Some(TokenType::Num(Num::new(v as isize))),
+                        None => {
+                            self.lex_error(NumOverflow);
+                            token = Some(TokenType::Token(Token::new(ERR)));
+                        }
+                    }
                 }
             } else if ch == '\'' {  // 字符
                 let mut c: char = '0';
@@ -242,11 +381,11 @@ impl<'a> Lexer<'a> {
                 match self.ch {
                     Some(ch) => {
                         if ch == '\'' {         // 没有数据
-                            lex_error(self.scanner, CharNoData as usize);
+                            self.lex_error(CharNoData);
                             token = Some(TokenType::Token(Token::new(ERR)));
                         } else if ch == '\r' {  // 换行
                             self.scan(None);
-                            lex_error(self.scanner, CharNoRQution as usize);
+                            self.lex_error(CharNoRQution);
                             token = Some(TokenType::Token(Token::new(ERR)));
                         } else if ch == '\\' {  // 转义
                             self.scan(None);
@@ -265,12 +404,12 @@ impl<'a> Lexer<'a> {
                                         c = '\'';
                                     } else if ch == '\r' {          // 换行
                                         self.scan(None);
-                                        lex_error(self.scanner, CharNoRQution as usize);
+                                        self.lex_error(CharNoRQution);
                                         token = Some(TokenType::Token(Token::new(ERR)));
                                     }
                                 },
                                 None => {   // 文件结束
-                                    lex_error(self.scanner, CharNoRQution as usize);
+                                    self.lex_error(CharNoRQution);
                                     token = Some(TokenType::Token(Token::new(ERR)));
                                 }
                             }
@@ -279,7 +418,7 @@ impl<'a> Lexer<'a> {
                         }
                     }
                     None => {   // 文件结束
-                        lex_error(self.scanner, CharNoRQution as usize);
+                        self.lex_error(CharNoRQution);
                         token = Some(TokenType::Token(Token::new(ERR)));
                     }
                 }
@@ -288,20 +427,23 @@ impl<'a> Lexer<'a> {
                     if self.scan(Some('\'')) {
                         token = Some(TokenType::Char(Char::new(c)));
                     } else {
-                        lex_error(self.scanner, CharNoRQution as usize);
+                        self.lex_error(CharNoRQution);
                         token = Some(TokenType::Token(Token::new(ERR)));
                     }
                 }
             } else {
                 match ch {
-                    '#' => {    // 忽略行（忽略宏定义）
+                    '#' => {    // 预处理指令行，与注释一样记录下来供格式化原样输出
+                        let mut prev = '#';
                         loop {
                             match self.ch {
                                 Some(c) => {
-                                    if c == '\r' {          // 换行
-                                        self.scan(None);    // 吃掉\n
+                                    if c == '\n' && prev != '\\' {     // 换行，反斜杠续行除外
                                         break;
                                     }
+                                    if c != '\r' {
+                                        prev = c;
+                                    }
                                 },
                                 None => {                   // 文件结束
                                     break;
@@ -309,11 +451,15 @@ impl<'a> Lexer<'a> {
                             }
                             self.scan(None);
                         }
+                        self.add_comment();
                         token = Some(TokenType::Token(Token::new(ERR)));
                     },
                     '+' => {
                         if self.scan(Some('+')) {
                             token = Some(TokenType::Token(Token::new(INC)));
+                        } else if self.ch == Some('=') {
+                            token = Some(TokenType::Token(Token::new(ADDAS)));
+                            self.scan(None);
                         } else {
                             token = Some(TokenType::Token(Token::new(ADD)));
                         }
@@ -321,17 +467,26 @@ impl<'a> Lexer<'a> {
                     '-' => {
                         if self.scan(Some('-')) {
                             token = Some(TokenType::Token(Token::new(DEC)));
+                        } else if self.ch == Some('>') {
+                            token = Some(TokenType::Token(Token::new(ARROW)));
+                            self.scan(None);
+                        } else if self.ch == Some('=') {
+                            token = Some(TokenType::Token(Token::new(SUBAS)));
+                            self.scan(None);
                         } else {
                             token = Some(TokenType::Token(Token::new(SUB)));
                         }
                     },
                     '*' => {
-                        token = Some(TokenType::Token(Token::new(MUL)));
-                        self.scan(None);
+                        if self.scan(Some('=')) {
+                            token = Some(TokenType::Token(Token::new(MULAS)));
+                        } else {
+                            token = Some(TokenType::Token(Token::new(MUL)));
+                        }
                     },
                     '/' => {
                         self.scan(None);
-                        ch = self.ch.unwrap();
+                        ch = self.ch.unwrap_or('\0');
                         if ch == '/' {                  // 单行注释
                             loop {
                                 self.scan(None);
@@ -339,11 +494,11 @@ impl<'a> Lexer<'a> {
                                     break
                                 }
                                 ch = self.ch.unwrap();
-                                if ch == '\r' {
-                                    self.scan(None);    // 遇到换行，把\n吃掉
+                                if ch == '\n' {         // 遇到换行，交给空白符处理
                                     break;
                                 }
                             }
+                            self.add_comment();
                             token = Some(TokenType::Token(Token::new(ERR)));
                         } else if ch == '*' {       // 多行注释
                             loop {
@@ -358,19 +513,32 @@ impl<'a> Lexer<'a> {
                                 }
                             }
                             if let None = self.ch {
-                                lex_error(self.scanner, CommentNoEnd as usize);
+                                self.lex_error(CommentNoEnd);
                             }
+                            self.add_comment();
+                        } else if ch == '=' {
+                            token = Some(TokenType::Token(Token::new(DIVAS)));
+                            self.scan(None);
                         } else {
                             token = Some(TokenType::Token(Token::new(DIV)));
                         }
                     },
                     '%' => {
-                        token = Some(TokenType::Token(Token::new(MOD)));
-                        self.scan(None);
+                        if self.scan(Some('=')) {
+                            token = Some(TokenType::Token(Token::new(MODAS)));
+                        } else {
+                            token = Some(TokenType::Token(Token::new(MOD)));
+                        }
                     },
                     '>' => {
                         if self.scan(Some('=')) {
                             token = Some(TokenType::Token(Token::new(GE)));
+                        } else if self.ch == Some('>') {
+                            if self.scan(Some('=')) {
+                                token = Some(TokenType::Token(Token::new(SHRAS)));
+                            } else {
+                                token = Some(TokenType::Token(Token::new(SHR)));
+                            }
                         } else {
                             token = Some(TokenType::Token(Token::new(GT)));
                         }
@@ -378,6 +546,12 @@ impl<'a> Lexer<'a> {
                     '<' => {
                         if self.scan(Some('=')) {
                             token = Some(TokenType::Token(Token::new(LE)));
+                        } else if self.ch == Some('<') {
+                            if self.scan(Some('=')) {
+                                token = Some(TokenType::Token(Token::new(SHLAS)));
+                            } else {
+                                token = Some(TokenType::Token(Token::new(SHL)));
+                            }
                         } else {
                             token = Some(TokenType::Token(Token::new(LT)));
                         }
@@ -392,6 +566,9 @@ impl<'a> Lexer<'a> {
                     '&' => {
                         if self.scan(Some('&')) {
                             token = Some(TokenType::Token(Token::new(AND)));
+                        } else if self.ch == Some('=') {
+                            token = Some(TokenType::Token(Token::new(BANDAS)));
+                            self.scan(None);
                         } else {
                             token = Some(TokenType::Token(Token::new(LEA)));
                         }
@@ -399,13 +576,24 @@ impl<'a> Lexer<'a> {
                     '|' => {
                         if self.scan(Some('|')) {
                             token = Some(TokenType::Token(Token::new(OR)));
+                        } else if self.ch == Some('=') {
+                            token = Some(TokenType::Token(Token::new(BORAS)));
+                            self.scan(None);
                         } else {
-                            token = Some(TokenType::Token(Token::new(ERR)));
+                            token = Some(TokenType::Token(Token::new(BOR)));
                         }
-                        if token.clone().unwrap().get_tag() == ERR {
-                            lex_error(self.scanner, OrNoPair as usize);
+                    },
+                    '^' => {
+                        if self.scan(Some('=')) {
+                            token = Some(TokenType::Token(Token::new(XORAS)));
+                        } else {
+                            token = Some(TokenType::Token(Token::new(XOR)));
                         }
                     },
+                    '~' => {
+                        token = Some(TokenType::Token(Token::new(BNOT)));
+                        self.scan(None);
+                    },
                     '!' => {
                         if self.scan(Some('=')) {
                             token = Some(TokenType::Token(Token::new(NEQU)));
@@ -413,6 +601,14 @@ impl<'a> Lexer<'a> {
                             token = Some(TokenType::Token(Token::new(NOT)));
                         }
                     },
+                    '.' => {
+                        self.scan(None);
+                        if self.ch.is_some_and(|c| c.is_ascii_digit()) {    // 省略整数部分的浮点数
+                            token = Some(self.real());
+                        } else {
+                            token = Some(TokenType::Token(Token::new(DOT)));
+                        }
+                    },
                     ',' => {
                         token = Some(TokenType::Token(Token::new(COMMA)));
                         self.scan(None);
@@ -451,11 +647,13 @@ impl<'a> Lexer<'a> {
                     },
                     _ => {
                         token = Some(TokenType::Token(Token::new(ERR)));
-                        lex_error(self.scanner, TokenNoExist as usize);
+                        self.scan(None);        // 跳过无法识别的字符
+                        self.lex_error(TokenNoExist);
                     }
                 }
             }
 
+            token = token.map(|t| t.with_span(self.cur_span()));
             self.token = token.clone();
 
             // 有效，则返回token
@@ -468,19 +666,49 @@ impl<'a> Lexer<'a> {
         }
 
         if let None = token {
-            token = Some(TokenType::Token(Token::new(END)));
+            let end = (self.scanner.pos(), self.scanner.line_num(), self.scanner.col_num());
+            let span = Span::new(end.0, end.0, end.1, end.2, end.1, end.2);
+            token = Some(TokenType::Token(Token::new(END)).with_span(span));
         }
         self.token = token.clone();
         token.unwrap()
     }
 }
 
+// 依次返回记号和词法错误，错误排在其后的记号之前，文件结束时停止
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<SpannedToken, LexDiagnostic>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                return Some(item);
+            }
+            if self.token.as_ref().map(|t| t.get_tag()) == Some(END) {
+                return None;
+            }
+
+            let token = self.tokenize();
+            for d in std::mem::take(&mut self.errors) {
+                self.pending.push_back(Err(d));
+            }
+            if token.get_tag() != END {
+                self.pending.push_back(Ok(token.into()));
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::common::Tag::{DEC, DIV, END, INC, KwChar, KwInt, MUL};
+    use crate::common::LexError::{NumExpType, NumOverflow, TokenNoExist};
+    use crate::common::Tag::{ADDAS, AND, ARROW, ASSIGN, BANDAS, BNOT, BOR, BORAS, DEC, DIV, DIVAS, DOT, END, GE, ID, INC, KwChar, KwInt, LEA, LT, MODAS, MUL, MULAS, NUM, OR, SEMICON, SHL, SHLAS, SHR, SHRAS, SUBAS, XOR, XORAS};
+    use crate::common::SemError::VarReDef;
+    use crate::diagnostic::{Diagnostic, DiagnosticSink, ErrorCode};
     use crate::scanner::Scanner;
-    use crate::lexer::Lexer;
-    use crate::token::{Token, TokenType, Id, Num, Char, Str};
+    use crate::lexer::{LexDiagnostic, Lexer};
+    use crate::span::Span;
+    use crate::token::{Token, TokenType, TokenValue, Id, Num, Char, Str};
 
     #[test]
     fn test_tokenize() {
@@ -501,13 +729,98 @@ mod tests {
                                          TokenType::Char(Char::new('B')),
                                          TokenType::Str(Str::new("str2".to_string())),
                                          TokenType::Token(Token::new(END))];
-        let mut scanner = Scanner::new("./test_file/lexer.txt".to_string()).unwrap();
-        let mut lexer = Lexer::new(&mut scanner);
+        let source = "int val_name\n\nadd\n\nchar\n\nfunc_tests\n\n++--*/\n\n123\n\n0x10\n\n// abcdedffadasd\n\n\
+                      # 1223121313\n\n/*\ntesars\nfadzx\n*/\n\n0b11\n\n'a'\n\n\"str1\"\n\n'B'\n\n\"str2\"\n";
+        let mut scanner = Scanner::from_source(source);
+        let mut lexer = Lexer::new(&mut scanner, DiagnosticSink::new());
 
         for t in test_token {
             let token = lexer.tokenize();
 
-            assert_eq!(t, token);
+            assert_eq!(t, token.with_span(Span::dummy()));
         }
     }
+
+    #[test]
+    fn test_token_span() {
+        let mut scanner = Scanner::from_source("int x1;\n  \"ab\" 0x1F");
+        let mut lexer = Lexer::new(&mut scanner, DiagnosticSink::new());
+
+        let spans: Vec<Span> = (0..6).map(|_| lexer.tokenize().get_span()).collect();
+
+        assert_eq!(spans[0], Span::new(0, 3, 1, 1, 1, 3));       // int
+        assert_eq!(spans[1], Span::new(4, 6, 1, 5, 1, 6));       // x1
+        assert_eq!(spans[2], Span::new(6, 7, 1, 7, 1, 7));       // ;
+        assert_eq!(spans[3], Span::new(10, 14, 2, 3, 2, 6));     // "ab"
+        assert_eq!(spans[4], Span::new(15, 19, 2, 8, 2, 11));    // 0x1F
+        assert_eq!(spans[5], Span::new(19, 19, 2, 11, 2, 11));   // 文件结尾
+    }
+
+    #[test]
+    fn test_iterator() {
+        let items: Vec<_> = Lexer::from_source("int a = @12;").collect();
+        assert_eq!(items.len(), 6);
+
+        let tags: Vec<_> = items.iter().filter_map(|t| t.as_ref().ok()).map(|t| t.tag).collect();
+        assert_eq!(tags, vec![KwInt, ID, ASSIGN, NUM, SEMICON]);
+        assert_eq!(items[3], Err(LexDiagnostic { code: TokenNoExist, span: Span::new(8, 9, 1, 9, 1, 9) }));
+
+        let num = items[4].as_ref().unwrap();
+        assert_eq!(num.value, TokenValue::Num(12));
+        assert_eq!(num.span, Span::new(9, 11, 1, 10, 1, 11));
+        assert_eq!(items[1].as_ref().unwrap().to_string(), "[identifier]:a");
+    }
+
+    #[test]
+    fn test_iterator_shared_sink() {
+        let mut scanner = Scanner::from_source("a @ b");
+        let diag = DiagnosticSink::new();
+        diag.emit(Diagnostic::error(ErrorCode::Sem(VarReDef), "a".to_string(), Span::dummy()));
+        let items: Vec<_> = Lexer::new(&mut scanner, diag.clone()).collect();
+        assert_eq!(items.iter().filter(|t| t.is_err()).count(), 1);
+
+        // 只取出词法错误，其他阶段的诊断留在收集器中
+        let codes: Vec<_> = diag.diagnostics().iter().map(|d| d.code()).collect();
+        assert_eq!(codes, vec![Some(ErrorCode::Sem(VarReDef)), Some(ErrorCode::Lex(TokenNoExist))]);
+    }
+
+    #[test]
+    fn test_real() {
+        let items: Vec<_> = Lexer::from_source("1.5 .25 3. 0.5e2 1E-3 07.5 s.a 2e+").collect();
+        let values: Vec<_> = items.iter().filter_map(|t| t.as_ref().ok()).map(|t| t.value.clone()).collect();
+        assert_eq!(values[..6], [TokenValue::Real(1.5), TokenValue::Real(0.25), TokenValue::Real(3.0),
+                                 TokenValue::Real(50.0), TokenValue::Real(0.001), TokenValue::Real(7.5)]);
+
+        // 成员访问的点不是小数点，指数必须有数字
+        let tags: Vec<_> = items.iter().filter_map(|t| t.as_ref().ok()).map(|t| t.tag).skip(6).collect();
+        assert_eq!(tags, vec![ID, DOT, ID]);
+        assert_eq!(items.last().unwrap().as_ref().err().map(|d| d.code), Some(NumExpType));
+    }
+
+    #[test]
+    fn test_bit_tokens() {
+        let items: Vec<_> = Lexer::from_source("a|b||c^~d<<1>>e&&f&g<h>=i").collect();
+        assert!(items.iter().all(|t| t.is_ok()));       // 单个|不再是错误
+
+        let tags: Vec<_> = items.iter().filter_map(|t| t.as_ref().ok()).map(|t| t.tag).filter(|t| *t != ID && *t != NUM).collect();
+        assert_eq!(tags, vec![BOR, OR, XOR, BNOT, SHL, SHR, AND, LEA, LT, GE]);
+    }
+
+    #[test]
+    fn test_compound_tokens() {
+        let items: Vec<_> = Lexer::from_source("a+=b-=c*=d/=e%=f&=g|=h^=i<<=j>>=k->l/=/* c */m").collect();
+        let tags: Vec<_> = items.iter().filter_map(|t| t.as_ref().ok()).map(|t| t.tag).filter(|t| *t != ID).collect();
+        assert_eq!(tags, vec![ADDAS, SUBAS, MULAS, DIVAS, MODAS, BANDAS, BORAS, XORAS, SHLAS, SHRAS, ARROW, DIVAS]);
+    }
+
+    #[test]
+    fn test_num_limits() {
+        let items: Vec<_> = Lexer::from_source("9223372036854775807 0xffffffffffffffff 0b11 18446744073709551616 0x10000000000000000").collect();
+        let values: Vec<_> = items.iter().filter_map(|t| t.as_ref().ok()).map(|t| t.value.clone()).collect();
+        assert_eq!(values[..3], [TokenValue::Num(i64::MAX as isize), TokenValue::Num(-1), TokenValue::Num(3)]);
+
+        // 超出unsigned long long的整数报告词法错误
+        let codes: Vec<_> = items.iter().filter_map(|t| t.as_ref().err()).map(|d| d.code).collect();
+        assert_eq!(codes, vec![NumOverflow, NumOverflow]);
+    }
 }
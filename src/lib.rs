@@ -1,31 +1,39 @@
-mod scanner;
-mod common;
+// 原有模块沿用显式return、Vec<Box<Var>>等写法，只在这些模块上关闭冲突的clippy风格检查
+pub mod scanner;
+pub mod span;
+pub mod common;
+pub mod diagnostic;
+pub mod render;
+pub mod catalog;
+pub mod driver;
+pub mod ast;
+pub mod visit;
+#[allow(clippy::inherent_to_string, clippy::let_and_return, clippy::needless_return)]
 mod token;
+#[allow(clippy::unnecessary_lazy_evaluations)]
 mod keywords;
+#[allow(clippy::collapsible_if, clippy::manual_range_contains, clippy::partialeq_to_none, clippy::redundant_pattern_matching, clippy::while_let_loop)]
 mod lexer;
+#[allow(clippy::needless_return)]
 mod parser;
+mod lower;
+mod ast_dump;
+pub mod format;
+pub mod preprocess;
+pub mod lossless;
+#[allow(clippy::collapsible_if, clippy::map_entry, clippy::vec_box)]
 mod symtab;
+#[allow(clippy::boxed_local, clippy::needless_range_loop, clippy::vec_box)]
 mod symbol;
+mod types;
 mod plat;
+#[allow(clippy::let_and_return, clippy::needless_late_init, clippy::needless_return, clippy::question_mark, clippy::unnecessary_unwrap, clippy::vec_box)]
 mod gen_ir;
+#[allow(clippy::vec_box)]
 mod intercode;
+mod json;
 
-use failure;
-use crate::gen_ir::GenIR;
-use crate::lexer::Lexer;
-use crate::parser::Parser;
-use crate::scanner::Scanner;
-use crate::symtab::SymTab;
-
-pub fn run() -> Result<(), failure::Error> {
-    let mut scanner = Scanner::new("./test_file/compiler.txt".to_string())?;
-    let mut lexer = Lexer::new(&mut scanner);
-    let mut sym_tab = Box::new(SymTab::new());
-    let gen_ir = Some(Box::new(GenIR::new(sym_tab.clone())));
-    sym_tab.set_ir(gen_ir.clone());
-    let mut parser = Parser::new(&mut lexer, &mut sym_tab, gen_ir);
-    parser.analyze();
-    parser.print_inter_code();
-
-    Ok(())
-}
\ No newline at end of file
+pub use crate::driver::run;
+pub use crate::parser::parse;
+pub use crate::lexer::{LexDiagnostic, Lexer};
+pub use crate::token::{SpannedToken, TokenValue};
@@ -0,0 +1,226 @@
+/*
+	无损记号流：每个记号带有前后的空白、换行和注释(trivia)，按顺序拼接所有文本可以还原源代码
+	记号之后到行尾(含换行)的trivia属于该记号的trailing，其余属于下一个记号的leading
+*/
+use crate::common::Tag::{self, END};
+use crate::diagnostic::{Diagnostic, DiagnosticSink};
+use crate::lexer::Lexer;
+use crate::scanner::Scanner;
+use crate::span::Span;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TriviaKind {
+    Whitespace,                 // 空格、制表符
+    Newline,                    // \n或\r\n
+    LineComment,                // 单行注释，不含换行
+    BlockComment,               // 多行注释
+    Skipped,                    // 词法错误跳过的字符
+    Directive,                  // 预处理指令行，不含换行
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct LosslessToken {
+    pub tag: Tag,
+    pub text: String,               // 记号原文，文件结束标记为空
+    pub span: Span,
+    pub leading: Vec<Trivia>,       // 记号之前的trivia
+    pub trailing: Vec<Trivia>,      // 记号之后到行尾的trivia
+}
+
+impl LosslessToken {
+    // 包含trivia的完整文本
+    pub fn full_text(&self) -> String {
+        let mut text = String::new();
+        for t in self.leading.iter() {
+            text.push_str(&t.text);
+        }
+        text.push_str(&self.text);
+        for t in self.trailing.iter() {
+            text.push_str(&t.text);
+        }
+        text
+    }
+}
+
+// 还原源代码
+pub fn to_source(tokens: &[LosslessToken]) -> String {
+    tokens.iter().map(|t| t.full_text()).collect()
+}
+
+// 无损词法分析，最后一个记号是文件结束标记，返回记号和词法错误
+pub fn tokenize(source: &str) -> (Vec<LosslessToken>, Vec<Diagnostic>) {
+    let mut scanner = Scanner::from_source(source);
+    let diag = DiagnosticSink::new();
+    let mut lexer = Lexer::new(&mut scanner, diag.clone());
+    let mut spans = vec![];
+    loop {
+        let token = lexer.tokenize();
+        spans.push((token.get_tag(), token.get_span()));
+        if token.get_tag() == END {
+            break;
+        }
+    }
+    let comments: Vec<Span> = lexer.take_comments().iter().map(|c| c.get_span()).collect();
+
+    let mut cursor = Cursor::new(source, comments);
+    let mut tokens: Vec<LosslessToken> = vec![];
+    for (tag, span) in spans {
+        let mut leading = cursor.trivia(span.start());
+        // 上一个记号之后到行尾的部分
+        if let Some(prev) = tokens.last_mut() {
+            let newline = leading.iter().position(|t| t.kind == TriviaKind::Newline);
+            let rest = leading.split_off(newline.map_or(0, |i| i + 1));
+            prev.trailing = leading;
+            leading = rest;
+        }
+
+        let end = if tag == END { source.len() } else { span.end() };
+        let (text, span) = cursor.take(end);
+        tokens.push(LosslessToken {
+            tag,
+            text,
+            span: if tag == END { cursor.here() } else { span },
+            leading,
+            trailing: vec![],
+        });
+    }
+
+    (tokens, diag.take())
+}
+
+// 按扫描器的规则计算行列号
+struct Cursor<'a> {
+    source: &'a str,
+    scanner: Scanner,
+    comments: Vec<Span>,        // 词法分析记录的注释位置
+}
+
+impl<'a> Cursor<'a> {
+    fn new(source: &'a str, comments: Vec<Span>) -> Self {
+        Cursor {
+            source,
+            scanner: Scanner::from_source(source),
+            comments,
+        }
+    }
+
+    fn pos(&self) -> usize {
+        self.scanner.pos()
+    }
+
+    // 当前位置的空区间
+    fn here(&self) -> Span {
+        let (pos, line, col) = (self.scanner.pos(), self.scanner.line_num(), self.scanner.col_num());
+        Span::new(pos, pos, line, col, line, col)
+    }
+
+    // 读取到end为止的文本及其区间
+    fn take(&mut self, end: usize) -> (String, Span) {
+        let from = self.pos();
+        if from >= end {
+            return (String::new(), self.here());
+        }
+
+        let ch = self.scanner.scan().unwrap_or_default();
+        let start = (self.pos() - ch.len_utf8(), self.scanner.line_num(), self.scanner.col_num());
+        while self.pos() < end {
+            self.scanner.scan();
+        }
+        let span = Span::new(start.0, end, start.1, start.2, self.scanner.line_num(), self.scanner.col_num());
+
+        (self.source[from..end].to_string(), span)
+    }
+
+    // 读取到end为止的trivia
+    fn trivia(&mut self, end: usize) -> Vec<Trivia> {
+        let mut pieces = vec![];
+        while self.pos() < end {
+            let pos = self.pos();
+            let rest = &self.source[pos..end];
+            let (kind, len) = if let Some(comment) = self.comments.iter().find(|c| c.start() == pos) {
+                let kind = if rest.starts_with("//") {
+                    TriviaKind::LineComment
+                } else if rest.starts_with('#') {
+                    TriviaKind::Directive
+                } else {
+                    TriviaKind::BlockComment
+                };
+                (kind, comment.end() - pos)
+            } else if rest.starts_with('\n') {
+                (TriviaKind::Newline, 1)
+            } else if rest.starts_with("\r\n") {
+                (TriviaKind::Newline, 2)
+            } else if rest.starts_with([' ', '\t', '\r']) {
+                (TriviaKind::Whitespace, blank_len(rest))
+            } else {
+                let len = rest.find([' ', '\t', '\r', '\n', '/'])
+                    .unwrap_or(rest.len());
+                (TriviaKind::Skipped, len.max(rest.chars().next().map_or(1, |c| c.len_utf8())))
+            };
+
+            let (text, span) = self.take((pos + len).min(end));
+            pieces.push(Trivia { kind, text, span });
+        }
+
+        pieces
+    }
+}
+
+// 空白的长度，\r\n中的\r属于换行
+fn blank_len(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut len = 0;
+    while len < bytes.len() {
+        match bytes[len] {
+            b' ' | b'\t' => len += 1,
+            b'\r' if bytes.get(len + 1) != Some(&b'\n') => len += 1,
+            _ => break,
+        }
+    }
+    len.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::Tag::{END, ID, KwInt, SEMICON};
+    use crate::lossless::{to_source, tokenize, TriviaKind};
+
+    #[test]
+    fn test_lossless() {
+        let source = "// head\nint a;  /* tail */\r\n\n  @ b\t;";
+        let (tokens, diags) = tokenize(source);
+        assert_eq!(to_source(&tokens), source);
+        assert_eq!(diags.len(), 1);
+
+        let tags: Vec<_> = tokens.iter().map(|t| t.tag).collect();
+        assert_eq!(tags, vec![KwInt, ID, SEMICON, ID, SEMICON, END]);
+
+        let kinds = |trivia: &[crate::lossless::Trivia]| trivia.iter().map(|t| t.kind).collect::<Vec<_>>();
+        assert_eq!(kinds(&tokens[0].leading), vec![TriviaKind::LineComment, TriviaKind::Newline]);
+        assert_eq!(kinds(&tokens[2].trailing), vec![TriviaKind::Whitespace, TriviaKind::BlockComment, TriviaKind::Newline]);
+        assert_eq!(kinds(&tokens[3].leading), vec![TriviaKind::Newline, TriviaKind::Whitespace, TriviaKind::Skipped, TriviaKind::Whitespace]);
+
+        let comment = &tokens[2].trailing[1];
+        assert_eq!((comment.span.line(), comment.span.col(), comment.span.end_col()), (2, 9, 18));
+        assert_eq!(&source[tokens[3].span.start()..tokens[3].span.end()], "b");
+    }
+
+    #[test]
+    fn test_lossless_directive() {
+        let source = "#define N \\\n  4\nint a;\n";
+        let (tokens, diags) = tokenize(source);
+        assert_eq!(to_source(&tokens), source);
+        assert!(diags.is_empty());
+
+        let kinds: Vec<_> = tokens[0].leading.iter().map(|t| t.kind).collect();
+        assert_eq!(kinds, vec![TriviaKind::Directive, TriviaKind::Newline]);
+        assert_eq!(tokens[0].leading[0].text, "#define N \\\n  4");
+    }
+}
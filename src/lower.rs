@@ -0,0 +1,987 @@
+/*
+	中间代码生成的驱动：遍历语法树，按原先语法制导翻译的顺序调用GenIR产生四元式
+*/
+use std::collections::{HashMap, HashSet};
+use std::iter::Peekable;
+use std::slice::Iter;
+use crate::ast::{Block, BlockItem, Case, Decl, Declarator, DeclaratorKind, EnumSpec, Expr, ExprKind, ForInit, FunctionDef, Initializer, Item, Literal, Param, Stmt, StmtKind, TranslationUnit, TypeSpec, VarDecl};
+use crate::common::SemError::{ArrayLenInvalid, ConstOverflow, DecInitDeny, ExprIsRecord, InitTooMany, MemberReDef, NotConstExpr, RecordUnDef, TypeSpecErr, VarInitErr, VarIsFun, VarUnDec, VoidVar};
+use crate::common::Warning::UnusedVariable;
+use crate::common::Tag::{self, ADD, AND, ASSIGN, DIV, EQU, GE, GT, KwChar, KwDouble, KwFloat, KwInt, KwLong, KwShort, KwSigned, KwUnion, KwUnsigned, KwVoid, LE, LT, MOD, MUL, NEQU, NOT, OR, SUB, LEA, BOR, XOR, BNOT, SHL, SHR, ADDAS, SUBAS, MULAS, DIVAS, MODAS, BANDAS, BORAS, XORAS, SHLAS, SHRAS};
+use crate::diagnostic::DiagnosticSink;
+use crate::gen_ir::GenIR;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::plat::PARA_BASE;
+use crate::scanner::Scanner;
+use crate::span::Span;
+use crate::symbol::{Fun, InitItem, Var, sem_error, sem_warning};
+use crate::symtab::SymTab;
+use crate::types::{self, TypeId};
+
+// 一段源代码的编译结果
+pub(crate) struct Compiled {
+    pub(crate) unit: TranslationUnit,           // 语法树
+    pub(crate) types: HashMap<Span, String>,    // 语法树节点的类型
+    pub(crate) ir: GenIR,                       // 中间代码及其符号表
+}
+
+// 分析一段源代码并翻译为中间代码，各阶段的诊断都提交到diag
+pub(crate) fn compile(source: &str, diag: &DiagnosticSink) -> Compiled {
+    let mut scanner = Scanner::from_source(source);
+    let mut lexer = Lexer::new(&mut scanner, diag.clone());
+    let unit = Parser::new(&mut lexer).analyze();
+    types::reset();
+    let mut ir = GenIR::new(SymTab::new(diag.clone()));
+    let mut lower = Lower::new(&mut ir);
+    lower.lower(&unit);
+    let types = lower.take_types();
+
+    Compiled { unit, types, ir }
+}
+
+pub(crate) struct Lower<'a> {
+    ir: &'a mut GenIR,          // 中间代码生成器，持有符号表
+    types: HashMap<Span, String>,   // 语法树节点区间到推导出的类型，用于输出语法树
+    locals: Vec<(String, Span)>,    // 当前函数中定义的局部变量
+    used: HashSet<Span>,            // 被引用过的变量的定义位置
+}
+
+impl<'a> Lower<'a> {
+    pub(crate) fn new(ir: &'a mut GenIR) -> Self {
+        Lower {
+            ir,
+            types: HashMap::new(),
+            locals: vec![],
+            used: HashSet::new(),
+        }
+    }
+
+    // 取出翻译过程中记录的节点类型
+    pub(crate) fn take_types(&mut self) -> HashMap<Span, String> {
+        std::mem::take(&mut self.types)
+    }
+
+    // 符号表
+    fn sym_tab(&mut self) -> &mut SymTab {
+        self.ir.get_sym_tab()
+    }
+
+    // 翻译整个源文件
+    pub(crate) fn lower(&mut self, unit: &TranslationUnit) {
+        for item in &unit.items {
+            match item {
+                Item::Decl(decl) => self.decl(decl),
+                Item::Function(fun) => self.function(fun),
+            }
+        }
+    }
+
+    fn block_items(&mut self, items: &[BlockItem]) {
+        for item in items {
+            match item {
+                BlockItem::Decl(decl) => self.decl(decl),
+                BlockItem::Stmt(stmt) => self.statement(stmt),
+            }
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        self.block_items(&block.items);
+    }
+}
+
+// 声明与定义
+impl<'a> Lower<'a> {
+    fn decl(&mut self, decl: &Decl) {
+        let base = self.spec_type(&decl.ty, decl.vars.is_empty());
+        for var in &decl.vars {
+            if decl.typedef {
+                self.typedef(base, var);
+                continue;
+            }
+            let v = self.var_decl(decl.ext, base, var);
+            if !decl.ext && v.get_scope_path().len() > 1 {
+                self.locals.push((v.get_name(), v.get_span()));
+            }
+            self.ir.gen_var_init(v);
+        }
+    }
+
+    // 类型说明符对应的类型，结构体或联合有成员表时定义类型，alone表示没有声明变量，如struct S;
+    fn spec_type(&mut self, ty: &TypeSpec, alone: bool) -> TypeId {
+        if let Some(alias) = &ty.alias {
+            return match self.sym_tab().find_var(&alias.name).filter(|v| v.is_typedef()) {
+                Some(v) => v.get_type(),
+                None => {
+                    sem_error(&self.sym_tab().get_sink(), VarUnDec, &alias.name, alias.span);
+                    TypeId::INT
+                },
+            };
+        }
+        if let Some(spec) = &ty.enumer {
+            self.enumer(spec);
+            return TypeId::INT;     // 枚举类型按int处理
+        }
+        let spec = match ty.record.as_ref() {
+            Some(spec) => spec,
+            None if ty.tag == KwVoid => return TypeId::VOID,
+            None if ty.tag == KwFloat => return TypeId::FLOAT,
+            None if ty.tag == KwDouble => return TypeId::DOUBLE,
+            None => return self.int_type(ty),
+        };
+        let union = ty.tag == KwUnion;
+        let (name, span) = spec.name.as_ref().map_or(("", spec.span), |n| (n.name.as_str(), n.span));
+
+        let id = match &spec.members {
+            Some(members) => {
+                let id = self.sym_tab().def_record(union, name, span);
+                let members = self.members(members);
+                self.sym_tab().end_def_record(id, members);
+                id
+            },
+            None if alone => self.sym_tab().dec_record(union, name, span),
+            None => self.sym_tab().use_record(union, name, span),
+        };
+
+        TypeId::record(id)
+    }
+
+    // 由关键字组合确定整数类型，关键字的顺序任意，组合无效时按int处理
+    fn int_type(&mut self, ty: &TypeSpec) -> TypeId {
+        let count = |tag: Tag| ty.words.iter().filter(|t| **t == tag).count();
+        let (char, short, int, long) = (count(KwChar), count(KwShort), count(KwInt), count(KwLong));
+        let unsigned = count(KwUnsigned) > 0;
+
+        let valid = count(KwSigned) + count(KwUnsigned) <= 1 && char + short <= 1 && int <= 1 && long <= 2
+            && (char == 0 || int + long == 0) && (short == 0 || long == 0);
+        if !valid {
+            sem_error(&self.sym_tab().get_sink(), TypeSpecErr, "", ty.span);
+            return TypeId::INT;
+        }
+
+        match (char, short, long, unsigned) {
+            (1, _, _, false) => TypeId::CHAR,
+            (1, _, _, true) => TypeId::UCHAR,
+            (_, 1, _, false) => TypeId::SHORT,
+            (_, 1, _, true) => TypeId::USHORT,
+            (_, _, 2, false) => TypeId::LLONG,
+            (_, _, 2, true) => TypeId::ULLONG,
+            (_, _, 1, false) => TypeId::LONG,
+            (_, _, 1, true) => TypeId::ULONG,
+            (_, _, _, false) => TypeId::INT,
+            (_, _, _, true) => TypeId::UINT,
+        }
+    }
+
+    // 枚举常量依次取值，省略值时为前一个加1
+    fn enumer(&mut self, spec: &EnumSpec) {
+        let mut val = 0;
+        for item in spec.items.iter().flatten() {
+            if let Some(e) = &item.value {
+                val = self.const_int(e).unwrap_or(val);
+            } else if i32::try_from(val).is_err() {     // 前一个为INT_MAX
+                sem_error(&self.sym_tab().get_sink(), ConstOverflow, &item.name.name, item.name.span);
+            }
+            let v = Var::new_enum(self.sym_tab().get_scope_path(), item.name.name.clone(), val, item.name.span);
+            self.sym_tab().add_name(Box::new(v));
+            val += 1;
+        }
+    }
+
+    // typedef定义类型名，不能有初始值，void只能直接作为别名
+    fn typedef(&mut self, base: TypeId, var: &VarDecl) {
+        let name = var.name.name.clone();
+        let span = var.name.span;
+        if var.init.is_some() {
+            sem_error(&self.sym_tab().get_sink(), DecInitDeny, &name, span);
+        }
+
+        let t = if base == TypeId::VOID && matches!(var.decl.kind, DeclaratorKind::Name) {
+            base
+        } else {
+            self.declarator(base, &var.decl, false, &name, span)
+        };
+        let v = Var::new_typedef(self.sym_tab().get_scope_path(), t, name, span);
+        self.types.insert(var.span, v.type_name());
+        self.sym_tab().add_name(Box::new(v));
+    }
+
+    // 由声明符构造变量的类型，变量不能声明为void类型，出错时按int处理
+    // skip为真时跳过紧挨名字的一维数组，由调用者处理
+    fn declarator(&mut self, base: TypeId, decl: &Declarator, skip: bool, name: &str, span: Span) -> TypeId {
+        let mut t = base;
+        if t == TypeId::VOID {
+            sem_error(&self.sym_tab().get_sink(), VoidVar, "", span);
+            t = TypeId::INT;
+        }
+        self.derive(t, decl, skip, name, span)
+    }
+
+    // 从外到内依次加上指针、数组和函数，int *a[4]为int*的数组，int (*p)[4]为指向int[4]的指针
+    // 长度不是常量表达式时按1处理，省略的长度按0处理
+    fn derive(&mut self, t: TypeId, decl: &Declarator, skip: bool, name: &str, span: Span) -> TypeId {
+        match &decl.kind {
+            DeclaratorKind::Name => t,
+            DeclaratorKind::Pointer(inner) => self.derive(t.pointer(), inner, skip, name, span),
+            DeclaratorKind::Array { inner, .. } if skip && matches!(inner.kind, DeclaratorKind::Name) => t,
+            DeclaratorKind::Array { inner, len } => {
+                let len = len.as_ref().map_or(0, |e| self.const_int(e).unwrap_or(1));
+                self.check_array_len(len, name, span);
+                let t = if len > 0 { t.array(len) } else { t };
+                self.derive(t, inner, skip, name, span)
+            },
+            DeclaratorKind::Function { inner, params } => {
+                let params = params.iter().map(|p| self.param_type(p)).collect();
+                self.derive(TypeId::function(t, params), inner, skip, name, span)
+            },
+        }
+    }
+
+    // 变量和成员不能是函数类型，按int处理
+    fn check_object(&mut self, t: TypeId, name: &str, span: Span) -> TypeId {
+        if t.is_function() {
+            sem_error(&self.sym_tab().get_sink(), VarIsFun, name, span);
+            return TypeId::INT;
+        }
+        t
+    }
+
+    // 成员列表，成员不能初始化，不能重名，也不能是不完整的类型
+    fn members(&mut self, decls: &[Decl]) -> Vec<Var> {
+        let mut members: Vec<Var> = vec![];
+        for decl in decls {
+            let base = self.spec_type(&decl.ty, decl.vars.is_empty());
+            for var in &decl.vars {
+                let name = var.name.name.clone();
+                let span = var.name.span;
+                if var.init.is_some() {
+                    sem_error(&self.sym_tab().get_sink(), DecInitDeny, &name, span);
+                }
+
+                let t = self.declarator(base, &var.decl, false, &name, span);
+                let t = self.check_object(t, &name, span);
+                let mut v = Var::new_var(self.sym_tab().get_scope_path(), false, t, name.clone(), None);
+                v.set_span(span);
+                self.check_complete(&v);
+                self.types.insert(var.span, v.type_name());
+
+                if members.iter().any(|m| m.get_name() == name) {
+                    sem_error(&self.sym_tab().get_sink(), MemberReDef, &name, span);
+                } else {
+                    members.push(v);
+                }
+            }
+        }
+        members
+    }
+
+    // 结构体或联合对象(包括数组元素)的类型必须已经定义
+    fn check_complete(&mut self, v: &Var) {
+        let mut t = v.get_type();
+        while let Some(e) = t.pointee().filter(|_| t.is_array()) {
+            t = e;
+        }
+        if t.is_record() && !t.is_complete() {
+            sem_error(&self.sym_tab().get_sink(), RecordUnDef, &t.name(), v.get_span());
+        }
+    }
+
+    fn var_decl(&mut self, ext: bool, base: TypeId, var: &VarDecl) -> Box<Var> {
+        let name = var.name.name.clone();
+        let span = var.name.span;
+
+        // 第一维省略时由初始值推断长度
+        let infer = var.init.is_some() && matches!(var.decl.first_dim(), Some(None));
+        let t = self.declarator(base, &var.decl, infer, &name, span);
+        let mut t = self.check_object(t, &name, span);
+
+        let mut init_val = None;
+        let mut list = vec![];
+        match &var.init {
+            None => {},
+            Some(Initializer::Expr(e)) if !infer && str_init(t, e).is_none() => init_val = self.expr(e),
+            Some(init) if infer => {
+                let len = match init {
+                    Initializer::List { items, .. } => self.init_elements(t, None, &mut items.iter().peekable(), 0, &mut list),
+                    Initializer::Expr(e) => match str_init(t.array(1), e) {
+                        Some(s) => self.init_str(t.array(s.len() as isize + 1), s, e.span, 0, &mut list),
+                        None => {
+                            sem_error(&self.sym_tab().get_sink(), VarInitErr, &name, span);
+                            0
+                        },
+                    },
+                };
+                self.check_array_len(len, &name, span);
+                if len > 0 {
+                    t = t.array(len);
+                }
+            },
+            Some(init) => self.init_object(t, &mut std::slice::from_ref(init).iter().peekable(), 0, &mut list),
+        }
+        if !t.is_array() && !t.is_record() {    // 标量的初始值可以加大括号，如int x = {1}
+            init_val = init_val.or(list.pop().map(|(_, _, v)| v));
+        }
+
+        let mut v = Box::new(Var::new_var(self.sym_tab().get_scope_path(), ext, t, name, init_val));
+        v.set_init_list(list);
+        v.set_span(span);
+        if !ext {
+            self.check_complete(&v);
+        }
+        self.types.insert(var.span, v.type_name());
+        v
+    }
+
+    // 用初始值初始化偏移为offset、类型为t的对象，items中剩余的初始值按顺序使用，内层的大括号可以省略
+    fn init_object(&mut self, t: TypeId, items: &mut Peekable<Iter<Initializer>>, offset: isize, list: &mut Vec<InitItem>) {
+        let Some(&init) = items.peek() else {
+            return;
+        };
+
+        match init {
+            Initializer::List { items: inner, span } => {
+                items.next();
+                self.init_list(t, inner, *span, offset, list);
+            },
+            Initializer::Expr(e) => {
+                if let Some(s) = str_init(t, e) {  // 字符数组用字符串初始化
+                    items.next();
+                    self.init_str(t, s, e.span, offset, list);
+                } else if t.is_array() || t.is_record() {      // 省略了大括号，由后续的初始值依次初始化各个元素
+                    self.init_members(t, items, offset, list);
+                } else {
+                    items.next();
+                    if let Some(v) = self.expr(e) {
+                        list.push((offset, t, v));
+                    }
+                }
+            },
+        }
+    }
+
+    // 大括号中的初始值只用于类型为t的对象，多余的初始值报错
+    fn init_list(&mut self, t: TypeId, inits: &[Initializer], span: Span, offset: isize, list: &mut Vec<InitItem>) {
+        let mut items = inits.iter().peekable();
+        match inits {
+            [Initializer::Expr(e)] if str_init(t, e).is_some() => self.init_object(t, &mut items, offset, list),    // 如{"abc"}
+            _ if t.is_array() || t.is_record() => self.init_members(t, &mut items, offset, list),
+            _ => self.init_object(t, &mut items, offset, list),
+        }
+
+        if items.peek().is_some() {
+            sem_error(&self.sym_tab().get_sink(), InitTooMany, "", span);
+        }
+    }
+
+    // 依次初始化数组的元素或结构体的成员，联合只初始化第一个成员
+    fn init_members(&mut self, t: TypeId, items: &mut Peekable<Iter<Initializer>>, offset: isize, list: &mut Vec<InitItem>) {
+        if let (Some(elem), Some(len)) = (t.pointee(), t.array_len()) {
+            self.init_elements(elem, Some(len), items, offset, list);
+        } else if let Some(id) = t.record_id() {
+            // 初始化成员时会驻留新的类型，先取出成员的类型和偏移
+            let members: Vec<(TypeId, isize)> = types::with_record(id, |r| {
+                let count = if r.is_union() { 1 } else { usize::MAX };
+                r.get_members().iter().take(count).map(|m| (m.get_type(), m.get_offset())).collect()
+            });
+            for (mt, moffset) in members {
+                if items.peek().is_none() {
+                    break;
+                }
+                self.init_object(mt, items, offset + moffset, list);
+            }
+        } else {
+            self.init_object(t, items, offset, list);
+        }
+    }
+
+    // 依次初始化数组元素，长度未知时用完所有初始值，返回初始化的元素个数
+    fn init_elements(&mut self, elem: TypeId, len: Option<isize>, items: &mut Peekable<Iter<Initializer>>, offset: isize, list: &mut Vec<InitItem>) -> isize {
+        let mut count = 0;
+        while items.peek().is_some() && len.is_none_or(|len| count < len) {
+            self.init_object(elem, items, offset + count * elem.size(), list);
+            count += 1;
+        }
+        count
+    }
+
+    // 字符数组用字符串初始化，包括结尾的0，数组恰好放下字符串时省略结尾的0，返回数组长度
+    fn init_str(&mut self, t: TypeId, s: &str, span: Span, offset: isize, list: &mut Vec<InitItem>) -> isize {
+        let len = t.size();
+        if s.len() as isize > len {
+            sem_error(&self.sym_tab().get_sink(), InitTooMany, "", span);
+        }
+        for (i, b) in s.bytes().chain([0]).take(len as usize).enumerate() {
+            let mut v = Box::new(Var::new_int(b as isize));
+            v.set_span(span);
+            list.push((offset + i as isize, TypeId::CHAR, v));
+        }
+        len
+    }
+
+    // 数组长度必须是正整数
+    fn check_array_len(&mut self, len: isize, name: &str, span: Span) {
+        if len <= 0 {
+            sem_error(&self.sym_tab().get_sink(), ArrayLenInvalid, name, span);
+        }
+    }
+}
+
+// 函数
+impl<'a> Lower<'a> {
+    fn function(&mut self, fun: &FunctionDef) {
+        // 不支持按值返回结构体或联合，按int处理
+        let mut ret = self.spec_type(&fun.ret, false);
+        if ret.is_record() {
+            sem_error(&self.sym_tab().get_sink(), ExprIsRecord, &fun.name.name, fun.name.span);
+            ret = TypeId::INT;
+        }
+
+        // 进入作用域
+        self.sym_tab().enter();
+
+        let mut para_list: Vec<Box<Var>> = Vec::new();
+        let mut offset = PARA_BASE;     // 参数在返回地址和ebp之上
+        for para in &fun.params {
+            let mut v = self.param(para);
+            v.set_offset(offset);
+            offset += v.get_para_size();
+            let v = self.sym_tab().add_var(v);
+            para_list.push(v);
+        }
+
+        let mut f = Box::new(Fun::new(fun.ext, ret, fun.name.name.clone(), para_list));
+        f.set_span(fun.name.span);
+
+        match &fun.body {
+            None => self.sym_tab().dec_fun(f),      // 函数声明
+            Some(body) => {                         // 函数定义
+                self.sym_tab().def_fun(f);
+                self.ir.gen_fun_head();             // 产生函数入口
+                self.block(body);
+                self.check_unused();
+                self.ir.gen_fun_tail();             // 产生函数出口
+                self.sym_tab().end_def_fun();       // 结束函数定义
+            }
+        }
+
+        // 离开作用域
+        self.sym_tab().leave();
+    }
+
+    // 函数中定义后没有使用的局部变量给出警告
+    fn check_unused(&mut self) {
+        for (name, span) in std::mem::take(&mut self.locals) {
+            if !self.used.contains(&span) {
+                sem_warning(&self.sym_tab().get_sink(), UnusedVariable, &name, span);
+            }
+        }
+        self.used.clear();
+    }
+
+    // 数组参数按指向元素的指针传递，只有第一维长度可以省略，函数参数按函数指针传递
+    fn param_type(&mut self, para: &Param) -> TypeId {
+        let (name, span) = (&para.name.name, para.name.span);
+        let base = self.spec_type(&para.ty, false);
+        let first = para.decl.first_dim();
+        let t = self.declarator(base, &para.decl, first.is_some(), name, span);
+        if let Some(len) = first {
+            let len = len.as_ref().map_or(1, |e| self.const_int(e).unwrap_or(1));
+            self.check_array_len(len, name, span);
+            return t.pointer();
+        }
+        t.decay()
+    }
+
+    fn param(&mut self, para: &Param) -> Box<Var> {
+        let name = para.name.name.clone();
+        let span = para.name.span;
+        let t = self.param_type(para);
+
+        let mut v = Box::new(Var::new_var(self.sym_tab().get_scope_path(), false, t, name, None));
+        v.set_span(span);
+        if v.is_record() {     // 不支持按值传递结构体或联合
+            sem_error(&self.sym_tab().get_sink(), ExprIsRecord, &para.name.name, span);
+        }
+        self.types.insert(para.span, v.type_name());
+        v
+    }
+}
+
+// 语句
+impl<'a> Lower<'a> {
+    fn statement(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Expr(e) => {
+                self.alt_expr(e);
+            },
+            StmtKind::Block(block) => self.block(block),
+            StmtKind::While { cond, body } => self.while_stat(cond, body),
+            StmtKind::DoWhile { body, cond } => self.do_while_stat(body, cond),
+            StmtKind::For { init, cond, step, body } => self.for_stat(init, cond, step, body),
+            StmtKind::If { cond, then, els } => self.if_stat(cond, then, els),
+            StmtKind::Switch { cond, cases } => self.switch_stat(cond, cases),
+            StmtKind::Break => self.ir.gen_break(stmt.span),
+            StmtKind::Continue => self.ir.gen_continue(stmt.span),
+            StmtKind::Return(ret) => {
+                let ret = self.alt_expr(ret);
+                self.ir.gen_return(ret, stmt.span);     // 产生return语句
+            },
+        }
+    }
+
+    fn while_stat(&mut self, cond: &Option<Expr>, body: &Stmt) {
+        self.sym_tab().enter();
+
+        let (_while, _exit) = self.ir.gen_while_head();
+        let cond = self.alt_expr(cond);
+        self.ir.gen_while_cond(cond, Some(_exit.clone()));
+        self.statement(body);
+        self.ir.gen_while_tail(_while, _exit);
+
+        self.sym_tab().leave();
+    }
+
+    fn for_stat(&mut self, init: &ForInit, cond: &Option<Expr>, step: &Option<Expr>, body: &Stmt) {
+        self.sym_tab().enter();
+
+        match init {
+            ForInit::Decl(decl) => self.decl(decl),
+            ForInit::Expr(e) => {
+                self.alt_expr(e);
+            },
+        }
+        let (_for, _exit) = self.ir.gen_for_head();
+
+        let cond = self.alt_expr(cond);
+        let (_block, _step) = self.ir.gen_for_cond_begin(cond, _exit.clone());
+
+        self.alt_expr(step);    // 步进表达式
+        self.ir.gen_for_cond_end(_for, _block);
+        self.statement(body);
+        self.ir.gen_for_tail(_step, _exit);
+
+        self.sym_tab().leave();
+    }
+
+    fn do_while_stat(&mut self, body: &Stmt, cond: &Option<Expr>) {
+        // 进入do作用域
+        self.sym_tab().enter();
+        let (_do, _exit) = self.ir.gen_do_while_head();
+        self.statement(body);
+        // 离开do作用域
+        self.sym_tab().leave();
+
+        let cond = self.alt_expr(cond);
+        self.ir.gen_do_while_tail(cond, _do, _exit);
+    }
+
+    fn if_stat(&mut self, cond: &Expr, then: &Stmt, els: &Option<Box<Stmt>>) {
+        self.sym_tab().enter();
+
+        let cond = self.expr(cond);
+        let _else = self.ir.gen_if_head(cond);
+        self.statement(then);
+
+        self.sym_tab().leave();
+
+        if let Some(els) = els {
+            let _exit = self.ir.gen_else_head(_else);      // 有else
+            self.sym_tab().enter();
+            self.statement(els);
+            self.sym_tab().leave();
+            self.ir.gen_else_tail(_exit);
+        } else {
+            self.ir.gen_if_tail(_else);      // 无else
+        }
+    }
+
+    fn switch_stat(&mut self, cond: &Expr, cases: &[Case]) {
+        self.sym_tab().enter();
+
+        let _exit = self.ir.gen_switch_head();
+        let cond = self.expr(cond).filter(|cond| self.ir.not_record(cond) && self.ir.not_float(cond)).map(|cond| self.ir.gen_assign(cond));
+
+        for case in cases {
+            if let Some(label) = &case.label {
+                let lb = match &label.kind {
+                    ExprKind::Literal(lit) if !matches!(lit, Literal::Real(_)) => self.expr(label),
+                    _ => self.const_expr(label).map(|val| {
+                        let mut v = Box::new(Var::new_int(val));
+                        v.set_span(label.span);
+                        self.types.insert(label.span, v.type_name());
+                        v
+                    }),
+                };
+                let _case_exit = self.ir.gen_case_head(cond.clone(), lb);
+                self.sym_tab().enter();
+                self.block_items(&case.items);
+                self.sym_tab().leave();
+                self.ir.gen_case_tail(_case_exit);
+            } else {    // default
+                self.sym_tab().enter();
+                self.block_items(&case.items);
+                self.sym_tab().leave();
+            }
+        }
+
+        self.ir.gen_switch_tail(_exit);
+
+        self.sym_tab().leave();
+    }
+}
+
+// 表达式
+impl<'a> Lower<'a> {
+    // 可以为空的表达式，为空时返回特殊void变量
+    fn alt_expr(&mut self, e: &Option<Expr>) -> Option<Box<Var>> {
+        match e {
+            Some(e) => self.expr(e),
+            None => Var::get_void(),
+        }
+    }
+
+    // 翻译表达式并记录其类型
+    fn expr(&mut self, e: &Expr) -> Option<Box<Var>> {
+        let v = self.expr_kind(e);
+        if let Some(v) = &v {
+            self.types.insert(e.span, v.type_name());
+        }
+        v
+    }
+
+    fn expr_kind(&mut self, e: &Expr) -> Option<Box<Var>> {
+        match &e.kind {
+            ExprKind::Literal(lit) => {
+                let v = Box::new(Var::new_const(lit, e.span));
+                if let Literal::Str(_) = lit {
+                    Some(self.sym_tab().add_str(v))     // 字符串常量记录，返回命名后的常量
+                } else {
+                    Some(v)
+                }
+            },
+            ExprKind::Ident(name) => self.var(name, e.span),
+            ExprKind::Index { base, index } => {
+                let index = self.expr(index);
+                let array = match &base.kind {
+                    ExprKind::Ident(name) => {
+                        let array = self.var(name, base.span);
+                        if let Some(array) = &array {
+                            self.types.insert(base.span, array.type_name());
+                        }
+                        array
+                    },
+                    _ => self.expr(base),
+                };
+                self.ir.gen_array(array, index)
+            },
+            ExprKind::Call { callee, args } => {
+                let mut vals = Vec::new();
+                for arg in args {
+                    match self.expr(arg) {
+                        Some(v) => vals.push(v),
+                        None => break,      // 出错的实参之后不再处理
+                    }
+                }
+                let function = self.sym_tab().get_fun(callee.name.clone(), vals.clone(), callee.span);
+                self.ir.gen_call(function, vals)
+            },
+            ExprKind::Unary { op, expr } => {
+                let v = self.expr(expr);
+                self.ir.gen_one_op_left(*op, v)
+            },
+            ExprKind::Postfix { op, expr } => {
+                let v = self.expr(expr);
+                self.ir.gen_one_op_right(v, *op)
+            },
+            ExprKind::Binary { op, lhs, rhs } => {
+                let lval = self.expr(lhs);
+                let rval = self.expr(rhs);
+                self.ir.gen_two_op(lval, *op, rval)
+            },
+            ExprKind::Assign { op, lhs, rhs } => {
+                let lval = self.expr(lhs);
+                let rval = self.expr(rhs);
+                match compound(*op) {
+                    Some(op) => self.ir.gen_compound(lval, op, rval),
+                    None => self.ir.gen_two_op(lval, ASSIGN, rval),
+                }
+            },
+            ExprKind::Member { base, arrow, member } => {
+                let base = self.expr(base);
+                self.ir.gen_member(base, *arrow, &member.name, member.span)
+            },
+            ExprKind::Paren(e) => self.expr(e),
+            ExprKind::Error => None,
+        }
+    }
+
+    // 查找变量，并记录使用位置，枚举常量作为整数常量，类型名不能作为表达式
+    fn var(&mut self, name: &str, span: Span) -> Option<Box<Var>> {
+        let mut var = self.sym_tab().get_var(name.to_string(), span)?;
+        if var.is_typedef() {
+            sem_error(&self.sym_tab().get_sink(), VarUnDec, name, span);
+            return None;
+        }
+        self.used.insert(var.get_span());
+        if var.get_literal() {
+            var = Box::new(Var::new_int(var.get_val()));
+        }
+        var.set_span(span);
+        Some(var)
+    }
+
+    // 整数常量表达式求值，用于数组长度、case标签和枚举常量的值
+    fn const_expr(&mut self, e: &Expr) -> Option<isize> {
+        self.const_value(e).map(|(val, _)| val)
+    }
+
+    // 整数常量表达式的值和类型，按C的类型提升和常规算术转换在对应的位数下计算
+    fn const_value(&mut self, e: &Expr) -> Option<(isize, TypeId)> {
+        let val = match &e.kind {
+            ExprKind::Literal(lit @ (Literal::Num(_) | Literal::Char(_))) => {
+                let v = Var::new_const(lit, e.span);
+                Some((v.get_val(), v.get_type().promote()))
+            },
+            ExprKind::Ident(name) => match self.sym_tab().find_var(name) {
+                Some(v) => Some((v.get_val(), TypeId::INT)).filter(|_| v.get_literal()),    // 只有枚举常量
+                None => {
+                    sem_error(&self.sym_tab().get_sink(), VarUnDec, name, e.span);
+                    return None;
+                },
+            },
+            ExprKind::Paren(e) => return self.const_value(e),
+            ExprKind::Unary { op, expr } if *op == SUB || *op == NOT || *op == BNOT => {
+                let (v, t) = self.const_value(expr)?;
+                return Some(match *op {
+                    SUB => (t.cast(v.wrapping_neg()), t),
+                    NOT => ((v == 0) as isize, TypeId::INT),
+                    _ => (t.cast(!v), t),
+                });
+            },
+            ExprKind::Binary { op, lhs, rhs } => {
+                let (l, lt) = self.const_value(lhs)?;
+                let (r, rt) = self.const_value(rhs)?;
+                let t = if *op == SHL || *op == SHR { lt } else { types::common(lt, rt) };
+                let rt = if matches!(*op, GT | GE | LT | LE | EQU | NEQU | AND | OR) { TypeId::INT } else { t };
+                fold(*op, t, l, r).map(|val| (val, rt))     // 除数为0或移位数越界时不是常量
+            },
+            _ => None,
+        };
+
+        if val.is_none() {
+            sem_error(&self.sym_tab().get_sink(), NotConstExpr, "", e.span);
+        }
+        val
+    }
+
+    // 枚举常量和数组长度必须在int的范围内
+    fn const_int(&mut self, e: &Expr) -> Option<isize> {
+        let val = self.const_expr(e)?;
+        if i32::try_from(val).is_err() {
+            sem_error(&self.sym_tab().get_sink(), ConstOverflow, "", e.span);
+            return None;
+        }
+        Some(val)
+    }
+}
+
+// 字符数组的字符串初始值
+fn str_init(t: TypeId, e: &Expr) -> Option<&str> {
+    match &e.kind {
+        ExprKind::Literal(Literal::Str(s)) if t.is_array() && t.pointee() == Some(TypeId::CHAR) => Some(s),
+        _ => None,
+    }
+}
+
+// 复合赋值对应的双目运算
+fn compound(op: Tag) -> Option<Tag> {
+    let op = match op {
+        ADDAS => ADD,
+        SUBAS => SUB,
+        MULAS => MUL,
+        DIVAS => DIV,
+        MODAS => MOD,
+        BANDAS => LEA,
+        BORAS => BOR,
+        XORAS => XOR,
+        SHLAS => SHL,
+        SHRAS => SHR,
+        _ => return None,
+    };
+    Some(op)
+}
+
+// 常量的双目运算，t为运算的类型，按该类型的位数计算并截断，比较和逻辑运算的结果为0或1
+pub(crate) fn fold(op: Tag, t: TypeId, l: isize, r: isize) -> Option<isize> {
+    let l = t.cast(l);
+    let r = if op == SHL || op == SHR { r } else { t.cast(r) };
+    let unsigned = t.is_unsigned();
+    let ord = if unsigned { (l as u64).cmp(&(r as u64)) } else { l.cmp(&r) };
+    let val = match op {
+        ADD => l.wrapping_add(r),
+        SUB => l.wrapping_sub(r),
+        MUL => l.wrapping_mul(r),
+        DIV | MOD if r == 0 => return None,
+        DIV if unsigned => (l as u64 / r as u64) as isize,
+        MOD if unsigned => (l as u64 % r as u64) as isize,
+        DIV => l.wrapping_div(r),
+        MOD => l.wrapping_rem(r),
+        GT => ord.is_gt() as isize,
+        GE => ord.is_ge() as isize,
+        LT => ord.is_lt() as isize,
+        LE => ord.is_le() as isize,
+        EQU => (l == r) as isize,
+        NEQU => (l != r) as isize,
+        AND => (l != 0 && r != 0) as isize,
+        OR => (l != 0 || r != 0) as isize,
+        LEA => l & r,
+        BOR => l | r,
+        XOR => l ^ r,
+        SHL | SHR if !(0..t.size() * 8).contains(&r) => return None,    // 移位数越界时不是常量
+        SHL => l.wrapping_shl(r as u32),
+        SHR if unsigned => (l as u64 >> r) as isize,
+        SHR => l >> r,
+        _ => return None,
+    };
+    Some(t.cast(val))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::SemError::{ArrayLenInvalid, ConstOverflow, ExprIsFloat, ExprNotBase, ExprNotLeftVal, MemberUnDec, NotConstExpr, RecordUnDef, TypeSpecErr, VarIsFun, VarReDef, VarUnDec};
+    use crate::common::SynError::SemiconLost;
+    use crate::diagnostic::{Diagnostic, DiagnosticSink, ErrorCode, Severity};
+    use crate::lower::compile;
+
+    // 分析并生成中间代码，返回收集到的诊断
+    fn lower(source: &str) -> Vec<Diagnostic> {
+        let diag = DiagnosticSink::new();
+        compile(source, &diag);
+        diag.diagnostics()
+    }
+
+    // 各诊断的错误码和行号
+    fn diagnostics(source: &str) -> Vec<(Option<ErrorCode>, usize)> {
+        lower(source).iter().map(|d| (d.code(), d.span().line())).collect()
+    }
+
+    #[test]
+    fn test_collect_diagnostics() {
+        let diags = lower("int a\nint b;\nint b;\n");
+        assert_eq!(diags.len(), 2);
+        assert_eq!(diags[0].code(), Some(ErrorCode::Syn(SemiconLost)));
+        assert_eq!((diags[0].span().line(), diags[0].span().col()), (2, 1));
+        assert_eq!(diags[1].code(), Some(ErrorCode::Sem(VarReDef)));
+        assert_eq!(diags[1].span().line(), 3);
+        assert_eq!(diags[1].labels()[0].span().line(), 2);
+    }
+
+    #[test]
+    fn test_record_diagnostics() {
+        // 定义之前不能声明对象或访问成员，定义之后只能访问已有成员
+        let codes = diagnostics("struct S;\nstruct S *p;\nint main() { struct S s; return p->a; }\n\
+                                 struct S { int a; };\nint f() { return p->b + p->a; }\n");
+        assert_eq!(codes, vec![
+            (Some(ErrorCode::Sem(RecordUnDef)), 3),
+            (Some(ErrorCode::Sem(RecordUnDef)), 3),
+            (None, 3),                                  // s没有使用
+            (Some(ErrorCode::Sem(MemberUnDec)), 5),
+        ]);
+    }
+
+    #[test]
+    fn test_unused_warning() {
+        // 只检查局部变量，参数、全局变量和被内层同名变量遮蔽后使用的变量不算
+        let diags = lower("int g;\nint f(int p) {\n  int a = 1; int b; char s[4];\n\
+                           if (p) { int a; a = 2; }\n  b = a; return g;\n}\n");
+        assert_eq!(diags.len(), 1);
+        assert_eq!(diags[0].severity(), Severity::Warning);
+        assert_eq!(diags[0].flag(), Some("unused-variable"));
+        assert_eq!((diags[0].span().line(), diags[0].span().col()), (3, 26));
+    }
+
+    #[test]
+    fn test_declarator_diagnostics() {
+        // 函数类型可以作为类型名和指针指向的类型，变量和成员不能是函数类型
+        let codes = diagnostics("typedef int F(int n);\nF *fp;\nint (*hp)(int n), (*q)[4];\nF g;\nstruct S { F m; int (*a[2])[3]; };\n\
+                                 int f(F h, int b[][4]) { return b[1][2]; }\n");
+        assert_eq!(codes, vec![
+            (Some(ErrorCode::Sem(VarIsFun)), 4),
+            (Some(ErrorCode::Sem(VarIsFun)), 5),
+        ]);
+    }
+
+    #[test]
+    fn test_enum_typedef_diagnostics() {
+        // 局部变量可以遮蔽类型名，类型名不能作为表达式，数组长度必须是常量
+        let codes = diagnostics("typedef int T;\nenum { A, B = A + 2 };\nint n;\nint a[B], b[n];\n\
+                                 int f() { T x; int T; T = B; return T + x; }\nint g() { return T; }\n");
+        assert_eq!(codes, vec![
+            (Some(ErrorCode::Sem(NotConstExpr)), 4),
+            (Some(ErrorCode::Sem(VarUnDec)), 6),
+        ]);
+    }
+
+    #[test]
+    fn test_int_type_diagnostics() {
+        // 整数类型说明符可以任意顺序组合，但不能冲突
+        let codes = diagnostics("unsigned long long a;\nshort long b;\nlong int long c;\nsigned unsigned d;\n");
+        assert_eq!(codes, vec![
+            (Some(ErrorCode::Sem(TypeSpecErr)), 2),
+            (Some(ErrorCode::Sem(TypeSpecErr)), 4),
+        ]);
+    }
+
+    #[test]
+    fn test_float_diagnostics() {
+        // 浮点数不能取模、参与指针运算或作为switch条件，case标签必须是整数
+        let codes = diagnostics("double d;\nint a[2];\nint main() {\nd = d % 2;\nd = *(a + d);\n\
+                                 switch (d) { }\nswitch (1) { case 1.5: ; }\nreturn d; }\n");
+        assert_eq!(codes, vec![
+            (Some(ErrorCode::Sem(ExprIsFloat)), 4),
+            (Some(ErrorCode::Sem(ExprIsFloat)), 5),
+            (Some(ErrorCode::Sem(ExprIsFloat)), 6),
+            (Some(ErrorCode::Sem(NotConstExpr)), 7),
+        ]);
+    }
+
+    #[test]
+    fn test_bit_diagnostics() {
+        // 位运算可以出现在常量表达式中，移位数不小于int的位数时不是常量，操作数不能是指针或浮点数
+        let codes = diagnostics("enum { A = 1 << 4, B = ~A & 0xff };\nint a[B ^ 0xef | 2], b[A >> 32];\n\
+                                 int main() { int *p; double d;\np = p | 1;\nd = d << 2;\nd = ~d; }\n");
+        assert_eq!(codes, vec![
+            (Some(ErrorCode::Sem(NotConstExpr)), 2),
+            (Some(ErrorCode::Sem(ExprNotBase)), 4),
+            (Some(ErrorCode::Sem(ExprIsFloat)), 5),
+            (Some(ErrorCode::Sem(ExprIsFloat)), 6),
+        ]);
+    }
+
+    #[test]
+    fn test_compound_diagnostics() {
+        // 复合赋值的左边必须是左值，运算的限制与对应的双目运算相同
+        let codes = diagnostics("int main() { int x; int *p; double d;\nx <<= x += 1;\n\
+                                 1 += x;\nd %= 2;\nx -= p;\n}\n");
+        assert_eq!(codes, vec![
+            (Some(ErrorCode::Sem(ExprNotLeftVal)), 3),
+            (Some(ErrorCode::Sem(ExprIsFloat)), 4),
+            (Some(ErrorCode::Sem(ExprNotBase)), 5),
+        ]);
+    }
+
+    #[test]
+    fn test_const_width() {
+        // 常量表达式按int计算，1 << 31为INT_MIN，放不下int的枚举常量和数组长度是错误
+        let codes = diagnostics("enum { M = 1 << 40, P = 1 << 31 };\nenum { N = 0x10000000000, Q = 2147483647, R };\n\
+                                 int c[P < 0], d[~0 >> 31 == -1];\nint e[0x100000000];\nint f[-P];\n");
+        assert_eq!(codes, vec![
+            (Some(ErrorCode::Sem(NotConstExpr)), 1),
+            (Some(ErrorCode::Sem(ConstOverflow)), 2),
+            (Some(ErrorCode::Sem(ConstOverflow)), 2),
+            (Some(ErrorCode::Sem(ConstOverflow)), 4),
+            (Some(ErrorCode::Sem(ArrayLenInvalid)), 5),
+        ]);
+    }
+}
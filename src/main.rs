@@ -1,8 +1,7 @@
-
-
-use failure;
+use std::env;
+use std::process;
 use compiler::run;
 
-fn main() -> Result<(), failure::Error>{
-    run()
+fn main() {
+    process::exit(run(env::args().skip(1)));
 }
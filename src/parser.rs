@@ -1,53 +1,58 @@
-use crate::common::SynError::{self, ColonLost, ColonWrong, CommaLost, IdLost, IdWrong, LbraceLost, LbraceWrong, LparenLost, LparenWrong, NumLost, NumWrong, RbraceLost, RbraceWrong, RbrackLost, RparenLost, RparenWrong, SemiconLost, SemiconWrong, TypeLost, TypeWrong};
-use crate::common::Tag::{self, CH, DEC, ID, INC, KwWhile, LBRACE, LEA, LPAREN, MUL, NOT, NUM, RPAREN, STR, SUB, KwFor, KwDo, KwIf, KwSwitch, KwBreak, SEMICON, KwInt, KwVoid, KwChar, RBRACE, KwContinue, KwReturn, END, ASSIGN, KwElse, KwCase, KwDefault, COLON, LBRACK, RBRACK, COMMA, OR, AND, GT, GE, LT, ADD, NEQU, EQU, LE, DIV, KwExtern, MOD};
-use crate::gen_ir::GenIR;
+use std::collections::HashMap;
+use crate::ast::{Block, BlockItem, Case, Decl, Declarator, DeclaratorKind, EnumSpec, Enumerator, Expr, ExprKind, ForInit, FunctionDef, Ident, Initializer, Item, Literal, Param, RecordSpec, Stmt, StmtKind, TranslationUnit, TypeSpec, VarDecl};
+use crate::catalog::Catalog;
+use crate::common::SynError::{self, ColonLost, ColonWrong, CommaLost, IdLost, IdWrong, LbraceLost, LbraceWrong, LiteralLost, LiteralWrong, LparenLost, LparenWrong, NumLost, NumWrong, RbraceLost, RbraceWrong, RbrackLost, RbrackWrong, RparenLost, RparenWrong, SemiconLost, SemiconWrong, TypeLost, TypeWrong};
+use crate::common::Tag::{self, CH, DEC, ID, INC, KwWhile, LBRACE, LEA, LPAREN, MUL, NOT, NUM, RPAREN, STR, SUB, KwFor, KwDo, KwIf, KwSwitch, KwBreak, SEMICON, KwInt, KwVoid, KwChar, RBRACE, KwContinue, KwReturn, END, ASSIGN, KwElse, KwCase, KwDefault, COLON, LBRACK, RBRACK, COMMA, OR, AND, GT, GE, LT, ADD, NEQU, EQU, LE, DIV, KwExtern, MOD, KwStruct, KwUnion, DOT, ARROW, KwEnum, KwTypedef, KwShort, KwLong, KwSigned, KwUnsigned, KwFloat, KwDouble, REAL, BOR, XOR, BNOT, SHL, SHR, ADDAS, SUBAS, MULAS, DIVAS, MODAS, BANDAS, BORAS, XORAS, SHLAS, SHRAS};
+use crate::diagnostic::{Diagnostic, DiagnosticSink, ErrorCode};
 use crate::lexer::Lexer;
 use crate::scanner::Scanner;
-use crate::symbol::{Fun, Var};
-use crate::symtab::SymTab;
-use crate::token::{Num, TokenType};
+use crate::span::Span;
+use crate::token::{Comment, Num, TokenType};
 
-fn syn_error(scanner: &mut Scanner, code: usize, t: &TokenType)
+fn syn_error(diag: &DiagnosticSink, code: SynError, t: &TokenType)
 {
-    //语法错误信息串
-    const SYN_ERROR_TABLE: [&str; 15] = ["类型", "标识符", "数组长度",
-                                         "常量", "逗号", "分号", "=",
-                                         "冒号", "while", "(", ")",
-                                         "[", "]", "{", "}"];
-
-    if code % 2 == 0 {
-        println!("{}<第{}行>语法错误 : 在 {} 之前丢失 {} .", scanner.file_name(), scanner.line_num(),
-                                                           t.to_string(), SYN_ERROR_TABLE[code / 2]);
-    } else {
-        println!("{}<第{}行>语法错误 : 在 {} 之前没有正确匹配 {} .", scanner.file_name(), scanner.line_num(),
-                                                                   t.to_string(), SYN_ERROR_TABLE[code / 2]);
-    }
+    let message = Catalog::current().syn_error(code, &t.to_string());
+    diag.emit(Diagnostic::error(ErrorCode::Syn(code), message, t.get_span()));
+}
+
+// 分析一段源代码，返回语法树和词法、语法错误
+pub fn parse(source: &str) -> (TranslationUnit, Vec<Diagnostic>) {
+    let mut scanner = Scanner::from_source(source);
+    let diag = DiagnosticSink::new();
+    let mut lexer = Lexer::new(&mut scanner, diag.clone());
+    let unit = Parser::new(&mut lexer).analyze();
+
+    (unit, diag.take())
 }
 
 pub struct Parser<'a> {
     lexer: &'a mut Lexer<'a>,
     look: TokenType,
-    sym_tab: &'a mut SymTab,
-    ir: Option<Box<GenIR>>,
+    prev: Span,                 // 上一个记号的位置，用于计算语法树节点的区间
+    diag: DiagnosticSink,       // 诊断信息收集器
+    names: Vec<HashMap<String, bool>>,  // 各层作用域声明的名字，true表示typedef类型名
 }
 
 impl<'a> Parser<'a> {
-    pub(crate) fn new(lexer: &'a mut Lexer<'a>, sym_tab: &'a mut SymTab, ir: Option<Box<GenIR>>) -> Self {
+    pub(crate) fn new(lexer: &'a mut Lexer<'a>) -> Self {
+        let diag = lexer.get_sink();
         Parser {
             lexer,
             look: TokenType::Num(Num::new(1900)),
-            sym_tab,
-            ir,
+            prev: Span::dummy(),
+            diag,
+            names: vec![HashMap::new()],
         }
     }
 
-    // 输出中间代码
-    pub(crate) fn print_inter_code(&self) {
-        self.sym_tab.print_inter_code();
+    // 词法分析跳过的注释
+    pub(crate) fn take_comments(&mut self) -> Vec<Comment> {
+        self.lexer.take_comments()
     }
 
     // 移进
     fn move_token(&mut self) {
+        self.prev = self.look.get_span();
         self.look = self.lexer.tokenize();
     }
 
@@ -63,78 +68,153 @@ impl<'a> Parser<'a> {
 
     fn recovery(&mut self, cond: bool, lost: SynError, wrong: SynError) {
         if cond {
-            syn_error(self.lexer.get_scanner(), lost as usize, &self.look);
+            syn_error(&self.diag, lost, &self.look);
         } else {
-            syn_error(self.lexer.get_scanner(), wrong as usize, &self.look);
+            syn_error(&self.diag, wrong, &self.look);
+            self.move_token();
+        }
+    }
+
+    // 从start到上一个记号的区间
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.prev)
+    }
+
+    // 读取标识符，缺失时名称为空
+    fn ident(&mut self) -> Option<Ident> {
+        if let TokenType::Id(id) = &self.look {
+            let ident = Ident { name: id.get_name(), span: self.look.get_span() };
             self.move_token();
+            return Some(ident);
         }
+
+        None
+    }
+
+    // 当前记号是否是可见的typedef类型名，内层同名的变量会隐藏外层的类型名
+    fn is_type_name(&self) -> bool {
+        match &self.look {
+            TokenType::Id(id) => {
+                let name = id.get_name();
+                self.names.iter().rev().find_map(|scope| scope.get(&name).copied()).unwrap_or(false)
+            },
+            _ => false,
+        }
+    }
+
+    // 类型说明符的开始
+    fn type_start(&self) -> bool {
+        type_first(&self.look) || self.is_type_name()
+    }
+
+    // 在当前作用域记录声明的名字
+    fn declare(&mut self, name: &Ident, typedef: bool) {
+        if let Some(scope) = self.names.last_mut() {
+            scope.insert(name.name.clone(), typedef);
+        }
+    }
+
+    // 缺失的标识符，位置记为当前记号
+    fn missing_ident(&self) -> Ident {
+        Ident { name: String::new(), span: self.look.get_span() }
     }
 
     // 语法分析主程序
-    pub(crate) fn analyze(&mut self) {
+    pub(crate) fn analyze(&mut self) -> TranslationUnit {
         self.move_token();      // 预先读入
-        self.program();
+        let start = self.look.get_span();
+        let mut items = vec![];
+        self.program(&mut items);
+
+        TranslationUnit {
+            items,
+            span: self.span_from(start),
+        }
     }
 
-    fn program(&mut self) {
+    fn program(&mut self, items: &mut Vec<Item>) {
         if equal_tag(&self.look, END) {
             return;
         } else {
-            self.segment();
-            self.program();
+            items.push(self.segment());
+            self.program(items);
         }
     }
 
     /*
-        <segment>			->	rsv_extern <type><def>|<type><def>
+        <segment>			->	<localdef>|rsv_extern <type><def>|<type><def>
     */
-    fn segment(&mut self) {
+    fn segment(&mut self) -> Item {
+        if equal_tag(&self.look, KwTypedef) {     // 类型名定义
+            return Item::Decl(self.local_def());
+        }
+
+        let start = self.look.get_span();
         let ext = self.match_tag(KwExtern);
         let t = self.var_type();
-        self.def(ext, t);
+        self.def(ext, t, start)
     }
 
 
     /*
-	    <def>					->	mul id <init><deflist>|ident <idtail>
+	    <def>					->	<declarator> <funtail>|<declarator> <init> <deflist>
     */
-    fn def(&mut self, ext: bool, t: Tag) {
-        let mut name = String::new();
-        if self.match_tag(MUL) {    // 指针
-            if equal_tag(&self.look, ID) {
-                if let TokenType::Id(id) = &self.look {
-                    name = id.get_name();
-                    self.move_token();
-                }
-            } else {
-                self.recovery(equal_tag(&self.look, SEMICON) || equal_tag(&self.look, COMMA) || equal_tag(&self.look, ASSIGN), IdLost, IdWrong);
-            }
+    fn def(&mut self, ext: bool, t: TypeSpec, start: Span) -> Item {
+        if (t.record.is_some() || t.enumer.is_some()) && self.match_tag(SEMICON) {     // 只声明结构体、联合或枚举
+            return Item::Decl(Decl { ext, typedef: false, ty: t, vars: vec![], span: self.span_from(start) });
+        }
 
-            let v = self.init(ext, t, true, name);
-            self.sym_tab.add_var(v);
-            self.def_list(ext, t);
-        } else {
-            if equal_tag(&self.look, ID) {  // 变量、数组、函数
-                if let TokenType::Id(id) = &self.look {
-                    name = id.get_name();
-                    self.move_token();
+        let (name, decl) = self.declarator();
+        match decl.kind {
+            DeclaratorKind::Function { inner, params } if matches!(inner.kind, DeclaratorKind::Name) => {     // 函数
+                self.declare(&name, false);
+                self.names.push(HashMap::new());    // 参数的作用域，函数体中可以遮蔽参数之外的类型名
+                for para in params.iter() {
+                    self.declare(&para.name, false);
                 }
-            } else {
-                self.recovery(equal_tag(&self.look, SEMICON) || equal_tag(&self.look, COMMA) || equal_tag(&self.look, ASSIGN) || equal_tag(&self.look, LPAREN) || equal_tag(&self.look, LBRACK), IdLost, IdWrong);
-            }
-            self.id_tail(ext, t, false, name);
+                let body = self.fun_tail();
+                self.names.pop();
+                Item::Function(FunctionDef { ext, ret: t, name, params, body, span: self.span_from(start) })
+            },
+            kind => {       // 变量、数组、指针
+                let decl = Declarator::new(kind, decl.span);
+                let mut vars = vec![self.var_def(name, decl)];
+                self.def_list(&mut vars);
+                self.declare_vars(&vars, false);
+                Item::Decl(Decl { ext, typedef: false, ty: t, vars, span: self.span_from(start) })
+            },
         }
     }
 
     /*
-        <type>				->	rsv_int|rsv_char|rsv_bool|rsv_void
+        <type>				->	<intwords>|rsv_void|rsv_float|rsv_double|rsv_struct<record>|rsv_union<record>|rsv_enum<enum>|typename
+        <intwords>			->	(rsv_int|rsv_char|rsv_short|rsv_long|rsv_signed|rsv_unsigned)<intwords>|^
     */
-    fn var_type(&mut self) -> Tag {
-        let mut tmp: Tag = KwInt;  // 默认类型
-
-        if type_first(&self.look) {
-            tmp = self.look.get_tag();
+    fn var_type(&mut self) -> TypeSpec {
+        let start = self.look.get_span();
+        let mut tmp = TypeSpec { tag: KwInt, words: vec![], record: None, enumer: None, alias: None, span: start };  // 默认类型
+
+        if int_first(&self.look) {
+            // 整数类型可以由多个关键字组成，组合是否有效由语义分析检查
+            while int_first(&self.look) {
+                tmp.words.push(self.look.get_tag());
+                self.move_token();
+            }
+            tmp.tag = [KwChar, KwShort, KwLong].into_iter().find(|t| tmp.words.contains(t)).unwrap_or(KwInt);
+            tmp.span = self.span_from(start);
+        } else if type_first(&self.look) {
+            tmp.tag = self.look.get_tag();
             self.move_token();
+            if tmp.tag == KwStruct || tmp.tag == KwUnion {
+                tmp.record = Some(Box::new(self.record(start)));
+                tmp.span = self.span_from(start);
+            } else if tmp.tag == KwEnum {
+                tmp.enumer = Some(Box::new(self.enumer(start)));
+                tmp.span = self.span_from(start);
+            }
+        } else if self.is_type_name() {
+            tmp.tag = ID;
+            tmp.alias = self.ident();
         } else {
             self.recovery(equal_tag(&self.look, ID) || equal_tag(&self.look, MUL), TypeLost, TypeWrong);
         }
@@ -142,36 +222,122 @@ impl<'a> Parser<'a> {
         tmp
     }
 
-    fn block(&mut self) {
+    /*
+        <record>			->	ident<recordbody>|lbrace<members>rbrace
+        <recordbody>		->	lbrace<members>rbrace|^
+    */
+    fn record(&mut self, start: Span) -> RecordSpec {
+        let name = self.ident();
+        if name.is_none() && !equal_tag(&self.look, LBRACE) {
+            self.recovery(equal_tag(&self.look, ID) || equal_tag(&self.look, MUL) || equal_tag(&self.look, SEMICON), IdLost, IdWrong);
+        }
+
+        let mut members = None;
+        if self.match_tag(LBRACE) {
+            let mut decls = vec![];
+            self.members(&mut decls);
+            if !self.match_tag(RBRACE) {
+                self.recovery(equal_tag(&self.look, ID) || equal_tag(&self.look, MUL) || equal_tag(&self.look, SEMICON), RbraceLost, RbraceWrong);
+            }
+            members = Some(decls);
+        }
+
+        RecordSpec { name, members, span: self.span_from(start) }
+    }
+
+    /*
+        <enum>				->	ident<enumbody>|lbrace<enumlist>rbrace
+        <enumbody>			->	lbrace<enumlist>rbrace|^
+        <enumlist>			->	ident<enumvalue> comma <enumlist>|ident<enumvalue>|^
+        <enumvalue>			->	assign <expr>|^
+    */
+    fn enumer(&mut self, start: Span) -> EnumSpec {
+        let name = self.ident();
+        if name.is_none() && !equal_tag(&self.look, LBRACE) {
+            self.recovery(equal_tag(&self.look, ID) || equal_tag(&self.look, MUL) || equal_tag(&self.look, SEMICON), IdLost, IdWrong);
+        }
+
+        let mut items = None;
+        if self.match_tag(LBRACE) {
+            let mut list = vec![];
+            while let Some(name) = self.ident() {
+                let value = if self.match_tag(ASSIGN) { Some(self.expr()) } else { None };
+                self.declare(&name, false);
+                let span = self.span_from(name.span);
+                list.push(Enumerator { name, value, span });
+                if !self.match_tag(COMMA) {     // 最后一个常量后面可以有逗号
+                    break;
+                }
+            }
+            if !self.match_tag(RBRACE) {
+                self.recovery(equal_tag(&self.look, ID) || equal_tag(&self.look, MUL) || equal_tag(&self.look, SEMICON), RbraceLost, RbraceWrong);
+            }
+            items = Some(list);
+        }
+
+        EnumSpec { name, items, span: self.span_from(start) }
+    }
+
+    /*
+        <members>			->	<localdef><members>|^
+    */
+    fn members(&mut self, decls: &mut Vec<Decl>) {
+        if self.type_start() {
+            decls.push(self.local_def());
+            self.members(decls);
+        }
+    }
+
+    fn block(&mut self) -> Block {
+        let start = self.look.get_span();
         if !self.match_tag(LBRACE) {
             self.recovery(type_first(&self.look) || statement_first(&self.look) || equal_tag(&self.look, RBRACE), LbraceLost, LbraceWrong);
         }
 
-        self.subprogram();
+        let mut items = vec![];
+        self.names.push(HashMap::new());
+        self.subprogram(&mut items);
+        self.names.pop();
 
         if !self.match_tag(RBRACE) {
             self.recovery(type_first(&self.look) || statement_first(&self.look) || equal_tag(&self.look, KwExtern) || equal_tag(&self.look, KwElse) || equal_tag(&self.look, KwCase) || equal_tag(&self.look, KwDefault), RbraceLost, RbraceWrong);
         }
+
+        Block { items, span: self.span_from(start) }
     }
 
-    fn subprogram(&mut self) {
-        if type_first(&self.look) { // 局部变量
-            self.local_def();
-            self.subprogram();
+    fn subprogram(&mut self, items: &mut Vec<BlockItem>) {
+        if equal_tag(&self.look, KwTypedef) || self.type_start() { // 局部变量和类型名
+            items.push(BlockItem::Decl(self.local_def()));
+            self.subprogram(items);
         } else if statement_first(&self.look) { // 语句
-            self.statement();
-            self.subprogram();
+            items.push(BlockItem::Stmt(self.statement()));
+            self.subprogram(items);
         }
     }
 
     /*
-	    <localdef>		->	<type><defdata><deflist>
+	    <localdef>		->	rsv_typedef <type><defdata><deflist>|<type><defdata><deflist>
     */
-    fn local_def(&mut self) {
+    fn local_def(&mut self) -> Decl {
+        let start = self.look.get_span();
+        let typedef = self.match_tag(KwTypedef);
         let t = self.var_type();
-        let v = self.defdata(false, t);
-        self.sym_tab.add_var(v);
-        self.def_list(false, t);
+        let mut vars = vec![];
+        if (t.record.is_none() && t.enumer.is_none()) || !self.match_tag(SEMICON) {     // 只声明结构体、联合或枚举时没有变量
+            vars.push(self.defdata());
+            self.def_list(&mut vars);
+        }
+        self.declare_vars(&vars, typedef);
+
+        Decl { ext: false, typedef, ty: t, vars, span: self.span_from(start) }
+    }
+
+    // 记录声明的变量或类型名
+    fn declare_vars(&mut self, vars: &[VarDecl], typedef: bool) {
+        for var in vars {
+            self.declare(&var.name, typedef);
+        }
     }
 }
 
@@ -180,45 +346,56 @@ impl <'a> Parser<'a> {
     /*
     	<altexpr>			->	<expr>|^
     */
-    fn alt_expr(&mut self) -> Option<Box<Var>> {
+    fn alt_expr(&mut self) -> Option<Expr> {
         return if expr_first(&self.look) {
-            self.expr()
+            Some(self.expr())
         } else {
-            Var::get_void()     // 返回特殊void变量
+            None                // 空表达式
         }
     }
 
     /*
 	    <expr> 				-> 	<assexpr>
     */
-    fn expr(&mut self) -> Option<Box<Var>> {
+    fn expr(&mut self) -> Expr {
         self.ass_expr()
     }
 
     /*
 	    <assexpr>			->	<orexpr><asstail>
     */
-    fn ass_expr(&mut self) -> Option<Box<Var>> {
+    fn ass_expr(&mut self) -> Expr {
         let lval = self.or_expr();
         self.ass_tail(lval)
     }
 
     /*
-	    <asstail>			->	assign<assexpr>|^
+	    <asstail>			->	<asss><assexpr>|^
     */
-    fn ass_tail(&mut self, lval: Option<Box<Var>>) -> Option<Box<Var>> {
-        return if self.match_tag(ASSIGN) {
-            self.ass_expr();
-            self.ass_tail(lval)
+    fn ass_tail(&mut self, lval: Expr) -> Expr {
+        return if ass_opr(&self.look) {
+            let opt = self.asss();
+            let rval = self.ass_expr();
+            let span = lval.span.to(rval.span);
+            Expr::new(ExprKind::Assign { op: opt, lhs: Box::new(lval), rhs: Box::new(rval) }, span)
         } else {
             lval
         }
     }
 
+    /*
+	    <asss>				->	assign|addas|subas|mulas|divas|modas|bandas|boras|xoras|shlas|shras
+    */
+    fn asss(&mut self) -> Tag {
+        let opt = self.look.get_tag();
+        self.move_token();
+        opt
+    }
+
     /*
 	    <orexpr> 			-> 	<andexpr><ortail>
     */
-    fn or_expr(&mut self) -> Option<Box<Var>> {
+    fn or_expr(&mut self) -> Expr {
         let lval = self.and_expr();
         self.or_tail(lval)
     }
@@ -226,32 +403,32 @@ impl <'a> Parser<'a> {
     /*
 	    <ortail> 			-> 	or <andexpr> <ortail>|^
     */
-    fn or_tail(&mut self, lval: Option<Box<Var>>) -> Option<Box<Var>> {
+    fn or_tail(&mut self, lval: Expr) -> Expr {
         return if self.match_tag(OR) {
             let rval = self.and_expr();
+            let result = binary(lval, OR, rval);
 
-            self.or_tail(rval)
+            self.or_tail(result)
         } else {
             lval
         }
     }
 
     /*
-	    <andexpr> 		-> 	<cmpexpr><andtail>
+	    <andexpr> 		-> 	<borexpr><andtail>
     */
-    fn and_expr(&mut self) -> Option<Box<Var>> {
-        let lval = self.cmp_expr();
+    fn and_expr(&mut self) -> Expr {
+        let lval = self.bor_expr();
         self.and_tail(lval)
     }
 
     /*
-	    <andtail> 		-> 	and <cmpexpr> <andtail>|^
+	    <andtail> 		-> 	and <borexpr> <andtail>|^
     */
-    fn and_tail(&mut self, lval: Option<Box<Var>>) -> Option<Box<Var>> {
+    fn and_tail(&mut self, lval: Expr) -> Expr {
         return if self.match_tag(AND) {
-            let rval = self.cmp_expr();
-            let mut ir = self.ir.clone().unwrap();
-            let result = ir.gen_two_op(lval, AND, rval);
+            let rval = self.bor_expr();
+            let result = binary(lval, AND, rval);
 
             self.and_tail(result)
         } else {
@@ -260,22 +437,87 @@ impl <'a> Parser<'a> {
     }
 
     /*
-	    <cmpexpr>			->	<aloexpr><cmptail>
+	    <borexpr> 		-> 	<xorexpr><bortail>
     */
-    fn cmp_expr(&mut self) -> Option<Box<Var>> {
-        let lval = self.alo_expr();
+    fn bor_expr(&mut self) -> Expr {
+        let lval = self.xor_expr();
+        self.bor_tail(lval)
+    }
+
+    /*
+	    <bortail> 		-> 	bor <xorexpr> <bortail>|^
+    */
+    fn bor_tail(&mut self, lval: Expr) -> Expr {
+        return if self.match_tag(BOR) {
+            let rval = self.xor_expr();
+            let result = binary(lval, BOR, rval);
+
+            self.bor_tail(result)
+        } else {
+            lval
+        }
+    }
+
+    /*
+	    <xorexpr> 		-> 	<bandexpr><xortail>
+    */
+    fn xor_expr(&mut self) -> Expr {
+        let lval = self.band_expr();
+        self.xor_tail(lval)
+    }
+
+    /*
+	    <xortail> 		-> 	xor <bandexpr> <xortail>|^
+    */
+    fn xor_tail(&mut self, lval: Expr) -> Expr {
+        return if self.match_tag(XOR) {
+            let rval = self.band_expr();
+            let result = binary(lval, XOR, rval);
+
+            self.xor_tail(result)
+        } else {
+            lval
+        }
+    }
+
+    /*
+	    <bandexpr> 		-> 	<cmpexpr><bandtail>
+    */
+    fn band_expr(&mut self) -> Expr {
+        let lval = self.cmp_expr();
+        self.band_tail(lval)
+    }
+
+    /*
+	    <bandtail> 		-> 	lea <cmpexpr> <bandtail>|^
+    */
+    fn band_tail(&mut self, lval: Expr) -> Expr {
+        return if self.match_tag(LEA) {        // 双目的&是按位与
+            let rval = self.cmp_expr();
+            let result = binary(lval, LEA, rval);
+
+            self.band_tail(result)
+        } else {
+            lval
+        }
+    }
+
+    /*
+	    <cmpexpr>			->	<sftexpr><cmptail>
+    */
+    fn cmp_expr(&mut self) -> Expr {
+        let lval = self.sft_expr();
         self.cmp_tail(lval)
     }
 
     /*
-	    <cmptail>			->	<cmps><aloexpr><cmptail>|^
+	    <cmptail>			->	<cmps><sftexpr><cmptail>|^
     */
-    fn cmp_tail(&mut self, lval: Option<Box<Var>>) -> Option<Box<Var>> {
+    fn cmp_tail(&mut self, lval: Expr) -> Expr {
         return if equal_tag(&self.look, GT) || equal_tag(&self.look, GE) || equal_tag(&self.look, LT) || equal_tag(&self.look, LE) || equal_tag(&self.look, EQU) || equal_tag(&self.look, NEQU) {
             let opt = self.cmps();
-            let rval = self.alo_expr();
-            let mut ir = self.ir.clone().unwrap();
-            let result = ir.gen_two_op(lval, opt, rval);
+            let rval = self.sft_expr();
+            let result = binary(lval, opt, rval);
             self.cmp_tail(result)
         } else {
             lval
@@ -291,10 +533,42 @@ impl <'a> Parser<'a> {
         opt
     }
 
+    /*
+	    <sftexpr>			->	<aloexpr><sfttail>
+    */
+    fn sft_expr(&mut self) -> Expr {
+        let lval = self.alo_expr();
+        self.sft_tail(lval)
+    }
+
+    /*
+	    <sfttail>			->	<sfts><aloexpr><sfttail>|^
+    */
+    fn sft_tail(&mut self, lval: Expr) -> Expr {
+        return if equal_tag(&self.look, SHL) || equal_tag(&self.look, SHR) {
+            let opt = self.sfts();
+            let rval = self.alo_expr();
+
+            let result = binary(lval, opt, rval);
+            self.sft_tail(result)
+        } else {
+            lval
+        }
+    }
+
+    /*
+	    <sfts>				->	shl|shr
+    */
+    fn sfts(&mut self) -> Tag {
+        let opt = self.look.get_tag();
+        self.move_token();
+        opt
+    }
+
     /*
 	    <aloexpr>			->	<item><alotail>
     */
-    fn alo_expr(&mut self) -> Option<Box<Var>> {
+    fn alo_expr(&mut self) -> Expr {
         let lval = self.item();
         self.alo_tail(lval)
     }
@@ -302,13 +576,12 @@ impl <'a> Parser<'a> {
     /*
 	    <alotail>			->	<adds><item><alotail>|^
     */
-    fn alo_tail(&mut self, lval: Option<Box<Var>>) -> Option<Box<Var>> {
+    fn alo_tail(&mut self, lval: Expr) -> Expr {
         return if equal_tag(&self.look, ADD) || equal_tag(&self.look, SUB) {
             let opt = self.adds();
             let rval = self.item();
-            let mut ir = self.ir.clone().unwrap();
 
-            let result = ir.gen_two_op(lval, opt, rval);
+            let result = binary(lval, opt, rval);
             self.alo_tail(result)
         } else {
             lval
@@ -327,7 +600,7 @@ impl <'a> Parser<'a> {
     /*
 	    <item>				->	<factor><itemtail>
     */
-    fn item(&mut self) -> Option<Box<Var>> {
+    fn item(&mut self) -> Expr {
         let lval = self.factor();
         self.item_tail(lval)
     }
@@ -335,12 +608,11 @@ impl <'a> Parser<'a> {
     /*
 	    <itemtail>		->	<muls><factor><itemtail>|^
     */
-    fn item_tail(&mut self, lval: Option<Box<Var>>) -> Option<Box<Var>> {
+    fn item_tail(&mut self, lval: Expr) -> Expr {
         return if equal_tag(&self.look, MUL) || equal_tag(&self.look, DIV) || equal_tag(&self.look, MOD) {
             let opt = self.muls();
             let rval = self.factor();
-            let mut ir = self.ir.clone().unwrap();
-            let result = ir.gen_two_op(lval, opt, rval);
+            let result = binary(lval, opt, rval);
 
             self.item_tail(result)
         } else {
@@ -360,20 +632,21 @@ impl <'a> Parser<'a> {
     /*
 	    <factor> 			-> 	<lop><factor>|<val>
     */
-    fn factor(&mut self) -> Option<Box<Var>> {
-        return if equal_tag(&self.look, NOT) || equal_tag(&self.look, SUB) || equal_tag(&self.look, LEA) || equal_tag(&self.look, MUL) || equal_tag(&self.look, INC) || equal_tag(&self.look, DEC) {
+    fn factor(&mut self) -> Expr {
+        return if equal_tag(&self.look, NOT) || equal_tag(&self.look, BNOT) || equal_tag(&self.look, SUB) || equal_tag(&self.look, LEA) || equal_tag(&self.look, MUL) || equal_tag(&self.look, INC) || equal_tag(&self.look, DEC) {
+            let start = self.look.get_span();
             let opt = self.lop();
             let v = self.factor();
-            let mut ir = self.ir.clone().unwrap();
 
-            ir.gen_one_op_left(opt, v)
+            let span = start.to(v.span);
+            Expr::new(ExprKind::Unary { op: opt, expr: Box::new(v) }, span)
         } else {
             self.val()
         }
     }
 
     /*
-	    <lop> 				-> 	not|sub|lea|mul|incr|decr
+	    <lop> 				-> 	not|bnot|sub|lea|mul|incr|decr
     */
     fn lop(&mut self) -> Tag {
         let opt = self.look.get_tag();
@@ -382,20 +655,50 @@ impl <'a> Parser<'a> {
     }
 
     /*
-	    <val>					->	<elem><rop>
+	    <val>					->	<elem><postfix><rop>
     */
-    fn val(&mut self) -> Option<Box<Var>> {
+    fn val(&mut self) -> Expr {
         let v = self.elem();
+        let v = self.postfix(v);
         return if equal_tag(&self.look, INC) || equal_tag(&self.look, DEC) {
             let opt = self.rop();
-            let mut ir = self.ir.clone().unwrap();
 
-            ir.gen_one_op_right(v, opt)
+            let span = self.span_from(v.span);
+            Expr::new(ExprKind::Postfix { op: opt, expr: Box::new(v) }, span)
         } else {
             v
         }
     }
 
+    /*
+	    <postfix>			->	dot ident<postfix>|arrow ident<postfix>|lbrack<expr>rbrack<postfix>|^
+    */
+    fn postfix(&mut self, base: Expr) -> Expr {
+        if equal_tag(&self.look, DOT) || equal_tag(&self.look, ARROW) {
+            let arrow = equal_tag(&self.look, ARROW);
+            self.move_token();
+            let member = match self.ident() {
+                Some(member) => member,
+                None => {
+                    let member = self.missing_ident();
+                    self.recovery(lval_opr(&self.look) || equal_tag(&self.look, SEMICON) || equal_tag(&self.look, RPAREN), IdLost, IdWrong);
+                    member
+                }
+            };
+            let span = self.span_from(base.span);
+            self.postfix(Expr::new(ExprKind::Member { base: Box::new(base), arrow, member }, span))
+        } else if self.match_tag(LBRACK) {
+            let index = self.expr();
+            if !self.match_tag(RBRACK) {
+                self.recovery(lval_opr(&self.look), RbrackLost, RbrackWrong);
+            }
+            let span = self.span_from(base.span);
+            self.postfix(Expr::new(ExprKind::Index { base: Box::new(base), index: Box::new(index) }, span))
+        } else {
+            base
+        }
+    }
+
     /*
 	    <rop>					->	incr|decr|^
     */
@@ -408,104 +711,92 @@ impl <'a> Parser<'a> {
     /*
 	    <elem>				->	ident<idexpr>|lparen<expr>rparen|<literal>
     */
-    fn elem(&mut self) -> Option<Box<Var>> {
-        let mut v = None;
-        if equal_tag(&self.look, ID) {
-            if let TokenType::Id(id) = &self.look {
-                let name = id.get_name();
-                self.move_token();
-                v = self.id_expr(name);
-            }
-        } else if self.match_tag(LPAREN) {
-            v = self.expr();
+    fn elem(&mut self) -> Expr {
+        if let Some(name) = self.ident() {
+            self.id_expr(name)
+        } else if equal_tag(&self.look, LPAREN) {
+            let start = self.look.get_span();
+            self.move_token();
+            let v = self.expr();
             if !self.match_tag(RPAREN) {
                 self.recovery(lval_opr(&self.look), RparenLost, RparenWrong);
             }
+            Expr::new(ExprKind::Paren(Box::new(v)), self.span_from(start))
         } else {
             // 常量
-            v = self.literal();
+            self.literal()
         }
-
-        v
     }
 
     /*
-        <literal>			->	number|string|chara
+        <literal>			->	number|string|chara|real
     */
-    fn literal(&mut self) -> Option<Box<Var>> {
-        let mut ret_v = None;
-
-        if equal_tag(&self.look, NUM) || equal_tag(&self.look, STR) || equal_tag(&self.look, CH) {
-            let v = Box::new(Var::new_const(&self.look));
-            ret_v = Some(v.clone());
-            if equal_tag(&self.look, STR) {
-                self.sym_tab.add_str(v);        // 字符串常量记录
-            } else {
-                self.sym_tab.add_var(v);        // 其他常量也记录到符号表
-            }
-            self.move_token();
-        }
+    fn literal(&mut self) -> Expr {
+        let lit = match &self.look {
+            TokenType::Num(num) => Literal::Num(num.get_val()),
+            TokenType::Char(c) => Literal::Char(c.get_ch()),
+            TokenType::Str(s) => Literal::Str(s.get_str()),
+            TokenType::Real(r) => Literal::Real(r.get_val()),
+            _ => {      // 缺少运算对象，后面是表达式之后能出现的记号时不读入
+                let span = self.look.get_span();
+                self.recovery(expr_follow(&self.look), LiteralLost, LiteralWrong);
+                return Expr::new(ExprKind::Error, span);
+            },
+        };
+        let span = self.look.get_span();
+        self.move_token();
 
-        ret_v
+        Expr::new(ExprKind::Literal(lit), span)
     }
 
     /*
 	    <idexpr>			->	lbrack <expr> rbrack|lparen<realarg>rparen|^
     */
-    fn id_expr(&mut self, name: String) -> Option<Box<Var>> {
-        let v;
+    fn id_expr(&mut self, name: Ident) -> Expr {
+        let span = name.span;
         if self.match_tag(LBRACK) {
             let index = self.expr();
             if !self.match_tag(RBRACK) {
                 self.recovery(lval_opr(&self.look), LbraceLost, LbraceWrong);
             }
-            let array = self.sym_tab.get_var(name);
-            let mut ir = self.ir.clone().unwrap();
-            v = ir.gen_array(array, index);
+            let base = Expr::new(ExprKind::Ident(name.name), span);
+            Expr::new(ExprKind::Index { base: Box::new(base), index: Box::new(index) }, self.span_from(span))
         } else if self.match_tag(LPAREN) {
             let mut args = Vec::new();
             self.real_arg(&mut args);
             if !self.match_tag(RPAREN) {
                 self.recovery(rval_opr(&self.look), RparenLost, RparenWrong);
             }
-            let function = self.sym_tab.get_fun(name, args.clone());
-            let mut ir = self.ir.clone().unwrap();
-            v = ir.gen_call(function, args);
+            Expr::new(ExprKind::Call { callee: name, args }, self.span_from(span))
         } else {
-            v = self.sym_tab.get_var(name);
+            Expr::new(ExprKind::Ident(name.name), span)
         }
-
-        v
     }
 
     /*
 	    <real_arg>			->	<arg><arglist>|^
     */
-    fn real_arg(&mut self, args: &mut Vec<Box<Var>>) {
+    fn real_arg(&mut self, args: &mut Vec<Expr>) {
         if expr_first(&self.look) {
-            if let Some(arg) = self.arg() {
-                args.push(arg);
-                self.args_list(args);
-            }
+            args.push(self.arg());
+            self.args_list(args);
         }
     }
 
     /*
 	    <arglist>			->	comma<arg><arglist>|^
     */
-    fn args_list(&mut self, args: &mut Vec<Box<Var>>) {
+    fn args_list(&mut self, args: &mut Vec<Expr>) {
         if self.match_tag(COMMA) {
-            if let Some(arg) = self.arg() {
-                args.push(arg);
-                self.args_list(args);
-            }
+            args.push(self.arg());
+            self.args_list(args);
         }
     }
 
     /*
 	    <arg> 				-> 	<expr>
     */
-    fn arg(&mut self) -> Option<Box<Var>> {
+    fn arg(&mut self) -> Expr {
         self.expr()
     }
 }
@@ -520,46 +811,58 @@ impl<'a> Parser<'a> {
 										|rsv_continue semicon
 										|rsv_return<altexpr>semicon
     */
-    fn statement(&mut self) {
-        match self.look.get_tag() {
+    fn statement(&mut self) -> Stmt {
+        let start = self.look.get_span();
+        let kind = match self.look.get_tag() {
             KwWhile => self.while_stat(),
             KwFor => self.for_stat(),
             KwDo => self.do_while_stat(),
             KwIf => self.if_stat(),
             KwSwitch => self.switch_stat(),
             KwBreak => {
-                if let Some(mut ir) = self.ir.clone() {
-                    ir.gen_break();
-                }
                 self.move_token();
                 if !self.match_tag(SEMICON) {
                     self.recovery(type_first(&self.look) || statement_first(&self.look) || equal_tag(&self.look, RBRACE), SemiconLost, SemiconWrong);
                 }
+                StmtKind::Break
             },
             KwContinue => {
-                if let Some(mut ir) = self.ir.clone() {
-                    ir.gen_continue();
-                }
                 self.move_token();
                 if !self.match_tag(SEMICON) {
                     self.recovery(type_first(&self.look)|| statement_first(&self.look) || equal_tag(&self.look, RBRACE), SemiconLost, SemiconWrong);
                 }
+                StmtKind::Continue
             },
             KwReturn => {
                 self.move_token();
-                if let Some(mut ir) = self.ir.clone() {
-                    ir.gen_return(self.alt_expr()); // 产生return语句
-                }
+                let ret = self.alt_expr();
                 if !self.match_tag(SEMICON) {
                     self.recovery(type_first(&self.look)|| statement_first(&self.look) || equal_tag(&self.look, RBRACE), SemiconLost, SemiconWrong);
                 }
+                StmtKind::Return(ret)
             },
             _ => {
-                self.alt_expr();
+                let e = self.alt_expr();
                 if !self.match_tag(SEMICON) {
                     self.recovery(type_first(&self.look)|| statement_first(&self.look) || equal_tag(&self.look, RBRACE), SemiconLost, SemiconWrong);
                 }
+                StmtKind::Expr(e)
             }
+        };
+
+        Stmt::new(kind, self.span_from(start))
+    }
+
+    /*
+	    <block>				->	<block>|<statement>
+    */
+    fn body(&mut self) -> Stmt {
+        if equal_tag(&self.look, LBRACE) {
+            let block = self.block();
+            let span = block.span;
+            Stmt::new(StmtKind::Block(block), span)
+        } else {
+            self.statement()
         }
     }
 
@@ -567,85 +870,59 @@ impl<'a> Parser<'a> {
 	    <whilestat>		->	rsv_while lparen<altexpr>rparen<block>
 	    <block>				->	<block>|<statement>
     */
-    fn while_stat(&mut self) {
-        self.sym_tab.enter();
-
-        let mut ir = self.ir.clone().unwrap();
-        let (_while, _exit) = ir.gen_while_head();
-
+    fn while_stat(&mut self) -> StmtKind {
         self.match_tag(KwWhile);
         if !self.match_tag(LPAREN) {
             self.recovery(expr_first(&self.look) || equal_tag(&self.look, RPAREN), TypeLost, TypeWrong);
         }
 
         let cond = self.alt_expr();
-        ir.gen_while_cond(cond, Some(_exit.clone()));
 
         if !self.match_tag(RPAREN) {
             self.recovery(equal_tag(&self.look, LBRACE), RparenLost, RparenWrong);
         }
 
-        // block
-        if equal_tag(&self.look, LBRACE) {
-            self.block();
-        } else {
-            self.statement();
-        }
-
-        ir.gen_while_tail(_while.clone(), _exit.clone());
-        self.sym_tab.leave();
+        let body = Box::new(self.body());
+        StmtKind::While { cond, body }
     }
 
     /*
 	    <forstat> 		-> 	rsv_for lparen <forinit> semicon <altexpr> semicon <altexpr> rparen <block>
 	    <block>				->	<block>|<statement>
     */
-    fn for_stat(&mut self) {
-        self.sym_tab.enter();
-
-        let mut ir = self.ir.clone().unwrap();
-
-        if self.match_tag(KwFor) {
-            if !self.match_tag(LPAREN) {
-                self.recovery(type_first(&self.look) || expr_first(&self.look), LparenLost, LparenWrong);
-            }
-
-            self.for_init();
-            let (_for, _exit) = ir.gen_for_head();
-            if !self.match_tag(SEMICON) {
-                self.recovery(expr_first(&self.look), SemiconLost, SemiconWrong);
-            }
-
-            let cond = self.alt_expr();
-            let (_block, _step) = ir.gen_for_cond_begin(cond, _exit.clone());
+    fn for_stat(&mut self) -> StmtKind {
+        self.match_tag(KwFor);
+        if !self.match_tag(LPAREN) {
+            self.recovery(type_first(&self.look) || expr_first(&self.look), LparenLost, LparenWrong);
+        }
 
-            if !self.match_tag(RPAREN) {
-                self.recovery(equal_tag(&self.look, LBRACE), RparenLost, RparenWrong);
-            }
-            ir.gen_for_cond_end(_for, _block);
-            if equal_tag(&self.look, LBRACE) {
-                self.block();
-            } else {
-                self.statement();
-            }
-            ir.gen_for_tail(_step, _exit);
+        let init = self.for_init();
+        let cond = self.alt_expr();
+        if !self.match_tag(SEMICON) {
+            self.recovery(expr_first(&self.look) || equal_tag(&self.look, RPAREN), SemiconLost, SemiconWrong);
         }
 
+        let step = self.alt_expr();    // 步进表达式
+        if !self.match_tag(RPAREN) {
+            self.recovery(equal_tag(&self.look, LBRACE), RparenLost, RparenWrong);
+        }
+        let body = Box::new(self.body());
 
-        self.sym_tab.leave();
+        StmtKind::For { init: Box::new(init), cond, step, body }
     }
 
     /*
 	    <forinit> 		->  <localdef> | <altexpr>
     */
-    fn for_init(&mut self) {
-        if type_first(&self.look) {
-            self.local_def();
+    fn for_init(&mut self) -> ForInit {
+        if self.type_start() {
+            ForInit::Decl(self.local_def())
         } else {
-            self.alt_expr();
+            let e = self.alt_expr();
             if !self.match_tag(SEMICON) {
                 self.recovery(expr_first(&self.look), SemiconLost, SemiconWrong);
             }
+            ForInit::Expr(e)
         }
     }
 
@@ -653,17 +930,9 @@ impl<'a> Parser<'a> {
 	    <dowhilestat> -> 	rsv_do <block> rsv_while lparen<altexpr>rparen semicon
 	    <block>				->	<block>|<statement>
     */
-    fn do_while_stat(&mut self) {
-        // 进入do作用域
-        self.sym_tab.enter();
-        let mut ir = self.ir.clone().unwrap();
-        let (_do, _exit) = ir.gen_do_while_head();
+    fn do_while_stat(&mut self) -> StmtKind {
         self.match_tag(KwDo);
-        if equal_tag(&self.look, LBRACE) {
-            self.block();
-        } else {
-            self.statement();
-        }
+        let body = Box::new(self.body());
 
         if !self.match_tag(KwWhile) {
             self.recovery(expr_first(&self.look) || equal_tag(&self.look, RPAREN), LparenLost, LparenWrong);
@@ -672,10 +941,7 @@ impl<'a> Parser<'a> {
             self.recovery(expr_first(&self.look) || equal_tag(&self.look, RPAREN), LparenLost, LparenWrong);
         }
 
-        // 离开do作用域
-        self.sym_tab.leave();
-
-        let cond = self.alt_expr().unwrap();
+        let cond = self.alt_expr();
 
         if !self.match_tag(RPAREN) {
             self.recovery(equal_tag(&self.look, SEMICON), RparenLost, RparenWrong);
@@ -684,223 +950,222 @@ impl<'a> Parser<'a> {
             self.recovery(type_first(&self.look) || statement_first(&self.look) || equal_tag(&self.look, RBRACE), SemiconLost, SemiconWrong);
         }
 
-        ir.gen_do_while_tail(Some(cond), _do, _exit);
+        StmtKind::DoWhile { body, cond }
     }
 
     /*
 	    <ifstat>			->	rsv_if lparen<expr>rparen<block><elsestat>
     */
-    fn if_stat(&mut self) {
-        self.sym_tab.enter();
-
-        let mut _else = None;
-        if self.match_tag(KwIf) {
-            if !self.match_tag(LPAREN) {
-                self.recovery(expr_first(&self.look), LparenLost, LparenWrong);
-            }
-
-            let cond = self.expr();
-            if let Some(mut ir) = self.ir.clone() {
-                _else = Some(ir.gen_if_head(cond));
-            }
-
-            if !self.match_tag(RPAREN) {
-                self.recovery(equal_tag(&self.look, LBRACE), RparenLost, RparenWrong);
-            }
-        }
-
-        if equal_tag(&self.look, LBRACE) {
-            self.block();
-        } else {
-            self.statement();
+    fn if_stat(&mut self) -> StmtKind {
+        self.match_tag(KwIf);
+        if !self.match_tag(LPAREN) {
+            self.recovery(expr_first(&self.look), LparenLost, LparenWrong);
         }
 
-        self.sym_tab.leave();
+        let cond = self.expr();
 
-        if equal_tag(&self.look, KwElse) {
-            let mut _exit = None;
-            if let Some(mut ir) = self.ir.clone() {
-                if let Some(_else) = _else {
-                    _exit = Some(ir.gen_else_head(_else));      // 有else
-                }
-            }
+        if !self.match_tag(RPAREN) {
+            self.recovery(equal_tag(&self.look, LBRACE), RparenLost, RparenWrong);
+        }
 
-            self.else_stat();
+        let then = Box::new(self.body());
+        let els = self.else_stat();
 
-            if let Some(mut ir) = self.ir.clone() {
-                if let Some(_exit) = _exit {
-                    ir.gen_else_tail(_exit);      // 有else
-                }
-            }
-        } else {
-            if let Some(mut ir) = self.ir.clone() {
-                if let Some(_else) = _else {
-                    ir.gen_if_tail(_else);      // 无else
-                }
-            }
-        }
+        StmtKind::If { cond, then, els }
     }
 
     /*
 	    <elsestat>		-> 	rsv_else<block>|^
     */
-    fn else_stat(&mut self) {
+    fn else_stat(&mut self) -> Option<Box<Stmt>> {
         if self.match_tag(KwElse) {
-            self.sym_tab.enter();
-
-            if equal_tag(&self.look, LBRACE) {
-                self.block();
-            } else {
-                self.statement();
-            }
-
-            self.sym_tab.leave();
+            return Some(Box::new(self.body()));
         }
+
+        None
     }
 
     /*
 	    <switchstat>	-> 	rsv_switch lparen <expr> rparen lbrac <casestat> rbrac
     */
-    fn switch_stat(&mut self) {
-        self.sym_tab.enter();
-
-        let mut _exit = None;
-
-        if let Some(mut ir) = self.ir.clone() {
-            _exit = Some(ir.gen_switch_head());
+    fn switch_stat(&mut self) -> StmtKind {
+        self.match_tag(KwSwitch);
+        if !self.match_tag(LPAREN) {
+            self.recovery(expr_first(&self.look), RparenLost, RparenWrong);
         }
 
-        if self.match_tag(KwSwitch) {
-            if !self.match_tag(LPAREN) {
-                self.recovery(expr_first(&self.look), RparenLost, RparenWrong);
-            }
-
-            let mut cond = self.expr().unwrap();
-            if let Some(mut ir) = self.ir.clone() {
-                cond = ir.gen_assign(cond);
-            }
-
-            if !self.match_tag(RPAREN) {
-                self.recovery(equal_tag(&self.look, LBRACE), RparenLost, RparenWrong);
-            }
-
-            if !self.match_tag(LBRACE) {
-                self.recovery(equal_tag(&self.look, KwCase) || equal_tag(&self.look, KwDefault), LbraceLost, LbraceWrong);
-            }
+        let cond = self.expr();
 
-            self.case_stat(Some(cond));
+        if !self.match_tag(RPAREN) {
+            self.recovery(equal_tag(&self.look, LBRACE), RparenLost, RparenWrong);
+        }
 
-            if !self.match_tag(RBRACE) {
-                self.recovery(type_first(&self.look) || statement_first(&self.look), RbraceLost, RbraceWrong);
-            }
+        if !self.match_tag(LBRACE) {
+            self.recovery(equal_tag(&self.look, KwCase) || equal_tag(&self.look, KwDefault), LbraceLost, LbraceWrong);
         }
 
-        if let Some(mut ir) = self.ir.clone() {
-            if let Some(_exit) = _exit {
-                ir.gen_switch_tail(_exit);
-            }
+        let mut cases = vec![];
+        self.case_stat(&mut cases);
+
+        if !self.match_tag(RBRACE) {
+            self.recovery(type_first(&self.look) || statement_first(&self.look), RbraceLost, RbraceWrong);
         }
 
-        self.sym_tab.leave();
+        StmtKind::Switch { cond, cases }
     }
 
     /*
 	    <casestat> 		-> 	rsv_case <caselabel> colon <subprogram><casestat>
 										| rsv_default colon <subprogram>
     */
-    fn case_stat(&mut self, cond: Option<Box<Var>>) {
+    fn case_stat(&mut self, cases: &mut Vec<Case>) {
+        let start = self.look.get_span();
         if self.match_tag(KwCase) {
-            let mut _case_exit = None;
             let lb = self.case_label();
-            if let Some(mut ir) = self.ir.clone() {
-                _case_exit = Some(ir.gen_case_head(cond.clone(), lb));
-            }
             if !self.match_tag(COLON) {
                 self.recovery(type_first(&self.look) || statement_first(&self.look), ColonLost, ColonWrong);
             }
-            self.sym_tab.enter();
-            self.subprogram();
-            self.sym_tab.leave();
-            if let Some(mut ir) = self.ir.clone() {
-                if let Some(_case_exit) = _case_exit {
-                    ir.gen_case_tail(_case_exit);
-                }
-            }
-            self.case_stat(cond);
+            let mut items = vec![];
+            self.subprogram(&mut items);
+            cases.push(Case { label: Some(lb), items, span: self.span_from(start) });
+            self.case_stat(cases);
         } else if self.match_tag(KwDefault) {
             if !self.match_tag(COLON) {
                 self.recovery(type_first(&self.look) || statement_first(&self.look), ColonLost, ColonWrong);
             }
-            self.sym_tab.enter();
-            self.subprogram();
-            self.sym_tab.leave();
+            let mut items = vec![];
+            self.subprogram(&mut items);
+            cases.push(Case { label: None, items, span: self.span_from(start) });
         }
     }
 
     /*
-	    <caselabel>		->	<literal>
+	    <caselabel>		->	<expr>
     */
-    fn case_label(&mut self) -> Option<Box<Var>> {
-        self.literal()
+    fn case_label(&mut self) -> Expr {
+        self.expr()
     }
 }
 
 // 声明与定义
 impl<'a> Parser<'a> {
     /*
-	    <init>				->	assign <expr>|^
+	    <init>				->	assign <initializer>|^
     */
-    fn init(&mut self, ext: bool, t: Tag, ptr: bool, name: String) -> Box<Var>{
-        let mut init_val: Option<Box<Var>> = None;
+    fn init(&mut self) -> Option<Initializer> {
         if self.match_tag(ASSIGN) {
-            init_val = self.expr();
+            Some(self.initializer())
+        } else {
+            None
         }
-
-        // 新的变量活指针
-        Box::new(Var::new_pointer(self.sym_tab.get_scope_path(), ext, t, ptr, name, init_val))
     }
 
-    fn varrdef(&mut self, ext: bool, t: Tag, ptr: bool, name: String) -> Box<Var>{
-        if self.match_tag(LBRACK) {
-            let mut len = 0;
-            if self.match_tag(NUM) {
-                if let TokenType::Num(num) = &self.look {
-                    len = num.get_val();
-                }
+    /*
+        <initializer>		->	lbrace <initlist> rbrace|<expr>
+        <initlist>			->	<initializer> comma <initlist>|<initializer>|^
+    */
+    fn initializer(&mut self) -> Initializer {
+        let start = self.look.get_span();
+        if !self.match_tag(LBRACE) {
+            return Initializer::Expr(self.expr());
+        }
 
-                self.move_token();
-            } else {
-                self.recovery(equal_tag(&self.look, RBRACK), NumLost, NumWrong);
+        let mut items = vec![];
+        while !equal_tag(&self.look, RBRACE) {
+            items.push(self.initializer());
+            if !self.match_tag(COMMA) {     // 最后一项后面可以有逗号
+                break;
             }
+        }
+        if !self.match_tag(RBRACE) {
+            self.recovery(equal_tag(&self.look, COMMA) || equal_tag(&self.look, SEMICON), RbraceLost, RbraceWrong);
+        }
 
+        Initializer::List { items, span: self.span_from(start) }
+    }
 
-            if !self.match_tag(RBRACK) {
-                self.recovery(equal_tag(&self.look, COMMA) || equal_tag(&self.look, SEMICON), RbrackLost, RbraceWrong);
-            }
+    /*
+	    <declarator>		->	mul <declarator>|<directdecl>
+	    <directdecl>		->	ident <decltail>|lparen <declarator> rparen <decltail>
+	    <decltail>			->	lbrack <expr> rbrack <decltail>|lbrack rbrack <decltail>|lparen <para> rparen <decltail>|^
+    */
+    fn declarator(&mut self) -> (Ident, Declarator) {
+        let start = self.look.get_span();
+        if self.match_tag(MUL) {    // 指针
+            let (name, inner) = self.declarator();
+            return (name, Declarator::new(DeclaratorKind::Pointer(Box::new(inner)), self.span_from(start)));
+        }
 
-            // 新的数组
-            Box::new(Var::new_array(self.sym_tab.get_scope_path(), ext, t, name, len))
+        let (name, mut decl) = if self.match_tag(LPAREN) {     // 括号改变结合的顺序，如(*p)[4]
+            let (name, mut decl) = self.declarator();
+            if !self.match_tag(RPAREN) {
+                self.recovery(equal_tag(&self.look, LBRACK) || equal_tag(&self.look, LPAREN) || decl_follow(&self.look), RparenLost, RparenWrong);
+            }
+            decl.span = self.span_from(start);      // 区间包括括号
+            (name, decl)
         } else {
-            self.init(ext, t, ptr, name)
+            let name = match self.ident() {
+                Some(name) => name,
+                None => {
+                    let name = self.missing_ident();
+                    self.recovery(equal_tag(&self.look, LBRACK) || equal_tag(&self.look, LPAREN) || decl_follow(&self.look), IdLost, IdWrong);
+                    name
+                }
+            };
+            let span = name.span;
+            (name, Declarator::new(DeclaratorKind::Name, span))
+        };
+
+        loop {
+            if self.match_tag(LBRACK) {         // 数组
+                let mut len = None;
+                if expr_first(&self.look) {
+                    len = Some(self.expr());
+                } else if !equal_tag(&self.look, RBRACK) {      // 省略的长度由初始值推断
+                    self.recovery(decl_follow(&self.look), NumLost, NumWrong);
+                }
+                if !self.match_tag(RBRACK) {
+                    self.recovery(equal_tag(&self.look, LBRACK) || decl_follow(&self.look), RbrackLost, RbraceWrong);
+                }
+                let span = decl.span.to(self.prev);
+                decl = Declarator::new(DeclaratorKind::Array { inner: Box::new(decl), len }, span);
+            } else if self.match_tag(LPAREN) {  // 函数
+                let mut params = vec![];
+                self.names.push(HashMap::new());    // 参数的作用域
+                self.para(&mut params);
+                self.names.pop();
+                if !self.match_tag(RPAREN) {
+                    self.recovery(equal_tag(&self.look, LBRACE) || equal_tag(&self.look, LBRACK) || decl_follow(&self.look), RparenLost, RparenWrong);
+                }
+                let span = decl.span.to(self.prev);
+                decl = Declarator::new(DeclaratorKind::Function { inner: Box::new(decl), params }, span);
+            } else {
+                return (name, decl);
+            }
         }
     }
 
+    // 声明符之后的初始值
+    fn var_def(&mut self, name: Ident, decl: Declarator) -> VarDecl {
+        let init = self.init();
+        let span = self.span_from(decl.span);
+        VarDecl { name, decl, init, span }
+    }
+
     /*
 	    <deflist>			->	comma <defdata> <deflist>| semicon
     */
-    fn def_list(&mut self, ext: bool, t: Tag) {
+    fn def_list(&mut self, vars: &mut Vec<VarDecl>) {
         if self.match_tag(COMMA) {  // 下一个声明
-            let v = self.defdata(ext, t);
-            self.sym_tab.add_var(v);
-            self.def_list(ext, t);
+            vars.push(self.defdata());
+            self.def_list(vars);
         } else if !self.match_tag(SEMICON) {
             // 出错了
             // 不是最后一个声明
             if equal_tag(&self.look, ID) || equal_tag(&self.look, MUL) {
                 self.recovery(true, CommaLost, ColonWrong);
-                let v = self.defdata(ext, t);
-                self.sym_tab.add_var(v);
-                self.def_list(ext, t);
+                vars.push(self.defdata());
+                self.def_list(vars);
             } else {
                 self.recovery(type_first(&self.look) || statement_first(&self.look) || equal_tag(&self.look, KwExtern) || equal_tag(&self.look, RBRACK),
                              SemiconLost, SemiconWrong);
@@ -909,56 +1174,11 @@ impl<'a> Parser<'a> {
     }
 
     /*
-	    <defdata>			->	ident <varrdef>|mul ident <init>
+	    <defdata>			->	<declarator> <init>
     */
-    fn defdata(&mut self, ext: bool, t: Tag) -> Box<Var> {
-        let mut name = String::new();
-
-        if equal_tag(&self.look, ID) {
-            if let TokenType::Id(id) = &self.look {
-                name = id.get_name();
-                self.move_token();
-            }
-            self.varrdef(ext, t, false, name)
-        } else if self.match_tag(MUL) {
-            if equal_tag(&self.look, ID) {
-                if let TokenType::Id(id) = &self.look {
-                    name = id.get_name();
-                    self.move_token();
-                }
-            } else {
-                self.recovery(equal_tag(&self.look, SEMICON) || equal_tag(&self.look, COMMA) || equal_tag(&self.look, ASSIGN), IdLost, IdWrong);
-            }
-            self.init(ext, t, true, name)
-        } else {
-            self.recovery(equal_tag(&self.look, SEMICON) || equal_tag(&self.look, COMMA) || equal_tag(&self.look, ASSIGN) || equal_tag(&self.look, LBRACK), IdLost, IdWrong);
-
-            self.varrdef(ext, t, false, name)
-        }
-    }
-
-    /*
-	    <idtail>			->	<varrdef><deflist>|lparen <para> rparen <funtail>
-    */
-    fn id_tail(&mut self, ext: bool, t: Tag, ptr: bool, name: String) {
-
-        if self.match_tag(LPAREN) {     // 函数
-            // 进入作用域
-            self.sym_tab.enter();
-            let mut para_list: Vec<Box<Var>> = Vec::new();
-            self.para(&mut para_list);
-            if !self.match_tag(RPAREN) {
-                self.recovery(equal_tag(&self.look, LBRACK) || equal_tag(&self.look, SEMICON), RparenLost, RparenWrong);
-            }
-            let fun = Box::new(Fun::new(ext, t, name, para_list));
-            self.fun_tail(fun);
-            // 离开作用域
-            self.sym_tab.leave();
-        } else {
-            let v = self.varrdef(ext, t, ptr, name);
-            self.sym_tab.add_var(v);
-            self.def_list(ext, t);
-        }
+    fn defdata(&mut self) -> VarDecl {
+        let (name, decl) = self.declarator();
+        self.var_def(name, decl)
     }
 }
 
@@ -967,92 +1187,51 @@ impl<'a> Parser<'a> {
     /*
         <funtail>			->	<block>|semicon
     */
-    fn fun_tail(&mut self, f: Box<Fun>) {
+    fn fun_tail(&mut self) -> Option<Block> {
         if self.match_tag(SEMICON) {        // 函数声明
-            self.sym_tab.dec_fun(f);
+            None
         } else {    // 函数定义
-            self.sym_tab.def_fun(f);        // 函数定义
-            self.block();
-            self.sym_tab.end_def_fun();     // 结束函数定义
+            Some(self.block())
         }
     }
 
     /*
 	    <para>				->	<type><paradata><paralist>|^
     */
-    fn para(&mut self, para_list: &mut Vec<Box<Var>>) {
+    fn para(&mut self, params: &mut Vec<Param>) {
         if !equal_tag(&self.look, RPAREN) {
             let t  = self.var_type();
-            let v = self.para_data(t);
-
-            self.sym_tab.add_var(v.clone());
-            para_list.push(v.clone());
-            self.para_list(para_list);
+            params.push(self.para_data(t));
+            self.para_list(params);
         }
     }
 
     /*
 	    <paralist>		->	comma<type><paradata><paralist>|^
     */
-    fn para_list(&mut self, para_list: &mut Vec<Box<Var>>) {
+    fn para_list(&mut self, params: &mut Vec<Param>) {
         if self.match_tag(COMMA) {  // 下一个参数
             let t = self.var_type();
-            let v = self.para_data(t);
-            self.sym_tab.add_var(v.clone());
-            para_list.push(v.clone());
-            self.para_list(para_list);
+            params.push(self.para_data(t));
+            self.para_list(params);
         }
     }
 
     /*
-	    <paradata>		->	mul ident|ident <paradatatail>
+	    <paradata>		->	<declarator>
     */
-    fn para_data(&mut self, t: Tag) -> Box<Var> {
-        let mut name = String::new();
-
-        return if self.match_tag(MUL) {
-            if equal_tag(&self.look, ID) {
-                if let TokenType::Id(id) = &self.look {
-                    name = id.get_name();
-                    self.move_token();
-                }
-            } else {
-                self.recovery(equal_tag(&self.look, COMMA) || equal_tag(&self.look, RPAREN), IdLost, IdWrong);
-            }
-
-            Box::new(Var::new_pointer(self.sym_tab.get_scope_path(), false, t, true, name, None))
-        } else if equal_tag(&self.look, ID) {
-            if let TokenType::Id(id) = &self.look {
-                name = id.get_name();
-                self.move_token();
-            }
-            self.para_data_tail(t, name)
-        } else {
-            self.recovery(equal_tag(&self.look, COMMA) || equal_tag(&self.look, RPAREN) || equal_tag(&self.look, LBRACK), IdLost, IdWrong);
-            Box::new(Var::new_pointer(self.sym_tab.get_scope_path(), false, t, false, name, None))
-        }
+    fn para_data(&mut self, t: TypeSpec) -> Param {
+        let (name, decl) = self.declarator();
+        self.declare(&name, false);
+        let span = self.span_from(t.span);
+        Param { ty: t, name, decl, span }
     }
+}
 
-    /*
-	    <paradatatail>->	lbrack rbrack|lbrack num rbrack|^
-    */
-    fn para_data_tail(&mut self, t: Tag, name: String) -> Box<Var> {
-        if self.match_tag(LBRACK) {
-            let mut len = 1;
-            if equal_tag(&self.look, NUM) {
-                if let TokenType::Num(num) = &self.look {
-                    len = num.get_val();
-                }
-                self.move_token();
-            }   // 可以没有指定长度
-            if !self.match_tag(RBRACK) {
-                self.recovery(equal_tag(&self.look, COMMA) || equal_tag(&self.look, RPAREN), RbrackLost, RbraceWrong);
-            }
-            return Box::new(Var::new_array(self.sym_tab.get_scope_path(), false, t, name, len));
-        }
-
-        Box::new(Var::new_pointer(self.sym_tab.get_scope_path(), false, t, false, name, None))
-    }
+// 双目运算表达式
+fn binary(lhs: Expr, op: Tag, rhs: Expr) -> Expr {
+    let span = lhs.span.to(rhs.span);
+    Expr::new(ExprKind::Binary { op, lhs: Box::new(lhs), rhs: Box::new(rhs) }, span)
 }
 
 // 语句
@@ -1064,27 +1243,165 @@ fn statement_first(look: &TokenType) -> bool {
 
 // 类型
 fn type_first(look: &TokenType) -> bool {
-    equal_tag(look, KwInt) || equal_tag(look, KwChar) || equal_tag(look, KwVoid)
+    int_first(look) || equal_tag(look, KwVoid) || equal_tag(look, KwFloat) || equal_tag(look, KwDouble) || equal_tag(look, KwStruct) || equal_tag(look, KwUnion) || equal_tag(look, KwEnum)
+}
+
+// 整数类型
+fn int_first(look: &TokenType) -> bool {
+    equal_tag(look, KwInt) || equal_tag(look, KwChar) || equal_tag(look, KwShort) || equal_tag(look, KwLong) ||
+    equal_tag(look, KwSigned) || equal_tag(look, KwUnsigned)
 }
 
 // 表达式
 fn expr_first(look: &TokenType) -> bool {
 
-    equal_tag(look, LPAREN) || equal_tag(look, NUM) || equal_tag(look, CH) || equal_tag(look, STR) || equal_tag(look, ID) || equal_tag(look, NOT)
-        || equal_tag(look, SUB) || equal_tag(look, LEA) || equal_tag(look, MUL) || equal_tag(look, INC) || equal_tag(look, DEC)
+    equal_tag(look, LPAREN) || equal_tag(look, NUM) || equal_tag(look, REAL) || equal_tag(look, CH) || equal_tag(look, STR) || equal_tag(look, ID) || equal_tag(look, NOT)
+        || equal_tag(look, BNOT) || equal_tag(look, SUB) || equal_tag(look, LEA) || equal_tag(look, MUL) || equal_tag(look, INC) || equal_tag(look, DEC)
+}
+
+// 赋值和复合赋值
+fn ass_opr(look: &TokenType) -> bool {
+    equal_tag(look, ASSIGN) || equal_tag(look, ADDAS) || equal_tag(look, SUBAS) || equal_tag(look, MULAS) || equal_tag(look, DIVAS) || equal_tag(look, MODAS)
+        || equal_tag(look, BANDAS) || equal_tag(look, BORAS) || equal_tag(look, XORAS) || equal_tag(look, SHLAS) || equal_tag(look, SHRAS)
 }
 
 // 左值运算
 fn lval_opr(look: &TokenType) -> bool {
-    equal_tag(look, ASSIGN) || equal_tag(look, OR) || equal_tag(look, AND) || equal_tag(look, GT) || equal_tag(look, GE) || equal_tag(look, LT)
+    ass_opr(look) || equal_tag(look, OR) || equal_tag(look, AND) || equal_tag(look, GT) || equal_tag(look, GE) || equal_tag(look, LT)
         || equal_tag(look, LE) || equal_tag(look, EQU) || equal_tag(look, NEQU) || equal_tag(look, ADD) || equal_tag(look, SUB) || equal_tag(look, MUL) || equal_tag(look, DIV)
+        || equal_tag(look, MOD) || equal_tag(look, LEA) || equal_tag(look, BOR) || equal_tag(look, XOR) || equal_tag(look, SHL) || equal_tag(look, SHR)
+}
+
+// 表达式之后能出现的记号
+fn expr_follow(look: &TokenType) -> bool {
+    rval_opr(look) || ass_opr(look) || equal_tag(look, SEMICON) || equal_tag(look, COMMA) || equal_tag(look, COLON) || equal_tag(look, RPAREN)
+        || equal_tag(look, RBRACK) || equal_tag(look, RBRACE) || equal_tag(look, END)
+}
+
+// 声明符之后能出现的记号
+fn decl_follow(look: &TokenType) -> bool {
+    equal_tag(look, SEMICON) || equal_tag(look, COMMA) || equal_tag(look, ASSIGN) || equal_tag(look, RPAREN)
 }
 
 fn rval_opr(look: &TokenType) -> bool {
     equal_tag(look, OR) || equal_tag(look, AND) || equal_tag(look, GT) || equal_tag(look, GE) || equal_tag(look, LT)
         || equal_tag(look, LE) || equal_tag(look, EQU) || equal_tag(look, NEQU) || equal_tag(look, ADD) || equal_tag(look, SUB) || equal_tag(look, MUL) || equal_tag(look, DIV)
+        || equal_tag(look, MOD) || equal_tag(look, LEA) || equal_tag(look, BOR) || equal_tag(look, XOR) || equal_tag(look, SHL) || equal_tag(look, SHR)
 }
 
 fn equal_tag(look: &TokenType, tag: Tag) -> bool {
     look.get_tag() == tag
 }
+
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{DeclaratorKind, ExprKind, Item, StmtKind, BlockItem, TranslationUnit};
+    use crate::common::Tag::{ADD, BOR, EQU, LEA, MUL, SHL, SHLAS, XOR};
+    use crate::parser::parse;
+
+    // 语法分析，要求没有错误
+    fn analyze(source: &str) -> TranslationUnit {
+        let (unit, diags) = parse(source);
+        assert!(diags.is_empty());
+        unit
+    }
+
+    #[test]
+    fn test_build_ast() {
+        let unit = analyze("int main() {\n    a = 1 + 2 * b;\n}\n");
+
+        let fun = match &unit.items[0] {
+            Item::Function(fun) => fun,
+            _ => panic!("expect function"),
+        };
+        assert_eq!(fun.name.name, "main");
+        let body = fun.body.as_ref().unwrap();
+        let stmt = match &body.items[0] {
+            BlockItem::Stmt(stmt) => stmt,
+            _ => panic!("expect statement"),
+        };
+        assert_eq!((stmt.span.line(), stmt.span.col(), stmt.span.end_col()), (2, 5, 18));
+
+        // 乘法优先于加法，赋值的右边是 1 + (2 * b)
+        let rhs = match &stmt.kind {
+            StmtKind::Expr(Some(e)) => match &e.kind {
+                ExprKind::Assign { rhs, .. } => rhs,
+                _ => panic!("expect assignment"),
+            },
+            _ => panic!("expect expression"),
+        };
+        match &rhs.kind {
+            ExprKind::Binary { op, rhs, .. } => {
+                assert_eq!(*op, ADD);
+                assert!(matches!(rhs.kind, ExprKind::Binary { op: MUL, .. }));
+            },
+            _ => panic!("expect binary"),
+        }
+    }
+
+    #[test]
+    fn test_nested_declarator() {
+        // 后缀优先于前缀，*a[4]为Pointer(Array(Name))，括号改变结合顺序，(*p)[4]为Array(Pointer(Name))
+        let unit = analyze("int *a[4], (*p)[4];");
+        let vars = match &unit.items[0] {
+            Item::Decl(decl) => &decl.vars,
+            _ => panic!("expect declaration"),
+        };
+        match &vars[0].decl.kind {
+            DeclaratorKind::Pointer(inner) => assert!(matches!(&inner.kind, DeclaratorKind::Array { inner, .. } if matches!(inner.kind, DeclaratorKind::Name))),
+            _ => panic!("expect pointer"),
+        }
+        match &vars[1].decl.kind {
+            DeclaratorKind::Array { inner, .. } => assert!(matches!(&inner.kind, DeclaratorKind::Pointer(inner) if matches!(inner.kind, DeclaratorKind::Name))),
+            _ => panic!("expect array"),
+        }
+        assert_eq!((vars[1].name.name.as_str(), vars[1].decl.span.col(), vars[1].decl.span.end_col()), ("p", 12, 18));
+    }
+
+    #[test]
+    fn test_bit_precedence() {
+        let unit = analyze("int main() { a = b | c ^ d & e == f << 1 + g; }");
+
+        let fun = match &unit.items[0] {
+            Item::Function(fun) => fun,
+            _ => panic!("expect function"),
+        };
+        let mut e = match &fun.body.as_ref().unwrap().items[0] {
+            BlockItem::Stmt(stmt) => match &stmt.kind {
+                StmtKind::Expr(Some(e)) => match &e.kind {
+                    ExprKind::Assign { rhs, .. } => rhs,
+                    _ => panic!("expect assignment"),
+                },
+                _ => panic!("expect expression"),
+            },
+            _ => panic!("expect statement"),
+        };
+
+        // 按C的优先级，从低到高依次是 | ^ & == << +
+        let mut ops = vec![];
+        while let ExprKind::Binary { op, rhs, .. } = &e.kind {
+            ops.push(*op);
+            e = rhs;
+        }
+        assert_eq!(ops, vec![BOR, XOR, LEA, EQU, SHL, ADD]);
+    }
+
+    #[test]
+    fn test_compound_assoc() {
+        let unit = analyze("int main() { x <<= x += 1; }");
+
+        // 复合赋值右结合
+        let fun = match &unit.items[0] {
+            Item::Function(fun) => fun,
+            _ => panic!("expect function"),
+        };
+        match &fun.body.as_ref().unwrap().items[0] {
+            BlockItem::Stmt(stmt) => match &stmt.kind {
+                StmtKind::Expr(Some(e)) => assert!(matches!(&e.kind, ExprKind::Assign { op: SHLAS, rhs, .. } if matches!(rhs.kind, ExprKind::Assign { .. }))),
+                _ => panic!("expect expression"),
+            },
+            _ => panic!("expect statement"),
+        }
+    }
+}
@@ -1,2 +1,3 @@
-
-pub const STACK_BASE: i32 = 12;
\ No newline at end of file
+pub const STACK_BASE: i32 = 12;         // 局部变量之前保留的栈空间，[ebp-4]保存ebx
+pub const PARA_BASE: isize = 8;         // 第一个参数相对ebp的偏移，之前是旧ebp和返回地址
+pub const PARA_SIZE: isize = 4;         // 每个参数占用的栈空间
@@ -0,0 +1,1135 @@
+/*
+	预处理：处理#include、#define、#undef、条件编译和#line指令，输出展开后的源代码
+	输出中的每一段都记录了在原文件中的位置，编译阶段的诊断信息据此映射回原文件
+*/
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+use crate::catalog::Catalog;
+use crate::common::PreError::{self, CondAfterElse, CondInvalid, CondNoEnd, CondNoIf, DirectiveUnknown, ErrorDirective, IncludeInvalid,
+                              IncludeNested, IncludeNotFound, LineInvalid, MacroArgsCount, MacroArgsNoEnd, MacroNameLost, MacroParamInvalid};
+use crate::diagnostic::{Diagnostic, DiagnosticSink, ErrorCode};
+use crate::span::Span;
+
+const MAX_INCLUDE_DEPTH: usize = 200;       // #include最大嵌套层数
+
+// 三个字符和两个字符的标点，其余标点都是单个字符
+const PUNCT3: [&str; 3] = ["...", "<<=", ">>="];
+const PUNCT2: [&str; 20] = ["##", "||", "&&", "==", "!=", "<=", ">=", "<<", ">>", "++", "--", "->",
+                            "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^="];
+
+// 参与预处理的源文件
+struct SourceFile {
+    name: String,                   // 文件名
+    path: Option<PathBuf>,          // 文件路径，#line指定的文件名和标准输入没有路径
+    source: String,
+    lines: Vec<usize>,              // 各行起始的字节偏移
+}
+
+impl SourceFile {
+    fn new(name: String, path: Option<PathBuf>, source: String) -> Self {
+        let lines = std::iter::once(0).chain(source.match_indices('\n').map(|(i, _)| i + 1)).collect();
+        SourceFile {
+            name,
+            path,
+            source,
+            lines,
+        }
+    }
+
+    // 字节偏移处字符的行列号，与扫描器相同，换行符不计列
+    fn line_col(&self, offset: usize) -> (usize, usize) {
+        let line = self.lines.partition_point(|&start| start <= offset);
+        let rest = &self.source[offset..];
+        let col = self.source[self.lines[line - 1]..offset].chars().count();
+        (line, if rest.is_empty() || rest.starts_with('\n') { col } else { col + 1 })
+    }
+
+    // 偏移之前最后一个字符的行列号
+    fn line_col_before(&self, offset: usize) -> (usize, usize) {
+        match self.source[..offset].char_indices().next_back() {
+            Some((i, _)) => self.line_col(i),
+            None => (1, 0),
+        }
+    }
+
+    // 区间对应的Span，空区间与词法分析的文件结尾相同，取前一个字符之后的位置
+    fn span(&self, start: usize, end: usize, shift: isize) -> Span {
+        let (line, col) = if end > start { self.line_col(start) } else { self.line_col_before(start) };
+        let (end_line, end_col) = self.line_col_before(end.max(start));
+        let shifted = |line: usize| (line as isize + shift).max(1) as usize;
+
+        Span::new(start, end, shifted(line), col, shifted(end_line), end_col)
+    }
+}
+
+// 记号在原文件中的位置
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct Origin {
+    file: usize,                    // 所在文件
+    start: usize,                   // 起始字节偏移
+    end: usize,                     // 结束字节偏移(不包含)
+    expanded: bool,                 // 由宏展开产生，位置是整个宏调用
+    presumed: usize,                // #line指定的文件，用于显示文件名
+    shift: isize,                   // #line指定的行号与实际行号之差
+}
+
+impl Origin {
+    // 合并两个位置，不在同一文件时取前者
+    fn to(self, other: Origin) -> Origin {
+        if self.file != other.file {
+            return self;
+        }
+        Origin {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+            ..self
+        }
+    }
+}
+
+// 输出中的一段连续文本
+struct Segment {
+    out: usize,                     // 在输出中的起始偏移
+    out_end: usize,                 // 在输出中的结束偏移(不包含)
+    origin: Origin,
+}
+
+impl Segment {
+    // 输出偏移对应的原文件偏移，宏展开的文本对应整个宏调用
+    fn source_start(&self, pos: usize) -> usize {
+        if self.origin.expanded { self.origin.start } else { self.origin.start + (pos - self.out) }
+    }
+
+    fn source_end(&self, pos: usize) -> usize {
+        if self.origin.expanded { self.origin.end } else { self.origin.start + (pos - self.out) }
+    }
+}
+
+// 预处理输出到原文件的位置映射
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+    segments: Vec<Segment>,
+}
+
+impl SourceMap {
+    fn new() -> Self {
+        SourceMap {
+            files: vec![],
+            segments: vec![],
+        }
+    }
+
+    // 记录一段输出，与前一段在原文件中相邻时合并
+    fn push(&mut self, out: usize, len: usize, origin: Origin) {
+        if let Some(last) = self.segments.last_mut() {
+            let joined = if origin.expanded { last.origin == origin } else { !last.origin.expanded && last.origin.end == origin.start };
+            if last.out_end == out && joined && last.origin.file == origin.file && last.origin.shift == origin.shift
+                && last.origin.presumed == origin.presumed {
+                last.out_end += len;
+                last.origin.end = last.origin.end.max(origin.end);
+                return;
+            }
+        }
+        self.segments.push(Segment { out, out_end: out + len, origin });
+    }
+
+    // 原文件中位置对应的文件名和区间
+    fn locate(&self, origin: Origin) -> (String, Span) {
+        let span = self.files[origin.file].span(origin.start, origin.end, origin.shift);
+        (self.files[origin.presumed].name.clone(), span)
+    }
+
+    // 包含输出偏移的一段，偏移在输出末尾时取最后一段
+    fn segment(&self, pos: usize) -> Option<&Segment> {
+        let i = self.segments.partition_point(|s| s.out_end <= pos);
+        self.segments.get(i).or_else(|| self.segments.last())
+    }
+
+    // 预处理输出中的区间映射回原文件，返回文件名和原文件中的区间
+    pub fn map_span(&self, span: Span) -> (String, Span) {
+        let first = match self.segment(span.start()) {
+            Some(first) if !span.is_dummy() => first,
+            _ => return (self.files.first().map_or(String::new(), |f| f.name.clone()), span),
+        };
+
+        let start = first.source_start(span.start().min(first.out_end));
+        let mut end = start;
+        if span.end() > span.start() {
+            end = match self.segment(span.end() - 1) {
+                Some(last) if last.origin.file == first.origin.file => last.source_end(span.end().min(last.out_end)),
+                _ => first.source_end(first.out_end),
+            };
+            if end <= start {
+                end = first.source_end(first.out_end).max(start);
+            }
+        }
+
+        self.locate(Origin { start, end, ..first.origin })
+    }
+
+    // 诊断信息的位置映射回原文件
+    pub fn map_diagnostic(&self, diag: Diagnostic) -> Diagnostic {
+        diag.relocate(|span| self.map_span(span))
+    }
+
+    // 文件内容，用于显示诊断的源代码片段，没有内容时为空
+    pub fn source(&self, name: &str) -> &str {
+        self.files.iter().find(|f| f.name == name).map_or("", |f| f.source.as_str())
+    }
+}
+
+// 预处理记号的种类
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Kind {
+    Ident,
+    Number,
+    Str,
+    Char,
+    Punct,
+    Space,                          // 空白和注释
+    Splice,                         // 反斜杠续行
+    Newline,
+}
+
+#[derive(Clone, Debug)]
+struct Tok {
+    kind: Kind,
+    text: String,
+    origin: Origin,
+    hide: Vec<String>,              // 展开时不再展开的宏
+}
+
+impl Tok {
+    fn new(kind: Kind, text: String, origin: Origin) -> Self {
+        Tok {
+            kind,
+            text,
+            origin,
+            hide: vec![],
+        }
+    }
+
+    fn is(&self, punct: &str) -> bool {
+        self.kind == Kind::Punct && self.text == punct
+    }
+
+    fn is_space(&self) -> bool {
+        matches!(self.kind, Kind::Space | Kind::Splice | Kind::Newline)
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+// 按首字符判断记号种类，用于##拼接的结果
+fn kind_of(text: &str) -> Kind {
+    match text.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => Kind::Ident,
+        Some(c) if c.is_ascii_digit() => Kind::Number,
+        Some('"') => Kind::Str,
+        Some('\'') => Kind::Char,
+        _ => Kind::Punct,
+    }
+}
+
+// 相邻的两个记号直接连接时是否会被词法分析当成一个记号
+fn glues(a: &Tok, b: &Tok) -> bool {
+    let (x, y) = match (a.text.chars().next_back(), b.text.chars().next()) {
+        (Some(x), Some(y)) if !a.is_space() && !b.is_space() => (x, y),
+        _ => return false,
+    };
+    (is_ident_char(x) && is_ident_char(y)) || PUNCT2.contains(&format!("{}{}", x, y).as_str()) || (x == '/' && y == '*')
+}
+
+// 切分预处理记号，记号文本依次连接就是源代码
+fn lex(source: &str, file: usize) -> Vec<Tok> {
+    let mut toks = vec![];
+    let mut pos = 0;
+    while pos < source.len() {
+        let rest = &source[pos..];
+        let c = rest.chars().next().unwrap();
+        let line_end = rest.find('\n').unwrap_or(rest.len());
+
+        let (kind, len) = if rest.starts_with("\\\n") {
+            (Kind::Splice, 2)
+        } else if rest.starts_with("\\\r\n") {
+            (Kind::Splice, 3)
+        } else if rest.starts_with('\n') {
+            (Kind::Newline, 1)
+        } else if rest.starts_with("\r\n") {
+            (Kind::Newline, 2)
+        } else if rest.starts_with("//") {
+            (Kind::Space, rest[..line_end].trim_end_matches('\r').len())
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            (Kind::Space, comment.find("*/").map_or(rest.len(), |i| i + 4))
+        } else if c == ' ' || c == '\t' || c == '\r' || c == '\x0b' || c == '\x0c' {
+            let len = rest.find(|c: char| !matches!(c, ' ' | '\t' | '\r' | '\x0b' | '\x0c')).unwrap_or(rest.len());
+            // \r\n中的\r属于换行
+            let len = if rest[..len].ends_with('\r') && rest[len..].starts_with('\n') { len - 1 } else { len };
+            (Kind::Space, len.max(1))
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            (Kind::Ident, rest.find(|c: char| !is_ident_char(c)).unwrap_or(rest.len()))
+        } else if c.is_ascii_digit() || (c == '.' && rest[1..].starts_with(|c: char| c.is_ascii_digit())) {
+            (Kind::Number, rest.find(|c: char| !(is_ident_char(c) || c == '.')).unwrap_or(rest.len()))
+        } else if c == '"' || c == '\'' {
+            // 到右引号为止，没有右引号时到行尾，错误留给词法分析报告
+            let mut len = line_end.min(rest.len());
+            let mut escaped = false;
+            for (i, ch) in rest.char_indices().skip(1) {
+                if i >= line_end {
+                    break;
+                }
+                if escaped {
+                    escaped = false;
+                } else if ch == '\\' {
+                    escaped = true;
+                } else if ch == c {
+                    len = i + 1;
+                    break;
+                }
+            }
+            let len = if rest[..len].ends_with('\r') { len - 1 } else { len };
+            (if c == '"' { Kind::Str } else { Kind::Char }, len.max(1))
+        } else if let Some(p) = PUNCT3.iter().find(|p| rest.starts_with(*p)) {
+            (Kind::Punct, p.len())
+        } else if let Some(p) = PUNCT2.iter().find(|p| rest.starts_with(*p)) {
+            (Kind::Punct, p.len())
+        } else {
+            (Kind::Punct, c.len_utf8())
+        };
+
+        let origin = Origin { file, start: pos, end: pos + len, expanded: false, presumed: file, shift: 0 };
+        toks.push(Tok::new(kind, rest[..len].to_string(), origin));
+        pos += len;
+    }
+
+    toks
+}
+
+// 去掉首尾的空白
+fn trim(toks: &[Tok]) -> &[Tok] {
+    let start = toks.iter().position(|t| !t.is_space()).unwrap_or(toks.len());
+    let end = toks.iter().rposition(|t| !t.is_space()).map_or(start, |i| i + 1);
+    &toks[start..end]
+}
+
+// 宏定义
+#[derive(Clone)]
+struct Macro {
+    params: Option<Vec<String>>,    // 函数式宏的参数，可变参数为__VA_ARGS__
+    variadic: bool,
+    body: Vec<Tok>,
+}
+
+// 条件编译的状态
+struct Cond {
+    outer: bool,                    // 外层是否有效
+    taken: bool,                    // 是否已经有分支有效
+    active: bool,                   // 当前分支是否有效
+    has_else: bool,                 // 是否已经遇到#else
+    origin: Origin,                 // #if指令的位置
+}
+
+// 正在处理的文件
+struct FileState {
+    file: usize,
+    base: usize,                    // 文件开始时的条件指令层数，#elif等不能对应其他文件中的#if
+    presumed: usize,                // #line指定的文件名
+    shift: isize,                   // #line指定的行号与实际行号之差
+}
+
+impl FileState {
+    fn apply(&self, mut tok: Tok) -> Tok {
+        tok.origin.presumed = self.presumed;
+        tok.origin.shift = self.shift;
+        tok
+    }
+}
+
+// 预处理结果
+pub struct Preprocessed {
+    pub text: String,               // 展开后的源代码
+    pub map: SourceMap,             // 到原文件的位置映射
+}
+
+pub struct Preprocessor {
+    include_paths: Vec<PathBuf>,    // -I指定的包含目录
+    macros: HashMap<String, Macro>,
+    conds: Vec<Cond>,
+    once: HashSet<PathBuf>,         // #pragma once的文件
+    depth: usize,                   // 当前#include嵌套层数
+    map: SourceMap,
+    diag: DiagnosticSink,           // 诊断信息收集器
+}
+
+impl Preprocessor {
+    pub fn new(diag: DiagnosticSink) -> Self {
+        Preprocessor {
+            include_paths: vec![],
+            macros: HashMap::new(),
+            conds: vec![],
+            once: HashSet::new(),
+            depth: 0,
+            map: SourceMap::new(),
+            diag,
+        }
+    }
+
+    // 添加包含目录，按添加的顺序查找
+    pub fn add_include_path<P: Into<PathBuf>>(&mut self, path: P) {
+        self.include_paths.push(path.into());
+    }
+
+    // 预处理一个文件，file_name用于诊断信息和查找相对路径的包含文件
+    pub fn run(mut self, file_name: &str, source: &str) -> Preprocessed {
+        let path = if file_name == "<stdin>" { None } else { Some(PathBuf::from(file_name)) };
+        self.map.files.push(SourceFile::new(file_name.to_string(), path, source.to_string()));
+
+        let mut toks = vec![];
+        self.file(0, &mut toks);
+
+        let mut text = String::new();
+        let mut prev: Option<&Tok> = None;
+        for tok in toks.iter() {
+            // 宏展开的结果与前后的记号之间补充空格，避免连成一个记号
+            if let Some(prev) = prev {
+                if (prev.origin.expanded || tok.origin.expanded) && prev.origin != tok.origin && glues(prev, tok) {
+                    self.map.push(text.len(), 1, tok.origin);
+                    text.push(' ');
+                }
+            }
+            self.map.push(text.len(), tok.text.len(), tok.origin);
+            text.push_str(&tok.text);
+            prev = Some(tok);
+        }
+
+        Preprocessed {
+            text,
+            map: self.map,
+        }
+    }
+
+    fn error(&self, code: PreError, detail: &str, origin: Origin) {
+        let (file_name, span) = self.map.locate(origin);
+        let message = Catalog::current().pre_error(code, detail);
+        self.diag.emit(Diagnostic::error(ErrorCode::Pre(code), message, span).with_file_name(file_name));
+    }
+
+    // 当前是否在有效的条件分支中
+    fn active(&self) -> bool {
+        self.conds.last().is_none_or(|c| c.active)
+    }
+
+    // 处理一个文件，结果追加到out
+    fn file(&mut self, file: usize, out: &mut Vec<Tok>) {
+        let toks = lex(&self.map.files[file].source, file);
+        let base = self.conds.len();
+        let mut state = FileState { file, base, presumed: file, shift: 0 };
+        let mut text = vec![];          // 等待宏展开的正文
+        let mut line_start = true;
+        let mut i = 0;
+
+        while i < toks.len() {
+            if line_start {
+                let j = i + toks[i..].iter().take_while(|t| matches!(t.kind, Kind::Space | Kind::Splice)).count();
+                if j < toks.len() && toks[j].is("#") {
+                    out.extend(self.expand(mem::take(&mut text)));
+
+                    let k = j + toks[j..].iter().take_while(|t| t.kind != Kind::Newline).count();
+                    let hash = state.apply(toks[j].clone());
+                    let line: Vec<Tok> = toks[j + 1..k].iter()
+                        .filter(|t| t.kind != Kind::Splice)
+                        .map(|t| state.apply(t.clone()))
+                        .collect();
+                    self.directive(&hash, &line, &mut state, out);
+
+                    // 指令所在的行输出为空行
+                    if k < toks.len() {
+                        out.push(state.apply(toks[k].clone()));
+                    }
+                    i = k + 1;
+                    continue;
+                }
+            }
+
+            let tok = &toks[i];
+            line_start = tok.kind == Kind::Newline;
+            if tok.kind == Kind::Newline || (tok.kind != Kind::Splice && self.active()) {
+                text.push(state.apply(tok.clone()));
+            }
+            i += 1;
+        }
+        out.extend(self.expand(text));
+
+        // 本文件中没有结束的条件指令
+        while self.conds.len() > base {
+            let cond = self.conds.pop().unwrap();
+            self.error(CondNoEnd, "", cond.origin);
+        }
+    }
+}
+
+// 预处理指令
+impl Preprocessor {
+    fn directive(&mut self, hash: &Tok, line: &[Tok], state: &mut FileState, out: &mut Vec<Tok>) {
+        let origin = line.iter().fold(hash.origin, |o, t| o.to(t.origin));
+        let line = trim(line);
+        let name = match line.first() {
+            Some(name) => name,
+            None => return,             // 空指令
+        };
+        let rest = &line[1..];
+
+        match (name.kind, name.text.as_str()) {
+            (Kind::Ident, "if") => {
+                let value = self.active() && self.condition(rest, origin);
+                self.push_cond(value, origin);
+            },
+            (Kind::Ident, "ifdef") | (Kind::Ident, "ifndef") => {
+                let value = match trim(rest).first() {
+                    Some(t) if t.kind == Kind::Ident => self.macros.contains_key(&t.text) == (name.text == "ifdef"),
+                    _ => {
+                        if self.active() {
+                            self.error(MacroNameLost, "", origin);
+                        }
+                        false
+                    },
+                };
+                self.push_cond(value, origin);
+            },
+            (Kind::Ident, "elif") => {
+                let cond = match self.conds.last() {
+                    Some(cond) if self.conds.len() > state.base => cond,
+                    _ => return self.error(CondNoIf, "#elif", origin),
+                };
+                if cond.has_else {
+                    return self.error(CondAfterElse, "#elif", origin);
+                }
+                let value = cond.outer && !cond.taken && self.condition(rest, origin);
+                let cond = self.conds.last_mut().unwrap();
+                cond.active = value;
+                cond.taken |= value;
+            },
+            (Kind::Ident, "else") => {
+                if self.conds.len() <= state.base {
+                    return self.error(CondNoIf, "#else", origin);
+                }
+                let cond = self.conds.last_mut().unwrap();
+                if cond.has_else {
+                    return self.error(CondAfterElse, "#else", origin);
+                }
+                cond.active = cond.outer && !cond.taken;
+                cond.taken = true;
+                cond.has_else = true;
+            },
+            (Kind::Ident, "endif") => {
+                if self.conds.len() <= state.base {
+                    return self.error(CondNoIf, "#endif", origin);
+                }
+                self.conds.pop();
+            },
+            _ if !self.active() => {},  // 无效分支中的其他指令
+            (Kind::Ident, "define") => self.define(rest, origin),
+            (Kind::Ident, "undef") => match trim(rest).first() {
+                Some(t) if t.kind == Kind::Ident => {
+                    self.macros.remove(&t.text);
+                },
+                _ => self.error(MacroNameLost, "", origin),
+            },
+            (Kind::Ident, "include") => self.include(rest, origin, state, out),
+            (Kind::Ident, "line") => self.line(rest, origin, state),
+            (Kind::Number, _) => self.line(line, origin, state),    // GNU风格的行标记: # 12 "a.c"
+            (Kind::Ident, "pragma") => {
+                if trim(rest).first().is_some_and(|t| t.text == "once") {
+                    if let Some(path) = &self.map.files[state.file].path {
+                        self.once.insert(fs::canonicalize(path).unwrap_or_else(|_| path.clone()));
+                    }
+                }
+            },
+            (Kind::Ident, "error") => {
+                let text: String = trim(rest).iter().map(|t| t.text.as_str()).collect();
+                self.error(ErrorDirective, &text, origin);
+            },
+            _ => self.error(DirectiveUnknown, &format!("#{}", name.text), origin),
+        }
+    }
+
+    fn push_cond(&mut self, value: bool, origin: Origin) {
+        let outer = self.active();
+        self.conds.push(Cond {
+            outer,
+            taken: value,
+            active: outer && value,
+            has_else: false,
+            origin,
+        });
+    }
+
+    // #define
+    fn define(&mut self, toks: &[Tok], origin: Origin) {
+        let toks = trim(toks);
+        let name = match toks.first() {
+            Some(t) if t.kind == Kind::Ident => t.text.clone(),
+            _ => return self.error(MacroNameLost, "", origin),
+        };
+
+        let mut i = 1;
+        let mut params = None;
+        let mut variadic = false;
+        // 名称之后紧跟左括号的是函数式宏
+        if toks.get(1).is_some_and(|t| t.is("(")) {
+            let mut names = vec![];
+            i = 2;
+            loop {
+                i += toks[i..].iter().take_while(|t| t.is_space()).count();
+                match toks.get(i) {
+                    Some(t) if t.is(")") && names.is_empty() => break,
+                    Some(t) if t.kind == Kind::Ident && !names.contains(&t.text) => names.push(t.text.clone()),
+                    Some(t) if t.is("...") => {
+                        names.push("__VA_ARGS__".to_string());
+                        variadic = true;
+                    },
+                    _ => return self.error(MacroParamInvalid, &name, origin),
+                }
+                i += 1;
+                i += toks[i..].iter().take_while(|t| t.is_space()).count();
+                match toks.get(i) {
+                    Some(t) if t.is(")") => break,
+                    Some(t) if t.is(",") && !variadic => i += 1,
+                    _ => return self.error(MacroParamInvalid, &name, origin),
+                }
+            }
+            i += 1;
+            params = Some(names);
+        }
+
+        let body = trim(&toks[i..]).to_vec();
+        self.macros.insert(name, Macro { params, variadic, body });
+    }
+
+    // #include "file" 或 #include <file>
+    fn include(&mut self, toks: &[Tok], origin: Origin, state: &FileState, out: &mut Vec<Tok>) {
+        let mut target = include_target(trim(toks));
+        if target.is_none() {
+            // 宏展开后再试一次
+            let expanded = self.expand(trim(toks).to_vec());
+            target = include_target(trim(&expanded));
+        }
+        let (name, quoted) = match target {
+            Some(target) => target,
+            None => return self.error(IncludeInvalid, "", origin),
+        };
+
+        // 引号形式先在当前文件所在目录查找
+        let mut dirs = vec![];
+        if quoted {
+            let current = self.map.files[state.file].path.as_deref().and_then(Path::parent);
+            dirs.push(current.unwrap_or_else(|| Path::new("")).to_path_buf());
+        }
+        dirs.extend(self.include_paths.iter().cloned());
+        let path = match dirs.iter().map(|dir| dir.join(&name)).find(|path| path.is_file()) {
+            Some(path) => path,
+            None => return self.error(IncludeNotFound, &name, origin),
+        };
+        if self.depth >= MAX_INCLUDE_DEPTH {
+            return self.error(IncludeNested, &name, origin);
+        }
+        if self.once.contains(&fs::canonicalize(&path).unwrap_or_else(|_| path.clone())) {
+            return;
+        }
+        let source = match fs::read(&path) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(_) => return self.error(IncludeNotFound, &name, origin),
+        };
+
+        let file = self.map.files.len();
+        self.map.files.push(SourceFile::new(path.to_string_lossy().into_owned(), Some(path), source));
+        self.depth += 1;
+        self.file(file, out);
+        self.depth -= 1;
+    }
+
+    // #line 行号 ["文件名"]
+    fn line(&mut self, toks: &[Tok], origin: Origin, state: &mut FileState) {
+        let toks: Vec<Tok> = self.expand(trim(toks).to_vec()).into_iter().filter(|t| !t.is_space()).collect();
+        let number = match toks.first() {
+            Some(t) if t.kind == Kind::Number && t.text.bytes().all(|b| b.is_ascii_digit()) => t.text.parse::<isize>().ok(),
+            _ => None,
+        };
+        let number = match number {
+            Some(number) if number > 0 => number,
+            _ => return self.error(LineInvalid, "", origin),
+        };
+
+        if let Some(t) = toks.get(1) {
+            if t.kind != Kind::Str {
+                return self.error(LineInvalid, "", origin);
+            }
+            let name = t.text[1..t.text.len() - 1].to_string();
+            state.presumed = match self.map.files.iter().position(|f| f.name == name) {
+                Some(file) => file,
+                None => {
+                    self.map.files.push(SourceFile::new(name, None, String::new()));
+                    self.map.files.len() - 1
+                },
+            };
+        }
+
+        // 指令的下一行是指定的行号
+        let (line, _) = self.map.files[state.file].line_col_before(origin.end);
+        state.shift = number - (line as isize + 1);
+    }
+}
+
+// #include的文件名和是否为引号形式
+fn include_target(toks: &[Tok]) -> Option<(String, bool)> {
+    let first = toks.first()?;
+    if first.kind == Kind::Str && first.text.len() >= 2 && first.text.ends_with('"') {
+        return Some((first.text[1..first.text.len() - 1].to_string(), true));
+    }
+    if first.is("<") {
+        let end = toks.iter().position(|t| t.is(">"))?;
+        let name: String = toks[1..end].iter().map(|t| t.text.as_str()).collect();
+        return if name.is_empty() { None } else { Some((name, false)) };
+    }
+
+    None
+}
+
+// 宏展开
+impl Preprocessor {
+    // 展开记号序列，展开的结果与其后的记号一起重新扫描
+    fn expand(&self, toks: Vec<Tok>) -> Vec<Tok> {
+        let mut input: VecDeque<Tok> = toks.into();
+        let mut out = vec![];
+
+        while let Some(tok) = input.pop_front() {
+            let m = match self.macros.get(&tok.text) {
+                Some(m) if tok.kind == Kind::Ident && !tok.hide.contains(&tok.text) => m,
+                _ => {
+                    out.push(tok);
+                    continue;
+                },
+            };
+
+            let mut hide = tok.hide.clone();
+            hide.push(tok.text.clone());
+            let result = match &m.params {
+                None => {
+                    let origin = Origin { expanded: true, ..tok.origin };
+                    self.substitute(m, &[], origin, &hide)
+                },
+                Some(params) => {
+                    // 函数式宏的名称之后没有左括号时不展开
+                    let skip = input.iter().take_while(|t| t.is_space()).count();
+                    if !input.get(skip).is_some_and(|t| t.is("(")) {
+                        out.push(tok);
+                        continue;
+                    }
+                    let mut consumed: Vec<Tok> = input.drain(..=skip).collect();
+                    let args = match collect_args(&mut input, &mut consumed, params.len(), m.variadic) {
+                        Some((args, close)) => (args, close),
+                        None => {
+                            self.error(MacroArgsNoEnd, &tok.text, tok.origin);
+                            out.push(tok);
+                            out.extend(consumed);
+                            continue;
+                        },
+                    };
+                    let (mut args, close) = args;
+                    if params.is_empty() && args.len() == 1 && trim(&args[0]).is_empty() {
+                        args.clear();
+                    }
+                    if m.variadic && args.len() + 1 == params.len() {
+                        args.push(vec![]);
+                    }
+                    if args.len() != params.len() {
+                        self.error(MacroArgsCount, &tok.text, tok.origin.to(close.origin));
+                        out.push(tok);
+                        out.extend(consumed);
+                        continue;
+                    }
+                    let origin = Origin { expanded: true, ..tok.origin.to(close.origin) };
+                    self.substitute(m, &args, origin, &hide)
+                },
+            };
+
+            for t in result.into_iter().rev() {
+                input.push_front(t);
+            }
+        }
+
+        out
+    }
+
+    // 替换宏体中的参数，处理#和##，宏体中的记号位置改为宏调用的位置
+    fn substitute(&self, m: &Macro, args: &[Vec<Tok>], origin: Origin, hide: &[String]) -> Vec<Tok> {
+        let params = m.params.as_deref().unwrap_or(&[]);
+        let param = |t: &Tok| if t.kind == Kind::Ident { params.iter().position(|p| *p == t.text) } else { None };
+        let body = &m.body;
+        let mut result: Vec<Tok> = vec![];
+
+        let mut i = 0;
+        while i < body.len() {
+            let tok = &body[i];
+            let next = i + 1 + body[i + 1..].iter().take_while(|t| t.is_space()).count();
+
+            if tok.is("#") && m.params.is_some() {
+                if let Some(p) = body.get(next).and_then(param) {
+                    result.push(Tok::new(Kind::Str, stringize(&args[p]), origin));
+                    i = next + 1;
+                    continue;
+                }
+            }
+            if let Some(p) = param(tok) {
+                let pasted = result.iter().rev().find(|t| !t.is_space()).is_some_and(|t| t.is("##"))
+                    || body.get(next).is_some_and(|t| t.is("##"));
+                let arg = trim(&args[p]).to_vec();
+                // 实参保留自身的位置，##两侧的实参不展开
+                result.extend(if pasted { arg } else { self.expand(arg) });
+                i += 1;
+                continue;
+            }
+
+            result.push(Tok { origin, ..tok.clone() });
+            i += 1;
+        }
+
+        let mut result = paste(result, origin);
+        for t in result.iter_mut() {
+            for name in hide {
+                if !t.hide.contains(name) {
+                    t.hide.push(name.clone());
+                }
+            }
+        }
+
+        result
+    }
+}
+
+// 收集函数式宏的实参，返回实参和右括号，没有右括号时返回None
+fn collect_args(input: &mut VecDeque<Tok>, consumed: &mut Vec<Tok>, params: usize, variadic: bool) -> Option<(Vec<Vec<Tok>>, Tok)> {
+    let mut args: Vec<Vec<Tok>> = vec![vec![]];
+    let mut depth = 0;
+
+    while let Some(tok) = input.pop_front() {
+        consumed.push(tok.clone());
+        if tok.is("(") {
+            depth += 1;
+        } else if tok.is(")") {
+            if depth == 0 {
+                return Some((args, tok));
+            }
+            depth -= 1;
+        } else if tok.is(",") && depth == 0 && !(variadic && args.len() >= params) {
+            args.push(vec![]);
+            continue;
+        }
+
+        // 实参中的换行当作空格
+        let tok = if tok.kind == Kind::Newline { Tok { kind: Kind::Space, text: " ".to_string(), ..tok } } else { tok };
+        args.last_mut().unwrap().push(tok);
+    }
+
+    None
+}
+
+// #运算，实参转为字符串常量
+fn stringize(arg: &[Tok]) -> String {
+    let mut text = String::from("\"");
+    let mut space = false;
+    for tok in trim(arg) {
+        if tok.is_space() {
+            space = true;
+            continue;
+        }
+        if space {
+            text.push(' ');
+            space = false;
+        }
+        if tok.kind == Kind::Str || tok.kind == Kind::Char {
+            text.push_str(&tok.text.replace('\\', "\\\\").replace('"', "\\\""));
+        } else {
+            text.push_str(&tok.text);
+        }
+    }
+    text.push('"');
+    text
+}
+
+// ##运算，拼接两侧的记号
+fn paste(mut toks: Vec<Tok>, origin: Origin) -> Vec<Tok> {
+    while let Some(i) = toks.iter().position(|t| t.is("##")) {
+        let left = toks[..i].iter().rposition(|t| !t.is_space());
+        let right = toks[i + 1..].iter().position(|t| !t.is_space()).map(|j| i + 1 + j);
+
+        let text = format!("{}{}",
+                           left.map_or("", |l| toks[l].text.as_str()),
+                           right.map_or("", |r| toks[r].text.as_str()));
+        let start = left.unwrap_or(i);
+        let end = right.unwrap_or(i);
+        if text.is_empty() {
+            toks.drain(start..=end);
+        } else {
+            toks.splice(start..=end, [Tok::new(kind_of(&text), text, origin)]);
+        }
+    }
+
+    toks
+}
+
+// 条件表达式
+impl Preprocessor {
+    // #if和#elif的条件，表达式无效时报错并按假处理
+    fn condition(&self, toks: &[Tok], origin: Origin) -> bool {
+        let value = self.replace_defined(toks).and_then(|toks| {
+            let toks: Vec<Tok> = self.expand(toks).into_iter().filter(|t| !t.is_space()).collect();
+            let mut eval = Eval { toks: &toks, pos: 0 };
+            let value = eval.cond()?;
+            if eval.pos == toks.len() { Some(value) } else { None }
+        });
+
+        match value {
+            Some(value) => value != 0,
+            None => {
+                self.error(CondInvalid, "", origin);
+                false
+            },
+        }
+    }
+
+    // 把defined X和defined(X)替换为1或0
+    fn replace_defined(&self, toks: &[Tok]) -> Option<Vec<Tok>> {
+        let mut out = vec![];
+        let mut i = 0;
+        while i < toks.len() {
+            let tok = &toks[i];
+            if tok.kind != Kind::Ident || tok.text != "defined" {
+                out.push(tok.clone());
+                i += 1;
+                continue;
+            }
+
+            let rest: Vec<(usize, &Tok)> = toks.iter().enumerate().skip(i + 1).filter(|(_, t)| !t.is_space()).take(3).collect();
+            let (name, end) = match rest.as_slice() {
+                [(_, l), (_, name), (end, r), ..] if l.is("(") && name.kind == Kind::Ident && r.is(")") => (name, *end),
+                [(end, name), ..] if name.kind == Kind::Ident => (name, *end),
+                _ => return None,
+            };
+            let value = if self.macros.contains_key(&name.text) { "1" } else { "0" };
+            out.push(Tok::new(Kind::Number, value.to_string(), tok.origin));
+            i = end + 1;
+        }
+
+        Some(out)
+    }
+}
+
+// 整数常量表达式求值，剩余的标识符按0处理
+struct Eval<'a> {
+    toks: &'a [Tok],
+    pos: usize,
+}
+
+impl<'a> Eval<'a> {
+    fn peek(&self) -> Option<&'a Tok> {
+        self.toks.get(self.pos)
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        if self.peek().is_some_and(|t| t.is(punct)) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    // 条件运算
+    fn cond(&mut self) -> Option<i64> {
+        let c = self.binary(1)?;
+        if !self.eat("?") {
+            return Some(c);
+        }
+        let a = self.cond()?;
+        if !self.eat(":") {
+            return None;
+        }
+        let b = self.cond()?;
+        Some(if c != 0 { a } else { b })
+    }
+
+    // 二元运算的优先级，数字越大越优先
+    fn precedence(tok: &Tok) -> Option<u8> {
+        if tok.kind != Kind::Punct {
+            return None;
+        }
+        Some(match tok.text.as_str() {
+            "||" => 1,
+            "&&" => 2,
+            "|" => 3,
+            "^" => 4,
+            "&" => 5,
+            "==" | "!=" => 6,
+            "<" | ">" | "<=" | ">=" => 7,
+            "<<" | ">>" => 8,
+            "+" | "-" => 9,
+            "*" | "/" | "%" => 10,
+            _ => return None,
+        })
+    }
+
+    fn binary(&mut self, min: u8) -> Option<i64> {
+        let mut lhs = self.unary()?;
+        while let Some(op) = self.peek() {
+            let prec = match Eval::precedence(op) {
+                Some(prec) if prec >= min => prec,
+                _ => break,
+            };
+            self.pos += 1;
+            let rhs = self.binary(prec + 1)?;
+            lhs = match op.text.as_str() {
+                "||" => (lhs != 0 || rhs != 0) as i64,
+                "&&" => (lhs != 0 && rhs != 0) as i64,
+                "|" => lhs | rhs,
+                "^" => lhs ^ rhs,
+                "&" => lhs & rhs,
+                "==" => (lhs == rhs) as i64,
+                "!=" => (lhs != rhs) as i64,
+                "<" => (lhs < rhs) as i64,
+                ">" => (lhs > rhs) as i64,
+                "<=" => (lhs <= rhs) as i64,
+                ">=" => (lhs >= rhs) as i64,
+                "<<" => lhs.wrapping_shl(rhs as u32),
+                ">>" => lhs.wrapping_shr(rhs as u32),
+                "+" => lhs.wrapping_add(rhs),
+                "-" => lhs.wrapping_sub(rhs),
+                "*" => lhs.wrapping_mul(rhs),
+                "/" if rhs != 0 => lhs.wrapping_div(rhs),
+                "%" if rhs != 0 => lhs.wrapping_rem(rhs),
+                _ => return None,       // 除以0
+            };
+        }
+
+        Some(lhs)
+    }
+
+    fn unary(&mut self) -> Option<i64> {
+        let tok = self.peek()?;
+        self.pos += 1;
+        match tok.kind {
+            Kind::Number => parse_number(&tok.text),
+            Kind::Char => parse_char(&tok.text),
+            Kind::Ident => Some(0),
+            Kind::Punct => match tok.text.as_str() {
+                "+" => self.unary(),
+                "-" => self.unary().map(i64::wrapping_neg),
+                "!" => self.unary().map(|v| (v == 0) as i64),
+                "~" => self.unary().map(|v| !v),
+                "(" => {
+                    let value = self.cond()?;
+                    if self.eat(")") { Some(value) } else { None }
+                },
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+// 整数常量，与词法分析相同支持十六进制、二进制和八进制，忽略u、l后缀
+fn parse_number(text: &str) -> Option<i64> {
+    let text = text.trim_end_matches(['u', 'U', 'l', 'L']);
+    let (digits, radix) = if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        (hex, 16)
+    } else if let Some(bin) = text.strip_prefix("0b").or_else(|| text.strip_prefix("0B")) {
+        (bin, 2)
+    } else if text.len() > 1 && text.starts_with('0') {
+        (&text[1..], 8)
+    } else {
+        (text, 10)
+    };
+    u64::from_str_radix(digits, radix).ok().map(|v| v as i64)
+}
+
+// 字符常量
+fn parse_char(text: &str) -> Option<i64> {
+    let inner = text.strip_prefix('\'')?.strip_suffix('\'')?;
+    let mut chars = inner.chars();
+    let value = match (chars.next()?, chars.next()) {
+        ('\\', Some(c)) => match c {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            c => c,
+        },
+        (c, _) => c,
+    };
+    Some(value as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::common::PreError::{CondNoEnd, IncludeNotFound, MacroArgsCount};
+    use crate::diagnostic::{DiagnosticSink, ErrorCode};
+    use crate::preprocess::Preprocessor;
+
+    #[test]
+    fn test_expand_macros() {
+        let source = "#define N 4\n#define M -1\n#define SQ(x) ((x) * (x))\n#define STR(x) #x\n#define CAT(a, b) a ## b\n\
+                      #define F(x) G\n#define G(y) y + 1\n\
+                      #if defined(N) && N > 3 || 0\nint CAT(v, 1)[N];\n#elif 1\nint wrong;\n#else\nint wrong;\n#endif\n\
+                      int s = SQ(N - 1);\nchar *t = STR(a  \"b\");\nint r = F(0)(2);\nint n = -M;\n";
+        let diag = DiagnosticSink::new();
+        let pp = Preprocessor::new(diag.clone()).run("a.c", source);
+        assert!(diag.take().is_empty());
+        assert_eq!(pp.text, "\n\n\n\n\n\n\n\nint v1[4];\n\n\n\n\n\n\
+                             int s = ((4 - 1) * (4 - 1));\nchar *t = \"a \\\"b\\\"\";\nint r = 2 + 1;\nint n = - -1;\n");
+    }
+
+    #[test]
+    fn test_map_spans() {
+        let dir = std::env::temp_dir().join(format!("pp-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.h"), "#pragma once\n#define ONE 1\nint h;\n").unwrap();
+
+        let source = "#include \"a.h\"\n#include \"a.h\"\n#define ADD(a, b) a + b\nint x = ADD(ONE,\n  y);\n#include \"b.h\"\n#if 1\n";
+        let diag = DiagnosticSink::new();
+        let pp = Preprocessor::new(diag.clone()).run(dir.join("m.c").to_str().unwrap(), source);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let diags = diag.take();
+        let codes: Vec<_> = diags.iter().map(|d| d.code()).collect();
+        assert_eq!(codes, vec![Some(ErrorCode::Pre(IncludeNotFound)), Some(ErrorCode::Pre(CondNoEnd))]);
+        assert_eq!((diags[0].span().line(), diags[0].span().col(), diags[0].span().end_col()), (6, 1, 14));
+        assert_eq!(pp.text, "\n\nint h;\n\n\n\nint x = 1 + y;\n\n\n");
+
+        // 头文件中的记号映射回头文件
+        let h = pp.text.find('h').unwrap();
+        let (name, span) = pp.map.map_span(crate::span::Span::new(h, h + 1, 3, 5, 3, 5));
+        assert!(name.ends_with("a.h"));
+        assert_eq!((span.line(), span.col()), (3, 5));
+
+        // 宏体中的记号映射到整个宏调用，实参保留自身的位置
+        let plus = pp.text.find('+').unwrap();
+        let (_, span) = pp.map.map_span(crate::span::Span::new(plus, plus + 1, 6, 11, 6, 11));
+        assert_eq!((span.line(), span.col(), span.end_line(), span.end_col()), (4, 9, 5, 4));
+        let y = pp.text.find('y').unwrap();
+        let (_, span) = pp.map.map_span(crate::span::Span::new(y, y + 1, 6, 13, 6, 13));
+        assert_eq!((span.line(), span.col()), (5, 3));
+    }
+
+    #[test]
+    fn test_macro_args_count() {
+        let diag = DiagnosticSink::new();
+        let pp = Preprocessor::new(diag.clone()).run("a.c", "#define F(a, b) a\nint x = F(1);\n");
+        let diags = diag.take();
+        assert_eq!(diags[0].code(), Some(ErrorCode::Pre(MacroArgsCount)));
+        assert_eq!(pp.text, "\nint x = F(1);\n");
+    }
+}
@@ -0,0 +1,206 @@
+/*
+	诊断信息渲染，按照源代码片段的形式输出错误位置
+*/
+use crate::catalog::Catalog;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::span::Span;
+
+const MAX_SPAN_LINES: usize = 4;        // 多行区间最多显示的行数
+
+// 带标注的源代码行
+struct Annotation {
+    line: usize,                // 行号
+    start_col: usize,           // 起始列
+    end_col: usize,             // 结束列(包含)
+    primary: bool,              // 是否是主要位置
+    message: String,            // 标注信息
+}
+
+// 诊断输出格式
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ErrorFormat {
+    Human,                      // 带源代码片段的文本
+    Json,                       // 每行一个JSON对象
+}
+
+impl ErrorFormat {
+    pub fn parse(name: &str) -> Option<ErrorFormat> {
+        match name {
+            "human" => Some(ErrorFormat::Human),
+            "json" => Some(ErrorFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+pub struct Renderer<'a> {
+    lines: Vec<&'a str>,        // 源代码的各行
+    context: usize,             // 主要位置之前显示的上下文行数
+    catalog: Catalog,           // 消息目录
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Renderer {
+            lines: source.split('\n').map(|l| l.strip_suffix('\r').unwrap_or(l)).collect(),
+            context: 1,
+            catalog: Catalog::current(),
+        }
+    }
+
+    // 设置上下文行数
+    pub fn with_context(mut self, context: usize) -> Self {
+        self.context = context;
+        self
+    }
+
+    // 设置消息目录
+    pub fn with_catalog(mut self, catalog: Catalog) -> Self {
+        self.catalog = catalog;
+        self
+    }
+
+    // 按指定格式渲染一条诊断
+    pub fn render_as(&self, diag: &Diagnostic, format: ErrorFormat) -> String {
+        match format {
+            ErrorFormat::Human => self.render(diag),
+            ErrorFormat::Json => diag.to_json(),
+        }
+    }
+
+    // 渲染一条诊断
+    pub fn render(&self, diag: &Diagnostic) -> String {
+        let mut out = String::new();
+        let span = diag.span();
+
+        // 头部: error[E2010]: 信息
+        out.push_str(self.catalog.severity(diag.severity()));
+        if let Some(code) = diag.code() {
+            out.push_str(&format!("[{}]", code.code()));
+        }
+        out.push_str(&format!(": {}", diag.message()));
+        if let Some(flag) = diag.flag() {
+            out.push_str(&format!(" [-W{}]", flag));
+        }
+        out.push('\n');
+
+        if span.is_dummy() {
+            for note in diag.notes() {
+                out.push_str(&format!("  = {}: {}\n", self.catalog.severity(Severity::Note), note));
+            }
+            return out;
+        }
+
+        let annotations = self.annotations(diag);
+        let first_line = span.line().saturating_sub(self.context).max(1);
+        let max_line = annotations.iter().map(|a| a.line).max().unwrap_or(span.line()).max(span.line());
+        let width = max_line.to_string().len();
+        let gutter = " ".repeat(width);
+
+        out.push_str(&format!("{}--> {}:{}:{}\n", gutter, diag.file_name(), span.line(), span.col()));
+        out.push_str(&format!("{} |\n", gutter));
+
+        // 需要显示的行: 上下文行 + 所有标注行
+        let mut shown: Vec<usize> = (first_line..span.line()).collect();
+        shown.extend(annotations.iter().map(|a| a.line));
+        shown.sort_unstable();
+        shown.dedup();
+
+        let mut last = 0;
+        for line in shown {
+            let text = match self.lines.get(line - 1) {
+                Some(text) => *text,
+                None => continue,
+            };
+            if last != 0 && line > last + 1 {
+                out.push_str("...\n");
+            }
+            last = line;
+
+            out.push_str(&format!("{:>width$} | {}\n", line, text, width = width));
+            for a in annotations.iter().filter(|a| a.line == line) {
+                let mark = if a.primary { "^" } else { "-" };
+                let underline = mark.repeat(a.end_col + 1 - a.start_col);
+                let line_out = format!("{} | {}{} {}", gutter, padding(text, a.start_col), underline, a.message);
+                out.push_str(line_out.trim_end());
+                out.push('\n');
+            }
+        }
+
+        out.push_str(&format!("{} |\n", gutter));
+        for note in diag.notes() {
+            out.push_str(&format!("{} = {}: {}\n", gutter, self.catalog.severity(Severity::Note), note));
+        }
+
+        out
+    }
+
+    // 计算主要位置和次要标注覆盖的各行
+    fn annotations(&self, diag: &Diagnostic) -> Vec<Annotation> {
+        let mut annotations = self.span_annotations(diag.span(), true, "");
+        for label in diag.labels() {
+            if !label.span().is_dummy() {
+                annotations.extend(self.span_annotations(label.span(), false, label.message()));
+            }
+        }
+
+        annotations
+    }
+
+    fn span_annotations(&self, span: Span, primary: bool, message: &str) -> Vec<Annotation> {
+        let mut annotations = vec![];
+        let end_line = span.end_line().max(span.line());
+        let last_line = end_line.min(span.line() + MAX_SPAN_LINES - 1);
+
+        for line in span.line()..=last_line {
+            let len = self.lines.get(line - 1).map_or(0, |l| l.chars().count());
+            let start_col = if line == span.line() { span.col().max(1) } else { 1 };
+            let end_col = if line == end_line && span.end_col() >= start_col {
+                span.end_col()
+            } else {
+                len.max(start_col)
+            };
+            annotations.push(Annotation {
+                line,
+                start_col,
+                end_col,
+                primary,
+                message: if line == last_line { message.to_string() } else { String::new() },
+            });
+        }
+
+        annotations
+    }
+}
+
+// 标注前的空白，保留制表符以便对齐
+fn padding(text: &str, col: usize) -> String {
+    text.chars().take(col - 1).map(|c| if c == '\t' { '\t' } else { ' ' }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::catalog::{Catalog, Lang};
+    use crate::common::SemError::VarReDef;
+    use crate::diagnostic::{Diagnostic, ErrorCode};
+    use crate::render::Renderer;
+    use crate::span::Span;
+
+    #[test]
+    fn test_render_snippet() {
+        let source = "int a;\nint b;\nint b;\n";
+        let diag = Diagnostic::error(ErrorCode::Sem(VarReDef), "b 变量重定义".to_string(), Span::new(18, 19, 3, 5, 3, 5))
+            .with_label(Span::new(11, 12, 2, 5, 2, 5), "此处已有定义".to_string())
+            .with_file_name("a.c".to_string());
+
+        let expected = "error[E3000]: b 变量重定义\n \
+                        --> a.c:3:5\n  \
+                        |\n\
+                        2 | int b;\n  \
+                        |     - 此处已有定义\n\
+                        3 | int b;\n  \
+                        |     ^\n  \
+                        |\n";
+        assert_eq!(Renderer::new(source).with_catalog(Catalog::new(Lang::En)).render(&diag), expected);
+    }
+}
@@ -1,19 +1,14 @@
 use failure;
 use std::fs::File;
-use std::io::Read;
-
-const BUF_LEN: usize = 20;
+use std::io::{self, Read};
 
 pub struct Scanner {
     file_name: String,                  // 文件名
-    file: Option<File>,                 // 文件指针
+    source: String,                     // 源代码内容
 
     // 内部状态
-    line: [u8; BUF_LEN],                // 文件内容
-    line_len: usize,                    // 当前行的长度
-    read_pos: usize,                    // 读取的位置
+    read_pos: usize,                    // 读取的位置(字节偏移)
     last_char: char,                    // 上一个字符，主要用于判断行位置
-    need_read: bool,                      // 保存是否读取到缓冲区最后一个字符
 
     //读取状态
     line_num: usize,                    // 记录行号
@@ -21,58 +16,58 @@ pub struct Scanner {
 }
 
 impl Scanner {
+    // 从文件读取源代码
     pub fn new(file_name: String) -> Result<Scanner, failure::Error> {
         let file = File::open(&file_name)?;
 
-        Ok(Scanner {
+        Scanner::from_reader(file_name, file)
+    }
+
+    // 从内存中的字符串读取源代码
+    pub fn from_source<S: Into<String>>(source: S) -> Scanner {
+        Scanner::with_name("<string>".to_string(), source.into())
+    }
+
+    // 从任意输入流读取源代码
+    pub fn from_reader<R: Read>(file_name: String, mut reader: R) -> Result<Scanner, failure::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        Ok(Scanner::with_name(file_name, String::from_utf8_lossy(&bytes).into_owned()))
+    }
+
+    // 从标准输入读取源代码
+    pub fn from_stdin() -> Result<Scanner, failure::Error> {
+        Scanner::from_reader("<stdin>".to_string(), io::stdin().lock())
+    }
+
+    pub(crate) fn with_name(file_name: String, source: String) -> Scanner {
+        Scanner {
             file_name,
-            file: Some(file),
-            line: [0; BUF_LEN],
-            line_len: 0,
+            source,
             read_pos: 0,
             last_char: char::from(0),
-            need_read: true,
             line_num: 1,
             col_num: 0,
-        })
+        }
     }
 
     pub fn scan(&mut self) -> Option<char> {
-        if let Some(file) = self.file.as_mut() {
-            if self.need_read {
-                self.line_len = file.read(&mut self.line[..]).unwrap_or_else(|_| 0);
-
-                if self.line_len == 0 {     // 数据读取完毕
-                    self.file = None;
-                    return None;
-                    // return Err(Error::new(ErrorKind::Other, "文件数据读取完毕").into())
-                }
-                self.need_read = false;
-            }
-
-            // 从缓冲区中读取一个字符
-            let ch = self.line[self.read_pos] as char;
-            self.read_pos += 1;
-            if self.read_pos == self.line_len { // 读取到最后一个字符了，则记录
-                self.read_pos = 0;
-                self.need_read = true;
-            }
-
-            if self.last_char == '\n' {         // 新行
-                self.line_num += 1;             // 行号累加
-                self.col_num = 0;               // 列号清空
-            }
-
-            if ch != '\n' {                     // 不是换行
-                self.col_num += 1;              // 列号新增
-            }
-            self.last_char = ch;                // 记录上一个字符
-
-            return Some(ch);
+        // 读取下一个字符，读取完毕则返回None
+        let ch = self.source[self.read_pos..].chars().next()?;
+        self.read_pos += ch.len_utf8();
+
+        if self.last_char == '\n' {         // 新行
+            self.line_num += 1;             // 行号累加
+            self.col_num = 0;               // 列号清空
+        }
+
+        if ch != '\n' {                     // 不是换行
+            self.col_num += 1;              // 列号新增
         }
+        self.last_char = ch;                // 记录上一个字符
 
-        return None;
-        // Err(Error::new(ErrorKind::Other, "文件指针不存在").into())
+        Some(ch)
     }
 
     pub fn file_name(&self) -> String {
@@ -86,6 +81,16 @@ impl Scanner {
     pub fn col_num(&self) -> usize {
         self.col_num
     }
+
+    // 源代码内容，用于显示错误位置的上下文
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    // 已读取内容的字节偏移
+    pub fn pos(&self) -> usize {
+        self.read_pos
+    }
 }
 
 #[cfg(test)]
@@ -95,19 +100,27 @@ mod tests {
     #[test]
     fn test_scan() {
         let test_str = "int val_name\r\nadd\r\nchar\r\nfunc_tests\r\n+-*/";
-        let mut scanner = Scanner::new("./test_file/scanner.txt".to_string()).unwrap();
+        let mut scanner = Scanner::from_source(test_str);
 
         for c in test_str.chars() {
-            let ch = scanner.scan();
-            if let None = ch {
-                assert!(false);
-                break;
-            }
-            assert_eq!(ch.unwrap(), c);
+            assert_eq!(scanner.scan(), Some(c));
         }
 
-        if let None = scanner.scan() {
-            assert!(true)
-        }
+        assert_eq!(scanner.scan(), None);
+    }
+
+    #[test]
+    fn test_scan_reader() {
+        let mut scanner = Scanner::from_reader("test.c".to_string(), "ab\ncd".as_bytes()).unwrap();
+
+        assert_eq!(scanner.file_name(), "test.c");
+        assert_eq!(scanner.scan(), Some('a'));
+        assert_eq!(scanner.scan(), Some('b'));
+        assert_eq!((scanner.line_num(), scanner.col_num()), (1, 2));
+        assert_eq!(scanner.scan(), Some('\n'));
+        assert_eq!(scanner.scan(), Some('c'));
+        assert_eq!((scanner.line_num(), scanner.col_num()), (2, 1));
+        assert_eq!(scanner.scan(), Some('d'));
+        assert_eq!(scanner.scan(), None);
     }
 }
@@ -0,0 +1,73 @@
+/*
+	源代码区间，记录字节偏移以及起止行列，用于定位词法记号和错误
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Span {
+    start: usize,               // 起始字节偏移
+    end: usize,                 // 结束字节偏移(不包含)
+    line: usize,                // 起始行号
+    col: usize,                 // 起始列号
+    end_line: usize,            // 结束行号
+    end_col: usize,             // 结束列号(包含)
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, col: usize, end_line: usize, end_col: usize) -> Self {
+        Span {
+            start,
+            end,
+            line,
+            col,
+            end_line,
+            end_col,
+        }
+    }
+
+    // 空区间，用于没有源代码位置的符号(临时变量、常量等)
+    pub fn dummy() -> Self {
+        Span::default()
+    }
+
+    pub fn is_dummy(&self) -> bool {
+        self.line == 0
+    }
+
+    // 合并两个区间，得到覆盖二者的最小区间
+    pub fn to(&self, other: Span) -> Span {
+        if self.is_dummy() {
+            return other;
+        }
+        if other.is_dummy() {
+            return *self;
+        }
+
+        let (first, last) = if self.start <= other.start { (*self, other) } else { (other, *self) };
+        let tail = if last.end >= first.end { last } else { first };
+
+        Span::new(first.start, tail.end, first.line, first.col, tail.end_line, tail.end_col)
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn col(&self) -> usize {
+        self.col
+    }
+
+    pub fn end_line(&self) -> usize {
+        self.end_line
+    }
+
+    pub fn end_col(&self) -> usize {
+        self.end_col
+    }
+}
@@ -1,87 +1,177 @@
-use crate::plat::STACK_BASE;
-use crate::common::{SemError, Tag};
-use crate::common::SemError::VoidVar;
-use crate::common::Tag::{KwChar, KwInt, KwVoid};
-use crate::intercode::{InterCode, InterInst};
-use crate::token::TokenType;
-use crate::symtab::{FOUR, ONE, VOID_VAR};
-
-pub(crate)  fn sem_error(code: usize, name: &str) {
-    //语义错误信息串
-    const SEM_ERROR_TABLE: [&str; 21] = ["变量重定义",										//附加名称信息
-                                         "函数重定义",
-                                         "变量未声明",
-                                         "函数未声明",
-                                         "函数声明与定义不匹配",
-                                         "函数行参实参不匹配",
-                                         "变量声明时不允许初始化",
-                                         "函数定义不能声明extern",
-                                         "数组长度应该是正整数",
-                                         "变量初始化类型错误",
-                                         "全局变量初始化值不是常量",
-                                         "变量不能声明为void类型",					//没有名称信息
-                                         "无效的左值表达式",
-                                         "赋值表达式类型不兼容",
-                                         "表达式运算对象不能是基本类型",
-                                         "表达式运算对象不是基本类型",
-                                         "数组索引运算类型错误",
-                                         "void的函数返回值不能参与表达式运算",
-                                         "break语句不能出现在循环或switch语句之外",
-                                         "continue不能出现在循环之外",
-                                         "return语句和函数返回值类型不匹配"];
-    println!("语义错误: {} {}.", name, SEM_ERROR_TABLE[code]);
+use std::io::{self, Write};
+use crate::plat::{PARA_SIZE, STACK_BASE};
+use crate::catalog::Catalog;
+use crate::common::{SemError, Warning};
+use crate::diagnostic::{Diagnostic, DiagnosticSink, ErrorCode};
+use crate::intercode::{InterCode, InterInst, symbol};
+use crate::span::Span;
+use crate::ast::Literal;
+use crate::types::{self, TypeId};
+
+pub(crate) fn sem_error(diag: &DiagnosticSink, code: SemError, name: &str, span: Span) {
+    diag.emit(sem_diagnostic(code, name, span));
 }
 
+// 构造语义错误
+pub(crate) fn sem_diagnostic(code: SemError, name: &str, span: Span) -> Diagnostic {
+    let message = Catalog::current().sem_error(code, name);
+    Diagnostic::error(ErrorCode::Sem(code), message, span)
+}
+
+// 警告，带有-W选项使用的名称
+pub(crate) fn sem_warning(diag: &DiagnosticSink, warning: Warning, name: &str, span: Span) {
+    let message = Catalog::current().warning(warning, name);
+    diag.emit(Diagnostic::warning(message, span).with_flag(warning.name()));
+}
+
+/*
+	结构体或联合类型，成员的offset记录其在对象中的偏移
+*/
+#[derive(Clone)]
+pub struct Record {
+    union: bool,                     // 是否是联合
+    name: String,                    // 标签名，匿名时为空
+    scope_path: Vec<i32>,            // 作用域路径
+    members: Vec<Var>,               // 成员列表
+    defined: bool,                   // 是否已经定义
+    size: isize,                     // 大小
+    align: isize,                    // 对齐字节数
+    span: Span,                      // 标签在源代码中的位置
+}
+
+impl Record {
+    pub(crate) fn new(union: bool, name: String, scope_path: Vec<i32>, span: Span) -> Self {
+        Record {
+            union,
+            name,
+            scope_path,
+            members: vec![],
+            defined: false,
+            size: 0,
+            align: 1,
+            span,
+        }
+    }
+
+    // 类型名称，如struct node、union <anonymous>
+    pub(crate) fn type_name(&self) -> String {
+        let kind = if self.union { "union" } else { "struct" };
+        if self.name.is_empty() {
+            format!("{} <anonymous>", kind)
+        } else {
+            format!("{} {}", kind, self.name)
+        }
+    }
+
+    // 完成定义，计算成员偏移、大小和对齐：结构体成员依次按对齐排列，联合成员都从0开始
+    pub(crate) fn define(&mut self, mut members: Vec<Var>) {
+        let mut size = 0;
+        let mut align = 1;
+        for m in members.iter_mut() {
+            let a = m.get_align();
+            align = align.max(a);
+            if self.union {
+                m.set_offset(0);
+                size = size.max(m.get_size());
+            } else {
+                let off = (size + a - 1) / a * a;
+                m.set_offset(off);
+                size = off + m.get_size();
+            }
+        }
+
+        self.size = (size + align - 1) / align * align;    // 总大小按最大对齐补齐
+        self.align = align;
+        self.members = members;
+        self.defined = true;
+    }
+
+    pub(crate) fn get_members(&self) -> &[Var] {
+        &self.members
+    }
+
+    pub(crate) fn get_member(&self, name: &str) -> Option<Box<Var>> {
+        self.members.iter().find(|m| m.get_name() == name).cloned().map(Box::new)
+    }
+
+    pub(crate) fn is_defined(&self) -> bool {
+        self.defined
+    }
+
+    pub(crate) fn is_union(&self) -> bool {
+        self.union
+    }
+
+    pub(crate) fn get_scope_path(&self) -> Vec<i32> {
+        self.scope_path.clone()
+    }
+
+    pub(crate) fn get_size(&self) -> isize {
+        self.size
+    }
+
+    pub(crate) fn get_align(&self) -> isize {
+        self.align
+    }
+
+    pub(crate) fn get_span(&self) -> Span {
+        self.span
+    }
+}
+
+// 初始化列表中的一项：标量在对象中的偏移、类型和初值，未给出的标量为0
+pub(crate) type InitItem = (isize, TypeId, Box<Var>);
+
 #[derive(Clone)]
 pub struct Var {
     // 特殊标记
     literal: bool,           // 是否是常量
+    typedef: bool,           // 是否是typedef定义的类型名
     scope_path: Vec<i32>,    // 作用域路径
 
     // 基本声明形式
     externed: bool,          // 是否extern声明
-    var_type: Tag,           // 变量类型
+    ty: TypeId,              // 变量类型
     name: String,            // 变量名称
-    is_ptr: bool,            // 是否是指针
-    is_array: bool,          // 是否是数组
-    array_size: isize,         // 数组长度
 
     // 初始值部分
     is_left: bool,              // 是否可以作为左值
     init_data: Option<Box<Var>>,     // 缓存初值数据，延迟处置处理
+    init_list: Vec<InitItem>,        // 数组和结构体的初始化列表
     inited: bool,               // 是否初始化
     int_val: isize,
+    float_val: f64,             // 浮点数初值
     char_value: char,
     str_val: String,            // 字符串常量初值
     ptr_val: String,            // 字符指针初值
     ptr: Option<Box<Var>>,           // 变量指针类型
 
     // 附加信息
-    size: isize,               // 变量的大小
     offset: isize,             // 变量的栈帧偏移
+    span: Span,                // 定义变量(或产生临时变量)的源代码位置
 }
 
 impl Var {
     fn new() -> Self {
         Var {
             literal: false,
+            typedef: false,
             scope_path: vec![],
             externed: false,
-            var_type: Tag::ERR,
+            ty: TypeId::INT,
             name: "".to_string(),
-            is_ptr: false,
-            is_array: false,
-            array_size: 0,
             is_left: false,
             init_data: None,
+            init_list: vec![],
             inited: false,
             int_val: 0,
+            float_val: 0.0,
             char_value: '0',
             str_val: "".to_string(),
             ptr_val: "".to_string(),
             ptr: None,
-            size: 0,
             offset: 0,
+            span: Span::dummy(),
         }
     }
 
@@ -89,102 +179,114 @@ impl Var {
     fn clear(&mut self) {
         self.scope_path.push(-1);   // 默认全局作用域
         self.externed = false;
-        self.is_ptr = false;
-        self.is_array = false;
         self.is_left = true;
         self.inited = false;
         self.literal = false;
-        self.size = 0;
+        self.typedef = false;
         self.offset = 0;
         self.ptr = None;
         self.init_data = None;
+        self.init_list = vec![];
     }
 
-    // 变量，指针
-    pub(crate) fn new_pointer(sp: Vec<i32>, ext: bool, t: Tag, ptr: bool, name: String, init: Option<Box<Var>>) -> Self {
+    // 变量，数组不能作为左值
+    pub(crate) fn new_var(sp: Vec<i32>, ext: bool, t: TypeId, name: String, init: Option<Box<Var>>) -> Self {
         let mut var = Var::new();
 
         var.clear();
         var.set_scope_path(sp);
         var.set_extern(ext);
         var.set_type(t);
-        var.set_ptr(ptr);
+        var.set_left(!t.is_array());
         var.set_name(name);
         var.set_init_data(init);
 
         var
     }
 
-    // 变量，数组
-    pub(crate) fn new_array(sp: Vec<i32>, ext: bool, t: Tag, name: String, len: isize) -> Self {
+    // typedef定义的类型名，与变量共用名字空间
+    pub(crate) fn new_typedef(sp: Vec<i32>, t: TypeId, name: String, span: Span) -> Self {
         let mut var = Var::new();
 
         var.clear();
         var.set_scope_path(sp);
-        var.set_extern(ext);
         var.set_type(t);
         var.set_name(name);
-        var.set_array(len);
+        var.set_left(false);
+        var.set_span(span);
+        var.typedef = true;
+
+        var
+    }
+
+    // 枚举常量，按作用域记录名字，使用时作为整数常量
+    pub(crate) fn new_enum(sp: Vec<i32>, name: String, val: isize, span: Span) -> Self {
+        let mut var = Var::new_int(val);
+
+        var.set_scope_path(sp);
+        var.set_name(name);
+        var.set_span(span);
 
         var
     }
 
 	// 常量,不涉及作用域的变化，字符串存储在字符串表，其他常量作为初始值(使用完删除)
-    pub(crate) fn new_const(lt: &TokenType) -> Self {
+    pub(crate) fn new_const(lit: &Literal, span: Span) -> Self {
         let mut var = Var::new();
 
         var.clear();
         var.set_literal(true);
         var.set_left(false);
+        var.set_span(span);
 
-        match lt {
-            TokenType::Str(str) => {
-                var.set_type(KwChar);
-                // var.set_name(GenIR::genLb());
-                var.set_str_val(str.get_str());
-                var.set_array(var.str_val.len() as isize);
+        match lit {
+            Literal::Str(str) => {
+                var.set_type(TypeId::CHAR.array(str.len() as isize + 1));     // 包括结尾的0
+                var.set_str_val(str.clone());
             },
-            TokenType::Num(num) => {
-                var.set_type(KwInt);
+            Literal::Num(num) => {
+                // int放不下的整数按long long处理，超过long long的值由词法分析按位模式存为负数
+                var.set_type(if *num < 0 { TypeId::ULLONG } else if i32::try_from(*num).is_ok() { TypeId::INT } else { TypeId::LLONG });
                 var.set_name("<int>".to_string());
-                var.set_int_val(num.get_val());
+                var.set_int_val(*num);
             },
-            TokenType::Char(c) => {
-                var.set_type(KwChar);
+            Literal::Char(c) => {
+                var.set_type(TypeId::CHAR);
                 var.set_name("<char>".to_string());
-                var.set_int_val(0);
-                var.set_char_value(c.get_ch());
+                var.set_int_val(*c as isize);
+                var.set_char_value(*c);
+            },
+            Literal::Real(r) => {
+                var.set_type(TypeId::DOUBLE);
+                var.set_name("<double>".to_string());
+                var.set_float_val(*r);
             },
-            _ => {
-
-            }
         }
 
         var
     }
 
-    // 拷贝一个临时变量
+    // 拷贝一个临时变量，数组退化为指针
     pub(crate) fn new_copy_temp(sp: Vec<i32>, v: Box<Var>) -> Self {
         let mut var = Var::new();
 
         var.clear();
         var.set_scope_path(sp);
-        var.set_type(v.get_type());
-        var.set_ptr(v.get_ptr() || v.get_array());
+        var.set_type(v.get_type().decay());
         var.set_name("".to_string());
         var.set_left(false);
+        var.set_span(v.get_span());
 
         var
     }
 
     // 临时变量
-    pub(crate) fn new_temp(sp: Vec<i32>, t: Tag, ptr: bool) -> Self {
+    pub(crate) fn new_temp(sp: Vec<i32>, t: TypeId) -> Self {
         let mut var = Var::new();
 
         var.clear();
         var.set_scope_path(sp);
         var.set_type(t);
-        var.set_ptr(ptr);
         var.set_name("".to_string());
         var.set_left(false);
 
@@ -200,8 +302,7 @@ impl Var {
         var.set_left(false);
         var.set_int_val(0);
         var.set_literal(false);
-        var.set_type(KwVoid);
-        var.set_ptr(true);
+        var.set_type(TypeId::VOID);
 
         var
     }
@@ -214,63 +315,82 @@ impl Var {
         var.set_name("<int>".to_string());
         var.set_left(false);
         var.set_literal(true);
-        var.set_type(KwInt);
+        var.set_type(TypeId::INT);
         var.set_int_val(val);
 
         var
     }
 
+    // 浮点常量，默认为double类型
+    pub(crate) fn new_float(val: f64) -> Self {
+        let mut var = Var::new();
+
+        var.clear();
+        var.set_name("<double>".to_string());
+        var.set_left(false);
+        var.set_literal(true);
+        var.set_type(TypeId::DOUBLE);
+        var.set_float_val(val);
+
+        var
+    }
+
     pub(crate) fn get_void() -> Option<Box<Var>>{
-        VOID_VAR
+        Some(Box::new(Var::new_void()))
     }
 
     /*
 	    获取true变量
     */
     pub(crate) fn get_true() -> Option<Box<Var>> {
-        ONE
+        Some(Box::new(Var::new_int(1)))
     }
 
     /*
-	    获取步长变量
+	    获取步长变量，指针和数组的步长是所指类型的大小
     */
     pub(crate) fn get_step(v: Box<Var>) -> Option<Box<Var>> {
-        return if v.is_base() {
-            ONE
-        } else if v.get_type() == KwChar {
-            ONE
-        } else if v.get_type() == KwInt {
-            FOUR
-        } else {
-            None
+        if v.is_base() {
+            return Some(Box::new(Var::new_int(1)));
         }
+        v.get_type().pointee().filter(|t| t.size() > 0).map(|t| Box::new(Var::new_int(t.size())))
     }
 
     /*
-	    是基本类型
+	    是基本类型，即不是指针、数组或void
     */
     pub(crate) fn is_base(&self) -> bool {
-        !self.is_ptr && !self.is_array
+        !self.ty.is_pointer() && !self.ty.is_array() && !self.is_void()
     }
 
     // 是void
     pub(crate) fn is_void(&self) -> bool {
-        self.get_type() == KwVoid
+        self.ty == TypeId::VOID
     }
 
-    // 是引用类型
+    // 是引用类型，即*p产生的临时变量
     pub(crate) fn is_ref(&self) -> bool {
-        !self.is_ptr
+        self.ptr.is_some()
+    }
+
+    // 是结构体或联合对象
+    pub(crate) fn is_record(&self) -> bool {
+        self.ty.is_record()
     }
 
     // 是字符类型
     pub(crate) fn is_char(&self) -> bool {
-        self.var_type == KwChar
+        self.ty == TypeId::CHAR
     }
 
-    // 是否是常量
-    pub(crate) fn not_const(&self) -> bool {
-        !self.literal
+    // 是空指针常量
+    pub(crate) fn is_null(&self) -> bool {
+        self.literal && self.ty == TypeId::INT && self.int_val == 0
+    }
+
+    // 能否用val给该变量赋值，指针还可以用空指针常量赋值
+    pub(crate) fn can_assign(&self, val: &Var) -> bool {
+        types::assignable(self.ty, val.ty) || (self.ty.decay().is_pointer() && val.is_null())
     }
 
     // 获取常量值
@@ -278,6 +398,11 @@ impl Var {
         self.int_val
     }
 
+    // 获取常量的浮点值，整数常量转换为浮点数
+    pub(crate) fn get_float_val(&self) -> f64 {
+        if self.ty.is_float() { self.float_val } else { self.int_val as f64 }
+    }
+
     // 是否初始化
     pub(crate) fn is_un_init(&self) -> bool {
         !self.inited
@@ -287,6 +412,63 @@ impl Var {
     pub(crate) fn get_ptr_val(&self) -> String {
         self.ptr_val.clone()
     }
+
+    // 记录常量初值，字符串记录其标签，指针用空指针常量初始化时记录0
+    pub(crate) fn set_init_val(&mut self, init: &Var) {
+        if init.get_array() {
+            self.ptr_val = init.name.clone();
+        } else if !self.is_base() {
+            self.ptr_val = init.int_val.to_string();
+        } else if self.ty.is_float() {
+            self.float_val = init.get_float_val();
+        } else {
+            self.int_val = self.ty.cast(init.int_val);
+        }
+        self.inited = true;
+    }
+
+    pub(crate) fn get_str_val(&self) -> String {
+        self.str_val.clone()
+    }
+
+    // 变量的大小，extern声明不分配空间
+    pub(crate) fn get_size(&self) -> isize {
+        if self.externed { 0 } else { self.ty.size() }
+    }
+
+    // 作为参数时占用的栈空间，long long和double占两个单元
+    pub(crate) fn get_para_size(&self) -> isize {
+        if self.ty.is_arith() && self.ty.size() == 8 { 2 * PARA_SIZE } else { PARA_SIZE }
+    }
+
+    // 对齐字节数，char及char数组按字节对齐，结构体按成员的最大对齐，其他按4字节对齐
+    pub(crate) fn get_align(&self) -> isize {
+        self.ty.align()
+    }
+
+    // 类型名称，如int、char*、int[4]、struct node*
+    pub(crate) fn type_name(&self) -> String {
+        self.ty.name()
+    }
+
+    // 中间代码中的显示形式，常量显示其值
+    pub(crate) fn to_ir(&self) -> String {
+        if self.literal {
+            if self.get_array() {
+                format!("{:?}", self.str_val)
+            } else if self.is_char() {
+                format!("'{}'", self.char_value.escape_default())
+            } else if self.ty.is_float() {
+                format!("{:?}", self.float_val)
+            } else {
+                self.int_val.to_string()
+            }
+        } else if self.name.is_empty() {
+            "<tmp>".to_string()
+        } else {
+            self.name.clone()
+        }
+    }
 }
 
 impl Var {
@@ -299,6 +481,12 @@ impl Var {
         self.int_val = int_val;
     }
 
+    // 浮点数同时记录截断后的整数值，用于给整数变量初始化
+    pub(crate) fn set_float_val(&mut self, float_val: f64) {
+        self.float_val = float_val;
+        self.int_val = float_val as isize;
+    }
+
     pub(crate) fn set_str_val(&mut self, str_val: String) {
         self.str_val = str_val;
     }
@@ -311,6 +499,10 @@ impl Var {
         self.literal
     }
 
+    pub(crate) fn is_typedef(&self) -> bool {
+        self.typedef
+    }
+
     pub(crate) fn set_left(&mut self, left: bool) {
         self.is_left = left;
     }
@@ -335,44 +527,32 @@ impl Var {
         self.init_data.clone()
     }
 
+    pub(crate) fn set_init_list(&mut self, list: Vec<InitItem>) {
+        self.init_list = list
+    }
+
+    pub(crate) fn get_init_list(&self) -> Vec<InitItem> {
+        self.init_list.clone()
+    }
+
     pub(crate) fn set_extern(&mut self, ext: bool) {
         self.externed = ext;
-        self.size = 0;
     }
 
     pub(crate) fn get_extern(&self) -> bool {
         self.externed
     }
 
-    pub(crate) fn set_type(&mut self, t: Tag) {
-        self.var_type = t;
-        if self.var_type == KwVoid {
-            sem_error(VoidVar as usize, "");     // 不允许void变量
-            self.var_type = KwInt;    // 默认为int
-        }
-
-        if !self.externed && self.var_type == KwInt {
-            self.size = 4;
-        } else if !self.externed && self.var_type == KwChar {
-            self.size = 1;
-        }
-    }
-
-    pub(crate) fn get_type(&self) -> Tag {
-        self.var_type
+    pub(crate) fn set_type(&mut self, t: TypeId) {
+        self.ty = t;
     }
 
-    pub(crate) fn set_ptr(&mut self, ptr: bool) {
-        if ptr {
-            self.is_ptr = true;
-            if !self.externed {
-                self.size = 4;
-            }
-        }
+    pub(crate) fn get_type(&self) -> TypeId {
+        self.ty
     }
 
     pub(crate) fn get_ptr(&self) -> bool {
-        self.is_ptr
+        self.ty.is_pointer()
     }
 
     pub(crate) fn set_name(&mut self, n: String) {
@@ -383,21 +563,8 @@ impl Var {
         self.name.clone()
     }
 
-    pub(crate) fn set_array(&mut self, len: isize) {
-        if len <= 0 {
-            sem_error(SemError::ArrayLenInvalid as usize, &self.name);
-        } else {
-            self.is_array = true;
-            self.is_left = false;   // 数组不能作为左值
-            self.array_size = len;
-            if !self.externed {
-                self.size *= len;
-            }
-        }
-    }
-
     pub(crate) fn get_array(&self) -> bool {
-        self.is_array
+        self.ty.is_array()
     }
 
     pub(crate) fn set_offset(&mut self, off: isize) {
@@ -417,48 +584,44 @@ impl Var {
     pub(crate) fn get_pointer(&self) -> Option<Box<Var>> {
         self.ptr.clone()
     }
+
+    pub(crate) fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+
+    pub(crate) fn get_span(&self) -> Span {
+        self.span
+    }
 }
 
 #[derive(Clone)]
 pub struct Fun {
     externed: bool,                          // 声明或定义
-    return_type: Tag,                        // 返回类型
+    return_type: TypeId,                     // 返回类型
     name: String,                            // 函数名称
     para_var: Vec<Box<Var>>,                 // 参数列表
 
     // 临时变量地址分配
     max_depth: i32,                          // 栈的最大深度，初始0,标识函数栈分配的最大空间
     cur_esp:   i32,                          // 当前栈指针位置，初始化为0，即ebp存储点
-    relocated: bool,                         // 栈帧重定位标记
 
     // 作用域管理
     scope_esp: Vec<i32>,                     // 作用域栈指针位置
     inter_code: Option<InterCode>,           // 中间代码
-    return_point: Option<Box<InterInst>>     // 返回点
+    return_point: Option<Box<InterInst>>,    // 返回点
+
+    span: Span,                              // 函数名在源代码中的位置
 }
 
 impl Fun {
-    // 声明定义匹配
+    // 声明定义匹配，函数类型必须相同
     pub(crate) fn match_fun(&self, f: Box<Fun>) -> bool {
-        if self.name != f.get_name() {
-            return false;
-        }
-
-        if self.para_var.len() != f.para_var.len() {
-            return false;
-        }
-
-        for i in 0..self.para_var.len() {
-            if self.para_var[i].get_type() != f.para_var[i].get_type() {
-                return false;
-            }
-        }
-
-        if self.return_type != f.return_type {
-            return false;
-        }
+        self.name == f.get_name() && self.get_fun_type() == f.get_fun_type()
+    }
 
-        true
+    // 函数类型
+    pub(crate) fn get_fun_type(&self) -> TypeId {
+        TypeId::function(self.return_type, self.para_var.iter().map(|p| p.get_type()).collect())
     }
 
     // 行参实惨匹配
@@ -469,7 +632,7 @@ impl Fun {
 
         let len = self.para_var.len();
         for i in 0..len {
-            if self.para_var[i].get_type() != args[i].get_type() {
+            if !self.para_var[i].can_assign(&args[i]) {
                 return false;
             }
         }
@@ -486,6 +649,16 @@ impl Fun {
         self.scope_esp.push(0);
     }
 
+    // 为局部变量分配栈空间，按4字节对齐，引用只需要保存地址的空间
+    pub(crate) fn locate(&mut self, var: &mut Var) {
+        let size = if var.is_ref() { 4 } else { (var.get_size() + 3) / 4 * 4 };
+        if let Some(esp) = self.scope_esp.last_mut() {
+            *esp += size as i32;
+        }
+        self.cur_esp += size as i32;
+        var.set_offset(-(self.cur_esp as isize));
+    }
+
     // 离开当前作用域
     pub(crate) fn leave_scope(&mut self) {
         self.max_depth = if self.cur_esp > self.max_depth { self.cur_esp } else { self.max_depth }; // 计算最大深度
@@ -496,14 +669,8 @@ impl Fun {
 }
 
 impl Fun {
-    pub(crate) fn new(ext: bool, t: Tag, n: String, para_list: Vec<Box<Var>>) -> Self {
-        let mut para_list = para_list;
-        let mut arg_off = 4;
-        for i in 0..para_list.len() {
-            para_list[i].set_offset(arg_off);
-            arg_off += 4;
-        }
-
+    // 参数的栈帧偏移由语法分析在添加参数时设置
+    pub(crate) fn new(ext: bool, t: TypeId, n: String, para_list: Vec<Box<Var>>) -> Self {
         Fun {
             externed: ext,
             return_type: t,
@@ -511,10 +678,10 @@ impl Fun {
             para_var: para_list,
             max_depth: STACK_BASE,
             cur_esp: STACK_BASE,
-            relocated: false,
             scope_esp: vec![0],
-            inter_code: None,
+            inter_code: Some(InterCode::new()),
             return_point: None,
+            span: Span::dummy(),
         }
     }
 
@@ -522,10 +689,6 @@ impl Fun {
         self.name.clone()
     }
 
-    pub(crate) fn get_return_type(&self) -> Tag {
-        self.return_type
-    }
-
     pub(crate) fn set_extern(&mut self, ext: bool) {
         self.externed = ext;
     }
@@ -534,10 +697,15 @@ impl Fun {
         self.externed
     }
 
-    pub(crate) fn get_type(&self) -> Tag {
+    pub(crate) fn get_type(&self) -> TypeId {
         self.return_type
     }
 
+    // 参数占用的栈空间，调用后由调用者释放
+    pub(crate) fn get_para_size(&self) -> isize {
+        self.para_var.iter().map(|p| p.get_para_size()).sum()
+    }
+
     // 获取参数列表，用于为参数生成加载代码
     pub(crate) fn get_para_var(&self) -> Vec<Box<Var>> {
         self.para_var.clone()
@@ -548,9 +716,12 @@ impl Fun {
         self.max_depth
     }
 
-    // 设置最大栈帧深度
-    pub(crate) fn set_max_depth(&mut self, max_depth: i32) {
-        self.max_depth = max_depth;
+    pub(crate) fn set_span(&mut self, span: Span) {
+        self.span = span;
+    }
+
+    pub(crate) fn get_span(&self) -> Span {
+        self.span
     }
 }
 
@@ -571,14 +742,33 @@ impl Fun {
     }
 
     // 输出中间代码
-    pub(crate) fn print_inter_code(&self) {
+    pub(crate) fn print_ir(&self, out: &mut dyn Write) -> io::Result<()> {
         if self.externed {
-            return;
+            return Ok(());
         }
-        println!("-------------<{}>Start--------------", self.name.clone());
-        if let Some(inter_code) = self.inter_code.clone() {
-            inter_code.print_code();
+        writeln!(out, "{}:", self.name)?;
+        if let Some(inter_code) = &self.inter_code {
+            inter_code.print_ir(out)?;
         }
-        println!("-------------<{}>End--------------", self.name.clone());
+
+        Ok(())
+    }
+
+    // 函数体用到的运行库函数
+    pub(crate) fn helpers(&self) -> Vec<&'static str> {
+        self.inter_code.as_ref().map_or_else(Vec::new, |code| code.helpers())
+    }
+
+    // 输出汇编代码
+    pub(crate) fn print_code(&self, out: &mut dyn Write) -> io::Result<()> {
+        if self.externed {
+            return Ok(());
+        }
+        writeln!(out, "{}:", symbol(&self.name))?;
+        if let Some(inter_code) = &self.inter_code {
+            inter_code.print_code(out, self)?;
+        }
+
+        Ok(())
     }
 }
@@ -1,52 +1,56 @@
 use std::collections::HashMap;
-use crate::common::SemError::{ExternFunDef, FunCallErr, FunDecErr, FunReDef, FunUnDec, VarReDef, VarUnDec};
-use crate::gen_ir::GenIR;
-use crate::intercode::InterInst;
-use crate::symbol::{Fun, Var, sem_error};
-
-pub(crate) const VOID_VAR: Option<Box<Var>> = None;
-pub(crate) const ZERO: Option<Box<Var>> = None;
-pub(crate) const ONE: Option<Box<Var>> = None;
-pub(crate) const FOUR: Option<Box<Var>> = None;
+use std::io::{self, Write};
+use crate::catalog::{Catalog, Note};
+use crate::common::SemError::{self, ExternFunDef, FunCallErr, FunDecErr, FunReDef, FunUnDec, RecordReDef, RecordTagErr, VarReDef, VarUnDec};
+use crate::diagnostic::DiagnosticSink;
+use crate::intercode::{InterInst, ptr_data, symbol};
+use crate::span::Span;
+use crate::symbol::{Fun, Record, Var, sem_diagnostic, sem_error};
+use crate::types::{self, TypeId};
 
 #[derive(Clone)]
 pub struct SymTab {
     // 声明记录顺序
     var_list: Vec<String>,  // 记录变量的添加顺序
     fun_list: Vec<String>,  // 记录函数的添加顺序
+    str_list: Vec<String>,  // 记录字符串常量的添加顺序
 
     // 内部数据结构
     var_tab: HashMap<String, Vec<Box<Var>>>,
-    str_tab: HashMap<String, Box<Var>>,
+    str_tab: HashMap<String, Box<Var>>,     // 以字符串内容为键，相同内容共用一个常量
     fun_tab: HashMap<String, Box<Fun>>,
+    record_tab: HashMap<String, Vec<usize>>,    // 标签名到结构体和联合的编号，匿名类型不记录
 
     // 辅助分析数据记录
     cur_fun: Option<Box<Fun>>,          // 当前分析的函数
     scope_id: i32,                      // 作用域的唯一编号
     scope_path: Vec<i32>,               // 动态记录作用域的路径，全局为0,0 1 2-第一个函数的第一个局部块
-    ir: Option<Box<GenIR>>,             // 中间代码生成器
+    tmp_id: i32,                        // 临时变量的编号
+    diag: DiagnosticSink,               // 诊断信息收集器
 }
 
 impl SymTab {
 
     // 初始化符号表
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(diag: DiagnosticSink) -> Self {
         SymTab {
             var_list: vec![],
             fun_list: vec![],
+            str_list: vec![],
             var_tab: Default::default(),
             str_tab: Default::default(),
             fun_tab: Default::default(),
+            record_tab: Default::default(),
             cur_fun: None,
             scope_id: 0,
             scope_path: vec![0],
-            ir: None,
+            tmp_id: 0,
+            diag,
         }
     }
 
-    // 设置中间代码生成器
-    pub(crate) fn set_ir(&mut self, ir: Option<Box<GenIR>>) {
-        self.ir = ir;
+    pub(crate) fn get_sink(&self) -> DiagnosticSink {
+        self.diag.clone()
     }
 
     pub(crate) fn enter(&mut self) {
@@ -68,44 +72,95 @@ impl SymTab {
         self.scope_path.clone()
     }
 
-    // 添加一个变量到符号表
-    pub(crate) fn add_var(&mut self, var: Box<Var>) {
+    // 添加一个变量到符号表，函数内的变量分配栈空间，返回添加后的变量
+    pub(crate) fn add_var(&mut self, mut var: Box<Var>) -> Box<Var> {
+        if var.get_literal() {      // 常量不记录
+            return var;
+        }
+
+        if let Some(fun) = self.cur_fun.as_mut() {
+            if !var.get_extern() {
+                fun.locate(&mut var);
+            }
+        }
+
+        if var.get_name().is_empty() {      // 临时变量只需要命名
+            self.tmp_id += 1;
+            var.set_name(format!("%t{}", self.tmp_id));
+            return var;
+        }
+
+        self.insert_var(var)
+    }
+
+    // 记录枚举常量和typedef类型名，不分配空间
+    pub(crate) fn add_name(&mut self, var: Box<Var>) -> Box<Var> {
+        self.insert_var(var)
+    }
+
+    // 按作用域登记名字，同一作用域内重名报错
+    fn insert_var(&mut self, var: Box<Var>) -> Box<Var> {
         let name = var.get_name();
         if self.var_tab.contains_key(&name) {
             // 判断同名变量是否不在一个作用域
             let var_list = self.var_tab.get_mut(&name).unwrap();
             let mut is_exit = false;
+            let mut last_span = Span::dummy();
 
             for v in var_list.clone() {
                 if var.get_scope_path() == v.get_scope_path() {
                     is_exit = true;
+                    last_span = v.get_span();
 
                     break;
                 }
             }
 
-            if !is_exit || name.get(..1).unwrap() == "<" {
-                var_list.push(var);
+            if !is_exit || name.starts_with('<') {
+                var_list.push(var.clone());
             } else {
-                sem_error(VarReDef as usize, &name);
+                self.sem_error_at(VarReDef, &name, var.get_span(), last_span, Note::PreviousDefinition);
             }
         } else {
             self.var_tab.insert(name.clone(), vec![]);
             let v = self.var_tab.get_mut(&name).unwrap();
-            v.push(var);
+            v.push(var.clone());
             self.var_list.push(name.clone());
         }
+
+        var
+    }
+
+    // 添加字符串常量，内容相同的字符串只保留一份
+    pub(crate) fn add_str(&mut self, mut v: Box<Var>) -> Box<Var> {
+        let key = v.get_str_val();
+        if let Some(s) = self.str_tab.get(&key) {
+            let mut s = s.clone();
+            s.set_span(v.get_span());   // 记录使用位置
+            return s;
+        }
+
+        v.set_name(format!("__str{}", self.str_list.len()));
+        self.str_tab.insert(key.clone(), v.clone());
+        self.str_list.push(key);
+        v
     }
 
-    pub(crate) fn add_str(&mut self, v: Box<Var>) {
-        self.str_tab.insert(v.get_name(), v);
+    pub(crate) fn get_var(&self, name: String, span: Span) -> Option<Box<Var>> {
+        let select = self.find_var(&name);
+
+        if select.is_none() {
+            sem_error(&self.diag, VarUnDec, &name, span);    // 变量未声明
+        }
+
+        select
     }
 
-    pub(crate) fn get_var(&self, name: String) -> Option<Box<Var>> {
+    // 查找可见的同名变量，找不到时不报错
+    pub(crate) fn find_var(&self, name: &str) -> Option<Box<Var>> {
         let mut select: Option<Box<Var>> = None;
 
-        if self.var_tab.contains_key(&name) {
-            let var_list = self.var_tab.get(&name).unwrap();
+        if let Some(var_list) = self.var_tab.get(name) {
             let path_len = self.scope_path.len();
             let mut max_len = 0;
 
@@ -120,13 +175,96 @@ impl SymTab {
             }
         }
 
-        if let None = select {
-            sem_error(VarUnDec as usize, &name);    // 变量未声明
+        select
+    }
+
+    // 查找可见的同名标签，规则与变量查找相同
+    fn find_record(&self, name: &str) -> Option<usize> {
+        let path_len = self.scope_path.len();
+        let mut max_len = 0;
+        let mut select = None;
+
+        for &id in self.record_tab.get(name).into_iter().flatten() {
+            let path = types::with_record(id, |r| r.get_scope_path());
+            let len = path.len();
+            if len <= path_len && path[len - 1] == self.scope_path[len - 1] && len > max_len {
+                max_len = len;
+                select = Some(id);
+            }
         }
 
         select
     }
 
+    // 在当前作用域查找同名标签
+    fn find_local_record(&self, name: &str) -> Option<usize> {
+        self.record_tab.get(name)?.iter().copied().find(|&id| types::with_record(id, |r| r.get_scope_path() == self.scope_path))
+    }
+
+    // 新建一个未定义的类型
+    fn new_record(&mut self, union: bool, name: &str, span: Span) -> usize {
+        let id = types::new_record(Record::new(union, name.to_string(), self.scope_path.clone(), span));
+        if !name.is_empty() {
+            self.record_tab.entry(name.to_string()).or_default().push(id);
+        }
+        id
+    }
+
+    // 检查标签的种类是否与之前的声明一致
+    fn check_record_tag(&self, id: usize, union: bool, name: &str, span: Span) {
+        let (last_union, last) = types::with_record(id, |r| (r.is_union(), r.get_span()));
+        if last_union != union {
+            self.sem_error_at(RecordTagErr, name, span, last, Note::PreviousDeclaration);
+        }
+    }
+
+    // 使用一个标签，如struct S x，没有可见的声明时在当前作用域声明一个不完整类型
+    pub(crate) fn use_record(&mut self, union: bool, name: &str, span: Span) -> usize {
+        match self.find_record(name) {
+            Some(id) => {
+                self.check_record_tag(id, union, name, span);
+                id
+            },
+            None => self.new_record(union, name, span),
+        }
+    }
+
+    // 声明一个标签，如struct S;，只在当前作用域查找
+    pub(crate) fn dec_record(&mut self, union: bool, name: &str, span: Span) -> usize {
+        match self.find_local_record(name) {
+            Some(id) => {
+                self.check_record_tag(id, union, name, span);
+                id
+            },
+            None => self.new_record(union, name, span),
+        }
+    }
+
+    // 开始定义一个类型，当前作用域已有声明时补全该声明，匿名类型总是新建
+    pub(crate) fn def_record(&mut self, union: bool, name: &str, span: Span) -> usize {
+        if name.is_empty() {
+            return self.new_record(union, name, span);
+        }
+
+        match self.find_local_record(name) {
+            Some(id) if types::with_record(id, |r| r.is_defined()) => {
+                let last = types::with_record(id, |r| r.get_span());
+                self.sem_error_at(RecordReDef, name, span, last, Note::PreviousDefinition);
+                self.new_record(union, "", span)    // 重定义的类型仍然分析成员，但不能再被引用
+            },
+            Some(id) => {
+                self.check_record_tag(id, union, name, span);
+                id
+            },
+            None => self.new_record(union, name, span),
+        }
+    }
+
+    // 结束定义，计算布局
+    pub(crate) fn end_def_record(&mut self, id: usize, members: Vec<Var>) {
+        types::define_record(id, members);
+    }
+
     // 声明一个函数
     pub(crate) fn dec_fun(&mut self, mut fun: Box<Fun>) {
         fun.set_extern(true);
@@ -137,7 +275,7 @@ impl SymTab {
             let last = self.fun_tab.get(&fun.get_name()).unwrap().clone();
             if !last.match_fun(fun.clone()) {
                 // 函数声明与定义不匹配
-                sem_error(FunDecErr as usize, &fun.get_name());
+                self.sem_error_at(FunDecErr, &fun.get_name(), fun.get_span(), last.get_span(), Note::PreviousDeclaration);
             }
 
         } else {
@@ -149,11 +287,11 @@ impl SymTab {
 
     // 定义一个函数
     pub(crate) fn def_fun(&mut self, mut fun: Box<Fun>) {
-        let mut cur_fun = fun.clone();
         if fun.get_extern() {   // extern不允许出现在定义
-            sem_error(ExternFunDef as usize, &fun.get_name());
+            sem_error(&self.diag, ExternFunDef, &fun.get_name(), fun.get_span());
             fun.set_extern(false);
         }
+        let mut cur_fun = fun.clone();
 
         // 没有该名字的函数
         if !self.fun_tab.contains_key(&fun.get_name()) {
@@ -166,39 +304,47 @@ impl SymTab {
             if last.get_extern() {
                 // 之前是声明
                 if !last.match_fun(fun.clone()) {   // 匹配的声明
-                    sem_error(FunDecErr as usize, &fun.get_name());
+                    self.sem_error_at(FunDecErr, &fun.get_name(), fun.get_span(), last.get_span(), Note::PreviousDeclaration);
                 }
                 last.define(fun.clone());
             } else {
                 // 重定义
-                sem_error(FunReDef as usize, &fun.get_name());
+                self.sem_error_at(FunReDef, &fun.get_name(), fun.get_span(), last.get_span(), Note::PreviousDefinition);
             }
             cur_fun = last;
         }
 
-        self.cur_fun = Some(cur_fun.clone());
-        // 产生函数入口
-        self.ir.clone().unwrap().gen_fun_head(cur_fun.clone());
+        self.cur_fun = Some(cur_fun);
     }
 
-    // 结束定义一个函数
+    // 结束定义一个函数，保存生成的代码
     pub(crate) fn end_def_fun(&mut self) {
-        // 产生函数出口
-        self.cur_fun = None;
+        if let Some(fun) = self.cur_fun.take() {
+            self.fun_tab.insert(fun.get_name(), fun);
+        }
     }
 
-    pub(crate) fn get_fun(&self, name: String, args: Vec<Box<Var>>) -> Option<Box<Fun>> {
+    pub(crate) fn get_fun(&self, name: String, args: Vec<Box<Var>>, span: Span) -> Option<Box<Fun>> {
         if self.fun_tab.contains_key(&name) {
             let last = self.fun_tab.get(&name).unwrap().clone();
             if !last.match_args(args) {
-                sem_error(FunCallErr as usize, &name);
+                self.sem_error_at(FunCallErr, &name, span, last.get_span(), Note::FunctionDeclared);
                 return None;
             }
             return Some(last);
         }
-        sem_error(FunUnDec as usize, &name);
+        sem_error(&self.diag, FunUnDec, &name, span);
         None
     }
+
+    // 报告语义错误，并标注之前声明的位置
+    fn sem_error_at(&self, code: SemError, name: &str, span: Span, last: Span, note: Note) {
+        let mut diag = sem_diagnostic(code, name, span);
+        if !last.is_dummy() {
+            diag = diag.with_label(last, Catalog::current().note(note).to_string());
+        }
+        self.diag.emit(diag);
+    }
 }
 
 impl SymTab {
@@ -212,10 +358,312 @@ impl SymTab {
         self.cur_fun.clone()
     }
 
+    pub(crate) fn get_cur_fun_mut(&mut self) -> Option<&mut Box<Fun>> {
+        self.cur_fun.as_mut()
+    }
+
     // 输出中间代码
-    pub(crate) fn print_inter_code(&self) {
-        for i in 0..self.fun_list.len() {
-            self.fun_tab[&self.fun_list[i].clone()].print_inter_code();
+    pub(crate) fn print_ir(&self, out: &mut dyn Write) -> io::Result<()> {
+        for name in self.fun_list.iter() {
+            self.fun_tab[name].print_ir(out)?;
+        }
+
+        Ok(())
+    }
+
+    // 全局变量，按声明顺序
+    fn global_vars(&self) -> Vec<&Var> {
+        self.var_list.iter()
+            .flat_map(|name| self.var_tab[name].iter().map(|v| v.as_ref()))
+            .filter(|v| v.get_scope_path().len() == 1 && !v.get_literal() && !v.is_typedef())
+            .collect()
+    }
+
+    // 输出全局变量和字符串常量的存储空间
+    fn gen_data(&self, globals: &[&Var], out: &mut dyn Write) -> io::Result<()> {
+        let defined: Vec<&Var> = globals.iter().copied().filter(|v| !v.get_extern()).collect();
+
+        // 常量初始化的全局变量
+        writeln!(out, "section .data")?;
+        for var in defined.iter().filter(|v| has_const_init(v)) {
+            if var.get_align() > 1 {
+                writeln!(out, "align {}", var.get_align())?;
+            }
+            if !var.get_init_list().is_empty() {
+                writeln!(out, "{}:", symbol(&var.get_name()))?;
+                init_data(var, out)?;
+            } else if var.is_base() {
+                writeln!(out, "{}: {} {}", symbol(&var.get_name()), data_width(var.get_type()), const_data(var.get_type(), var))?;
+            } else {
+                writeln!(out, "{}: dd {}", symbol(&var.get_name()), ptr_data(var))?;
+            }
+        }
+
+        // 零初始化的全局变量
+        writeln!(out, "section .bss")?;
+        for var in defined.iter().filter(|v| !has_const_init(v)) {
+            if var.get_align() > 1 {
+                writeln!(out, "alignb {}", var.get_align())?;
+            }
+            writeln!(out, "{}: resb {}", symbol(&var.get_name()), var.get_size())?;
+        }
+
+        // 字符串常量
+        writeln!(out, "section .rodata")?;
+        for key in self.str_list.iter() {
+            let s = &self.str_tab[key];
+            writeln!(out, "{}: db {}", symbol(&s.get_name()), nasm_str(&s.get_str_val()))?;
         }
+
+        Ok(())
+    }
+
+    // 输出完整的NASM汇编模块
+    pub(crate) fn gen_asm(&self, out: &mut dyn Write) -> io::Result<()> {
+        let globals = self.global_vars();
+
+        // 外部符号: 只声明未定义的函数和extern变量
+        for name in self.fun_list.iter() {
+            if self.fun_tab[name].get_extern() {
+                writeln!(out, "extern {}", symbol(name))?;
+            }
+        }
+        for var in globals.iter().filter(|v| v.get_extern()) {
+            writeln!(out, "extern {}", symbol(&var.get_name()))?;
+        }
+        let mut helpers: Vec<&str> = self.fun_list.iter().flat_map(|name| self.fun_tab[name].helpers()).collect();
+        helpers.sort();
+        helpers.dedup();
+        for name in helpers {
+            writeln!(out, "extern {}", name)?;     // long long除法的运行库函数
+        }
+
+        self.gen_data(&globals, out)?;
+
+        // 函数代码
+        writeln!(out, "section .text")?;
+        for name in self.fun_list.iter() {
+            if !self.fun_tab[name].get_extern() {
+                writeln!(out, "global {}", symbol(name))?;
+            }
+        }
+        for name in self.fun_list.iter() {
+            self.fun_tab[name].print_code(out)?;
+        }
+
+        Ok(())
+    }
+}
+
+// 是否有常量初值，只有常量初值的全局变量放在.data段
+fn has_const_init(var: &Var) -> bool {
+    !var.get_init_list().is_empty() || !var.is_un_init() && var.get_init_data().is_some_and(|init| init.get_literal())
+}
+
+// 按偏移输出初始化列表，字符串初值记录其标签，没有初值的部分填0
+fn init_data(var: &Var, out: &mut dyn Write) -> io::Result<()> {
+    let mut list = var.get_init_list();
+    list.sort_by_key(|(offset, ..)| *offset);
+
+    let mut pos = 0;
+    for (offset, t, val) in list.iter() {
+        if *offset > pos {
+            writeln!(out, "times {} db 0", offset - pos)?;
+        }
+        let text = if val.get_array() { symbol(&val.get_name()) } else { const_data(*t, val) };
+        writeln!(out, "{} {}", data_width(*t), text)?;
+        pos = offset + t.size();
+    }
+    if var.get_size() > pos {
+        writeln!(out, "times {} db 0", var.get_size() - pos)?;
+    }
+
+    Ok(())
+}
+
+// 按类型的大小选择数据定义伪指令
+fn data_width(t: TypeId) -> &'static str {
+    match t.size() {
+        1 => "db",
+        2 => "dw",
+        8 => "dq",
+        _ => "dd",
+    }
+}
+
+// 常量按类型的表示，浮点数输出其二进制位，保证与编译时的值完全相同
+fn const_data(t: TypeId, val: &Var) -> String {
+    if t.is_float() {
+        format!("0x{:x}", t.float_bits(val.get_float_val()))
+    } else {
+        t.cast(val.get_val()).to_string()
+    }
+}
+
+// 字符串的NASM表示，可打印字符放在引号内，其他字节用数值表示，以0结尾
+fn nasm_str(s: &str) -> String {
+    let mut parts = vec![];
+    let mut text = String::new();
+    for b in s.bytes() {
+        if (0x20..0x7f).contains(&b) && b != b'"' {
+            text.push(b as char);
+        } else {
+            if !text.is_empty() {
+                parts.push(format!("\"{}\"", text));
+                text.clear();
+            }
+            parts.push(b.to_string());
+        }
+    }
+    if !text.is_empty() {
+        parts.push(format!("\"{}\"", text));
+    }
+    parts.push("0".to_string());
+
+    parts.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostic::DiagnosticSink;
+    use crate::lower;
+    use crate::symtab::nasm_str;
+
+    fn compile(source: &str) -> String {
+        let diag = DiagnosticSink::new();
+        let mut compiled = lower::compile(source, &diag);
+        assert!(diag.diagnostics().is_empty());
+
+        let mut out = vec![];
+        compiled.ir.get_sym_tab().gen_asm(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_gen_asm() {
+        let asm = compile("extern int puts(char *s);\nint g = 3;\nchar buf[8];\n\
+                           int main() { int x = g; puts(\"hi\"); return x; }\n");
+        let lines: Vec<&str> = asm.lines().collect();
+
+        assert_eq!(lines[0], "extern $puts");
+        let data = lines.iter().position(|l| *l == "section .data").unwrap();
+        let bss = lines.iter().position(|l| *l == "section .bss").unwrap();
+        let rodata = lines.iter().position(|l| *l == "section .rodata").unwrap();
+        let text = lines.iter().position(|l| *l == "section .text").unwrap();
+        assert!(data < bss && bss < rodata && rodata < text);
+        assert!(lines[data..bss].contains(&"$g: dd 3"));
+        assert!(lines[data..bss].contains(&"align 4"));
+        assert!(lines[bss..text].contains(&"$buf: resb 8"));
+        assert!(lines[rodata..text].contains(&"$__str0: db \"hi\", 0"));
+        assert!(lines[text..].contains(&"global $main"));
+        assert_eq!(lines.last(), Some(&"ret"));
+    }
+
+    #[test]
+    fn test_gen_asm_record() {
+        let asm = compile("struct pair { char a; int b; char name[5]; } g;\nunion u { char c; int i; } h[2];\n\
+                           int main() { struct pair x; x = g; return x.b; }\n");
+        let lines: Vec<&str> = asm.lines().collect();
+
+        // 结构体按成员对齐补齐，联合取最大成员
+        assert!(lines.contains(&"$g: resb 16"));
+        assert!(lines.contains(&"$h: resb 8"));
+    }
+
+    #[test]
+    fn test_gen_asm_multi_dim() {
+        let asm = compile("int grid[3][4];\nint len(char **argv) { return argv[1][2]; }\n\
+                           int main() { char *names[2]; grid[2][1] = 5; return len(names) + *grid[1]; }\n");
+        let lines: Vec<&str> = asm.lines().collect();
+
+        assert!(lines.contains(&"$grid: resb 48"));
+    }
+
+    #[test]
+    fn test_gen_asm_init() {
+        let asm = compile("int a[4] = {1, 2};\nchar s[] = \"hi\";\nstruct pt { char c; int x; } p = {'x', 5};\n\
+                           int main() { char t[3] = {'a'}; return t[0]; }\n");
+        let text = asm.split("section .bss").next().unwrap();
+        assert!(text.contains("$a:\ndd 1\ndd 2\ntimes 8 db 0\n"));
+        assert!(text.contains("$s:\ndb 104\ndb 105\ndb 0\n"));
+        assert!(text.contains("$p:\ndb 120\ntimes 3 db 0\ndd 5\n"));
+    }
+
+    #[test]
+    fn test_gen_asm_enum() {
+        let asm = compile("enum color { RED, GREEN = 4, BLUE };\ntypedef char name[BLUE];\nname g;\n\
+                           int main() { switch (g[0]) { case GREEN + 1: return RED; } return BLUE; }\n");
+        let lines: Vec<&str> = asm.lines().collect();
+
+        assert!(lines.contains(&"$g: resb 5"));
+        assert!(!asm.contains("RED"));              // 枚举常量和类型名不分配空间
+        assert!(!asm.contains("name"));
+    }
+
+    #[test]
+    fn test_gen_asm_integer_types() {
+        let asm = compile("short s = -2;\nunsigned short us;\nlong long big = 5000000000;\n\
+                           long long mul(long long a, int b) { return a * b; }\n\
+                           int main() { unsigned u = 7; long long x = big / 3; if (u > s) x = mul(x, 2); return us + u / 3; }\n");
+        let lines: Vec<&str> = asm.lines().collect();
+
+        assert!(lines.contains(&"$s: dw -2"));
+        assert!(lines.contains(&"$big: dq 5000000000"));
+        assert!(lines.contains(&"extern __divdi3"));       // 64位除法调用库函数
+    }
+
+    #[test]
+    fn test_gen_asm_float() {
+        let asm = compile("double g = 1.5;\nfloat h = -2;\nint n = 3.9;\n\
+                           float half(float f) { return f / 2; }\n\
+                           int main() { double d = 0.25; d = d * n; if (d > h) d = half(d); return d; }\n");
+        let lines: Vec<&str> = asm.lines().collect();
+
+        assert!(lines.contains(&"$g: dq 0x3ff8000000000000"));  // 浮点初值按二进制位输出
+        assert!(lines.contains(&"$h: dd 0xc0000000"));
+        assert!(lines.contains(&"$n: dd 3"));                   // 截断为整数
+    }
+
+    #[test]
+    fn test_gen_asm_bit() {
+        let asm = compile("unsigned mask = ~0;\nlong long big;\n\
+                           int main() { int x = 6; unsigned u = mask >> x; big = big << x | 1; return (x & 3) ^ ~u; }\n");
+        let lines: Vec<&str> = asm.lines().collect();
+
+        assert!(lines.contains(&"$mask: dd 4294967295"));   // 常量直接取反
+    }
+
+    #[test]
+    fn test_gen_asm_bit_const() {
+        let asm = compile("unsigned mask = 1 << 3;\nint a = 3|4;\nint b = (1 << 31) >> 31;\nlong long c = 0xf0 & 0x3c ^ 1;\n");
+        let lines: Vec<&str> = asm.lines().collect();
+
+        // 全局变量的初值在编译期计算，结果按运算类型截断
+        assert!(lines.contains(&"$mask: dd 8"));
+        assert!(lines.contains(&"$a: dd 7"));
+        assert!(lines.contains(&"$b: dd -1"));
+        assert!(lines.contains(&"$c: dq 49"));
+    }
+
+    #[test]
+    fn test_gen_asm_symbol() {
+        let asm = compile("int eax;\nchar byte[2];\nextern int cs;\nint word() { eax = cs; return byte[1]; }\n\
+                           int main() { return word(); }\n");
+        let lines: Vec<&str> = asm.lines().collect();
+
+        // 变量名与寄存器名或NASM关键字相同时加$前缀，指令中的用法见intercode的测试
+        assert!(lines.contains(&"extern $cs"));
+        assert!(lines.contains(&"$eax: resb 4"));
+        assert!(lines.contains(&"$byte: resb 2"));
+        assert!(lines.contains(&"global $word"));
+        assert!(lines.contains(&"$word:"));
+    }
+
+    #[test]
+    fn test_nasm_str() {
+        assert_eq!(nasm_str("hi"), "\"hi\", 0");
+        assert_eq!(nasm_str("a\"b\n"), "\"a\", 34, \"b\", 10, 0");
+        assert_eq!(nasm_str(""), "0");
+        assert_eq!(nasm_str("é"), "195, 169, 0");
     }
 }
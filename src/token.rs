@@ -1,27 +1,7 @@
-use crate::common::Tag::{self, CH, ID, NUM, STR};
-
-const TOKEN_NAME: [&str; 48] = [
-    "error",                                      // 错误，异常，结束标记等
-    "文件结尾",                                    // 文件结束
-    "标识符",                                      // 标识符
-    "int", "char", "void",                        // 数据类型
-    "extern",                                     // extern
-    "数字", "字符", "字符串",                      // 字面量
-    "!", "&",                                    // 单目运算 ! - & *
-    "+", "-", "*", "/", "%",                     // 算术运算符
-    "++","--",
-    ">", ">=", "<", "<=", "==", "!=",            // 比较运算符
-    "&&", "||",                                  // 逻辑运算
-    "(", ")",                                    // ()
-    "[", "]",                                    // []
-    "{", "}",                                    // {}
-    ",", ":", ";",                               // 逗号,冒号,分号
-    "=",                                         // 赋值
-    "if", "else",                                // if-else
-    "switch", "case", "default",                 // swicth-case-deault
-    "while", "do", "for",                        // 循环
-    "break", "continue", "return"                // break, continue, return
-];
+use std::fmt;
+use crate::catalog::Catalog;
+use crate::common::Tag::{self, CH, ID, NUM, REAL, STR};
+use crate::span::Span;
 
 trait TokenToString {
     fn to_string(&self) -> String;
@@ -30,23 +10,53 @@ trait TokenToString {
 #[derive(Clone, PartialEq, Debug)]
 pub struct Token {
     tag: Tag,
+    span: Span,         // 记号在源代码中的位置
 }
 
 impl Token {
     pub(crate) fn new(tag: Tag) -> Self{
         Token {
             tag,
+            span: Span::dummy(),
         }
     }
 
     pub(crate) fn get_tag(&self) -> Tag {
         self.tag
     }
+
+    pub(crate) fn get_span(&self) -> Span {
+        self.span
+    }
 }
 
 impl TokenToString for Token {
     fn to_string(&self) -> String {
-        TOKEN_NAME[self.tag as usize].to_string()
+        Catalog::current().token_name(self.tag).to_string()
+    }
+}
+
+// 注释，语法分析不需要，格式化时原样保留
+#[derive(Clone, PartialEq, Debug)]
+pub(crate) struct Comment {
+    text: String,       // 包含注释符号的原文
+    span: Span,
+}
+
+impl Comment {
+    pub(crate) fn new(text: String, span: Span) -> Self {
+        Comment {
+            text,
+            span,
+        }
+    }
+
+    pub(crate) fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    pub(crate) fn get_span(&self) -> Span {
+        self.span
     }
 }
 
@@ -125,6 +135,32 @@ impl TokenToString for Num {
     }
 }
 
+// 浮点数
+#[derive(Clone, PartialEq, Debug)]
+pub struct Real {
+    token: Token,
+    val: f64,
+}
+
+impl Real {
+    pub(crate) fn new(v: f64) -> Self {
+        Real {
+            token: Token::new(REAL),
+            val: v,
+        }
+    }
+
+    pub(crate) fn get_val(&self) -> f64 {
+        self.val
+    }
+}
+
+impl TokenToString for Real {
+    fn to_string(&self) -> String {
+        format!("[{}]:{:?}", self.token.to_string(), self.val)
+    }
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct Char {
     token: Token,
@@ -157,6 +193,7 @@ pub enum TokenType {
     Str(Str),
     Num(Num),
     Char(Char),
+    Real(Real),
 }
 
 impl TokenType {
@@ -166,19 +203,50 @@ impl TokenType {
                             TokenType::Id(id) => id.token.get_tag(),
                             TokenType::Str(str) => str.token.get_tag(),
                             TokenType::Num(num) => num.token.get_tag(),
-                            TokenType::Char(c) => c.token.get_tag()
+                            TokenType::Char(c) => c.token.get_tag(),
+                            TokenType::Real(r) => r.token.get_tag(),
                         };
 
         tag
     }
 
+    pub(crate) fn get_span(&self) -> Span {
+        self.token().get_span()
+    }
+
+    // 设置记号位置
+    pub(crate) fn with_span(mut self, span: Span) -> Self {
+        match &mut self {
+            TokenType::Token(t) => t.span = span,
+            TokenType::Id(id) => id.token.span = span,
+            TokenType::Str(str) => str.token.span = span,
+            TokenType::Num(num) => num.token.span = span,
+            TokenType::Char(c) => c.token.span = span,
+            TokenType::Real(r) => r.token.span = span,
+        };
+
+        self
+    }
+
+    fn token(&self) -> &Token {
+        match self {
+            TokenType::Token(t) => t,
+            TokenType::Id(id) => &id.token,
+            TokenType::Str(str) => &str.token,
+            TokenType::Num(num) => &num.token,
+            TokenType::Char(c) => &c.token,
+            TokenType::Real(r) => &r.token,
+        }
+    }
+
     pub(crate) fn to_string(&self) -> String {
         return match self {
             TokenType::Token(t) => to_string(t),
             TokenType::Id(id) => to_string(id),
             TokenType::Str(str) => to_string(str),
             TokenType::Num(num) => to_string(num),
-            TokenType::Char(c) => to_string(c)
+            TokenType::Char(c) => to_string(c),
+            TokenType::Real(r) => to_string(r),
         }
     }
 }
@@ -186,3 +254,55 @@ impl TokenType {
 fn to_string(t: &dyn TokenToString) -> String {
     t.to_string()
 }
+
+// 记号携带的值，关键字和运算符没有值
+#[derive(Clone, PartialEq, Debug)]
+pub enum TokenValue {
+    None,
+    Id(String),
+    Num(isize),
+    Char(char),
+    Str(String),
+    Real(f64),
+}
+
+// 带位置的记号，由公开的词法分析器返回
+#[derive(Clone, PartialEq, Debug)]
+pub struct SpannedToken {
+    pub tag: Tag,
+    pub value: TokenValue,
+    pub span: Span,
+}
+
+impl From<TokenType> for SpannedToken {
+    fn from(token: TokenType) -> Self {
+        let value = match &token {
+            TokenType::Token(_) => TokenValue::None,
+            TokenType::Id(id) => TokenValue::Id(id.get_name()),
+            TokenType::Str(str) => TokenValue::Str(str.get_str()),
+            TokenType::Num(num) => TokenValue::Num(num.get_val()),
+            TokenType::Char(c) => TokenValue::Char(c.get_ch()),
+            TokenType::Real(r) => TokenValue::Real(r.get_val()),
+        };
+
+        SpannedToken {
+            tag: token.get_tag(),
+            value,
+            span: token.get_span(),
+        }
+    }
+}
+
+// 与--emit=tokens的输出格式相同
+impl fmt::Display for SpannedToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = Catalog::current().token_name(self.tag);
+        match &self.value {
+            TokenValue::None => write!(f, "{}", name),
+            TokenValue::Id(s) | TokenValue::Str(s) => write!(f, "[{}]:{}", name, s),
+            TokenValue::Num(n) => write!(f, "[{}]:{}", name, n),
+            TokenValue::Char(c) => write!(f, "[{}]:{}", name, c),
+            TokenValue::Real(r) => write!(f, "[{}]:{:?}", name, r),
+        }
+    }
+}
@@ -0,0 +1,444 @@
+/*
+	类型表示，所有类型驻留在类型表中并以TypeId引用，结构相同的类型编号相同，可以直接比较
+*/
+use std::cell::RefCell;
+use std::collections::HashMap;
+use crate::symbol::{Record, Var};
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum Type {
+    Int,
+    Char,
+    Void,
+    UChar,
+    Short,
+    UShort,
+    UInt,
+    Long,
+    ULong,
+    LongLong,
+    ULongLong,
+    Float,
+    Double,
+    Record(usize),                                  // 结构体或联合，记录类型表中的编号
+    Pointer(TypeId),                                // 指针
+    Array(TypeId, isize),                           // 数组，元素类型和长度
+    Function { ret: TypeId, params: Vec<TypeId> },  // 函数，返回类型和参数类型
+}
+
+// 驻留后的类型
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct TypeId(usize);
+
+struct TypeTable {
+    types: Vec<Type>,                   // 以编号为下标
+    ids: HashMap<Type, TypeId>,         // 类型到编号
+    records: Vec<Record>,               // 结构体和联合，以下标为编号
+}
+
+// 每个线程一张类型表，每次编译开始时重建，结构体和联合不会带到下一个文件
+thread_local! {
+    static TYPES: RefCell<TypeTable> = RefCell::new(TypeTable::new());
+}
+
+// 开始新的编译，清空上一次编译留下的类型和结构体
+pub(crate) fn reset() {
+    TYPES.with(|t| *t.borrow_mut() = TypeTable::new());
+}
+
+impl TypeTable {
+    // 基本类型按固定的顺序预先驻留
+    fn new() -> Self {
+        let mut table = TypeTable {
+            types: vec![],
+            ids: HashMap::new(),
+            records: vec![],
+        };
+        for t in [Type::Int, Type::Char, Type::Void, Type::UChar, Type::Short, Type::UShort, Type::UInt,
+                  Type::Long, Type::ULong, Type::LongLong, Type::ULongLong, Type::Float, Type::Double] {
+            table.intern(t);
+        }
+        table
+    }
+
+    fn intern(&mut self, t: Type) -> TypeId {
+        if let Some(id) = self.ids.get(&t) {
+            return *id;
+        }
+        let id = TypeId(self.types.len());
+        self.types.push(t.clone());
+        self.ids.insert(t, id);
+        id
+    }
+}
+
+impl Type {
+    pub(crate) fn intern(self) -> TypeId {
+        TYPES.with(|t| t.borrow_mut().intern(self))
+    }
+}
+
+impl TypeId {
+    pub(crate) const INT: TypeId = TypeId(0);
+    pub(crate) const CHAR: TypeId = TypeId(1);
+    pub(crate) const VOID: TypeId = TypeId(2);
+    pub(crate) const UCHAR: TypeId = TypeId(3);
+    pub(crate) const SHORT: TypeId = TypeId(4);
+    pub(crate) const USHORT: TypeId = TypeId(5);
+    pub(crate) const UINT: TypeId = TypeId(6);
+    pub(crate) const LONG: TypeId = TypeId(7);
+    pub(crate) const ULONG: TypeId = TypeId(8);
+    pub(crate) const LLONG: TypeId = TypeId(9);
+    pub(crate) const ULLONG: TypeId = TypeId(10);
+    pub(crate) const FLOAT: TypeId = TypeId(11);
+    pub(crate) const DOUBLE: TypeId = TypeId(12);
+
+    // 在类型表中查看类型，不复制，f中不能驻留新的类型
+    pub(crate) fn with_kind<R>(self, f: impl FnOnce(&Type) -> R) -> R {
+        TYPES.with(|t| f(&t.borrow().types[self.0]))
+    }
+
+    // 指向该类型的指针
+    pub(crate) fn pointer(self) -> TypeId {
+        Type::Pointer(self).intern()
+    }
+
+    // 以该类型为元素的数组
+    pub(crate) fn array(self, len: isize) -> TypeId {
+        Type::Array(self, len).intern()
+    }
+
+    pub(crate) fn function(ret: TypeId, params: Vec<TypeId>) -> TypeId {
+        Type::Function { ret, params }.intern()
+    }
+
+    pub(crate) fn record(id: usize) -> TypeId {
+        Type::Record(id).intern()
+    }
+
+    pub(crate) fn is_pointer(self) -> bool {
+        self.with_kind(|k| matches!(k, Type::Pointer(_)))
+    }
+
+    pub(crate) fn is_array(self) -> bool {
+        self.with_kind(|k| matches!(k, Type::Array(..)))
+    }
+
+    pub(crate) fn is_record(self) -> bool {
+        self.with_kind(|k| matches!(k, Type::Record(_)))
+    }
+
+    pub(crate) fn is_function(self) -> bool {
+        self.with_kind(|k| matches!(k, Type::Function { .. }))
+    }
+
+    // 算术类型，即整数和浮点类型
+    pub(crate) fn is_arith(self) -> bool {
+        self.is_integer() || self.is_float()
+    }
+
+    pub(crate) fn is_integer(self) -> bool {
+        self.rank() > 0
+    }
+
+    pub(crate) fn is_float(self) -> bool {
+        self.with_kind(|k| matches!(k, Type::Float | Type::Double))
+    }
+
+    // 无符号整数类型
+    pub(crate) fn is_unsigned(self) -> bool {
+        self.with_kind(|k| matches!(k, Type::UChar | Type::UShort | Type::UInt | Type::ULong | Type::ULongLong))
+    }
+
+    // 需要两个32位寄存器的整数类型，即long long
+    pub(crate) fn is_wide(self) -> bool {
+        self.is_integer() && self.size() == 8
+    }
+
+    // 整数类型的等级，char < short < int < long < long long，非整数类型为0
+    fn rank(self) -> u8 {
+        self.with_kind(|k| match k {
+            Type::Char | Type::UChar => 1,
+            Type::Short | Type::UShort => 2,
+            Type::Int | Type::UInt => 3,
+            Type::Long | Type::ULong => 4,
+            Type::LongLong | Type::ULongLong => 5,
+            _ => 0,
+        })
+    }
+
+    // 同等级的无符号类型
+    fn to_unsigned(self) -> TypeId {
+        self.with_kind(|k| match k {
+            Type::Char => TypeId::UCHAR,
+            Type::Short => TypeId::USHORT,
+            Type::Int => TypeId::UINT,
+            Type::Long => TypeId::ULONG,
+            Type::LongLong => TypeId::ULLONG,
+            _ => self,
+        })
+    }
+
+    // 整数提升，比int小的类型都能用int表示
+    pub(crate) fn promote(self) -> TypeId {
+        if self.is_integer() && self.rank() < TypeId::INT.rank() { TypeId::INT } else { self }
+    }
+
+    // 把整数值转换为该类型能表示的值，超出范围的按补码截断
+    pub(crate) fn cast(self, val: isize) -> isize {
+        match (self.size(), self.is_unsigned()) {
+            (1, false) => val as i8 as isize,
+            (1, true) => val as u8 as isize,
+            (2, false) => val as i16 as isize,
+            (2, true) => val as u16 as isize,
+            (4, false) => val as i32 as isize,
+            (4, true) => val as u32 as isize,
+            _ => val,
+        }
+    }
+
+    // 浮点数在该类型下的二进制表示，float只有低32位
+    pub(crate) fn float_bits(self, val: f64) -> u64 {
+        if self == TypeId::FLOAT { (val as f32).to_bits() as u64 } else { val.to_bits() }
+    }
+
+    // 结构体或联合的编号
+    pub(crate) fn record_id(self) -> Option<usize> {
+        self.with_kind(|k| match k {
+            Type::Record(id) => Some(*id),
+            _ => None,
+        })
+    }
+
+    // 指针指向的类型或数组的元素类型
+    pub(crate) fn pointee(self) -> Option<TypeId> {
+        self.with_kind(|k| match k {
+            Type::Pointer(t) | Type::Array(t, _) => Some(*t),
+            _ => None,
+        })
+    }
+
+    // 数组的长度
+    pub(crate) fn array_len(self) -> Option<isize> {
+        self.with_kind(|k| match k {
+            Type::Array(_, len) => Some(*len),
+            _ => None,
+        })
+    }
+
+    // 数组退化为指向元素的指针，函数退化为函数指针
+    pub(crate) fn decay(self) -> TypeId {
+        if self.is_array() {
+            self.pointee().unwrap_or(self).pointer()
+        } else if self.is_function() {
+            self.pointer()
+        } else {
+            self
+        }
+    }
+
+    // 大小，void、函数和未定义的结构体为0
+    pub(crate) fn size(self) -> isize {
+        self.with_kind(|k| match *k {
+            Type::Int | Type::UInt | Type::Long | Type::ULong | Type::Float | Type::Pointer(_) => 4,
+            Type::Char | Type::UChar => 1,
+            Type::Short | Type::UShort => 2,
+            Type::LongLong | Type::ULongLong | Type::Double => 8,
+            Type::Void | Type::Function { .. } => 0,
+            Type::Array(t, len) => t.size() * len,
+            Type::Record(id) => with_record(id, |r| r.get_size()),
+        })
+    }
+
+    // 对齐字节数
+    pub(crate) fn align(self) -> isize {
+        self.with_kind(|k| match *k {
+            Type::Char | Type::UChar | Type::Void | Type::Function { .. } => 1,
+            Type::Short | Type::UShort => 2,
+            Type::Array(t, _) => t.align(),
+            Type::Record(id) => with_record(id, |r| r.get_align()),
+            _ => 4,
+        })
+    }
+
+    // 是否是完整类型，数组要求元素完整
+    pub(crate) fn is_complete(self) -> bool {
+        self.with_kind(|k| match *k {
+            Type::Void => false,
+            Type::Array(t, _) => t.is_complete(),
+            Type::Record(id) => with_record(id, |r| r.is_defined()),
+            _ => true,
+        })
+    }
+
+    // 对象中各个标量的偏移和类型，按存储顺序，联合只取第一个成员
+    pub(crate) fn scalars(self, offset: isize) -> Vec<(isize, TypeId)> {
+        self.with_kind(|k| match *k {
+            Type::Array(t, len) => (0..len).flat_map(|i| t.scalars(offset + i * t.size())).collect(),
+            Type::Record(id) => with_record(id, |r| {
+                let count = if r.is_union() { 1 } else { usize::MAX };
+                r.get_members().iter().take(count).flat_map(|m| m.get_type().scalars(offset + m.get_offset())).collect()
+            }),
+            _ => vec![(offset, self)],
+        })
+    }
+
+    // 类型名称，如int、char*、int[4]、int(*)[4]、struct node*
+    pub(crate) fn name(self) -> String {
+        self.declarator(String::new())
+    }
+
+    // 按C的声明形式输出类型，inner为已经生成的内层声明符
+    fn declarator(self, inner: String) -> String {
+        self.with_kind(|k| match k {
+            Type::Int => format!("int{}", inner),
+            Type::Char => format!("char{}", inner),
+            Type::Void => format!("void{}", inner),
+            Type::UChar => format!("unsigned char{}", inner),
+            Type::Short => format!("short{}", inner),
+            Type::UShort => format!("unsigned short{}", inner),
+            Type::UInt => format!("unsigned int{}", inner),
+            Type::Long => format!("long{}", inner),
+            Type::ULong => format!("unsigned long{}", inner),
+            Type::LongLong => format!("long long{}", inner),
+            Type::ULongLong => format!("unsigned long long{}", inner),
+            Type::Float => format!("float{}", inner),
+            Type::Double => format!("double{}", inner),
+            Type::Record(id) => format!("{}{}", with_record(*id, |r| r.type_name()), inner),
+            Type::Pointer(t) => {
+                if t.is_array() || t.is_function() {
+                    t.declarator(format!("(*{})", inner))
+                } else {
+                    t.declarator(format!("*{}", inner))
+                }
+            },
+            Type::Array(t, len) => t.declarator(format!("{}[{}]", inner, len)),
+            Type::Function { ret, params } => {
+                let params: Vec<String> = params.iter().map(|p| p.name()).collect();
+                ret.declarator(format!("{}({})", inner, params.join(", ")))
+            },
+        })
+    }
+}
+
+// 简单赋值的类型规则：算术类型之间可以赋值，结构体要求同一类型，指针要求指向相同的类型或void，数组按退化后的指针处理
+pub(crate) fn assignable(lval: TypeId, rval: TypeId) -> bool {
+    let (l, r) = (lval.decay(), rval.decay());
+    if l.is_arith() && r.is_arith() {
+        return true;
+    }
+
+    if l.is_record() || r.is_record() {
+        return l.record_id() == r.record_id();
+    }
+    match (l.pointee(), r.pointee()) {
+        (Some(a), Some(b)) => a == b || a == TypeId::VOID || b == TypeId::VOID,
+        _ => false,
+    }
+}
+
+// 常用算术转换：有浮点类型时取较大的浮点类型，否则先做整数提升，类型相同或符号相同时取等级高的，
+// 否则等级高的无符号类型优先，有符号类型能表示无符号类型的所有值时取有符号类型，否则取有符号类型对应的无符号类型
+pub(crate) fn common(a: TypeId, b: TypeId) -> TypeId {
+    if a == TypeId::DOUBLE || b == TypeId::DOUBLE {
+        return TypeId::DOUBLE;
+    }
+    if a == TypeId::FLOAT || b == TypeId::FLOAT {
+        return TypeId::FLOAT;
+    }
+    let (a, b) = (a.promote(), b.promote());
+    if a == b {
+        return a;
+    }
+    if a.is_unsigned() == b.is_unsigned() {
+        return if a.rank() >= b.rank() { a } else { b };
+    }
+
+    let (u, s) = if a.is_unsigned() { (a, b) } else { (b, a) };
+    if u.rank() >= s.rank() {
+        u
+    } else if s.size() > u.size() {
+        s
+    } else {
+        s.to_unsigned()
+    }
+}
+
+// 新建一个未定义的结构体或联合，返回其编号
+pub(crate) fn new_record(record: Record) -> usize {
+    TYPES.with(|t| {
+        let mut t = t.borrow_mut();
+        t.records.push(record);
+        t.records.len() - 1
+    })
+}
+
+// 查看结构体或联合，不复制，f中不能新建或定义结构体
+pub(crate) fn with_record<R>(id: usize, f: impl FnOnce(&Record) -> R) -> R {
+    TYPES.with(|t| f(&t.borrow().records[id]))
+}
+
+// 完成结构体或联合的定义，布局在类型表之外计算，避免计算成员大小时重复借用，未定义的结构体没有成员，复制的开销很小
+pub(crate) fn define_record(id: usize, members: Vec<Var>) {
+    let mut r = with_record(id, |r| r.clone());
+    r.define(members);
+    TYPES.with(|t| t.borrow_mut().records[id] = r);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::span::Span;
+    use crate::symbol::Record;
+    use crate::types::{Type, TypeId, assignable, common, new_record, reset, with_record};
+
+    #[test]
+    fn test_intern() {
+        let p = TypeId::INT.pointer();
+        assert_eq!(p, Type::Pointer(TypeId::INT).intern());
+        assert_ne!(p, TypeId::CHAR.pointer());
+        assert_eq!(TypeId::INT.array(4).decay(), p);
+
+        assert_eq!(p.pointer().name(), "int**");
+        assert_eq!(TypeId::INT.array(3).pointer().name(), "int(*)[3]");
+        assert_eq!(TypeId::CHAR.pointer().array(2).name(), "char*[2]");
+        assert_eq!(TypeId::function(TypeId::INT, vec![TypeId::CHAR.pointer()]).name(), "int(char*)");
+        assert_eq!((TypeId::INT.array(3).array(2).size(), TypeId::CHAR.array(5).align()), (24, 1));
+    }
+
+    #[test]
+    fn test_assignable() {
+        assert!(assignable(TypeId::CHAR, TypeId::INT));
+        assert!(assignable(TypeId::INT.pointer(), TypeId::INT.array(4)));
+        assert!(!assignable(TypeId::INT.pointer(), TypeId::CHAR.pointer()));
+        assert!(!assignable(TypeId::INT.pointer(), TypeId::INT));
+        assert!(!assignable(TypeId::INT.pointer().pointer(), TypeId::INT.array(2).array(2)));
+        assert!(assignable(TypeId::USHORT, TypeId::LLONG));
+    }
+
+    #[test]
+    fn test_common() {
+        assert_eq!(common(TypeId::CHAR, TypeId::USHORT), TypeId::INT);     // 整数提升
+        assert_eq!(common(TypeId::INT, TypeId::UINT), TypeId::UINT);
+        assert_eq!(common(TypeId::UINT, TypeId::LONG), TypeId::ULONG);     // long不能表示unsigned int的所有值
+        assert_eq!(common(TypeId::UINT, TypeId::LLONG), TypeId::LLONG);
+        assert_eq!(common(TypeId::ULLONG, TypeId::CHAR), TypeId::ULLONG);
+        assert_eq!((TypeId::UCHAR.cast(300), TypeId::SHORT.cast(40000), TypeId::UINT.cast(-1)), (44, -25536, 0xffffffff));
+        assert_eq!((TypeId::ULLONG.name(), TypeId::SHORT.size(), TypeId::LLONG.align()), ("unsigned long long".to_string(), 2, 4));
+        assert_eq!(common(TypeId::ULLONG, TypeId::FLOAT), TypeId::FLOAT);
+        assert_eq!(common(TypeId::FLOAT, TypeId::DOUBLE), TypeId::DOUBLE);
+        assert!(TypeId::DOUBLE.is_arith() && !TypeId::DOUBLE.is_wide());
+    }
+
+    #[test]
+    fn test_reset() {
+        // 重建类型表后，上一次编译的结构体和派生类型都不再保留
+        let record = |name: &str| Record::new(false, name.to_string(), vec![0], Span::dummy());
+        new_record(record("S"));
+        let p = TypeId::record(new_record(record("T"))).pointer();
+        reset();
+        assert_eq!(new_record(record("U")), 0);
+        assert_eq!(with_record(0, |r| r.type_name()), "struct U");
+        assert_eq!(TypeId::record(0).pointer(), p);    // 编号从头分配
+        assert_eq!(p.name(), "struct U*");
+    }
+}
@@ -0,0 +1,751 @@
+/*
+	语法树遍历：Visitor只读遍历，VisitorMut可以就地改写节点
+	每种节点和每种语句、表达式都有对应的visit方法，默认实现调用同名的walk函数遍历子节点，
+	重写某个方法后可以调用walk函数继续向下遍历
+*/
+use crate::ast::{Block, BlockItem, Case, Decl, Declarator, DeclaratorKind, EnumSpec, Expr, ExprKind, ForInit, FunctionDef, Ident, Initializer, Item, Param, RecordSpec, Stmt, StmtKind, TranslationUnit, VarDecl};
+
+pub trait Visitor {
+    fn visit_unit(&mut self, unit: &TranslationUnit) { walk_unit(self, unit) }
+    fn visit_item(&mut self, item: &Item) { walk_item(self, item) }
+    fn visit_decl(&mut self, decl: &Decl) { walk_decl(self, decl) }
+    fn visit_var_decl(&mut self, var: &VarDecl) { walk_var_decl(self, var) }
+    fn visit_declarator(&mut self, decl: &Declarator) { walk_declarator(self, decl) }
+    fn visit_initializer(&mut self, init: &Initializer) { walk_initializer(self, init) }
+    fn visit_record(&mut self, record: &RecordSpec) { walk_record(self, record) }
+    fn visit_enum(&mut self, enumer: &EnumSpec) { walk_enum(self, enumer) }
+    fn visit_function(&mut self, fun: &FunctionDef) { walk_function(self, fun) }
+    fn visit_param(&mut self, para: &Param) { walk_param(self, para) }
+    fn visit_block(&mut self, block: &Block) { walk_block(self, block) }
+    fn visit_block_item(&mut self, item: &BlockItem) { walk_block_item(self, item) }
+    fn visit_ident(&mut self, _ident: &Ident) {}
+
+    // 语句
+    fn visit_stmt(&mut self, stmt: &Stmt) { walk_stmt(self, stmt) }
+    fn visit_expr_stmt(&mut self, stmt: &Stmt) { walk_expr_stmt(self, stmt) }
+    fn visit_block_stmt(&mut self, stmt: &Stmt) { walk_block_stmt(self, stmt) }
+    fn visit_while(&mut self, stmt: &Stmt) { walk_while(self, stmt) }
+    fn visit_do_while(&mut self, stmt: &Stmt) { walk_do_while(self, stmt) }
+    fn visit_for(&mut self, stmt: &Stmt) { walk_for(self, stmt) }
+    fn visit_if(&mut self, stmt: &Stmt) { walk_if(self, stmt) }
+    fn visit_switch(&mut self, stmt: &Stmt) { walk_switch(self, stmt) }
+    fn visit_case(&mut self, case: &Case) { walk_case(self, case) }
+    fn visit_break(&mut self, _stmt: &Stmt) {}
+    fn visit_continue(&mut self, _stmt: &Stmt) {}
+    fn visit_return(&mut self, stmt: &Stmt) { walk_return(self, stmt) }
+
+    // 表达式
+    fn visit_expr(&mut self, expr: &Expr) { walk_expr(self, expr) }
+    fn visit_literal(&mut self, _expr: &Expr) {}
+    fn visit_var(&mut self, _expr: &Expr) {}
+    fn visit_index(&mut self, expr: &Expr) { walk_index(self, expr) }
+    fn visit_member(&mut self, expr: &Expr) { walk_member(self, expr) }
+    fn visit_call(&mut self, expr: &Expr) { walk_call(self, expr) }
+    fn visit_unary(&mut self, expr: &Expr) { walk_unary(self, expr) }
+    fn visit_postfix(&mut self, expr: &Expr) { walk_postfix(self, expr) }
+    fn visit_binary(&mut self, expr: &Expr) { walk_binary(self, expr) }
+    fn visit_assign(&mut self, expr: &Expr) { walk_assign(self, expr) }
+    fn visit_paren(&mut self, expr: &Expr) { walk_paren(self, expr) }
+    fn visit_error(&mut self, _expr: &Expr) {}
+}
+
+pub fn walk_unit<V: Visitor + ?Sized>(v: &mut V, unit: &TranslationUnit) {
+    for item in unit.items.iter() {
+        v.visit_item(item);
+    }
+}
+
+pub fn walk_item<V: Visitor + ?Sized>(v: &mut V, item: &Item) {
+    match item {
+        Item::Decl(decl) => v.visit_decl(decl),
+        Item::Function(fun) => v.visit_function(fun),
+    }
+}
+
+pub fn walk_decl<V: Visitor + ?Sized>(v: &mut V, decl: &Decl) {
+    if let Some(record) = &decl.ty.record {
+        v.visit_record(record);
+    }
+    if let Some(enumer) = &decl.ty.enumer {
+        v.visit_enum(enumer);
+    }
+    for var in decl.vars.iter() {
+        v.visit_var_decl(var);
+    }
+}
+
+pub fn walk_var_decl<V: Visitor + ?Sized>(v: &mut V, var: &VarDecl) {
+    v.visit_ident(&var.name);
+    v.visit_declarator(&var.decl);
+    if let Some(init) = &var.init {
+        v.visit_initializer(init);
+    }
+}
+
+// 声明符中的数组长度和函数参数
+pub fn walk_declarator<V: Visitor + ?Sized>(v: &mut V, decl: &Declarator) {
+    match &decl.kind {
+        DeclaratorKind::Name => {},
+        DeclaratorKind::Pointer(inner) => v.visit_declarator(inner),
+        DeclaratorKind::Array { inner, len } => {
+            v.visit_declarator(inner);
+            if let Some(len) = len {
+                v.visit_expr(len);
+            }
+        },
+        DeclaratorKind::Function { inner, params } => {
+            v.visit_declarator(inner);
+            for para in params.iter() {
+                v.visit_param(para);
+            }
+        },
+    }
+}
+
+// 初始化列表按顺序遍历每一项
+pub fn walk_initializer<V: Visitor + ?Sized>(v: &mut V, init: &Initializer) {
+    match init {
+        Initializer::Expr(e) => v.visit_expr(e),
+        Initializer::List { items, .. } => {
+            for item in items.iter() {
+                v.visit_initializer(item);
+            }
+        },
+    }
+}
+
+// 结构体或联合的标签和成员声明
+pub fn walk_record<V: Visitor + ?Sized>(v: &mut V, record: &RecordSpec) {
+    if let Some(name) = &record.name {
+        v.visit_ident(name);
+    }
+    for decl in record.members.iter().flatten() {
+        v.visit_decl(decl);
+    }
+}
+
+// 枚举的标签和常量
+pub fn walk_enum<V: Visitor + ?Sized>(v: &mut V, enumer: &EnumSpec) {
+    if let Some(name) = &enumer.name {
+        v.visit_ident(name);
+    }
+    for item in enumer.items.iter().flatten() {
+        v.visit_ident(&item.name);
+        if let Some(value) = &item.value {
+            v.visit_expr(value);
+        }
+    }
+}
+
+pub fn walk_function<V: Visitor + ?Sized>(v: &mut V, fun: &FunctionDef) {
+    if let Some(record) = &fun.ret.record {
+        v.visit_record(record);
+    }
+    if let Some(enumer) = &fun.ret.enumer {
+        v.visit_enum(enumer);
+    }
+    v.visit_ident(&fun.name);
+    for para in fun.params.iter() {
+        v.visit_param(para);
+    }
+    if let Some(body) = &fun.body {
+        v.visit_block(body);
+    }
+}
+
+pub fn walk_param<V: Visitor + ?Sized>(v: &mut V, para: &Param) {
+    if let Some(record) = &para.ty.record {
+        v.visit_record(record);
+    }
+    if let Some(enumer) = &para.ty.enumer {
+        v.visit_enum(enumer);
+    }
+    v.visit_ident(&para.name);
+    v.visit_declarator(&para.decl);
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(v: &mut V, block: &Block) {
+    for item in block.items.iter() {
+        v.visit_block_item(item);
+    }
+}
+
+pub fn walk_block_item<V: Visitor + ?Sized>(v: &mut V, item: &BlockItem) {
+    match item {
+        BlockItem::Decl(decl) => v.visit_decl(decl),
+        BlockItem::Stmt(stmt) => v.visit_stmt(stmt),
+    }
+}
+
+// 按语句种类分派
+pub fn walk_stmt<V: Visitor + ?Sized>(v: &mut V, stmt: &Stmt) {
+    match &stmt.kind {
+        StmtKind::Expr(_) => v.visit_expr_stmt(stmt),
+        StmtKind::Block(_) => v.visit_block_stmt(stmt),
+        StmtKind::While { .. } => v.visit_while(stmt),
+        StmtKind::DoWhile { .. } => v.visit_do_while(stmt),
+        StmtKind::For { .. } => v.visit_for(stmt),
+        StmtKind::If { .. } => v.visit_if(stmt),
+        StmtKind::Switch { .. } => v.visit_switch(stmt),
+        StmtKind::Break => v.visit_break(stmt),
+        StmtKind::Continue => v.visit_continue(stmt),
+        StmtKind::Return(_) => v.visit_return(stmt),
+    }
+}
+
+pub fn walk_expr_stmt<V: Visitor + ?Sized>(v: &mut V, stmt: &Stmt) {
+    if let StmtKind::Expr(Some(e)) = &stmt.kind {
+        v.visit_expr(e);
+    }
+}
+
+pub fn walk_block_stmt<V: Visitor + ?Sized>(v: &mut V, stmt: &Stmt) {
+    if let StmtKind::Block(block) = &stmt.kind {
+        v.visit_block(block);
+    }
+}
+
+pub fn walk_while<V: Visitor + ?Sized>(v: &mut V, stmt: &Stmt) {
+    if let StmtKind::While { cond, body } = &stmt.kind {
+        if let Some(cond) = cond {
+            v.visit_expr(cond);
+        }
+        v.visit_stmt(body);
+    }
+}
+
+pub fn walk_do_while<V: Visitor + ?Sized>(v: &mut V, stmt: &Stmt) {
+    if let StmtKind::DoWhile { body, cond } = &stmt.kind {
+        v.visit_stmt(body);
+        if let Some(cond) = cond {
+            v.visit_expr(cond);
+        }
+    }
+}
+
+pub fn walk_for<V: Visitor + ?Sized>(v: &mut V, stmt: &Stmt) {
+    if let StmtKind::For { init, cond, step, body } = &stmt.kind {
+        match init.as_ref() {
+            ForInit::Decl(decl) => v.visit_decl(decl),
+            ForInit::Expr(Some(e)) => v.visit_expr(e),
+            ForInit::Expr(None) => {},
+        }
+        if let Some(cond) = cond {
+            v.visit_expr(cond);
+        }
+        if let Some(step) = step {
+            v.visit_expr(step);
+        }
+        v.visit_stmt(body);
+    }
+}
+
+pub fn walk_if<V: Visitor + ?Sized>(v: &mut V, stmt: &Stmt) {
+    if let StmtKind::If { cond, then, els } = &stmt.kind {
+        v.visit_expr(cond);
+        v.visit_stmt(then);
+        if let Some(els) = els {
+            v.visit_stmt(els);
+        }
+    }
+}
+
+pub fn walk_switch<V: Visitor + ?Sized>(v: &mut V, stmt: &Stmt) {
+    if let StmtKind::Switch { cond, cases } = &stmt.kind {
+        v.visit_expr(cond);
+        for case in cases.iter() {
+            v.visit_case(case);
+        }
+    }
+}
+
+pub fn walk_case<V: Visitor + ?Sized>(v: &mut V, case: &Case) {
+    if let Some(label) = &case.label {
+        v.visit_expr(label);
+    }
+    for item in case.items.iter() {
+        v.visit_block_item(item);
+    }
+}
+
+pub fn walk_return<V: Visitor + ?Sized>(v: &mut V, stmt: &Stmt) {
+    if let StmtKind::Return(Some(e)) = &stmt.kind {
+        v.visit_expr(e);
+    }
+}
+
+// 按表达式种类分派
+pub fn walk_expr<V: Visitor + ?Sized>(v: &mut V, expr: &Expr) {
+    match &expr.kind {
+        ExprKind::Literal(_) => v.visit_literal(expr),
+        ExprKind::Ident(_) => v.visit_var(expr),
+        ExprKind::Index { .. } => v.visit_index(expr),
+        ExprKind::Member { .. } => v.visit_member(expr),
+        ExprKind::Call { .. } => v.visit_call(expr),
+        ExprKind::Unary { .. } => v.visit_unary(expr),
+        ExprKind::Postfix { .. } => v.visit_postfix(expr),
+        ExprKind::Binary { .. } => v.visit_binary(expr),
+        ExprKind::Assign { .. } => v.visit_assign(expr),
+        ExprKind::Paren(_) => v.visit_paren(expr),
+        ExprKind::Error => v.visit_error(expr),
+    }
+}
+
+pub fn walk_index<V: Visitor + ?Sized>(v: &mut V, expr: &Expr) {
+    if let ExprKind::Index { base, index } = &expr.kind {
+        v.visit_expr(base);
+        v.visit_expr(index);
+    }
+}
+
+pub fn walk_member<V: Visitor + ?Sized>(v: &mut V, expr: &Expr) {
+    if let ExprKind::Member { base, member, .. } = &expr.kind {
+        v.visit_expr(base);
+        v.visit_ident(member);
+    }
+}
+
+pub fn walk_call<V: Visitor + ?Sized>(v: &mut V, expr: &Expr) {
+    if let ExprKind::Call { callee, args } = &expr.kind {
+        v.visit_ident(callee);
+        for arg in args.iter() {
+            v.visit_expr(arg);
+        }
+    }
+}
+
+pub fn walk_unary<V: Visitor + ?Sized>(v: &mut V, expr: &Expr) {
+    if let ExprKind::Unary { expr, .. } = &expr.kind {
+        v.visit_expr(expr);
+    }
+}
+
+pub fn walk_postfix<V: Visitor + ?Sized>(v: &mut V, expr: &Expr) {
+    if let ExprKind::Postfix { expr, .. } = &expr.kind {
+        v.visit_expr(expr);
+    }
+}
+
+pub fn walk_binary<V: Visitor + ?Sized>(v: &mut V, expr: &Expr) {
+    if let ExprKind::Binary { lhs, rhs, .. } = &expr.kind {
+        v.visit_expr(lhs);
+        v.visit_expr(rhs);
+    }
+}
+
+pub fn walk_assign<V: Visitor + ?Sized>(v: &mut V, expr: &Expr) {
+    if let ExprKind::Assign { lhs, rhs, .. } = &expr.kind {
+        v.visit_expr(lhs);
+        v.visit_expr(rhs);
+    }
+}
+
+pub fn walk_paren<V: Visitor + ?Sized>(v: &mut V, expr: &Expr) {
+    if let ExprKind::Paren(inner) = &expr.kind {
+        v.visit_expr(inner);
+    }
+}
+
+// 可改写的遍历，方法与Visitor一一对应，重写时可以替换整个节点
+pub trait VisitorMut {
+    fn visit_unit_mut(&mut self, unit: &mut TranslationUnit) { walk_unit_mut(self, unit) }
+    fn visit_item_mut(&mut self, item: &mut Item) { walk_item_mut(self, item) }
+    fn visit_decl_mut(&mut self, decl: &mut Decl) { walk_decl_mut(self, decl) }
+    fn visit_var_decl_mut(&mut self, var: &mut VarDecl) { walk_var_decl_mut(self, var) }
+    fn visit_declarator_mut(&mut self, decl: &mut Declarator) { walk_declarator_mut(self, decl) }
+    fn visit_initializer_mut(&mut self, init: &mut Initializer) { walk_initializer_mut(self, init) }
+    fn visit_record_mut(&mut self, record: &mut RecordSpec) { walk_record_mut(self, record) }
+    fn visit_enum_mut(&mut self, enumer: &mut EnumSpec) { walk_enum_mut(self, enumer) }
+    fn visit_function_mut(&mut self, fun: &mut FunctionDef) { walk_function_mut(self, fun) }
+    fn visit_param_mut(&mut self, para: &mut Param) { walk_param_mut(self, para) }
+    fn visit_block_mut(&mut self, block: &mut Block) { walk_block_mut(self, block) }
+    fn visit_block_item_mut(&mut self, item: &mut BlockItem) { walk_block_item_mut(self, item) }
+    fn visit_ident_mut(&mut self, _ident: &mut Ident) {}
+
+    // 语句
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) { walk_stmt_mut(self, stmt) }
+    fn visit_expr_stmt_mut(&mut self, stmt: &mut Stmt) { walk_expr_stmt_mut(self, stmt) }
+    fn visit_block_stmt_mut(&mut self, stmt: &mut Stmt) { walk_block_stmt_mut(self, stmt) }
+    fn visit_while_mut(&mut self, stmt: &mut Stmt) { walk_while_mut(self, stmt) }
+    fn visit_do_while_mut(&mut self, stmt: &mut Stmt) { walk_do_while_mut(self, stmt) }
+    fn visit_for_mut(&mut self, stmt: &mut Stmt) { walk_for_mut(self, stmt) }
+    fn visit_if_mut(&mut self, stmt: &mut Stmt) { walk_if_mut(self, stmt) }
+    fn visit_switch_mut(&mut self, stmt: &mut Stmt) { walk_switch_mut(self, stmt) }
+    fn visit_case_mut(&mut self, case: &mut Case) { walk_case_mut(self, case) }
+    fn visit_break_mut(&mut self, _stmt: &mut Stmt) {}
+    fn visit_continue_mut(&mut self, _stmt: &mut Stmt) {}
+    fn visit_return_mut(&mut self, stmt: &mut Stmt) { walk_return_mut(self, stmt) }
+
+    // 表达式
+    fn visit_expr_mut(&mut self, expr: &mut Expr) { walk_expr_mut(self, expr) }
+    fn visit_literal_mut(&mut self, _expr: &mut Expr) {}
+    fn visit_var_mut(&mut self, _expr: &mut Expr) {}
+    fn visit_index_mut(&mut self, expr: &mut Expr) { walk_index_mut(self, expr) }
+    fn visit_member_mut(&mut self, expr: &mut Expr) { walk_member_mut(self, expr) }
+    fn visit_call_mut(&mut self, expr: &mut Expr) { walk_call_mut(self, expr) }
+    fn visit_unary_mut(&mut self, expr: &mut Expr) { walk_unary_mut(self, expr) }
+    fn visit_postfix_mut(&mut self, expr: &mut Expr) { walk_postfix_mut(self, expr) }
+    fn visit_binary_mut(&mut self, expr: &mut Expr) { walk_binary_mut(self, expr) }
+    fn visit_assign_mut(&mut self, expr: &mut Expr) { walk_assign_mut(self, expr) }
+    fn visit_paren_mut(&mut self, expr: &mut Expr) { walk_paren_mut(self, expr) }
+    fn visit_error_mut(&mut self, _expr: &mut Expr) {}
+}
+
+pub fn walk_unit_mut<V: VisitorMut + ?Sized>(v: &mut V, unit: &mut TranslationUnit) {
+    for item in unit.items.iter_mut() {
+        v.visit_item_mut(item);
+    }
+}
+
+pub fn walk_item_mut<V: VisitorMut + ?Sized>(v: &mut V, item: &mut Item) {
+    match item {
+        Item::Decl(decl) => v.visit_decl_mut(decl),
+        Item::Function(fun) => v.visit_function_mut(fun),
+    }
+}
+
+pub fn walk_decl_mut<V: VisitorMut + ?Sized>(v: &mut V, decl: &mut Decl) {
+    if let Some(record) = &mut decl.ty.record {
+        v.visit_record_mut(record);
+    }
+    if let Some(enumer) = &mut decl.ty.enumer {
+        v.visit_enum_mut(enumer);
+    }
+    for var in decl.vars.iter_mut() {
+        v.visit_var_decl_mut(var);
+    }
+}
+
+pub fn walk_var_decl_mut<V: VisitorMut + ?Sized>(v: &mut V, var: &mut VarDecl) {
+    v.visit_ident_mut(&mut var.name);
+    v.visit_declarator_mut(&mut var.decl);
+    if let Some(init) = &mut var.init {
+        v.visit_initializer_mut(init);
+    }
+}
+
+pub fn walk_declarator_mut<V: VisitorMut + ?Sized>(v: &mut V, decl: &mut Declarator) {
+    match &mut decl.kind {
+        DeclaratorKind::Name => {},
+        DeclaratorKind::Pointer(inner) => v.visit_declarator_mut(inner),
+        DeclaratorKind::Array { inner, len } => {
+            v.visit_declarator_mut(inner);
+            if let Some(len) = len {
+                v.visit_expr_mut(len);
+            }
+        },
+        DeclaratorKind::Function { inner, params } => {
+            v.visit_declarator_mut(inner);
+            for para in params.iter_mut() {
+                v.visit_param_mut(para);
+            }
+        },
+    }
+}
+
+pub fn walk_initializer_mut<V: VisitorMut + ?Sized>(v: &mut V, init: &mut Initializer) {
+    match init {
+        Initializer::Expr(e) => v.visit_expr_mut(e),
+        Initializer::List { items, .. } => {
+            for item in items.iter_mut() {
+                v.visit_initializer_mut(item);
+            }
+        },
+    }
+}
+
+pub fn walk_record_mut<V: VisitorMut + ?Sized>(v: &mut V, record: &mut RecordSpec) {
+    if let Some(name) = &mut record.name {
+        v.visit_ident_mut(name);
+    }
+    for decl in record.members.iter_mut().flatten() {
+        v.visit_decl_mut(decl);
+    }
+}
+
+pub fn walk_enum_mut<V: VisitorMut + ?Sized>(v: &mut V, enumer: &mut EnumSpec) {
+    if let Some(name) = &mut enumer.name {
+        v.visit_ident_mut(name);
+    }
+    for item in enumer.items.iter_mut().flatten() {
+        v.visit_ident_mut(&mut item.name);
+        if let Some(value) = &mut item.value {
+            v.visit_expr_mut(value);
+        }
+    }
+}
+
+pub fn walk_function_mut<V: VisitorMut + ?Sized>(v: &mut V, fun: &mut FunctionDef) {
+    if let Some(record) = &mut fun.ret.record {
+        v.visit_record_mut(record);
+    }
+    if let Some(enumer) = &mut fun.ret.enumer {
+        v.visit_enum_mut(enumer);
+    }
+    v.visit_ident_mut(&mut fun.name);
+    for para in fun.params.iter_mut() {
+        v.visit_param_mut(para);
+    }
+    if let Some(body) = &mut fun.body {
+        v.visit_block_mut(body);
+    }
+}
+
+pub fn walk_param_mut<V: VisitorMut + ?Sized>(v: &mut V, para: &mut Param) {
+    if let Some(record) = &mut para.ty.record {
+        v.visit_record_mut(record);
+    }
+    if let Some(enumer) = &mut para.ty.enumer {
+        v.visit_enum_mut(enumer);
+    }
+    v.visit_ident_mut(&mut para.name);
+    v.visit_declarator_mut(&mut para.decl);
+}
+
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(v: &mut V, block: &mut Block) {
+    for item in block.items.iter_mut() {
+        v.visit_block_item_mut(item);
+    }
+}
+
+pub fn walk_block_item_mut<V: VisitorMut + ?Sized>(v: &mut V, item: &mut BlockItem) {
+    match item {
+        BlockItem::Decl(decl) => v.visit_decl_mut(decl),
+        BlockItem::Stmt(stmt) => v.visit_stmt_mut(stmt),
+    }
+}
+
+// 按语句种类分派
+pub fn walk_stmt_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut Stmt) {
+    match &stmt.kind {
+        StmtKind::Expr(_) => v.visit_expr_stmt_mut(stmt),
+        StmtKind::Block(_) => v.visit_block_stmt_mut(stmt),
+        StmtKind::While { .. } => v.visit_while_mut(stmt),
+        StmtKind::DoWhile { .. } => v.visit_do_while_mut(stmt),
+        StmtKind::For { .. } => v.visit_for_mut(stmt),
+        StmtKind::If { .. } => v.visit_if_mut(stmt),
+        StmtKind::Switch { .. } => v.visit_switch_mut(stmt),
+        StmtKind::Break => v.visit_break_mut(stmt),
+        StmtKind::Continue => v.visit_continue_mut(stmt),
+        StmtKind::Return(_) => v.visit_return_mut(stmt),
+    }
+}
+
+pub fn walk_expr_stmt_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut Stmt) {
+    if let StmtKind::Expr(Some(e)) = &mut stmt.kind {
+        v.visit_expr_mut(e);
+    }
+}
+
+pub fn walk_block_stmt_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut Stmt) {
+    if let StmtKind::Block(block) = &mut stmt.kind {
+        v.visit_block_mut(block);
+    }
+}
+
+pub fn walk_while_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut Stmt) {
+    if let StmtKind::While { cond, body } = &mut stmt.kind {
+        if let Some(cond) = cond {
+            v.visit_expr_mut(cond);
+        }
+        v.visit_stmt_mut(body);
+    }
+}
+
+pub fn walk_do_while_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut Stmt) {
+    if let StmtKind::DoWhile { body, cond } = &mut stmt.kind {
+        v.visit_stmt_mut(body);
+        if let Some(cond) = cond {
+            v.visit_expr_mut(cond);
+        }
+    }
+}
+
+pub fn walk_for_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut Stmt) {
+    if let StmtKind::For { init, cond, step, body } = &mut stmt.kind {
+        match init.as_mut() {
+            ForInit::Decl(decl) => v.visit_decl_mut(decl),
+            ForInit::Expr(Some(e)) => v.visit_expr_mut(e),
+            ForInit::Expr(None) => {},
+        }
+        if let Some(cond) = cond {
+            v.visit_expr_mut(cond);
+        }
+        if let Some(step) = step {
+            v.visit_expr_mut(step);
+        }
+        v.visit_stmt_mut(body);
+    }
+}
+
+pub fn walk_if_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut Stmt) {
+    if let StmtKind::If { cond, then, els } = &mut stmt.kind {
+        v.visit_expr_mut(cond);
+        v.visit_stmt_mut(then);
+        if let Some(els) = els {
+            v.visit_stmt_mut(els);
+        }
+    }
+}
+
+pub fn walk_switch_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut Stmt) {
+    if let StmtKind::Switch { cond, cases } = &mut stmt.kind {
+        v.visit_expr_mut(cond);
+        for case in cases.iter_mut() {
+            v.visit_case_mut(case);
+        }
+    }
+}
+
+pub fn walk_case_mut<V: VisitorMut + ?Sized>(v: &mut V, case: &mut Case) {
+    if let Some(label) = &mut case.label {
+        v.visit_expr_mut(label);
+    }
+    for item in case.items.iter_mut() {
+        v.visit_block_item_mut(item);
+    }
+}
+
+pub fn walk_return_mut<V: VisitorMut + ?Sized>(v: &mut V, stmt: &mut Stmt) {
+    if let StmtKind::Return(Some(e)) = &mut stmt.kind {
+        v.visit_expr_mut(e);
+    }
+}
+
+// 按表达式种类分派
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut Expr) {
+    match &expr.kind {
+        ExprKind::Literal(_) => v.visit_literal_mut(expr),
+        ExprKind::Ident(_) => v.visit_var_mut(expr),
+        ExprKind::Index { .. } => v.visit_index_mut(expr),
+        ExprKind::Member { .. } => v.visit_member_mut(expr),
+        ExprKind::Call { .. } => v.visit_call_mut(expr),
+        ExprKind::Unary { .. } => v.visit_unary_mut(expr),
+        ExprKind::Postfix { .. } => v.visit_postfix_mut(expr),
+        ExprKind::Binary { .. } => v.visit_binary_mut(expr),
+        ExprKind::Assign { .. } => v.visit_assign_mut(expr),
+        ExprKind::Paren(_) => v.visit_paren_mut(expr),
+        ExprKind::Error => v.visit_error_mut(expr),
+    }
+}
+
+pub fn walk_index_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut Expr) {
+    if let ExprKind::Index { base, index } = &mut expr.kind {
+        v.visit_expr_mut(base);
+        v.visit_expr_mut(index);
+    }
+}
+
+pub fn walk_member_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut Expr) {
+    if let ExprKind::Member { base, member, .. } = &mut expr.kind {
+        v.visit_expr_mut(base);
+        v.visit_ident_mut(member);
+    }
+}
+
+pub fn walk_call_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut Expr) {
+    if let ExprKind::Call { callee, args } = &mut expr.kind {
+        v.visit_ident_mut(callee);
+        for arg in args.iter_mut() {
+            v.visit_expr_mut(arg);
+        }
+    }
+}
+
+pub fn walk_unary_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut Expr) {
+    if let ExprKind::Unary { expr, .. } = &mut expr.kind {
+        v.visit_expr_mut(expr);
+    }
+}
+
+pub fn walk_postfix_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut Expr) {
+    if let ExprKind::Postfix { expr, .. } = &mut expr.kind {
+        v.visit_expr_mut(expr);
+    }
+}
+
+pub fn walk_binary_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut Expr) {
+    if let ExprKind::Binary { lhs, rhs, .. } = &mut expr.kind {
+        v.visit_expr_mut(lhs);
+        v.visit_expr_mut(rhs);
+    }
+}
+
+pub fn walk_assign_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut Expr) {
+    if let ExprKind::Assign { lhs, rhs, .. } = &mut expr.kind {
+        v.visit_expr_mut(lhs);
+        v.visit_expr_mut(rhs);
+    }
+}
+
+pub fn walk_paren_mut<V: VisitorMut + ?Sized>(v: &mut V, expr: &mut Expr) {
+    if let ExprKind::Paren(inner) = &mut expr.kind {
+        v.visit_expr_mut(inner);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{Expr, ExprKind, Literal};
+    use crate::common::Tag::ADD;
+    use crate::parser::parse;
+    use crate::span::Span;
+    use crate::visit::{Visitor, VisitorMut, walk_binary_mut, walk_call};
+
+    // 禁用函数检查
+    struct BannedCalls {
+        found: Vec<(String, Span)>,
+    }
+
+    impl Visitor for BannedCalls {
+        fn visit_call(&mut self, expr: &Expr) {
+            if let ExprKind::Call { callee, .. } = &expr.kind {
+                if callee.name == "gets" {
+                    self.found.push((callee.name.clone(), callee.span));
+                }
+            }
+            walk_call(self, expr);
+        }
+    }
+
+    #[test]
+    fn test_visitor() {
+        let (unit, diags) = parse("extern void gets(char *s);\nchar buf[8];\nint main() {\n    if (1) { gets(buf); }\n    for (;;) gets(buf);\n    return 0;\n}\n");
+        assert!(diags.is_empty());
+
+        let mut checker = BannedCalls { found: vec![] };
+        checker.visit_unit(&unit);
+        let lines: Vec<usize> = checker.found.iter().map(|(_, span)| span.line()).collect();
+        assert_eq!(lines, vec![4, 5]);
+    }
+
+    // 整数常量相加折叠
+    struct FoldAdd;
+
+    impl VisitorMut for FoldAdd {
+        fn visit_binary_mut(&mut self, expr: &mut Expr) {
+            walk_binary_mut(self, expr);
+            if let ExprKind::Binary { op: ADD, lhs, rhs } = &expr.kind {
+                if let (ExprKind::Literal(Literal::Num(a)), ExprKind::Literal(Literal::Num(b))) = (&lhs.kind, &rhs.kind) {
+                    *expr = Expr::new(ExprKind::Literal(Literal::Num(a + b)), expr.span);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_visitor_mut() {
+        let (mut unit, _) = parse("int a = 1 + 2 + 3;\n");
+        FoldAdd.visit_unit_mut(&mut unit);
+
+        let (expect, _) = parse("int a = 6;\n");
+        let init = |unit: &crate::ast::TranslationUnit| match &unit.items[0] {
+            crate::ast::Item::Decl(decl) => match decl.vars[0].init.clone().unwrap() {
+                crate::ast::Initializer::Expr(e) => e.kind,
+                _ => panic!("expect expression"),
+            },
+            _ => panic!("expect declaration"),
+        };
+        assert_eq!(init(&unit), init(&expect));
+    }
+}